@@ -0,0 +1,145 @@
+//! Operations on many hosts at once
+//!
+//! The hosts usually come from a `Selector`. They are all contacted at the
+//! same time, and one failing or offline host doesn't stop the others:
+//! each gets its own `HostResult`, in the order the hosts were given.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::docker::commands::{image_prune, system_prune, volume_prune};
+use crate::error::AppError;
+use crate::shell::ShellCommand;
+use crate::ssh::CommandOutput;
+use crate::transport::{CommandTransport, Target};
+
+/// Outcome of an operation on one host
+#[derive(Debug)]
+pub struct HostResult<T> {
+    pub host: String,
+    pub result: Result<T, AppError>,
+}
+
+/// What `prune` removes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PruneKind {
+    /// Stopped containers, unused networks and dangling images
+    #[default]
+    System,
+    /// Every image no container uses
+    Images,
+    /// Volumes no container uses
+    Volumes,
+}
+
+impl PruneKind {
+    pub fn all() -> &'static [PruneKind] {
+        &[PruneKind::System, PruneKind::Images, PruneKind::Volumes]
+    }
+}
+
+impl fmt::Display for PruneKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PruneKind::System => write!(f, "system"),
+            PruneKind::Images => write!(f, "images"),
+            PruneKind::Volumes => write!(f, "volumes"),
+        }
+    }
+}
+
+/// NixOS version of each host: `Ok(None)` when it answers but isn't NixOS,
+/// an error when it can't be reached
+pub async fn health(transport: &dyn CommandTransport, hosts: &[Target<'_>]) -> Vec<HostResult<Option<String>>> {
+    let command = ShellCommand::new("nixos-version")
+        .raw("2>/dev/null")
+        .or(ShellCommand::new("echo").arg("not-nixos"))
+        .build();
+
+    for_each(hosts, |host| {
+        let command = command.clone();
+        async move {
+            let output = transport.execute_read(host, &command).await?;
+            let version = output.stdout.trim();
+            Ok((output.success() && !version.is_empty() && version != "not-nixos").then(|| version.to_string()))
+        }
+    })
+    .await
+}
+
+/// Run a shell command line on every host
+pub async fn run_command(
+    transport: &dyn CommandTransport,
+    hosts: &[Target<'_>],
+    command: &str,
+) -> Vec<HostResult<CommandOutput>> {
+    tracing::info!("Running `{}` on {} hosts", command, hosts.len());
+    for_each(hosts, |host| transport.execute(host, command)).await
+}
+
+/// Prune unused Docker resources on every node
+pub async fn prune(transport: &dyn CommandTransport, nodes: &[&str], kind: PruneKind) -> Vec<HostResult<String>> {
+    let hosts: Vec<Target<'_>> = nodes.iter().map(|node| Target::Node(node)).collect();
+    for_each(&hosts, |host| async move {
+        match kind {
+            PruneKind::System => system_prune(transport, host.name()).await,
+            PruneKind::Images => image_prune(transport, host.name()).await,
+            PruneKind::Volumes => volume_prune(transport, host.name()).await,
+        }
+    })
+    .await
+}
+
+async fn for_each<'a, T, F, Fut>(hosts: &[Target<'a>], operation: F) -> Vec<HostResult<T>>
+where
+    F: Fn(Target<'a>) -> Fut,
+    Fut: std::future::Future<Output = Result<T, AppError>>,
+{
+    let results = futures::future::join_all(hosts.iter().map(|&host| operation(host))).await;
+    hosts
+        .iter()
+        .zip(results)
+        .map(|(host, result)| HostResult {
+            host: host.name().to_string(),
+            result,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::fixture::Fixture;
+    use crate::transport::FixtureTransport;
+
+    #[tokio::test]
+    async fn test_batch_operations() {
+        let health_command = "nixos-version 2>/dev/null || echo not-nixos";
+        let fixture = |target: &str, command: &str, stdout: &str, exit_code| Fixture {
+            target: Some(target.to_string()),
+            command: command.to_string(),
+            stdout: stdout.to_string(),
+            stderr: String::new(),
+            exit_code,
+        };
+        let transport = FixtureTransport::new()
+            .with(fixture("profile:DESK", health_command, "25.05.20250101.abcdef (Warbler)\n", 0))
+            .with(fixture("node:LXC_HOME", health_command, "not-nixos\n", 0))
+            .with(fixture("profile:DESK", "uptime", " 10:00 up 3 days\n", 0))
+            .with(fixture("node:LXC_HOME", "docker image prune -af 2>&1", "Total reclaimed space: 1GB\n", 0));
+
+        let hosts = [Target::Profile("DESK"), Target::Node("LXC_HOME"), Target::Profile("VPS")];
+        let results = health(&transport, &hosts).await;
+        assert_eq!(results[0].result.as_ref().unwrap().as_deref(), Some("25.05.20250101.abcdef (Warbler)"));
+        assert_eq!(results[1].result.as_ref().unwrap(), &None);
+        assert_eq!(results[2].host, "VPS");
+        assert!(results[2].result.is_err());
+
+        let results = run_command(&transport, &hosts[..1], "uptime").await;
+        assert!(results[0].result.as_ref().unwrap().stdout.contains("up 3 days"));
+
+        let results = prune(&transport, &["LXC_HOME"], PruneKind::Images).await;
+        assert!(results[0].result.as_ref().unwrap().contains("reclaimed"));
+    }
+}
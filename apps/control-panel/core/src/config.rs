@@ -1,7 +1,11 @@
 //! Configuration management for the control panel
 
+use arc_swap::ArcSwap;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
+use std::sync::Arc;
+
+use crate::inventory::InventoryReport;
 
 /// Main configuration structure
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -9,7 +13,10 @@ pub struct Config {
     pub server: ServerConfig,
     pub auth: AuthConfig,
     pub ssh: SshConfig,
-    pub proxmox: ProxmoxConfig,
+    /// Proxmox endpoints: one `[proxmox]` table or several `[[proxmox]]`
+    /// entries. Hosts not bound to one use their site's, else the first.
+    #[serde(deserialize_with = "one_or_many")]
+    pub proxmox: Vec<ProxmoxConfig>,
     pub dotfiles: DotfilesConfig,
     #[serde(default)]
     pub docker_nodes: Vec<DockerNode>,
@@ -17,30 +24,186 @@ pub struct Config {
     pub profiles: Vec<ProfileConfig>,
     #[serde(default)]
     pub grafana: Option<GrafanaConfig>,
+    #[serde(default)]
+    pub sites: Vec<SiteConfig>,
+    /// Named host groups (deploy-servers.conf `@GROUP` headers)
+    #[serde(default)]
+    pub groups: Vec<GroupConfig>,
+    #[serde(default)]
+    pub terminal: TerminalConfig,
+    #[serde(default)]
+    pub files: FilesConfig,
+    #[serde(default)]
+    pub tunnels: TunnelsConfig,
+    #[serde(default)]
+    pub stats: StatsConfig,
+    #[serde(default)]
+    pub events: EventsConfig,
+    #[serde(default)]
+    pub inventory: InventoryConfig,
+    /// Outcome of the inventory import, when enabled (see `inventory`)
+    #[serde(skip)]
+    pub inventory_report: Option<InventoryReport>,
+    /// Files and environment variables the configuration was read from
+    /// (see `layers`)
+    #[serde(skip)]
+    pub sources: Vec<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
+    /// Origins (`https://panel.example.com`) whose pages may make changes
+    /// and open terminals besides the panel's own, e.g. behind a proxy that
+    /// rewrites `Host`
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct AuthConfig {
     pub username: String,
-    pub password: String,
+    /// Plaintext password; prefer `password_hash` or `password_file`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    /// argon2 or bcrypt hash of the password
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_hash: Option<String>,
+    /// File holding the password or its hash (first line); takes precedence
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_file: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct SshConfig {
+    /// Default identity, used for hosts without their own `identity_file`
     pub private_key_path: String,
     pub default_user: String,
+    /// Environment variable holding the passphrase for encrypted keys
+    #[serde(default)]
+    pub passphrase_env: Option<String>,
+    /// File holding the passphrase for encrypted keys (used if
+    /// `passphrase_env` is unset or empty)
+    #[serde(default)]
+    pub passphrase_file: Option<String>,
+    /// Panel-specific known_hosts file (pinned and operator-accepted keys).
+    /// Checked before `~/.ssh/known_hosts`.
+    #[serde(default)]
+    pub known_hosts_file: Option<String>,
+    /// What to do with hosts that have no recorded key
+    #[serde(default)]
+    pub host_key_policy: HostKeyPolicy,
+    /// Seconds of silence before a keepalive is sent on a cached connection
+    /// (0: no keepalives)
+    #[serde(default = "default_keepalive_interval")]
+    pub keepalive_interval_secs: u64,
+    /// Cached connections unused for this many seconds are closed (0: never)
+    #[serde(default = "default_idle_timeout")]
+    pub idle_timeout_secs: u64,
+    /// Connect timeout per address, unless a host sets `ssh_timeout`
+    #[serde(default = "default_connect_timeout")]
+    pub connect_timeout_secs: u64,
+    /// Run commands for an LXC container through `pct exec` on the Proxmox
+    /// host when its own SSH can't be reached
+    #[serde(default = "default_pct_fallback")]
+    pub pct_fallback: bool,
+}
+
+fn default_keepalive_interval() -> u64 {
+    30
 }
 
+fn default_idle_timeout() -> u64 {
+    600
+}
+
+fn default_connect_timeout() -> u64 {
+    10
+}
+
+fn default_pct_fallback() -> bool {
+    true
+}
+
+/// Policy for servers whose host key is not in any known_hosts file
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HostKeyPolicy {
+    /// Pin the key on first connect (trust-on-first-use)
+    #[default]
+    Tofu,
+    /// Refuse to connect until an operator accepts the key
+    Strict,
+}
+
+/// How commands reach a host
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TransportKind {
+    /// Over SSH (the default)
+    #[default]
+    Ssh,
+    /// As a local process, for the machine the panel runs on
+    Local,
+    /// Through `pct exec` on the Proxmox host (LXC containers only, as root
+    /// inside the container)
+    Pct,
+}
+
+/// A Proxmox host the panel runs `pct` and `pvesh` on
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ProxmoxConfig {
+    /// Endpoint name, for `proxmox = "..."` on profiles and docker nodes
+    #[serde(default = "default_proxmox_name")]
+    pub name: String,
     pub host: String,
     pub user: String,
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub identity_file: Option<String>,
+    /// Cluster this host is a node of (default: a cluster of its own).
+    /// CTIDs are unique within a cluster, not across clusters.
+    #[serde(default)]
+    pub cluster: Option<String>,
+    /// Site of the host: its jump host, and the default endpoint for the
+    /// site's profiles and docker nodes
+    #[serde(default)]
+    pub site: Option<String>,
+    /// Reach the host through a bastion (overrides the site's jump host)
+    #[serde(default)]
+    pub jump_host: Option<String>,
+}
+
+fn default_proxmox_name() -> String {
+    "default".to_string()
+}
+
+impl ProxmoxConfig {
+    /// Cluster name, the endpoint's own name for a standalone host
+    pub fn cluster_name(&self) -> &str {
+        self.cluster.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// Accept a single table or an array of tables
+fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany<T> {
+        One(T),
+        Many(Vec<T>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(item) => vec![item],
+        OneOrMany::Many(items) => items,
+    })
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -55,6 +218,33 @@ pub struct DockerNode {
     pub ctid: u32,
     #[serde(default)]
     pub user: Option<String>,
+    #[serde(default)]
+    pub port: Option<u16>,
+    /// Private key for this node (default: `ssh.private_key_path`)
+    #[serde(default)]
+    pub identity_file: Option<String>,
+    /// Alternative addresses (wifi, tailscale, hostname), tried after `host`
+    #[serde(default)]
+    pub addresses: Vec<String>,
+    /// Connect timeout in seconds for each address
+    #[serde(default)]
+    pub ssh_timeout: Option<u64>,
+    /// Site this node belongs to (for its jump host)
+    #[serde(default)]
+    pub site: Option<String>,
+    /// Reach this node through a bastion (overrides the site's jump host)
+    #[serde(default)]
+    pub jump_host: Option<String>,
+    /// Proxmox endpoint hosting the container (default: the site's, else
+    /// the first)
+    #[serde(default)]
+    pub proxmox: Option<String>,
+    /// How commands reach this node
+    #[serde(default)]
+    pub transport: TransportKind,
+    /// Free-form tags for selectors (`critical`, `role:db`)
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -69,6 +259,305 @@ pub struct ProfileConfig {
     pub ctid: Option<u32>,
     #[serde(default)]
     pub base_profile: Option<String>,
+    /// SSH user (default: `ssh.default_user`)
+    #[serde(default)]
+    pub user: Option<String>,
+    #[serde(default)]
+    pub port: Option<u16>,
+    /// Private key for this profile (default: `ssh.private_key_path`)
+    #[serde(default)]
+    pub identity_file: Option<String>,
+    /// Alternative addresses (wifi, tailscale, hostname), tried after `ip`
+    #[serde(default)]
+    pub addresses: Vec<String>,
+    /// Connect timeout in seconds for each address
+    #[serde(default)]
+    pub ssh_timeout: Option<u64>,
+    /// Site this profile belongs to (for its jump host)
+    #[serde(default)]
+    pub site: Option<String>,
+    /// Reach this profile through a bastion (overrides the site's jump host)
+    #[serde(default)]
+    pub jump_host: Option<String>,
+    /// Proxmox endpoint hosting the container (default: the site's, else
+    /// the first)
+    #[serde(default)]
+    pub proxmox: Option<String>,
+    /// How commands reach this profile
+    #[serde(default)]
+    pub transport: TransportKind,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Inventory group (deploy-servers.conf `@GROUP`)
+    #[serde(default)]
+    pub group: Option<String>,
+    /// Free-form tags for selectors (`critical`, `site:komi`, `role:db`)
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Install command from deploy-servers.conf (`{DIR}`, `{PROFILE}`
+    /// placeholders unexpanded)
+    #[serde(default)]
+    pub deploy_command: Option<String>,
+    /// Nix config file, relative to the dotfiles directory
+    #[serde(default)]
+    pub config_file: Option<String>,
+}
+
+impl DockerNode {
+    /// Every address of this node in the order they are tried
+    pub fn all_addresses(&self) -> Vec<String> {
+        ordered_addresses(Some(&self.host), &self.addresses)
+    }
+}
+
+impl ProfileConfig {
+    /// Every address of this profile in the order they are tried
+    pub fn all_addresses(&self) -> Vec<String> {
+        ordered_addresses(self.ip.as_deref(), &self.addresses)
+    }
+}
+
+/// Expand a leading `~/` to the home directory
+pub fn expand_home(path: &str) -> std::path::PathBuf {
+    match (path.strip_prefix("~/"), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => std::path::PathBuf::from(home).join(rest),
+        _ => std::path::PathBuf::from(path),
+    }
+}
+
+/// Primary address first, then the alternatives, without duplicates
+fn ordered_addresses(primary: Option<&str>, alternatives: &[String]) -> Vec<String> {
+    let mut addresses: Vec<String> = Vec::new();
+    for address in primary.into_iter().chain(alternatives.iter().map(String::as_str)) {
+        let address = address.trim();
+        if !address.is_empty() && !addresses.iter().any(|a| a == address) {
+            addresses.push(address.to_string());
+        }
+    }
+    addresses
+}
+
+/// A group of hosts that share network access, e.g. a remote LAN only
+/// reachable through one bastion
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SiteConfig {
+    pub name: String,
+    /// Jump host(s) for every host of the site, in ProxyJump syntax:
+    /// comma-separated `[user@]host[:port]` hops or names of profiles /
+    /// docker nodes
+    #[serde(default)]
+    pub jump_host: Option<String>,
+}
+
+/// A named set of hosts, like a deploy-servers.conf `@GROUP|ICON` header.
+/// Members are listed here or name the group in their own `group`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct GroupConfig {
+    pub name: String,
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Profile and docker node names
+    #[serde(default)]
+    pub members: Vec<String>,
+}
+
+/// Interactive terminal sessions
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TerminalConfig {
+    /// Close a session after this many seconds without keyboard input
+    /// (0: never)
+    #[serde(default = "default_terminal_idle_timeout")]
+    pub idle_timeout_secs: u64,
+    /// Directory for session transcripts (default:
+    /// `~/.config/control-panel/transcripts`)
+    #[serde(default)]
+    pub transcript_dir: Option<String>,
+    /// Record session output to a transcript file
+    #[serde(default = "default_transcripts")]
+    pub transcripts: bool,
+}
+
+fn default_terminal_idle_timeout() -> u64 {
+    900
+}
+
+fn default_transcripts() -> bool {
+    true
+}
+
+impl Default for TerminalConfig {
+    fn default() -> Self {
+        Self {
+            idle_timeout_secs: default_terminal_idle_timeout(),
+            transcript_dir: None,
+            transcripts: default_transcripts(),
+        }
+    }
+}
+
+/// Remote file browser and editor
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FilesConfig {
+    /// Largest file opened in the editor
+    #[serde(default = "default_max_edit_bytes")]
+    pub max_edit_bytes: u64,
+    /// Largest file uploaded or downloaded
+    #[serde(default = "default_max_transfer_bytes")]
+    pub max_transfer_bytes: u64,
+    /// Copy a file to `<path>.bak-<timestamp>` before saving over it
+    #[serde(default = "default_backup")]
+    pub backup: bool,
+}
+
+fn default_max_edit_bytes() -> u64 {
+    1024 * 1024
+}
+
+fn default_max_transfer_bytes() -> u64 {
+    256 * 1024 * 1024
+}
+
+fn default_backup() -> bool {
+    true
+}
+
+impl Default for FilesConfig {
+    fn default() -> Self {
+        Self {
+            max_edit_bytes: default_max_edit_bytes(),
+            max_transfer_bytes: default_max_transfer_bytes(),
+            backup: default_backup(),
+        }
+    }
+}
+
+/// Profiles sourced from the dotfiles inventory files (see `inventory`)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct InventoryConfig {
+    /// Import profiles from the files below; config.toml entries override
+    #[serde(default)]
+    pub enabled: bool,
+    /// deploy.sh server lists, relative to the dotfiles directory, in order
+    #[serde(default = "default_deploy_servers")]
+    pub deploy_servers: Vec<String>,
+    /// Profile registry, relative to the dotfiles directory
+    #[serde(default = "default_registry")]
+    pub registry: Option<String>,
+}
+
+fn default_deploy_servers() -> Vec<String> {
+    vec![
+        "deploy-servers.conf".to_string(),
+        "deploy-servers-private.conf".to_string(),
+    ]
+}
+
+fn default_registry() -> Option<String> {
+    Some("profiles/registry.toml".to_string())
+}
+
+impl Default for InventoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            deploy_servers: default_deploy_servers(),
+            registry: default_registry(),
+        }
+    }
+}
+
+/// Local port forwards to services on hosts
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TunnelsConfig {
+    /// Close a tunnel after this many seconds without open connections
+    #[serde(default = "default_tunnel_idle_timeout")]
+    pub idle_timeout_secs: u64,
+    /// Address tunnels listen on. Use `0.0.0.0` to open them from browsers
+    /// on other machines.
+    #[serde(default = "default_tunnel_bind_address")]
+    pub bind_address: String,
+}
+
+fn default_tunnel_idle_timeout() -> u64 {
+    1800
+}
+
+fn default_tunnel_bind_address() -> String {
+    "127.0.0.1".to_string()
+}
+
+impl Default for TunnelsConfig {
+    fn default() -> Self {
+        Self {
+            idle_timeout_secs: default_tunnel_idle_timeout(),
+            bind_address: default_tunnel_bind_address(),
+        }
+    }
+}
+
+/// Container resource usage sampling (`docker stats`) on the docker nodes
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StatsConfig {
+    #[serde(default = "default_stats_enabled")]
+    pub enabled: bool,
+    /// Seconds between samples of each node
+    #[serde(default = "default_stats_interval")]
+    pub interval_secs: u64,
+    /// Samples kept per container for the sparklines
+    #[serde(default = "default_stats_history")]
+    pub history: usize,
+}
+
+fn default_stats_enabled() -> bool {
+    true
+}
+
+fn default_stats_interval() -> u64 {
+    30
+}
+
+fn default_stats_history() -> usize {
+    60
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_stats_enabled(),
+            interval_secs: default_stats_interval(),
+            history: default_stats_history(),
+        }
+    }
+}
+
+/// `docker events` subscriptions keeping a cached container list per
+/// docker node
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EventsConfig {
+    /// Without it, container lists are polled over SSH
+    #[serde(default = "default_events_enabled")]
+    pub enabled: bool,
+    /// Seconds between full resyncs while the stream is up (status texts
+    /// like "Up 3 minutes" only change there)
+    #[serde(default = "default_events_resync")]
+    pub resync_secs: u64,
+}
+
+fn default_events_enabled() -> bool {
+    true
+}
+
+fn default_events_resync() -> u64 {
+    300
+}
+
+impl Default for EventsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_events_enabled(),
+            resync_secs: default_events_resync(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -87,9 +576,22 @@ pub struct GrafanaDashboard {
 
 impl Config {
     /// Load configuration from a TOML file
+    ///
+    /// With `inventory.enabled`, profiles are also imported from the
+    /// dotfiles inventory files.
     pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
         let content = std::fs::read_to_string(path.as_ref())?;
-        let config: Config = toml::from_str(&content)?;
+        let mut config = Self::from_table(toml::from_str(&content)?)?;
+        config.sources = vec![path.as_ref().display().to_string()];
+        Ok(config)
+    }
+
+    /// Build the configuration from parsed (and possibly merged) TOML
+    pub(crate) fn from_table(table: toml::Table) -> anyhow::Result<Self> {
+        let mut config: Config = toml::Value::Table(table).try_into()?;
+        if config.inventory.enabled {
+            config.inventory_report = Some(crate::inventory::import(&mut config));
+        }
         Ok(config)
     }
 
@@ -103,11 +605,117 @@ impl Config {
         self.profiles.iter().find(|p| p.name == name)
     }
 
+    /// Get a site by name
+    pub fn get_site(&self, name: &str) -> Option<&SiteConfig> {
+        self.sites.iter().find(|s| s.name == name)
+    }
+
+    /// Get a group by name
+    pub fn get_group(&self, name: &str) -> Option<&GroupConfig> {
+        self.groups.iter().find(|g| g.name == name)
+    }
+
+    /// Get a Proxmox endpoint by name
+    pub fn get_proxmox(&self, name: &str) -> Option<&ProxmoxConfig> {
+        self.proxmox.iter().find(|p| p.name == name)
+    }
+
+    /// Proxmox endpoint of a docker node
+    pub fn get_node_proxmox(&self, node: &DockerNode) -> Option<&ProxmoxConfig> {
+        self.proxmox_for(node.proxmox.as_deref(), node.site.as_deref())
+    }
+
+    /// Proxmox endpoint of a profile
+    pub fn get_profile_proxmox(&self, profile: &ProfileConfig) -> Option<&ProxmoxConfig> {
+        self.proxmox_for(profile.proxmox.as_deref(), profile.site.as_deref())
+    }
+
+    /// The named endpoint (none if the name is unknown), else the site's,
+    /// else the first
+    fn proxmox_for(&self, name: Option<&str>, site: Option<&str>) -> Option<&ProxmoxConfig> {
+        match name {
+            Some(name) => self.get_proxmox(name),
+            None => site
+                .and_then(|site| self.proxmox.iter().find(|p| p.site.as_deref() == Some(site)))
+                .or_else(|| self.proxmox.first()),
+        }
+    }
+
+    /// Jump host for a Proxmox endpoint: its own, else its site's
+    pub fn get_proxmox_jump_host<'a>(&'a self, endpoint: &'a ProxmoxConfig) -> Option<&'a str> {
+        self.jump_host_or_site(endpoint.jump_host.as_deref(), endpoint.site.as_deref())
+    }
+
+    /// Jump host for a docker node: its own, else its site's
+    pub fn get_node_jump_host<'a>(&'a self, node: &'a DockerNode) -> Option<&'a str> {
+        self.jump_host_or_site(node.jump_host.as_deref(), node.site.as_deref())
+    }
+
+    /// Jump host for a profile: its own, else its site's
+    pub fn get_profile_jump_host<'a>(&'a self, profile: &'a ProfileConfig) -> Option<&'a str> {
+        self.jump_host_or_site(profile.jump_host.as_deref(), profile.site.as_deref())
+    }
+
+    fn jump_host_or_site<'a>(&'a self, jump_host: Option<&'a str>, site: Option<&str>) -> Option<&'a str> {
+        jump_host
+            .or_else(|| site.and_then(|s| self.get_site(s)).and_then(|s| s.jump_host.as_deref()))
+            .filter(|j| !j.trim().is_empty())
+    }
+
     /// Get the SSH user for a docker node
     pub fn get_ssh_user<'a>(&'a self, node: &'a DockerNode) -> &'a str {
         node.user.as_deref().unwrap_or(&self.ssh.default_user)
     }
 
+    /// Get the SSH user for a profile
+    pub fn get_profile_user<'a>(&'a self, profile: &'a ProfileConfig) -> &'a str {
+        profile.user.as_deref().unwrap_or(&self.ssh.default_user)
+    }
+
+    /// Passphrase for encrypted private keys, from the environment or a file
+    pub fn key_passphrase(&self) -> Option<String> {
+        if let Some(value) = self
+            .ssh
+            .passphrase_env
+            .as_deref()
+            .and_then(|var| std::env::var(var).ok())
+            .filter(|value| !value.is_empty())
+        {
+            return Some(value);
+        }
+
+        let path = self.ssh.passphrase_file.as_deref()?;
+        match std::fs::read_to_string(expand_home(path)) {
+            Ok(content) => Some(content.trim_end_matches(['\n', '\r']).to_string()),
+            Err(e) => {
+                tracing::warn!("Failed to read passphrase file {}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    /// Path of the panel's own known_hosts file
+    pub fn known_hosts_path(&self) -> std::path::PathBuf {
+        match self.ssh.known_hosts_file {
+            Some(ref path) => expand_home(path),
+            None => {
+                let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+                std::path::PathBuf::from(format!("{}/.config/control-panel/known_hosts", home))
+            }
+        }
+    }
+
+    /// Directory for terminal session transcripts
+    pub fn transcript_dir(&self) -> std::path::PathBuf {
+        match self.terminal.transcript_dir {
+            Some(ref path) => expand_home(path),
+            None => {
+                let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+                std::path::PathBuf::from(format!("{}/.config/control-panel/transcripts", home))
+            }
+        }
+    }
+
     /// Get the host IP for a profile (for SSH connections)
     pub fn get_profile_host(&self, profile_name: &str) -> Option<String> {
         // First check if there's a docker node with the same name
@@ -124,11 +732,42 @@ impl Config {
     }
 }
 
+/// Configuration that can be replaced while the panel runs (hot reload)
+///
+/// Clones share the same slot. `load` returns a snapshot that stays
+/// consistent for as long as it is held, even if a reload swaps in a new
+/// configuration meanwhile.
+#[derive(Clone)]
+pub struct SharedConfig(Arc<ArcSwap<Config>>);
+
+impl SharedConfig {
+    pub fn new(config: Config) -> Self {
+        Self(Arc::new(ArcSwap::from_pointee(config)))
+    }
+
+    /// Current configuration
+    pub fn load(&self) -> Arc<Config> {
+        self.0.load_full()
+    }
+
+    /// Replace the configuration, returning the previous one
+    pub fn store(&self, config: Config) -> Arc<Config> {
+        self.0.swap(Arc::new(config))
+    }
+}
+
+impl std::fmt::Debug for SharedConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.load().fmt(f)
+    }
+}
+
 impl Default for ServerConfig {
     fn default() -> Self {
         Self {
             host: "0.0.0.0".to_string(),
             port: 3100,
+            allowed_origins: Vec::new(),
         }
     }
 }
@@ -137,7 +776,9 @@ impl Default for AuthConfig {
     fn default() -> Self {
         Self {
             username: "admin".to_string(),
-            password: "admin".to_string(),
+            password: Some("admin".to_string()),
+            password_hash: None,
+            password_file: None,
         }
     }
 }
@@ -147,6 +788,14 @@ impl Default for SshConfig {
         Self {
             private_key_path: String::new(),
             default_user: "akunito".to_string(),
+            passphrase_env: None,
+            passphrase_file: None,
+            known_hosts_file: None,
+            host_key_policy: HostKeyPolicy::default(),
+            keepalive_interval_secs: default_keepalive_interval(),
+            idle_timeout_secs: default_idle_timeout(),
+            connect_timeout_secs: default_connect_timeout(),
+            pct_fallback: default_pct_fallback(),
         }
     }
 }
@@ -154,8 +803,14 @@ impl Default for SshConfig {
 impl Default for ProxmoxConfig {
     fn default() -> Self {
         Self {
+            name: default_proxmox_name(),
             host: "192.168.8.82".to_string(),
             user: "root".to_string(),
+            port: None,
+            identity_file: None,
+            cluster: None,
+            site: None,
+            jump_host: None,
         }
     }
 }
@@ -184,15 +839,33 @@ impl Default for Config {
             server: ServerConfig::default(),
             auth: AuthConfig::default(),
             ssh: SshConfig::default(),
-            proxmox: ProxmoxConfig::default(),
+            proxmox: vec![ProxmoxConfig::default()],
             dotfiles: DotfilesConfig::default(),
             docker_nodes: Vec::new(),
             profiles: Vec::new(),
             grafana: Some(GrafanaConfig::default()),
+            sites: Vec::new(),
+            groups: Vec::new(),
+            terminal: TerminalConfig::default(),
+            files: FilesConfig::default(),
+            tunnels: TunnelsConfig::default(),
+            stats: StatsConfig::default(),
+            events: EventsConfig::default(),
+            inventory: InventoryConfig::default(),
+            inventory_report: None,
+            sources: Vec::new(),
         }
     }
 }
 
+/// `fixtures/minimal.toml` with `dir` as the dotfiles directory (which also
+/// holds the `id_test` key path), followed by `extra`
+#[cfg(test)]
+pub(crate) fn minimal_toml(dir: &std::path::Path, extra: &str) -> String {
+    let minimal = include_str!("../fixtures/minimal.toml").replace("DIR", &dir.display().to_string());
+    format!("{}\n{}", minimal, extra)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -235,5 +908,87 @@ ip = "192.168.8.96"
         assert_eq!(config.server.port, 3100);
         assert_eq!(config.docker_nodes.len(), 1);
         assert_eq!(config.docker_nodes[0].name, "LXC_HOME");
+        assert_eq!(config.proxmox[0].name, "default");
+        assert_eq!(config.get_node_proxmox(&config.docker_nodes[0]).unwrap().host, "192.168.8.82");
+    }
+
+    #[test]
+    fn test_proxmox_endpoints() {
+        let config: Config = toml::from_str(
+            r#"
+[server]
+host = "0.0.0.0"
+port = 3100
+[auth]
+username = "admin"
+[ssh]
+private_key_path = "/home/user/.ssh/id_ed25519"
+default_user = "akunito"
+[dotfiles]
+path = "/home/akunito/.dotfiles"
+
+[[proxmox]]
+name = "home"
+host = "192.168.8.82"
+user = "root"
+
+[[proxmox]]
+name = "komi"
+host = "192.168.1.10"
+user = "root"
+site = "komi"
+
+[[sites]]
+name = "komi"
+jump_host = "LXC_tailscale"
+
+[[docker_nodes]]
+name = "LXC_HOME"
+host = "192.168.8.80"
+ctid = 100
+
+[[docker_nodes]]
+name = "KOMI_LXC_proxy"
+host = "192.168.1.102"
+ctid = 100
+site = "komi"
+
+[[docker_nodes]]
+name = "KOMI_LXC_mailer"
+host = "192.168.1.103"
+ctid = 103
+proxmox = "nowhere"
+"#,
+        )
+        .unwrap();
+
+        let endpoint = |node: usize| config.get_node_proxmox(&config.docker_nodes[node]).map(|e| e.name.as_str());
+        assert_eq!(endpoint(0), Some("home"));
+        // Through the site
+        assert_eq!(endpoint(1), Some("komi"));
+        assert_eq!(endpoint(2), None);
+        assert_eq!(config.get_proxmox_jump_host(&config.proxmox[1]), Some("LXC_tailscale"));
+        assert_eq!(config.proxmox[1].cluster_name(), "komi");
+    }
+
+    #[test]
+    fn test_profile_addresses() {
+        let profile: ProfileConfig = toml::from_str(
+            r#"
+name = "LAPTOP_X13"
+type = "laptop"
+hostname = "nixosx13aku"
+ip = "192.168.8.92"
+addresses = ["192.168.8.93", "192.168.8.92", " 100.64.0.8 ", ""]
+ssh_timeout = 5
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            profile.all_addresses(),
+            vec!["192.168.8.92", "192.168.8.93", "100.64.0.8"]
+        );
+        assert_eq!(profile.ssh_timeout, Some(5));
     }
 }
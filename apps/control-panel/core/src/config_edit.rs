@@ -0,0 +1,604 @@
+//! Inventory edits written back to config.toml
+//!
+//! Docker nodes, profiles and Grafana dashboards are added, changed and
+//! removed in the user config file (see `layers`) with `toml_edit`, so
+//! comments and formatting around them survive. A changed entry keeps its
+//! keys in place; only values that differ are rewritten, and comments on
+//! their lines stay.
+//!
+//! An edit is checked before the file is written: the merged configuration
+//! must still parse, gain no validation errors and show the change (an
+//! entry or list that another config file, the environment or the inventory
+//! import also sets can't be edited here). After writing, the configuration
+//! is reloaded right away, without waiting for the file watcher.
+
+use std::collections::HashMap;
+use std::path::Path;
+use toml_edit::{ArrayOfTables, DocumentMut, Item, Table};
+
+use crate::config::{Config, DockerNode, GrafanaDashboard, ProfileConfig, SharedConfig};
+use crate::error::AppError;
+use crate::infra::PROFILE_TYPES;
+use crate::layers::ConfigLayers;
+use crate::ssh::SshPool;
+use crate::validate::Finding;
+
+/// An editable list of `[[tables]]` in config.toml
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Section {
+    #[default]
+    DockerNodes,
+    Profiles,
+    Dashboards,
+}
+
+/// A form field of an entry
+#[derive(Debug, Clone, Copy)]
+pub struct Field {
+    /// TOML key
+    pub key: &'static str,
+    pub label: &'static str,
+    pub kind: FieldKind,
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    Text,
+    Number,
+    /// Comma-separated strings
+    List,
+    /// One of the values; the first is the default and isn't written
+    Choice(&'static [&'static str]),
+}
+
+const fn field(key: &'static str, label: &'static str, kind: FieldKind, required: bool) -> Field {
+    Field { key, label, kind, required }
+}
+
+const TRANSPORTS: FieldKind = FieldKind::Choice(&["ssh", "local", "pct"]);
+
+const DOCKER_NODE_FIELDS: &[Field] = &[
+    field("name", "Name", FieldKind::Text, true),
+    field("host", "Host", FieldKind::Text, true),
+    field("ctid", "CTID", FieldKind::Number, true),
+    field("user", "SSH user", FieldKind::Text, false),
+    field("port", "SSH port", FieldKind::Number, false),
+    field("identity_file", "Identity file", FieldKind::Text, false),
+    field("addresses", "Other addresses", FieldKind::List, false),
+    field("ssh_timeout", "SSH timeout (s)", FieldKind::Number, false),
+    field("site", "Site", FieldKind::Text, false),
+    field("jump_host", "Jump host", FieldKind::Text, false),
+    field("proxmox", "Proxmox endpoint", FieldKind::Text, false),
+    field("transport", "Transport", TRANSPORTS, false),
+    field("tags", "Tags", FieldKind::List, false),
+];
+
+const PROFILE_FIELDS: &[Field] = &[
+    field("name", "Name", FieldKind::Text, true),
+    field("type", "Type", FieldKind::Choice(PROFILE_TYPES), true),
+    field("hostname", "Hostname", FieldKind::Text, true),
+    field("ip", "IP", FieldKind::Text, false),
+    field("ctid", "CTID", FieldKind::Number, false),
+    field("base_profile", "Base profile", FieldKind::Text, false),
+    field("user", "SSH user", FieldKind::Text, false),
+    field("port", "SSH port", FieldKind::Number, false),
+    field("identity_file", "Identity file", FieldKind::Text, false),
+    field("addresses", "Other addresses", FieldKind::List, false),
+    field("ssh_timeout", "SSH timeout (s)", FieldKind::Number, false),
+    field("site", "Site", FieldKind::Text, false),
+    field("jump_host", "Jump host", FieldKind::Text, false),
+    field("proxmox", "Proxmox endpoint", FieldKind::Text, false),
+    field("transport", "Transport", TRANSPORTS, false),
+    field("description", "Description", FieldKind::Text, false),
+    field("group", "Group", FieldKind::Text, false),
+    field("tags", "Tags", FieldKind::List, false),
+    field("deploy_command", "Deploy command", FieldKind::Text, false),
+    field("config_file", "Nix config file", FieldKind::Text, false),
+];
+
+const DASHBOARD_FIELDS: &[Field] = &[
+    field("name", "Name", FieldKind::Text, true),
+    field("uid", "UID", FieldKind::Text, true),
+    field("slug", "Slug", FieldKind::Text, true),
+];
+
+impl Section {
+    pub fn all() -> &'static [Section] {
+        &[Section::DockerNodes, Section::Profiles, Section::Dashboards]
+    }
+
+    /// URL segment
+    pub fn slug(&self) -> &'static str {
+        match self {
+            Section::DockerNodes => "docker_nodes",
+            Section::Profiles => "profiles",
+            Section::Dashboards => "dashboards",
+        }
+    }
+
+    pub fn from_slug(slug: &str) -> Option<Section> {
+        Section::all().iter().copied().find(|s| s.slug() == slug)
+    }
+
+    /// Name of one entry
+    pub fn label(&self) -> &'static str {
+        match self {
+            Section::DockerNodes => "Docker node",
+            Section::Profiles => "Profile",
+            Section::Dashboards => "Grafana dashboard",
+        }
+    }
+
+    /// Form fields, in the order keys are written
+    pub fn fields(&self) -> &'static [Field] {
+        match self {
+            Section::DockerNodes => DOCKER_NODE_FIELDS,
+            Section::Profiles => PROFILE_FIELDS,
+            Section::Dashboards => DASHBOARD_FIELDS,
+        }
+    }
+
+    /// Where the `[[tables]]` are
+    fn path(&self) -> &'static [&'static str] {
+        match self {
+            Section::DockerNodes => &["docker_nodes"],
+            Section::Profiles => &["profiles"],
+            Section::Dashboards => &["grafana", "dashboards"],
+        }
+    }
+
+    /// `[[docker_nodes]]`, `[[grafana.dashboards]]`
+    pub fn header(&self) -> String {
+        format!("[[{}]]", self.path().join("."))
+    }
+
+    /// Entry names in the configuration
+    pub fn names(&self, config: &Config) -> Vec<String> {
+        match self {
+            Section::DockerNodes => config.docker_nodes.iter().map(|n| n.name.clone()).collect(),
+            Section::Profiles => config.profiles.iter().map(|p| p.name.clone()).collect(),
+            Section::Dashboards => config
+                .grafana
+                .iter()
+                .flat_map(|g| g.dashboards.iter().map(|d| d.name.clone()))
+                .collect(),
+        }
+    }
+
+    /// An entry of the configuration as TOML
+    pub fn entry(&self, config: &Config, name: &str) -> Option<toml::Table> {
+        let value = match self {
+            Section::DockerNodes => toml::Value::try_from(config.get_docker_node(name)?),
+            Section::Profiles => toml::Value::try_from(config.get_profile(name)?),
+            Section::Dashboards => {
+                let grafana = config.grafana.as_ref()?;
+                toml::Value::try_from(grafana.dashboards.iter().find(|d| d.name == name)?)
+            }
+        };
+        match value {
+            Ok(toml::Value::Table(table)) => Some(table),
+            _ => None,
+        }
+    }
+
+    /// Check that an entry deserializes
+    fn check(&self, entry: &toml::Table) -> Result<(), AppError> {
+        let value = toml::Value::Table(entry.clone());
+        let result = match self {
+            Section::DockerNodes => value.try_into::<DockerNode>().map(drop),
+            Section::Profiles => value.try_into::<ProfileConfig>().map(drop),
+            Section::Dashboards => value.try_into::<GrafanaDashboard>().map(drop),
+        };
+        result.map_err(|e| AppError::Validation(format!("{}: {}", self.label(), e)))
+    }
+}
+
+/// Build an entry from form values (all strings, empty for unset)
+pub fn form_to_entry(section: Section, values: &HashMap<String, String>) -> Result<toml::Table, AppError> {
+    let mut entry = toml::Table::new();
+    for field in section.fields() {
+        let value = values.get(field.key).map(|v| v.trim()).unwrap_or("");
+        if value.is_empty() {
+            if field.required {
+                return Err(AppError::Validation(format!("{} is required", field.label)));
+            }
+            continue;
+        }
+        let value = match field.kind {
+            FieldKind::Text => toml::Value::String(value.to_string()),
+            FieldKind::Number => value
+                .parse()
+                .map(toml::Value::Integer)
+                .map_err(|_| AppError::Validation(format!("{}: {:?} is not a number", field.label, value)))?,
+            FieldKind::List => toml::Value::Array(
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|v| !v.is_empty())
+                    .map(|v| toml::Value::String(v.to_string()))
+                    .collect(),
+            ),
+            FieldKind::Choice(choices) => {
+                if !choices.contains(&value) {
+                    return Err(AppError::Validation(format!(
+                        "{}: {:?} is not one of {}",
+                        field.label,
+                        value,
+                        choices.join(", ")
+                    )));
+                }
+                if !field.required && value == choices[0] {
+                    continue;
+                }
+                toml::Value::String(value.to_string())
+            }
+        };
+        entry.insert(field.key.to_string(), value);
+    }
+    section.check(&entry)?;
+    Ok(entry)
+}
+
+/// Form values of an entry
+pub fn entry_to_form(section: Section, entry: &toml::Table) -> HashMap<String, String> {
+    section
+        .fields()
+        .iter()
+        .filter_map(|field| {
+            let value = match entry.get(field.key)? {
+                toml::Value::String(s) => s.clone(),
+                toml::Value::Array(items) => items
+                    .iter()
+                    .map(|v| v.as_str().map(str::to_string).unwrap_or_else(|| v.to_string()))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                other => other.to_string(),
+            };
+            Some((field.key.to_string(), value))
+        })
+        .collect()
+}
+
+/// Add or change an entry, `original` naming the entry it replaces.
+/// Returns the findings of the new configuration.
+pub fn save_entry(
+    layers: &ConfigLayers,
+    shared: &SharedConfig,
+    pool: &SshPool,
+    section: Section,
+    original: Option<&str>,
+    entry: &toml::Table,
+) -> Result<Vec<Finding>, AppError> {
+    section.check(entry)?;
+    let name = entry_name(entry)?;
+    let path = user_file(layers)?;
+    let current = layers.load().map_err(|e| AppError::Config(e.to_string()))?;
+
+    let mut expected = section.names(&current);
+    if original != Some(name) && expected.iter().any(|n| n == name) {
+        return Err(AppError::Validation(format!("{} {} already exists", section.label(), name)));
+    }
+    expected.retain(|n| Some(n.as_str()) != original);
+    expected.push(name.to_string());
+
+    let content = std::fs::read_to_string(path)?;
+    let edited = edit_document(&content, section, original.unwrap_or(name), Some(entry))?;
+    apply(layers, shared, pool, path, &edited, section, expected)
+}
+
+/// Remove an entry from the user config file
+pub fn delete_entry(
+    layers: &ConfigLayers,
+    shared: &SharedConfig,
+    pool: &SshPool,
+    section: Section,
+    name: &str,
+) -> Result<Vec<Finding>, AppError> {
+    let path = user_file(layers)?;
+    let current = layers.load().map_err(|e| AppError::Config(e.to_string()))?;
+    let mut expected = section.names(&current);
+    expected.retain(|n| n != name);
+
+    let content = std::fs::read_to_string(path)?;
+    let edited = edit_document(&content, section, name, None)?;
+    apply(layers, shared, pool, path, &edited, section, expected)
+}
+
+/// Check the edited file, write it and reload
+fn apply(
+    layers: &ConfigLayers,
+    shared: &SharedConfig,
+    pool: &SshPool,
+    path: &Path,
+    edited: &str,
+    section: Section,
+    mut expected: Vec<String>,
+) -> Result<Vec<Finding>, AppError> {
+    let config = layers
+        .load_edited(path, edited)
+        .map_err(|e| AppError::Validation(e.to_string()))?;
+    let mut names = section.names(&config);
+    names.sort();
+    expected.sort();
+    if names != expected {
+        return Err(AppError::Validation(format!(
+            "{} in {} is overridden by another config file, the environment or the inventory import; edit it there",
+            section.header(),
+            path.display()
+        )));
+    }
+    crate::reload::reject_added_errors(&config.validate(), &shared.load())?;
+
+    // Replace the file in one step so the watcher never sees half of it
+    let temporary = path.with_extension("toml.tmp");
+    std::fs::write(&temporary, edited)?;
+    if let Ok(metadata) = std::fs::metadata(path) {
+        std::fs::set_permissions(&temporary, metadata.permissions())?;
+    }
+    std::fs::rename(&temporary, path)?;
+    tracing::info!("Wrote {} ({})", path.display(), section.header());
+
+    crate::reload::reload(layers, shared, pool)
+}
+
+fn user_file(layers: &ConfigLayers) -> Result<&Path, AppError> {
+    layers
+        .user_file()
+        .filter(|path| path.is_file())
+        .ok_or_else(|| AppError::Config("no config file to write the change to".to_string()))
+}
+
+fn entry_name(entry: &toml::Table) -> Result<&str, AppError> {
+    entry
+        .get("name")
+        .and_then(toml::Value::as_str)
+        .ok_or_else(|| AppError::Validation("name is required".to_string()))
+}
+
+/// Replace (or add) the entry called `name` with `entry`, or remove it
+/// when `entry` is `None`, keeping the rest of the document as it is
+pub fn edit_document(content: &str, section: Section, name: &str, entry: Option<&toml::Table>) -> Result<String, AppError> {
+    let mut document: DocumentMut = content
+        .parse()
+        .map_err(|e| AppError::Config(format!("config.toml doesn't parse: {}", e)))?;
+    let tables = tables_mut(&mut document, section, entry.is_some())?;
+    let index = tables
+        .iter()
+        .position(|t| t.get("name").and_then(Item::as_str) == Some(name));
+
+    match (entry, index) {
+        (Some(entry), Some(index)) => {
+            let table = tables.get_mut(index).expect("index from position");
+            update_table(table, &to_edit_table(section, entry)?);
+        }
+        (Some(entry), None) => {
+            let mut table = to_edit_table(section, entry)?;
+            // After the last entry of the list rather than the end of the file
+            if let Some(position) = tables.iter().filter_map(Table::position).max() {
+                table.set_position(position);
+            }
+            tables.push(table);
+        }
+        (None, Some(index)) => {
+            // A comment above the first entry usually heads the whole list
+            let heading = (index == 0).then(|| tables.get(0).map(|t| t.decor().clone())).flatten();
+            tables.remove(index);
+            if let (Some(heading), Some(next)) = (heading, tables.get_mut(0)) {
+                *next.decor_mut() = heading;
+            }
+        }
+        (None, None) => {
+            return Err(AppError::Validation(format!(
+                "{} {} isn't in config.toml (imported or set in another config file)",
+                section.label(),
+                name
+            )))
+        }
+    }
+    Ok(document.to_string())
+}
+
+/// The `[[tables]]` of a section, created if missing and `create` is set
+fn tables_mut(document: &mut DocumentMut, section: Section, create: bool) -> Result<&mut ArrayOfTables, AppError> {
+    let not_tables = || {
+        AppError::Config(format!(
+            "{} isn't written as {} tables; edit it by hand",
+            section.path().join("."),
+            section.header()
+        ))
+    };
+    let missing = || AppError::Validation(format!("config.toml has no {}", section.header()));
+
+    let (last, parents) = section.path().split_last().expect("non-empty path");
+    let mut table = document.as_table_mut();
+    for key in parents {
+        if !table.contains_key(key) {
+            if !create {
+                return Err(missing());
+            }
+            table.insert(key, Item::Table(Table::new()));
+        }
+        table = table.get_mut(key).and_then(Item::as_table_mut).ok_or_else(not_tables)?;
+    }
+    if !table.contains_key(last) {
+        if !create {
+            return Err(missing());
+        }
+        table.insert(last, Item::ArrayOfTables(ArrayOfTables::new()));
+    }
+    table.get_mut(last).and_then(Item::as_array_of_tables_mut).ok_or_else(not_tables)
+}
+
+/// An entry as a `toml_edit` table, keys in field order
+fn to_edit_table(section: Section, entry: &toml::Table) -> Result<Table, AppError> {
+    let mut table = Table::new();
+    for field in section.fields() {
+        if let Some(value) = entry.get(field.key) {
+            let value: toml_edit::Value = value
+                .to_string()
+                .parse()
+                .map_err(|e| AppError::Internal(format!("{}: {}", field.key, e)))?;
+            table.insert(field.key, Item::Value(value));
+        }
+    }
+    Ok(table)
+}
+
+/// Bring `table` to the values of `new`, leaving keys whose value doesn't
+/// change untouched and keeping the comments of those that do
+fn update_table(table: &mut Table, new: &Table) {
+    let removed: Vec<String> = table
+        .iter()
+        .map(|(key, _)| key.to_string())
+        .filter(|key| !new.contains_key(key))
+        .collect();
+    for key in removed {
+        table.remove(&key);
+    }
+
+    for (key, item) in new.iter() {
+        let Some(value) = item.as_value() else {
+            continue;
+        };
+        match table.get_mut(key).and_then(Item::as_value_mut) {
+            Some(existing) if same_value(existing, value) => {}
+            Some(existing) => {
+                let decor = existing.decor().clone();
+                *existing = value.clone();
+                *existing.decor_mut() = decor;
+            }
+            None => {
+                table.insert(key, item.clone());
+            }
+        }
+    }
+}
+
+/// Equal values, however they are formatted
+fn same_value(a: &toml_edit::Value, b: &toml_edit::Value) -> bool {
+    let parse = |value: &toml_edit::Value| {
+        let mut value = value.clone();
+        value.decor_mut().clear();
+        format!("v = {}", value).parse::<toml::Table>().ok()
+    };
+    parse(a).is_some() && parse(a) == parse(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::minimal_toml;
+    use tempfile::TempDir;
+
+    const CONTENT: &str = r#"# Docker nodes
+[[docker_nodes]]
+name = "LXC_HOME"
+host = "192.168.8.80"   # migrated to VPS soon
+ctid = 100
+addresses = [
+    "nixos-home",
+    "100.64.0.2",
+]
+
+# Retired, kept for the logs
+[[docker_nodes]]
+name = "LXC_old"
+host = "192.168.8.99"
+ctid = 199
+
+[[profiles]]
+name = "DESK"
+type = "desktop"
+hostname = "nixosaku"
+"#;
+
+    fn form(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_edit_document() {
+        let section = Section::DockerNodes;
+
+        // Change the CTID: the host comment and the address list stay
+        let entry = form_to_entry(
+            section,
+            &form(&[
+                ("name", "LXC_HOME"),
+                ("host", "192.168.8.80"),
+                ("ctid", "101"),
+                ("addresses", "nixos-home, 100.64.0.2"),
+                ("transport", "ssh"),
+            ]),
+        )
+        .unwrap();
+        let edited = edit_document(CONTENT, section, "LXC_HOME", Some(&entry)).unwrap();
+        assert!(edited.contains("host = \"192.168.8.80\"   # migrated to VPS soon\nctid = 101\n"));
+        assert!(edited.contains("    \"nixos-home\",\n"));
+        assert!(!edited.contains("transport"));
+        assert_eq!(edited.replace("ctid = 101", "ctid = 100"), CONTENT);
+
+        // Add after the last docker node, keys in field order
+        let entry = form_to_entry(
+            section,
+            &form(&[("name", "LXC_new"), ("host", "10.0.0.5"), ("ctid", "120"), ("tags", "role:db, critical")]),
+        )
+        .unwrap();
+        let edited = edit_document(CONTENT, section, "LXC_new", Some(&entry)).unwrap();
+        assert!(edited.contains(
+            "ctid = 199\n\n[[docker_nodes]]\nname = \"LXC_new\"\nhost = \"10.0.0.5\"\nctid = 120\ntags = [\"role:db\", \"critical\"]\n\n[[profiles]]"
+        ));
+
+        // Remove: the heading comment moves to the next entry
+        let edited = edit_document(CONTENT, section, "LXC_HOME", None).unwrap();
+        assert!(edited.contains("# Docker nodes\n[[docker_nodes]]\nname = \"LXC_old\""));
+        assert!(!edited.contains("Retired"));
+        assert!(edit_document(CONTENT, section, "LXC_gone", None).is_err());
+
+        // A new section is created where needed
+        let entry = form_to_entry(Section::Dashboards, &form(&[("name", "Nodes"), ("uid", "abc"), ("slug", "nodes")])).unwrap();
+        let edited = edit_document(CONTENT, Section::Dashboards, "Nodes", Some(&entry)).unwrap();
+        assert!(edited.contains("[[grafana.dashboards]]\nname = \"Nodes\""));
+
+        assert!(form_to_entry(section, &form(&[("name", "X"), ("host", "h")])).is_err());
+        assert!(form_to_entry(section, &form(&[("name", "X"), ("host", "h"), ("ctid", "abc")])).is_err());
+        assert!(form_to_entry(section, &form(&[("name", "X"), ("host", "h"), ("ctid", "1"), ("transport", "ftp")])).is_err());
+    }
+
+    #[test]
+    fn test_save_entry() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("profiles")).unwrap();
+        let path = dir.path().join("config.toml");
+        let content = minimal_toml(dir.path(), CONTENT)
+            .replace("192.168.8.80", "127.0.0.1")
+            .replace("192.168.8.99", "127.0.0.2");
+        std::fs::write(&path, &content).unwrap();
+
+        let layers = ConfigLayers { files: vec![path.clone()], env: false };
+        let shared = SharedConfig::new(layers.load().unwrap());
+        let pool = SshPool::new(&shared).unwrap();
+        let section = Section::DockerNodes;
+
+        let mut entry = section.entry(&shared.load(), "LXC_old").unwrap();
+        entry.insert("name".to_string(), toml::Value::String("LXC_archive".to_string()));
+        save_entry(&layers, &shared, &pool, section, Some("LXC_old"), &entry).unwrap();
+        assert!(shared.load().get_docker_node("LXC_archive").is_some());
+        assert!(shared.load().get_docker_node("LXC_old").is_none());
+        assert!(std::fs::read_to_string(&path).unwrap().contains("# Retired, kept for the logs"));
+
+        // Same CTID as LXC_HOME: rejected, the file is left alone
+        entry.insert("ctid".to_string(), toml::Value::Integer(100));
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert!(matches!(
+            save_entry(&layers, &shared, &pool, section, Some("LXC_archive"), &entry),
+            Err(AppError::Validation(_))
+        ));
+        assert!(save_entry(&layers, &shared, &pool, section, None, &section.entry(&shared.load(), "LXC_HOME").unwrap()).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), written);
+
+        delete_entry(&layers, &shared, &pool, section, "LXC_archive").unwrap();
+        assert_eq!(shared.load().docker_nodes.len(), 1);
+    }
+}
@@ -0,0 +1,148 @@
+//! Web login credentials
+//!
+//! The `[auth]` password can be given as an argon2 (`$argon2id$...`) or
+//! bcrypt (`$2b$...`) hash in `password_hash`, or in a file named by
+//! `password_file` (plaintext or a hash, first line), so config.toml doesn't
+//! have to hold it. A plaintext `password` still works.
+
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+
+use crate::config::{expand_home, AuthConfig};
+use crate::error::AppError;
+
+/// Kind of a stored password
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecretKind {
+    Argon2,
+    Bcrypt,
+    Plaintext,
+}
+
+impl SecretKind {
+    pub fn of(secret: &str) -> Self {
+        if secret.starts_with("$argon2") {
+            SecretKind::Argon2
+        } else if ["$2a$", "$2b$", "$2x$", "$2y$"].iter().any(|p| secret.starts_with(p)) {
+            SecretKind::Bcrypt
+        } else {
+            SecretKind::Plaintext
+        }
+    }
+}
+
+/// Check a password against a stored hash or plaintext password
+pub fn verify_password(stored: &str, candidate: &str) -> bool {
+    match SecretKind::of(stored) {
+        SecretKind::Argon2 => PasswordHash::new(stored)
+            .map(|hash| Argon2::default().verify_password(candidate.as_bytes(), &hash).is_ok())
+            .unwrap_or(false),
+        SecretKind::Bcrypt => bcrypt::verify(candidate, stored).unwrap_or(false),
+        SecretKind::Plaintext => constant_time_eq(stored.as_bytes(), candidate.as_bytes()),
+    }
+}
+
+/// Check that a hash is well formed
+pub fn check_hash(hash: &str) -> Result<(), String> {
+    match SecretKind::of(hash) {
+        SecretKind::Argon2 => match PasswordHash::new(hash) {
+            Ok(parsed) if parsed.salt.is_some() && parsed.hash.is_some() => Ok(()),
+            Ok(_) => Err("argon2 hash without salt or hash value".to_string()),
+            Err(e) => Err(e.to_string()),
+        },
+        SecretKind::Bcrypt if hash.len() == 60 => Ok(()),
+        SecretKind::Bcrypt => Err("bcrypt hashes are 60 characters".to_string()),
+        SecretKind::Plaintext => Err("not an argon2 or bcrypt hash".to_string()),
+    }
+}
+
+/// Argon2id hash of a password, for `auth.password_hash`
+pub fn hash_password(password: &str) -> Result<String, AppError> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| AppError::Internal(format!("Failed to hash password: {}", e)))
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+impl AuthConfig {
+    /// The stored password: `password_file`, else `password_hash`, else
+    /// `password`
+    pub fn secret(&self) -> Result<String, AppError> {
+        if let Some(ref path) = self.password_file {
+            let content = std::fs::read_to_string(expand_home(path))
+                .map_err(|e| AppError::Config(format!("auth.password_file {}: {}", path, e)))?;
+            let secret = content.lines().next().unwrap_or_default().trim().to_string();
+            if secret.is_empty() {
+                return Err(AppError::Config(format!("auth.password_file {} is empty", path)));
+            }
+            return Ok(secret);
+        }
+        self.password_hash
+            .clone()
+            .or_else(|| self.password.clone())
+            .ok_or_else(|| AppError::Config("[auth] needs password_hash, password_file or password".to_string()))
+    }
+
+    /// Check a login. Fails closed if the stored password can't be read.
+    pub fn verify(&self, username: &str, password: &str) -> bool {
+        if !constant_time_eq(username.as_bytes(), self.username.as_bytes()) {
+            return false;
+        }
+        match self.secret() {
+            Ok(secret) => verify_password(&secret, password),
+            Err(e) => {
+                tracing::error!("Login refused: {}", e);
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_password() {
+        let argon2 = hash_password("hunter2").unwrap();
+        assert_eq!(SecretKind::of(&argon2), SecretKind::Argon2);
+        assert!(verify_password(&argon2, "hunter2"));
+        assert!(!verify_password(&argon2, "hunter3"));
+        assert!(check_hash(&argon2).is_ok());
+
+        let bcrypt = bcrypt::hash("hunter2", 4).unwrap();
+        assert!(verify_password(&bcrypt, "hunter2"));
+        assert!(!verify_password(&bcrypt, "Hunter2"));
+        assert!(check_hash(&bcrypt).is_ok());
+
+        assert!(verify_password("plain", "plain"));
+        assert!(!verify_password("plain", "plai"));
+        assert!(check_hash("$argon2id$broken").is_err());
+    }
+
+    #[test]
+    fn test_password_file() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let path = file.path().to_path_buf();
+        std::fs::write(&path, format!("{}\n", hash_password("from-file").unwrap())).unwrap();
+
+        let auth = AuthConfig {
+            username: "admin".to_string(),
+            password: Some("ignored".to_string()),
+            password_hash: None,
+            password_file: Some(path.display().to_string()),
+        };
+        assert!(auth.verify("admin", "from-file"));
+        assert!(!auth.verify("admin", "ignored"));
+        assert!(!auth.verify("root", "from-file"));
+
+        file.close().unwrap();
+        assert!(!auth.verify("admin", "from-file"));
+    }
+}
@@ -1,20 +1,49 @@
-//! Docker command execution via SSH
+//! Docker command execution on a node (over any `CommandTransport`)
 
-use crate::docker::{ComposeStack, Container, ContainerStatus, NodeSummary};
+use crate::config::DockerNode;
+use crate::docker::stats::ContainerStats;
+use crate::docker::{json, problems, ComposeStack, Container, ContainerDetails, ContainerStatus, NodeSummary};
 use crate::error::AppError;
-use crate::ssh::SshPool;
+use crate::shell::{ContainerName, ProjectName, ShellCommand};
+use crate::ssh::{collect_output, CommandStream};
+use crate::transport::{CommandTransport, Target};
 use std::collections::HashMap;
 
 /// List all containers on a node
+///
+/// Reads `docker ps` as JSON (`--format '{{json .}}'`, the same as
+/// `--format json` on Docker 23+, so names and ports containing `|` can't
+/// break the parse), then completes the entries with one `docker inspect`
+/// for restart policies, image IDs, exact labels and health. If the inspect
+/// fails, the `docker ps` data is returned as it is.
 pub async fn list_containers(
-    ssh_pool: &mut SshPool,
+    transport: &dyn CommandTransport,
     node_name: &str,
 ) -> Result<Vec<Container>, AppError> {
-    // Use docker ps with custom format including compose project label
-    let format = "{{.ID}}|{{.Names}}|{{.Image}}|{{.Status}}|{{.Ports}}|{{.CreatedAt}}|{{.Label \"com.docker.compose.project\"}}";
-    let command = format!("docker ps -a --format '{}'", format);
+    list(transport, node_name, &[]).await
+}
+
+/// List the containers with these IDs, as `list_containers` does; removed
+/// ones are missing from the result
+pub async fn list_containers_by_id(
+    transport: &dyn CommandTransport,
+    node_name: &str,
+    ids: &[String],
+) -> Result<Vec<Container>, AppError> {
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    list(transport, node_name, ids).await
+}
+
+async fn list(transport: &dyn CommandTransport, node_name: &str, ids: &[String]) -> Result<Vec<Container>, AppError> {
+    let command = ShellCommand::new("docker")
+        .args(["ps", "-a", "--no-trunc"])
+        .args(ids.iter().flat_map(|id| ["--filter".to_string(), format!("id={}", id)]))
+        .args(["--format", "{{json .}}"])
+        .build();
 
-    let output = ssh_pool.execute(node_name, &command).await?;
+    let output = transport.execute_read(Target::Node(node_name), &command).await?;
 
     if !output.success() {
         return Err(AppError::Docker(format!(
@@ -23,42 +52,98 @@ pub async fn list_containers(
         )));
     }
 
-    let containers = output
+    let mut containers = output
         .stdout
         .lines()
         .filter(|line| !line.trim().is_empty())
-        .map(|line| parse_container_line(line))
+        .map(json::parse_ps_line)
         .collect::<Result<Vec<_>, _>>()?;
 
+    if containers.is_empty() {
+        return Ok(containers);
+    }
+
+    let command = ShellCommand::new("docker")
+        .arg("inspect")
+        .args(containers.iter().map(|c| c.id.as_str()))
+        .build();
+    let details = transport
+        .execute_read(Target::Node(node_name), &command)
+        .await
+        .and_then(|output| {
+            if output.success() {
+                json::parse_inspect(&output.stdout)
+            } else {
+                Err(AppError::Docker(output.stderr))
+            }
+        });
+    match details {
+        Ok(details) => json::merge_details(&mut containers, details),
+        Err(e) => tracing::warn!("docker inspect failed on {}, using docker ps only: {}", node_name, e),
+    }
+
     Ok(containers)
 }
 
-/// Parse a single container line from docker ps output
-fn parse_container_line(line: &str) -> Result<Container, AppError> {
-    let parts: Vec<&str> = line.split('|').collect();
+/// Full `docker inspect` view of one container, with the registry digests
+/// of its image
+pub async fn inspect_container(
+    transport: &dyn CommandTransport,
+    node_name: &str,
+    container: &str,
+) -> Result<ContainerDetails, AppError> {
+    let container = ContainerName::parse(container)?;
+    let command = ShellCommand::new("docker").arg("inspect").arg(&container).build();
+    let output = transport.execute_read(Target::Node(node_name), &command).await?;
+
+    if !output.success() {
+        return Err(AppError::Docker(format!(
+            "Failed to inspect container: {}",
+            output.stderr
+        )));
+    }
+
+    let mut details = json::parse_inspect(&output.stdout)?
+        .pop()
+        .ok_or_else(|| AppError::Docker(format!("No such container: {}", container)))?;
+
+    // Locally built images have no digests; that's not an error
+    let command = ShellCommand::new("docker")
+        .args(["image", "inspect", "--format", "{{json .RepoDigests}}"])
+        .arg(&details.image_id)
+        .build();
+    match transport.execute_read(Target::Node(node_name), &command).await {
+        Ok(output) if output.success() => details.repo_digests = json::parse_repo_digests(&output.stdout),
+        Ok(output) => tracing::debug!("No digests for {}: {}", details.image_id, output.stderr.trim()),
+        Err(e) => tracing::debug!("No digests for {}: {}", details.image_id, e),
+    }
+
+    Ok(details)
+}
+
+/// One `docker stats --no-stream` sample of the running containers
+pub async fn container_stats(
+    transport: &dyn CommandTransport,
+    node_name: &str,
+) -> Result<Vec<ContainerStats>, AppError> {
+    let command = ShellCommand::new("docker")
+        .args(["stats", "--no-stream", "--no-trunc", "--format", "{{json .}}"])
+        .build();
+    let output = transport.execute_read(Target::Node(node_name), &command).await?;
 
-    if parts.len() < 6 {
+    if !output.success() {
         return Err(AppError::Docker(format!(
-            "Invalid container line format: {}",
-            line
+            "Failed to read container stats: {}",
+            output.stderr
         )));
     }
 
-    let project = if parts.len() > 6 && !parts[6].is_empty() {
-        Some(parts[6].to_string())
-    } else {
-        None
-    };
-
-    Ok(Container {
-        id: parts[0].to_string(),
-        name: parts[1].to_string(),
-        image: parts[2].to_string(),
-        status: ContainerStatus::from_str(parts[3]),
-        ports: parts[4].to_string(),
-        created: parts[5].to_string(),
-        project,
-    })
+    output
+        .stdout
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(json::parse_stats_line)
+        .collect()
 }
 
 /// Group containers by compose project/stack
@@ -97,12 +182,13 @@ pub fn group_by_stack(containers: Vec<Container>) -> Vec<ComposeStack> {
 
 /// Start a container
 pub async fn start_container(
-    ssh_pool: &mut SshPool,
+    transport: &dyn CommandTransport,
     node_name: &str,
     container: &str,
 ) -> Result<(), AppError> {
-    let command = format!("docker start {}", container);
-    let output = ssh_pool.execute(node_name, &command).await?;
+    let container = ContainerName::parse(container)?;
+    let command = ShellCommand::new("docker").arg("start").arg(&container).build();
+    let output = transport.execute(Target::Node(node_name), &command).await?;
 
     if !output.success() {
         return Err(AppError::Docker(format!(
@@ -117,12 +203,13 @@ pub async fn start_container(
 
 /// Stop a container
 pub async fn stop_container(
-    ssh_pool: &mut SshPool,
+    transport: &dyn CommandTransport,
     node_name: &str,
     container: &str,
 ) -> Result<(), AppError> {
-    let command = format!("docker stop {}", container);
-    let output = ssh_pool.execute(node_name, &command).await?;
+    let container = ContainerName::parse(container)?;
+    let command = ShellCommand::new("docker").arg("stop").arg(&container).build();
+    let output = transport.execute(Target::Node(node_name), &command).await?;
 
     if !output.success() {
         return Err(AppError::Docker(format!(
@@ -137,12 +224,13 @@ pub async fn stop_container(
 
 /// Restart a container
 pub async fn restart_container(
-    ssh_pool: &mut SshPool,
+    transport: &dyn CommandTransport,
     node_name: &str,
     container: &str,
 ) -> Result<(), AppError> {
-    let command = format!("docker restart {}", container);
-    let output = ssh_pool.execute(node_name, &command).await?;
+    let container = ContainerName::parse(container)?;
+    let command = ShellCommand::new("docker").arg("restart").arg(&container).build();
+    let output = transport.execute(Target::Node(node_name), &command).await?;
 
     if !output.success() {
         return Err(AppError::Docker(format!(
@@ -157,13 +245,17 @@ pub async fn restart_container(
 
 /// Get container logs (last N lines)
 pub async fn get_container_logs(
-    ssh_pool: &mut SshPool,
+    transport: &dyn CommandTransport,
     node_name: &str,
     container: &str,
     tail: u32,
 ) -> Result<String, AppError> {
-    let command = format!("docker logs --tail {} {}", tail, container);
-    let output = ssh_pool.execute(node_name, &command).await?;
+    let container = ContainerName::parse(container)?;
+    let command = ShellCommand::new("docker")
+        .args(["logs", "--tail", &tail.to_string()])
+        .arg(&container)
+        .build();
+    let output = transport.execute_read(Target::Node(node_name), &command).await?;
 
     // Docker logs output goes to stderr for log output
     Ok(output.combined())
@@ -171,23 +263,30 @@ pub async fn get_container_logs(
 
 /// Get node summary (container counts)
 pub async fn get_node_summary(
-    ssh_pool: &mut SshPool,
+    transport: &dyn CommandTransport,
     node_name: &str,
     host: &str,
 ) -> NodeSummary {
-    match list_containers(ssh_pool, node_name).await {
+    match list_containers(transport, node_name).await {
         Ok(containers) => {
             let running = containers
                 .iter()
                 .filter(|c| c.status == ContainerStatus::Running)
                 .count();
+            let now = chrono::Utc::now();
+            let problems = containers
+                .iter()
+                .filter(|c| !problems::container_problems(c, now).is_empty())
+                .count();
 
             NodeSummary {
                 name: node_name.to_string(),
                 host: host.to_string(),
+                address: transport.address(Target::Node(node_name)),
                 total: containers.len(),
                 running,
                 stopped: containers.len() - running,
+                problems,
                 online: true,
             }
         }
@@ -196,74 +295,121 @@ pub async fn get_node_summary(
             NodeSummary {
                 name: node_name.to_string(),
                 host: host.to_string(),
+                address: None,
                 total: 0,
                 running: 0,
                 stopped: 0,
+                problems: 0,
                 online: false,
             }
         }
     }
 }
 
+/// Summaries for the given docker nodes, queried concurrently
+pub async fn get_all_node_summaries(transport: &dyn CommandTransport, nodes: &[DockerNode]) -> Vec<NodeSummary> {
+    futures::future::join_all(
+        nodes
+            .iter()
+            .map(|node| get_node_summary(transport, &node.name, &node.host)),
+    )
+    .await
+}
+
 /// Check if a container exists on a node
 #[allow(dead_code)]
 pub async fn container_exists(
-    ssh_pool: &mut SshPool,
+    transport: &dyn CommandTransport,
     node_name: &str,
     container: &str,
 ) -> Result<bool, AppError> {
-    let containers = list_containers(ssh_pool, node_name).await?;
+    let containers = list_containers(transport, node_name).await?;
     Ok(containers.iter().any(|c| c.name == container || c.id == container))
 }
 
 /// Pull latest image for a container
 pub async fn pull_container(
-    ssh_pool: &mut SshPool,
+    transport: &dyn CommandTransport,
     node_name: &str,
     container: &str,
 ) -> Result<String, AppError> {
-    // First get the image name from the container
-    let inspect_cmd = format!("docker inspect --format '{{{{.Config.Image}}}}' {}", container);
-    let inspect_output = ssh_pool.execute(node_name, &inspect_cmd).await?;
+    let stream = pull_container_stream(transport, node_name, container).await?;
+    let output = collect_output(stream).await;
 
-    if !inspect_output.success() {
+    if !output.success() {
         return Err(AppError::Docker(format!(
-            "Failed to get image for container: {}",
-            inspect_output.stderr
+            "Failed to pull image: {}",
+            output.stderr
         )));
     }
 
-    let image = inspect_output.stdout.trim();
-    let command = format!("docker pull {}", image);
-    let output = ssh_pool.execute(node_name, &command).await?;
+    tracing::info!("Pulled image for container {} on {}", container, node_name);
+    Ok(output.combined())
+}
 
-    if !output.success() {
+/// Pull latest image for a container, streaming `docker pull` progress
+pub async fn pull_container_stream(
+    transport: &dyn CommandTransport,
+    node_name: &str,
+    container: &str,
+) -> Result<CommandStream, AppError> {
+    let container = ContainerName::parse(container)?;
+
+    // First get the image name from the container
+    let inspect_cmd = ShellCommand::new("docker")
+        .args(["inspect", "--format", "{{.Config.Image}}"])
+        .arg(&container)
+        .build();
+    let inspect_output = transport.execute_read(Target::Node(node_name), &inspect_cmd).await?;
+
+    if !inspect_output.success() {
         return Err(AppError::Docker(format!(
-            "Failed to pull image: {}",
-            output.stderr
+            "Failed to get image for container: {}",
+            inspect_output.stderr
         )));
     }
 
-    tracing::info!("Pulled image {} for container {} on {}", image, container, node_name);
-    Ok(output.combined())
+    let image = inspect_output.stdout.trim();
+    let command = ShellCommand::new("docker").arg("pull").arg(image).build();
+    tracing::info!("Pulling image {} for container {} on {}", image, container, node_name);
+    transport.execute_streaming(Target::Node(node_name), &command).await
 }
 
 /// Recreate a container (pull + stop + rm + up)
 /// This assumes docker-compose is used
 pub async fn recreate_container(
-    ssh_pool: &mut SshPool,
+    transport: &dyn CommandTransport,
     node_name: &str,
     container: &str,
 ) -> Result<String, AppError> {
-    // Try docker-compose first, fall back to docker commands
-    let command = format!(
-        "docker-compose pull {} && docker-compose up -d --force-recreate {} 2>&1 || \
-         (docker pull $(docker inspect --format '{{{{.Config.Image}}}}' {}) && \
-          docker stop {} && docker rm {} && docker start {})",
-        container, container, container, container, container, container
-    );
+    let container = ContainerName::parse(container)?;
 
-    let output = ssh_pool.execute(node_name, &command).await?;
+    // Try docker-compose first, fall back to docker commands
+    let docker = |verb: &'static str| ShellCommand::new("docker").arg(verb).arg(&container);
+    let fallback = ShellCommand::new("docker")
+        .arg("pull")
+        .output_of(
+            ShellCommand::new("docker")
+                .args(["inspect", "--format", "{{.Config.Image}}"])
+                .arg(&container),
+        )
+        .and(docker("stop"))
+        .and(docker("rm"))
+        .and(docker("start"))
+        .group();
+    let command = ShellCommand::new("docker-compose")
+        .arg("pull")
+        .arg(&container)
+        .and(
+            ShellCommand::new("docker-compose")
+                .args(["up", "-d", "--force-recreate"])
+                .arg(&container)
+                .merge_stderr(),
+        )
+        .or(fallback)
+        .build();
+
+    let output = transport.execute(Target::Node(node_name), &command).await?;
 
     tracing::info!("Recreated container {} on {}", container, node_name);
     Ok(output.combined())
@@ -275,11 +421,11 @@ pub async fn recreate_container(
 
 /// Prune unused Docker resources
 pub async fn system_prune(
-    ssh_pool: &mut SshPool,
+    transport: &dyn CommandTransport,
     node_name: &str,
 ) -> Result<String, AppError> {
     let command = "docker system prune -f 2>&1";
-    let output = ssh_pool.execute(node_name, command).await?;
+    let output = transport.execute(Target::Node(node_name), command).await?;
 
     tracing::info!("System prune on {}", node_name);
     Ok(output.combined())
@@ -287,11 +433,11 @@ pub async fn system_prune(
 
 /// Remove unused volumes
 pub async fn volume_prune(
-    ssh_pool: &mut SshPool,
+    transport: &dyn CommandTransport,
     node_name: &str,
 ) -> Result<String, AppError> {
     let command = "docker volume prune -f 2>&1";
-    let output = ssh_pool.execute(node_name, command).await?;
+    let output = transport.execute(Target::Node(node_name), command).await?;
 
     tracing::info!("Volume prune on {}", node_name);
     Ok(output.combined())
@@ -299,11 +445,11 @@ pub async fn volume_prune(
 
 /// Remove unused images
 pub async fn image_prune(
-    ssh_pool: &mut SshPool,
+    transport: &dyn CommandTransport,
     node_name: &str,
 ) -> Result<String, AppError> {
     let command = "docker image prune -af 2>&1";
-    let output = ssh_pool.execute(node_name, command).await?;
+    let output = transport.execute(Target::Node(node_name), command).await?;
 
     tracing::info!("Image prune on {}", node_name);
     Ok(output.combined())
@@ -311,11 +457,11 @@ pub async fn image_prune(
 
 /// Get disk usage stats
 pub async fn disk_usage(
-    ssh_pool: &mut SshPool,
+    transport: &dyn CommandTransport,
     node_name: &str,
 ) -> Result<String, AppError> {
     let command = "docker system df 2>&1";
-    let output = ssh_pool.execute(node_name, command).await?;
+    let output = transport.execute_read(Target::Node(node_name), command).await?;
 
     Ok(output.combined())
 }
@@ -325,20 +471,31 @@ pub async fn disk_usage(
 // ============================================================================
 
 /// Find the docker-compose directory for a project
-async fn find_compose_dir(
-    ssh_pool: &mut SshPool,
+pub(crate) async fn find_compose_dir(
+    transport: &dyn CommandTransport,
     node_name: &str,
     project: &str,
 ) -> Result<String, AppError> {
-    // Get the working dir from a running container in this project
-    let command = format!(
-        "docker inspect --format '{{{{index .Config.Labels \"com.docker.compose.project.working_dir\"}}}}' \
-         $(docker ps -q --filter 'label=com.docker.compose.project={}' | head -1) 2>/dev/null || \
-         echo ''",
-        project
-    );
+    let project = ProjectName::parse(project)?;
 
-    let output = ssh_pool.execute(node_name, &command).await?;
+    // Get the working dir from a running container in this project
+    let command = ShellCommand::new("docker")
+        .args([
+            "inspect",
+            "--format",
+            "{{index .Config.Labels \"com.docker.compose.project.working_dir\"}}",
+        ])
+        .output_of(
+            ShellCommand::new("docker")
+                .args(["ps", "-q", "--filter"])
+                .arg(format!("label=com.docker.compose.project={}", project))
+                .pipe(ShellCommand::new("head").arg("-1")),
+        )
+        .raw("2>/dev/null")
+        .or(ShellCommand::new("echo").arg(""))
+        .build();
+
+    let output = transport.execute_read(Target::Node(node_name), &command).await?;
     let dir = output.stdout.trim().to_string();
 
     if dir.is_empty() {
@@ -351,8 +508,12 @@ async fn find_compose_dir(
         ];
 
         for path in &common_paths {
-            let check_cmd = format!("test -f {}/docker-compose.yml && echo '{}'", path, path);
-            let check = ssh_pool.execute(node_name, &check_cmd).await?;
+            let check_cmd = ShellCommand::new("test")
+                .arg("-f")
+                .path(format!("{}/docker-compose.yml", path))
+                .and(ShellCommand::new("echo").path(path))
+                .build();
+            let check = transport.execute_read(Target::Node(node_name), &check_cmd).await?;
             if !check.stdout.trim().is_empty() {
                 return Ok(check.stdout.trim().to_string());
             }
@@ -367,16 +528,21 @@ async fn find_compose_dir(
     Ok(dir)
 }
 
+/// Run compose commands in a stack's directory
+fn compose_in(dir: &str, command: ShellCommand) -> String {
+    ShellCommand::new("cd").path(dir).and(command).build()
+}
+
 /// Start a compose stack
 pub async fn stack_up(
-    ssh_pool: &mut SshPool,
+    transport: &dyn CommandTransport,
     node_name: &str,
     project: &str,
 ) -> Result<String, AppError> {
-    let dir = find_compose_dir(ssh_pool, node_name, project).await?;
+    let dir = find_compose_dir(transport, node_name, project).await?;
 
-    let command = format!("cd {} && docker-compose up -d 2>&1", dir);
-    let output = ssh_pool.execute(node_name, &command).await?;
+    let command = compose_in(&dir, ShellCommand::new("docker-compose").args(["up", "-d"]).merge_stderr());
+    let output = transport.execute(Target::Node(node_name), &command).await?;
 
     tracing::info!("Started stack {} on {}", project, node_name);
     Ok(output.combined())
@@ -384,14 +550,14 @@ pub async fn stack_up(
 
 /// Stop a compose stack (containers remain, can restart)
 pub async fn stack_stop(
-    ssh_pool: &mut SshPool,
+    transport: &dyn CommandTransport,
     node_name: &str,
     project: &str,
 ) -> Result<String, AppError> {
-    let dir = find_compose_dir(ssh_pool, node_name, project).await?;
+    let dir = find_compose_dir(transport, node_name, project).await?;
 
-    let command = format!("cd {} && docker-compose stop 2>&1", dir);
-    let output = ssh_pool.execute(node_name, &command).await?;
+    let command = compose_in(&dir, ShellCommand::new("docker-compose").arg("stop").merge_stderr());
+    let output = transport.execute(Target::Node(node_name), &command).await?;
 
     tracing::info!("Stopped stack {} on {}", project, node_name);
     Ok(output.combined())
@@ -399,14 +565,14 @@ pub async fn stack_stop(
 
 /// Remove a compose stack (containers are removed)
 pub async fn stack_down(
-    ssh_pool: &mut SshPool,
+    transport: &dyn CommandTransport,
     node_name: &str,
     project: &str,
 ) -> Result<String, AppError> {
-    let dir = find_compose_dir(ssh_pool, node_name, project).await?;
+    let dir = find_compose_dir(transport, node_name, project).await?;
 
-    let command = format!("cd {} && docker-compose down 2>&1", dir);
-    let output = ssh_pool.execute(node_name, &command).await?;
+    let command = compose_in(&dir, ShellCommand::new("docker-compose").arg("down").merge_stderr());
+    let output = transport.execute(Target::Node(node_name), &command).await?;
 
     tracing::info!("Removed stack {} on {}", project, node_name);
     Ok(output.combined())
@@ -414,14 +580,14 @@ pub async fn stack_down(
 
 /// Pull latest images for a compose stack
 pub async fn stack_pull(
-    ssh_pool: &mut SshPool,
+    transport: &dyn CommandTransport,
     node_name: &str,
     project: &str,
 ) -> Result<String, AppError> {
-    let dir = find_compose_dir(ssh_pool, node_name, project).await?;
+    let dir = find_compose_dir(transport, node_name, project).await?;
 
-    let command = format!("cd {} && docker-compose pull 2>&1", dir);
-    let output = ssh_pool.execute(node_name, &command).await?;
+    let command = compose_in(&dir, ShellCommand::new("docker-compose").arg("pull").merge_stderr());
+    let output = transport.execute(Target::Node(node_name), &command).await?;
 
     tracing::info!("Pulled images for stack {} on {}", project, node_name);
     Ok(output.combined())
@@ -429,32 +595,49 @@ pub async fn stack_pull(
 
 /// Rebuild a compose stack (pull + up --build)
 pub async fn stack_rebuild(
-    ssh_pool: &mut SshPool,
+    transport: &dyn CommandTransport,
     node_name: &str,
     project: &str,
 ) -> Result<String, AppError> {
-    let dir = find_compose_dir(ssh_pool, node_name, project).await?;
-
-    let command = format!(
-        "cd {} && docker-compose pull && docker-compose up -d --build --force-recreate 2>&1",
-        dir
-    );
-    let output = ssh_pool.execute(node_name, &command).await?;
+    let stream = stack_rebuild_stream(transport, node_name, project).await?;
+    let output = collect_output(stream).await;
 
     tracing::info!("Rebuilt stack {} on {}", project, node_name);
     Ok(output.combined())
 }
 
+/// Rebuild a compose stack, streaming pull and build output
+pub async fn stack_rebuild_stream(
+    transport: &dyn CommandTransport,
+    node_name: &str,
+    project: &str,
+) -> Result<CommandStream, AppError> {
+    let dir = find_compose_dir(transport, node_name, project).await?;
+
+    let command = compose_in(
+        &dir,
+        ShellCommand::new("docker-compose")
+            .arg("pull")
+            .merge_stderr()
+            .and(
+                ShellCommand::new("docker-compose")
+                    .args(["up", "-d", "--build", "--force-recreate"])
+                    .merge_stderr(),
+            ),
+    );
+    transport.execute_streaming(Target::Node(node_name), &command).await
+}
+
 /// Restart a compose stack
 pub async fn stack_restart(
-    ssh_pool: &mut SshPool,
+    transport: &dyn CommandTransport,
     node_name: &str,
     project: &str,
 ) -> Result<String, AppError> {
-    let dir = find_compose_dir(ssh_pool, node_name, project).await?;
+    let dir = find_compose_dir(transport, node_name, project).await?;
 
-    let command = format!("cd {} && docker-compose restart 2>&1", dir);
-    let output = ssh_pool.execute(node_name, &command).await?;
+    let command = compose_in(&dir, ShellCommand::new("docker-compose").arg("restart").merge_stderr());
+    let output = transport.execute(Target::Node(node_name), &command).await?;
 
     tracing::info!("Restarted stack {} on {}", project, node_name);
     Ok(output.combined())
@@ -462,15 +645,86 @@ pub async fn stack_restart(
 
 /// Get logs for a compose stack
 pub async fn stack_logs(
-    ssh_pool: &mut SshPool,
+    transport: &dyn CommandTransport,
     node_name: &str,
     project: &str,
     tail: u32,
 ) -> Result<String, AppError> {
-    let dir = find_compose_dir(ssh_pool, node_name, project).await?;
+    let dir = find_compose_dir(transport, node_name, project).await?;
 
-    let command = format!("cd {} && docker-compose logs --tail {} 2>&1", dir, tail);
-    let output = ssh_pool.execute(node_name, &command).await?;
+    let command = compose_in(
+        &dir,
+        ShellCommand::new("docker-compose")
+            .args(["logs", "--tail", &tail.to_string()])
+            .merge_stderr(),
+    );
+    let output = transport.execute_read(Target::Node(node_name), &command).await?;
 
     Ok(output.combined())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::docker::HealthStatus;
+    use crate::transport::FixtureTransport;
+
+    #[tokio::test]
+    async fn test_list_containers_from_fixture() {
+        let transport = FixtureTransport::from_toml(include_str!("../../fixtures/homelab.toml")).unwrap();
+        let containers = list_containers(&transport, "LXC_HOME").await.unwrap();
+        assert_eq!(containers.len(), 3);
+        assert_eq!(containers[0].name, "homelab-nextcloud-1");
+        assert_eq!(containers[0].status, ContainerStatus::Running);
+        assert_eq!(containers[2].project, None);
+        assert_eq!(containers[2].status, ContainerStatus::Exited);
+
+        // docker ps fields
+        let nextcloud = &containers[0];
+        assert_eq!(nextcloud.ports.len(), 2);
+        assert_eq!(nextcloud.published_ports().len(), 1);
+        assert_eq!(nextcloud.published_ports()[0].to_string(), "0.0.0.0:8080->80/tcp");
+        assert_eq!(nextcloud.created.unwrap().to_rfc3339(), "2025-01-10T09:12:44+00:00");
+        assert_eq!(nextcloud.mounts, ["homelab_nextcloud", "/srv/nextcloud/config"]);
+        assert_eq!(nextcloud.networks, ["homelab_default"]);
+
+        // docker inspect fields, labels exactly as set
+        assert_eq!(
+            nextcloud.labels["traefik.http.routers.cloud.rule"],
+            "Host(`cloud.home`) || Host(`nc.home`)"
+        );
+        assert_eq!(nextcloud.restart_policy.as_ref().unwrap().to_string(), "unless-stopped");
+        assert!(nextcloud.image_id.as_deref().unwrap().starts_with("sha256:"));
+        assert_eq!(nextcloud.health, None);
+        assert_eq!(containers[1].health, Some(HealthStatus::Healthy));
+        assert_eq!(containers[1].restart_policy.as_ref().unwrap().to_string(), "on-failure:5");
+
+        let stacks = group_by_stack(containers);
+        assert_eq!(stacks[0].name, "homelab");
+        assert_eq!(stacks[0].running_count, 2);
+        assert_eq!(stacks[1].name, "standalone");
+
+        // Other nodes have no recorded output
+        assert!(list_containers(&transport, "LXC_plane").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_inspect_container_from_fixture() {
+        let transport = FixtureTransport::from_toml(include_str!("../../fixtures/homelab.toml")).unwrap();
+        let details = inspect_container(&transport, "LXC_HOME", "homelab-db-1").await.unwrap();
+        assert_eq!(details.name, "homelab-db-1");
+        assert_eq!(details.status, ContainerStatus::Running);
+        assert_eq!(details.finished_at, None);
+        assert_eq!(details.restart_count, 1);
+        assert_eq!(details.health.as_ref().unwrap().log[0].output, "mysqld is alive");
+        assert_eq!(details.command, ["docker-entrypoint.sh", "mariadbd"]);
+        // Only variable names are kept
+        assert_eq!(details.env, ["MARIADB_ROOT_PASSWORD"]);
+        assert_eq!(details.ports[0].to_string(), "3306/tcp");
+        assert_eq!(details.mounts[0].name.as_deref(), Some("homelab_db"));
+        assert_eq!(details.networks[0].aliases, ["homelab-db-1", "db"]);
+        assert_eq!(details.repo_digests.len(), 1);
+
+        assert!(inspect_container(&transport, "LXC_HOME", "db; reboot").await.is_err());
+    }
+}
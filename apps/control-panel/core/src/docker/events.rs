@@ -0,0 +1,416 @@
+//! Container inventory kept current by `docker events`
+//!
+//! Every docker node gets a long-lived `docker events` stream. The node's
+//! containers are listed once the stream is up, then only the containers an
+//! event names are listed again (`docker ps --filter id=`). A full resync
+//! runs every `events.resync_secs` and after every reconnect, so changes
+//! missed while a stream was down are caught up. Subscribers get an
+//! `InventoryChange` for every update.
+//!
+//! The cache only answers while a node's stream is up; otherwise callers
+//! list the containers themselves, as they did before.
+
+use crate::config::SharedConfig;
+use crate::docker::commands::{list_containers, list_containers_by_id};
+use crate::docker::json::parse_event_line;
+use crate::docker::logs::until_closed;
+use crate::docker::Container;
+use crate::error::AppError;
+use crate::shell::ShellCommand;
+use crate::ssh::{CommandStream, OutputChunk};
+use crate::transport::{CommandTransport, Target};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::StreamExt;
+
+/// Events arriving this soon after another are handled together
+const DEBOUNCE: Duration = Duration::from_millis(300);
+/// First and longest wait before reconnecting a dropped stream
+const RETRY_MIN: Duration = Duration::from_secs(5);
+const RETRY_MAX: Duration = Duration::from_secs(120);
+/// How often the watched nodes are compared with the configuration
+const SUPERVISE_INTERVAL: Duration = Duration::from_secs(10);
+/// Shortest time between two full resyncs
+const MIN_RESYNC_SECS: u64 = 30;
+
+/// A container event of `docker events`
+#[derive(Debug, Clone)]
+pub struct DockerEvent {
+    /// Full container ID
+    pub id: String,
+    pub name: Option<String>,
+    /// `start`, `die`, `health_status: healthy`, ...
+    pub action: String,
+    pub time: Option<DateTime<Utc>>,
+}
+
+impl DockerEvent {
+    /// Whether the event can change what a container list shows (`exec_*`,
+    /// `attach` and the like don't)
+    pub fn changes_state(&self) -> bool {
+        !self.action.starts_with("exec_")
+            && !matches!(
+                self.action.as_str(),
+                "attach" | "detach" | "resize" | "top" | "export" | "commit" | "copy" | "archive-path" | "extract-to-dir"
+            )
+    }
+}
+
+/// Sent to subscribers when a node's cached containers change
+#[derive(Debug, Clone, Serialize)]
+pub struct InventoryChange {
+    pub node: String,
+    /// Names of the changed containers; empty after a full resync
+    pub containers: Vec<String>,
+    /// The node's event stream is up (and the cache answers)
+    pub live: bool,
+}
+
+/// Cached containers of a node
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct NodeInventory {
+    pub containers: Vec<Container>,
+    /// Time of the last full resync
+    pub synced: Option<DateTime<Utc>>,
+    /// The event stream is up
+    pub live: bool,
+    /// Why the stream is down
+    pub error: Option<String>,
+}
+
+/// Container lists of all docker nodes, shared by the watchers and views
+#[derive(Clone)]
+pub struct ContainerInventory {
+    nodes: Arc<RwLock<HashMap<String, NodeInventory>>>,
+    changes: broadcast::Sender<InventoryChange>,
+}
+
+impl Default for ContainerInventory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ContainerInventory {
+    pub fn new() -> Self {
+        Self {
+            nodes: Arc::default(),
+            changes: broadcast::channel(256).0,
+        }
+    }
+
+    pub fn node(&self, node: &str) -> Option<NodeInventory> {
+        self.nodes.read().ok()?.get(node).cloned()
+    }
+
+    /// A node's containers, while its event stream is up
+    pub fn containers(&self, node: &str) -> Option<Vec<Container>> {
+        self.node(node).filter(|n| n.live).map(|n| n.containers)
+    }
+
+    /// Changes of one node (or all), starting with the current state of
+    /// each. A subscriber that falls behind gets the current state again.
+    /// The stream ends when it is dropped.
+    pub fn changes(&self, node: Option<&str>) -> ReceiverStream<InventoryChange> {
+        let mut rx = self.changes.subscribe();
+        let (tx, out) = mpsc::channel(16);
+        let inventory = self.clone();
+        let node = node.map(str::to_string);
+
+        tokio::spawn(async move {
+            let mut pending = inventory.current(node.as_deref());
+            loop {
+                for change in pending.drain(..) {
+                    if node.as_ref().is_some_and(|n| *n != change.node) {
+                        continue;
+                    }
+                    if tx.send(change).await.is_err() {
+                        return;
+                    }
+                }
+                let change = tokio::select! {
+                    _ = tx.closed() => return,
+                    change = rx.recv() => change,
+                };
+                match change {
+                    Ok(change) => pending.push(change),
+                    Err(broadcast::error::RecvError::Lagged(_)) => pending = inventory.current(node.as_deref()),
+                    Err(broadcast::error::RecvError::Closed) => return,
+                }
+            }
+        });
+
+        ReceiverStream::new(out)
+    }
+
+    /// The state of the nodes as changes (a node without a watcher is not live)
+    fn current(&self, node: Option<&str>) -> Vec<InventoryChange> {
+        let Ok(nodes) = self.nodes.read() else { return Vec::new() };
+        let change = |name: &str, inventory: Option<&NodeInventory>| InventoryChange {
+            node: name.to_string(),
+            containers: Vec::new(),
+            live: inventory.is_some_and(|i| i.live),
+        };
+        match node {
+            Some(name) => vec![change(name, nodes.get(name))],
+            None => nodes.iter().map(|(name, i)| change(name, Some(i))).collect(),
+        }
+    }
+
+    fn notify(&self, node: &str, containers: Vec<String>, live: bool) {
+        // No subscribers is fine
+        let _ = self.changes.send(InventoryChange {
+            node: node.to_string(),
+            containers,
+            live,
+        });
+    }
+
+    /// Replace a node's containers after a full listing; the stream is up
+    fn resync(&self, node: &str, containers: Vec<Container>) {
+        if let Ok(mut nodes) = self.nodes.write() {
+            let inventory = nodes.entry(node.to_string()).or_default();
+            inventory.containers = containers;
+            inventory.synced = Some(Utc::now());
+            inventory.live = true;
+            inventory.error = None;
+        }
+        self.notify(node, Vec::new(), true);
+    }
+
+    /// Put the listed containers in place of those with `ids`; those not
+    /// listed were removed
+    fn update(&self, node: &str, ids: &BTreeSet<String>, listed: Vec<Container>) {
+        let mut changed = Vec::new();
+        if let Ok(mut nodes) = self.nodes.write() {
+            let containers = &mut nodes.entry(node.to_string()).or_default().containers;
+            containers.retain(|c| {
+                let removed = ids.contains(&c.id) && !listed.iter().any(|l| l.id == c.id);
+                if removed {
+                    changed.push(c.name.clone());
+                }
+                !removed
+            });
+            for container in listed {
+                changed.push(container.name.clone());
+                match containers.iter_mut().find(|c| c.id == container.id) {
+                    Some(cached) => *cached = container,
+                    // `docker ps` lists the newest first
+                    None => containers.insert(0, container),
+                }
+            }
+        }
+        self.notify(node, changed, true);
+    }
+
+    /// The node's stream dropped (or couldn't start)
+    fn disconnected(&self, node: &str, error: String) {
+        let was_live = match self.nodes.write() {
+            Ok(mut nodes) => {
+                let inventory = nodes.entry(node.to_string()).or_default();
+                inventory.error = Some(error);
+                std::mem::replace(&mut inventory.live, false)
+            }
+            Err(_) => false,
+        };
+        if was_live {
+            self.notify(node, Vec::new(), false);
+        }
+    }
+
+    /// Forget the nodes `keep` returns false for
+    fn retain(&self, keep: impl Fn(&str) -> bool) {
+        if let Ok(mut nodes) = self.nodes.write() {
+            nodes.retain(|name, _| keep(name));
+        }
+    }
+}
+
+/// Start `docker events` for containers, ended by dropping the stream
+pub async fn watch_events(transport: &dyn CommandTransport, node_name: &str) -> Result<CommandStream, AppError> {
+    let follower = ShellCommand::new("docker").args(["events", "--filter", "type=container", "--format", "{{json .}}"]);
+    transport
+        .execute_streaming(Target::Node(node_name), &until_closed(follower))
+        .await
+}
+
+/// Keep a node's inventory current until its stream ends; returns why it did
+async fn follow_node(
+    inventory: &ContainerInventory,
+    transport: &dyn CommandTransport,
+    node: &str,
+    resync: Duration,
+) -> String {
+    // Subscribe first, so nothing falls between the listing and the stream
+    let mut stream = match watch_events(transport, node).await {
+        Ok(stream) => stream,
+        Err(e) => return e.to_string(),
+    };
+    match list_containers(transport, node).await {
+        Ok(containers) => inventory.resync(node, containers),
+        Err(e) => return e.to_string(),
+    }
+    tracing::info!("Following docker events on {}", node);
+
+    let mut next_resync = Instant::now() + resync;
+    let mut stderr = String::new();
+    loop {
+        let chunk = tokio::select! {
+            chunk = stream.next() => chunk,
+            _ = tokio::time::sleep_until(next_resync) => {
+                match list_containers(transport, node).await {
+                    Ok(containers) => inventory.resync(node, containers),
+                    Err(e) => return e.to_string(),
+                }
+                next_resync = Instant::now() + resync;
+                continue;
+            }
+        };
+
+        // Collect the burst of events (a stack going down is several)
+        let mut ids = BTreeSet::new();
+        let mut ended = None;
+        let mut next = chunk;
+        loop {
+            match next {
+                Some(OutputChunk::Stdout(text)) => {
+                    for line in text.lines().filter(|l| !l.trim().is_empty()) {
+                        match parse_event_line(line) {
+                            Ok(Some(event)) if event.changes_state() => {
+                                ids.insert(event.id);
+                            }
+                            Ok(_) => {}
+                            Err(e) => tracing::debug!("{} on {}", e, node),
+                        }
+                    }
+                }
+                Some(OutputChunk::Stderr(text)) => stderr.push_str(&text),
+                Some(OutputChunk::Exit(code)) => {
+                    ended = Some(format!("docker events exited with code {}: {}", code, stderr.trim()));
+                }
+                None => ended = Some("docker events stream closed".to_string()),
+            }
+            if ended.is_some() {
+                break;
+            }
+            match tokio::time::timeout(DEBOUNCE, stream.next()).await {
+                Ok(chunk) => next = chunk,
+                Err(_) => break,
+            }
+        }
+
+        if !ids.is_empty() {
+            let ids_list: Vec<String> = ids.iter().cloned().collect();
+            match list_containers_by_id(transport, node, &ids_list).await {
+                Ok(listed) => inventory.update(node, &ids, listed),
+                Err(e) => return e.to_string(),
+            }
+        }
+        if let Some(reason) = ended {
+            return reason;
+        }
+    }
+}
+
+/// Follow a node's events until the task is aborted, reconnecting (and
+/// resyncing) with a growing delay when the stream drops
+async fn watch_node<T: CommandTransport>(inventory: ContainerInventory, transport: Arc<T>, node: String, config: SharedConfig) {
+    let mut retry = RETRY_MIN;
+    loop {
+        let resync = Duration::from_secs(config.load().events.resync_secs.max(MIN_RESYNC_SECS));
+        let started = Instant::now();
+        let reason = follow_node(&inventory, transport.as_ref(), &node, resync).await;
+        tracing::warn!("Docker events on {} stopped, reconnecting in {:?}: {}", node, retry, reason);
+        inventory.disconnected(&node, reason);
+
+        if started.elapsed() > RETRY_MAX {
+            retry = RETRY_MIN;
+        }
+        tokio::time::sleep(retry).await;
+        retry = (retry * 2).min(RETRY_MAX);
+    }
+}
+
+/// Watcher tasks by node; aborted when dropped
+#[derive(Default)]
+struct Watchers(HashMap<String, JoinHandle<()>>);
+
+impl Drop for Watchers {
+    fn drop(&mut self) {
+        for handle in self.0.values() {
+            handle.abort();
+        }
+    }
+}
+
+/// Watch every docker node until the task is dropped. Nodes added to or
+/// removed from the configuration (or `events.enabled`) are picked up
+/// within a few seconds.
+pub async fn run_watcher<T: CommandTransport + 'static>(inventory: ContainerInventory, transport: T, config: SharedConfig) {
+    let transport = Arc::new(transport);
+    let mut watchers = Watchers::default();
+    loop {
+        let current = config.load();
+        let wanted = |name: &str| current.events.enabled && current.get_docker_node(name).is_some();
+
+        watchers.0.retain(|name, handle| {
+            let keep = wanted(name);
+            if !keep {
+                handle.abort();
+            }
+            keep
+        });
+        if current.events.enabled {
+            for node in &current.docker_nodes {
+                if !watchers.0.contains_key(&node.name) {
+                    let task = watch_node(inventory.clone(), transport.clone(), node.name.clone(), config.clone());
+                    watchers.0.insert(node.name.clone(), tokio::spawn(task));
+                }
+            }
+        }
+        inventory.retain(|name| watchers.0.contains_key(name));
+
+        tokio::time::sleep(SUPERVISE_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::FixtureTransport;
+
+    #[tokio::test]
+    async fn test_events_update_inventory() {
+        let transport = FixtureTransport::from_toml(include_str!("../../fixtures/homelab.toml")).unwrap();
+        let inventory = ContainerInventory::new();
+        let mut changes = inventory.changes(Some("LXC_HOME"));
+        assert!(!changes.next().await.unwrap().live);
+
+        // The fixture's stream has nextcloud dying, an exec in the db and
+        // unifi being removed, then ends
+        let reason = follow_node(&inventory, &transport, "LXC_HOME", Duration::from_secs(300)).await;
+        assert!(reason.starts_with("docker events exited with code 0"));
+
+        let resync = changes.next().await.unwrap();
+        assert!(resync.live && resync.containers.is_empty());
+        let update = changes.next().await.unwrap();
+        assert_eq!(update.containers, ["unifi", "homelab-nextcloud-1"]);
+
+        let containers = inventory.containers("LXC_HOME").unwrap();
+        let names: Vec<_> = containers.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["homelab-nextcloud-1", "homelab-db-1"]);
+        assert_eq!(containers[0].status, crate::docker::ContainerStatus::Exited);
+
+        inventory.disconnected("LXC_HOME", reason);
+        assert!(!changes.next().await.unwrap().live);
+        assert!(inventory.containers("LXC_HOME").is_none());
+        assert!(inventory.node("LXC_HOME").unwrap().error.is_some());
+    }
+}
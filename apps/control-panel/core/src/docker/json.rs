@@ -0,0 +1,618 @@
+//! Parsing of `docker ps --format json`, `docker inspect`, `docker stats`
+//! and `docker events` output
+
+use super::events::DockerEvent;
+use super::stats::ContainerStats;
+use super::{
+    Container, ContainerDetails, ContainerStatus, Health, HealthProbe, HealthStatus, Mount, NetworkAttachment,
+    PortMapping, RestartPolicy,
+};
+use crate::error::AppError;
+use chrono::{DateTime, Datelike, Utc};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// One line of `docker ps --format '{{json .}}'`; lists are comma-joined
+/// strings there
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+struct PsLine {
+    #[serde(rename = "ID")]
+    id: String,
+    names: String,
+    image: String,
+    created_at: String,
+    labels: String,
+    mounts: String,
+    networks: String,
+    ports: String,
+    state: String,
+    status: String,
+}
+
+/// Parse one line of `docker ps --format '{{json .}}'`
+pub(super) fn parse_ps_line(line: &str) -> Result<Container, AppError> {
+    let raw: PsLine = serde_json::from_str(line)
+        .map_err(|e| AppError::Docker(format!("Invalid docker ps line ({}): {}", e, line)))?;
+
+    let labels = parse_labels(&raw.labels);
+    let status = if raw.state.is_empty() {
+        let Ok(status) = raw.status.parse();
+        status
+    } else {
+        ContainerStatus::from_state(&raw.state)
+    };
+
+    Ok(Container {
+        id: raw.id,
+        name: raw.names,
+        image: raw.image,
+        status,
+        health: status_health(&raw.status),
+        exit_code: status_exit_code(&raw.status),
+        status_text: raw.status,
+        ports: parse_ports(&raw.ports),
+        created: parse_ps_time(&raw.created_at),
+        project: labels.get("com.docker.compose.project").filter(|p| !p.is_empty()).cloned(),
+        labels,
+        mounts: split_list(&raw.mounts),
+        networks: split_list(&raw.networks),
+        restart_policy: None,
+        image_id: None,
+        restart_count: 0,
+        oom_killed: false,
+        started_at: None,
+        finished_at: None,
+    })
+}
+
+/// Labels from `docker ps` (`a=1,b=2`). Values may contain commas, so a
+/// segment without `=` belongs to the value before it.
+fn parse_labels(s: &str) -> BTreeMap<String, String> {
+    let mut labels = BTreeMap::new();
+    let mut last: Option<String> = None;
+    for segment in s.split(',').filter(|s| !s.is_empty()) {
+        match (segment.split_once('='), &last) {
+            (Some((key, value)), _) => {
+                labels.insert(key.to_string(), value.to_string());
+                last = Some(key.to_string());
+            }
+            (None, Some(key)) => {
+                if let Some(value) = labels.get_mut(key) {
+                    value.push(',');
+                    value.push_str(segment);
+                }
+            }
+            (None, None) => {
+                labels.insert(segment.to_string(), String::new());
+            }
+        }
+    }
+    labels
+}
+
+fn split_list(s: &str) -> Vec<String> {
+    s.split(',').map(str::trim).filter(|s| !s.is_empty()).map(String::from).collect()
+}
+
+/// Health from the status text: "Up 3 days (healthy)", "Up 5 seconds (health: starting)"
+fn status_health(status: &str) -> Option<HealthStatus> {
+    let (_, inner) = status.trim_end().strip_suffix(')')?.rsplit_once('(')?;
+    HealthStatus::parse(inner)
+}
+
+/// Exit code from the status text: "Exited (137) 2 hours ago", "Restarting (1) 5 seconds ago"
+fn status_exit_code(status: &str) -> Option<i64> {
+    let (_, rest) = status.split_once('(')?;
+    let (code, _) = rest.split_once(')')?;
+    if status.starts_with("Up") {
+        return None;
+    }
+    code.trim().parse().ok()
+}
+
+/// Ports from `docker ps`:
+/// `0.0.0.0:8080->80/tcp, :::8080->80/tcp, 3306/tcp, 0.0.0.0:5000-5001->5000-5001/udp`
+pub(super) fn parse_ports(s: &str) -> Vec<PortMapping> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .filter_map(|entry| {
+            let (host, container) = match entry.split_once("->") {
+                Some((host, container)) => (Some(host), container),
+                None => (None, entry),
+            };
+            let (ports, protocol) = container.split_once('/').unwrap_or((container, "tcp"));
+            let (container_port, range) = parse_port_range(ports)?;
+
+            let (host_ip, host_port) = match host {
+                Some(host) => {
+                    let (ip, port) = host.rsplit_once(':').unwrap_or(("", host));
+                    let ip = ip.trim_start_matches('[').trim_end_matches(']');
+                    (
+                        Some(ip.to_string()).filter(|ip| !ip.is_empty()),
+                        Some(parse_port_range(port)?.0),
+                    )
+                }
+                None => (None, None),
+            };
+
+            Some(PortMapping {
+                host_ip,
+                host_port,
+                container_port,
+                range,
+                protocol: protocol.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// `8080` or `5000-5010` as (first port, number of ports)
+fn parse_port_range(s: &str) -> Option<(u16, u16)> {
+    match s.split_once('-') {
+        Some((start, end)) => {
+            let (start, end) = (start.parse::<u16>().ok()?, end.parse::<u16>().ok()?);
+            Some((start, end.checked_sub(start)? + 1))
+        }
+        None => Some((s.parse().ok()?, 1)),
+    }
+}
+
+/// `docker ps` time: "2025-01-10 09:12:44 +0000 UTC"
+fn parse_ps_time(s: &str) -> Option<DateTime<Utc>> {
+    let stamp = s.split_whitespace().take(3).collect::<Vec<_>>().join(" ");
+    DateTime::parse_from_str(&stamp, "%Y-%m-%d %H:%M:%S %z")
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+/// `docker inspect` time (RFC 3339); Docker uses year 1 for "never"
+fn parse_time(s: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(s)
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+        .filter(|t| t.year() > 1)
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+struct Inspect {
+    id: String,
+    created: String,
+    name: String,
+    image: String,
+    restart_count: u64,
+    state: InspectState,
+    host_config: InspectHostConfig,
+    mounts: Vec<InspectMount>,
+    config: InspectConfig,
+    network_settings: InspectNetworkSettings,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+struct InspectState {
+    status: String,
+    #[serde(rename = "OOMKilled")]
+    oom_killed: bool,
+    exit_code: i64,
+    error: String,
+    started_at: String,
+    finished_at: String,
+    health: Option<InspectHealth>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+struct InspectHealth {
+    status: String,
+    failing_streak: u32,
+    log: Vec<InspectProbe>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+struct InspectProbe {
+    start: String,
+    exit_code: i64,
+    output: String,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+struct InspectHostConfig {
+    restart_policy: InspectRestartPolicy,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+struct InspectRestartPolicy {
+    name: String,
+    maximum_retry_count: u32,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+struct InspectMount {
+    #[serde(rename = "Type")]
+    kind: String,
+    name: String,
+    source: String,
+    destination: String,
+    #[serde(rename = "RW")]
+    rw: bool,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+struct InspectConfig {
+    image: String,
+    env: Option<Vec<String>>,
+    cmd: Option<Vec<String>>,
+    entrypoint: Option<Vec<String>>,
+    working_dir: String,
+    labels: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+struct InspectNetworkSettings {
+    ports: Option<BTreeMap<String, Option<Vec<InspectBinding>>>>,
+    networks: Option<BTreeMap<String, InspectNetwork>>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+struct InspectBinding {
+    host_ip: String,
+    host_port: String,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+struct InspectNetwork {
+    aliases: Option<Vec<String>>,
+    #[serde(rename = "IPAddress")]
+    ip_address: String,
+    gateway: String,
+    mac_address: String,
+}
+
+/// Parse the JSON array printed by `docker inspect <container>...`
+pub(super) fn parse_inspect(stdout: &str) -> Result<Vec<ContainerDetails>, AppError> {
+    let raw: Vec<Inspect> = serde_json::from_str(stdout)
+        .map_err(|e| AppError::Docker(format!("Invalid docker inspect output: {}", e)))?;
+    Ok(raw.into_iter().map(details_from).collect())
+}
+
+fn details_from(raw: Inspect) -> ContainerDetails {
+    let non_empty = |s: String| Some(s).filter(|s| !s.is_empty());
+
+    let health = raw.state.health.and_then(|h| {
+        Some(Health {
+            status: HealthStatus::parse(&h.status)?,
+            failing_streak: h.failing_streak,
+            log: h
+                .log
+                .into_iter()
+                .map(|p| HealthProbe {
+                    start: parse_time(&p.start),
+                    exit_code: p.exit_code,
+                    output: p.output.trim_end().to_string(),
+                })
+                .collect(),
+        })
+    });
+
+    let mut ports = Vec::new();
+    for (key, bindings) in raw.network_settings.ports.unwrap_or_default() {
+        let Some(mut port) = parse_ports(&key).pop() else {
+            continue;
+        };
+        match bindings.filter(|b| !b.is_empty()) {
+            Some(bindings) => ports.extend(bindings.into_iter().map(|b| PortMapping {
+                host_ip: non_empty(b.host_ip),
+                host_port: b.host_port.parse().ok(),
+                ..port.clone()
+            })),
+            None => {
+                port.host_ip = None;
+                ports.push(port);
+            }
+        }
+    }
+    ports.sort_by_key(|p| (p.container_port, p.host_port.is_none()));
+
+    let config = raw.config;
+    let command = config
+        .entrypoint
+        .unwrap_or_default()
+        .into_iter()
+        .chain(config.cmd.unwrap_or_default())
+        .collect();
+    let env = config
+        .env
+        .unwrap_or_default()
+        .into_iter()
+        .map(|var| var.split_once('=').map(|(name, _)| name.to_string()).unwrap_or(var))
+        .collect();
+
+    ContainerDetails {
+        id: raw.id,
+        name: raw.name.trim_start_matches('/').to_string(),
+        image: config.image,
+        image_id: raw.image,
+        repo_digests: Vec::new(),
+        created: parse_time(&raw.created),
+        status: ContainerStatus::from_state(&raw.state.status),
+        started_at: parse_time(&raw.state.started_at),
+        finished_at: parse_time(&raw.state.finished_at),
+        exit_code: raw.state.exit_code,
+        error: non_empty(raw.state.error),
+        oom_killed: raw.state.oom_killed,
+        restart_count: raw.restart_count,
+        restart_policy: RestartPolicy {
+            name: non_empty(raw.host_config.restart_policy.name).unwrap_or_else(|| "no".to_string()),
+            maximum_retry_count: raw.host_config.restart_policy.maximum_retry_count,
+        },
+        health,
+        command,
+        working_dir: non_empty(config.working_dir),
+        env,
+        labels: config.labels.unwrap_or_default(),
+        ports,
+        mounts: raw
+            .mounts
+            .into_iter()
+            .map(|m| Mount {
+                kind: m.kind,
+                name: non_empty(m.name),
+                source: m.source,
+                destination: m.destination,
+                read_only: !m.rw,
+            })
+            .collect(),
+        networks: raw
+            .network_settings
+            .networks
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(name, n)| NetworkAttachment {
+                name,
+                ip_address: non_empty(n.ip_address),
+                gateway: non_empty(n.gateway),
+                mac_address: non_empty(n.mac_address),
+                aliases: n.aliases.unwrap_or_default(),
+            })
+            .collect(),
+    }
+}
+
+/// Complete `docker ps` entries with what only `docker inspect` knows
+pub(super) fn merge_details(containers: &mut [Container], details: Vec<ContainerDetails>) {
+    let mut by_id: HashMap<String, ContainerDetails> = details.into_iter().map(|d| (d.id.clone(), d)).collect();
+    for container in containers {
+        let Some(details) = by_id.remove(&container.id) else {
+            continue;
+        };
+        // docker ps joins labels with commas; inspect has them exactly
+        container.labels = details.labels;
+        container.project = container
+            .labels
+            .get("com.docker.compose.project")
+            .filter(|p| !p.is_empty())
+            .cloned();
+        container.health = details.health.map(|h| h.status);
+        container.restart_policy = Some(details.restart_policy);
+        container.image_id = Some(details.image_id).filter(|id| !id.is_empty());
+        container.restart_count = details.restart_count;
+        container.oom_killed = details.oom_killed;
+        container.started_at = details.started_at;
+        container.finished_at = details.finished_at;
+        // Docker keeps the code of the last run while a container runs again
+        if details.finished_at.is_some() {
+            container.exit_code = Some(details.exit_code);
+        }
+        if container.created.is_none() {
+            container.created = details.created;
+        }
+    }
+}
+
+/// `docker image inspect --format '{{json .RepoDigests}}'`
+pub(super) fn parse_repo_digests(stdout: &str) -> Vec<String> {
+    serde_json::from_str::<Option<Vec<String>>>(stdout.trim())
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+}
+
+/// One line of `docker stats --format '{{json .}}'`; sizes and percentages
+/// are formatted text there
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+struct StatsLine {
+    #[serde(rename = "ID")]
+    id: String,
+    name: String,
+    #[serde(rename = "CPUPerc")]
+    cpu_perc: String,
+    mem_usage: String,
+    mem_perc: String,
+    #[serde(rename = "NetIO")]
+    net_io: String,
+    #[serde(rename = "BlockIO")]
+    block_io: String,
+    #[serde(rename = "PIDs")]
+    pids: String,
+}
+
+/// Parse one line of `docker stats --no-stream --format '{{json .}}'`
+pub(super) fn parse_stats_line(line: &str) -> Result<ContainerStats, AppError> {
+    let raw: StatsLine = serde_json::from_str(line)
+        .map_err(|e| AppError::Docker(format!("Invalid docker stats line ({}): {}", e, line)))?;
+
+    let (memory_bytes, memory_limit) = parse_size_pair(&raw.mem_usage);
+    let (net_rx, net_tx) = parse_size_pair(&raw.net_io);
+    let (block_read, block_write) = parse_size_pair(&raw.block_io);
+    Ok(ContainerStats {
+        id: raw.id,
+        name: raw.name,
+        cpu_percent: parse_percent(&raw.cpu_perc),
+        memory_bytes,
+        memory_limit,
+        memory_percent: parse_percent(&raw.mem_perc),
+        net_rx,
+        net_tx,
+        block_read,
+        block_write,
+        pids: raw.pids.trim().parse().unwrap_or(0),
+    })
+}
+
+/// "12.5%"; "--" (a container that is just stopping) is 0
+fn parse_percent(s: &str) -> f64 {
+    s.trim().trim_end_matches('%').parse().unwrap_or(0.0)
+}
+
+/// "123.4MiB / 7.6GiB"
+fn parse_size_pair(s: &str) -> (u64, u64) {
+    let (a, b) = s.split_once('/').unwrap_or((s, ""));
+    (parse_size(a).unwrap_or(0), parse_size(b).unwrap_or(0))
+}
+
+/// Docker's sizes: decimal units for network and block IO ("1.2kB", "3MB"),
+/// binary ones for memory ("7.6GiB")
+pub(super) fn parse_size(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit() && c != '.')?;
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let factor: f64 = match unit.trim() {
+        "B" => 1.0,
+        "kB" | "KB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        "TB" => 1e12,
+        "KiB" => 1024.0,
+        "MiB" => 1024.0 * 1024.0,
+        "GiB" => 1024.0 * 1024.0 * 1024.0,
+        "TiB" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    };
+    Some((number * factor).round() as u64)
+}
+
+/// One line of `docker events --format '{{json .}}'`
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct EventLine {
+    #[serde(rename = "Type")]
+    kind: String,
+    #[serde(rename = "Action")]
+    action: String,
+    #[serde(rename = "Actor")]
+    actor: EventActor,
+    time: i64,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+struct EventActor {
+    #[serde(rename = "ID")]
+    id: String,
+    attributes: HashMap<String, String>,
+}
+
+/// Parse one line of `docker events --format '{{json .}}'`; `None` for
+/// other objects than containers
+pub(super) fn parse_event_line(line: &str) -> Result<Option<DockerEvent>, AppError> {
+    let raw: EventLine = serde_json::from_str(line)
+        .map_err(|e| AppError::Docker(format!("Invalid docker event ({}): {}", e, line)))?;
+    if raw.kind != "container" || raw.actor.id.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(DockerEvent {
+        id: raw.actor.id,
+        name: raw.actor.attributes.get("name").cloned(),
+        action: raw.action,
+        time: DateTime::from_timestamp(raw.time, 0),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ports() {
+        let ports = parse_ports("0.0.0.0:8080->80/tcp, :::8080->80/tcp, 3306/tcp, 0.0.0.0:5000-5001->5000-5001/udp");
+        assert_eq!(ports.len(), 4);
+        assert_eq!(ports[0].host_ip.as_deref(), Some("0.0.0.0"));
+        assert_eq!(ports[0].host_port, Some(8080));
+        assert_eq!(ports[1].host_ip.as_deref(), Some("::"));
+        assert_eq!(ports[2].host_port, None);
+        assert_eq!(ports[2].container_port, 3306);
+        assert_eq!(ports[3].range, 2);
+        assert_eq!(ports[3].to_string(), "0.0.0.0:5000-5001->5000-5001/udp");
+        assert_eq!(ports[1].to_string(), "[::]:8080->80/tcp");
+    }
+
+    #[test]
+    fn test_parse_labels_and_health() {
+        let labels = parse_labels("com.docker.compose.project=homelab,traefik.http.routers.a.rule=Host(`a`),Host(`b`)");
+        assert_eq!(labels["com.docker.compose.project"], "homelab");
+        assert_eq!(labels["traefik.http.routers.a.rule"], "Host(`a`),Host(`b`)");
+
+        assert_eq!(status_health("Up 3 days (healthy)"), Some(HealthStatus::Healthy));
+        assert_eq!(status_health("Up 5 seconds (health: starting)"), Some(HealthStatus::Starting));
+        assert_eq!(status_health("Exited (137) 2 hours ago"), None);
+        assert_eq!(status_health("Up 3 days"), None);
+
+        assert_eq!(status_exit_code("Exited (137) 2 hours ago"), Some(137));
+        assert_eq!(status_exit_code("Restarting (1) 5 seconds ago"), Some(1));
+        assert_eq!(status_exit_code("Up 3 days (healthy)"), None);
+    }
+
+    #[test]
+    fn test_parse_stats_line() {
+        let stats = parse_stats_line(
+            r#"{"BlockIO":"52.4MB / 1.2GB","CPUPerc":"12.35%","Container":"3f4e2a1b9c0d","ID":"3f4e2a1b9c0d","MemPerc":"6.41%","MemUsage":"501.2MiB / 7.629GiB","Name":"homelab-nextcloud-1","NetIO":"1.5kB / 648B","PIDs":"23"}"#,
+        )
+        .unwrap();
+        assert_eq!(stats.name, "homelab-nextcloud-1");
+        assert_eq!(stats.cpu_percent, 12.35);
+        assert_eq!(stats.memory_bytes, 525_546_291);
+        assert_eq!(stats.memory_limit, 8_191_576_375);
+        assert_eq!(stats.net_rx, 1500);
+        assert_eq!(stats.net_tx, 648);
+        assert_eq!(stats.block_write, 1_200_000_000);
+        assert_eq!(stats.pids, 23);
+
+        // A container that is just stopping
+        let stats = parse_stats_line(r#"{"BlockIO":"--","CPUPerc":"--","ID":"1c2d","MemPerc":"--","MemUsage":"-- / --","Name":"unifi","NetIO":"--","PIDs":"--"}"#).unwrap();
+        assert_eq!((stats.cpu_percent, stats.memory_bytes, stats.pids), (0.0, 0, 0));
+
+        assert_eq!(parse_size("0B"), Some(0));
+        assert_eq!(parse_size("2.5 kB"), Some(2500));
+        assert_eq!(parse_size("12 parsecs"), None);
+    }
+
+    #[test]
+    fn test_parse_event_line() {
+        let event = parse_event_line(
+            r#"{"status":"health_status: unhealthy","id":"8a7b6c5d","from":"mariadb:11","Type":"container","Action":"health_status: unhealthy","Actor":{"ID":"8a7b6c5d","Attributes":{"image":"mariadb:11","name":"homelab-db-1"}},"scope":"local","time":1736762400,"timeNano":1736762400123456789}"#,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(event.id, "8a7b6c5d");
+        assert_eq!(event.name.as_deref(), Some("homelab-db-1"));
+        assert_eq!(event.action, "health_status: unhealthy");
+        assert_eq!(event.time.unwrap().to_rfc3339(), "2025-01-13T10:00:00+00:00");
+
+        let network = r#"{"Type":"network","Action":"connect","Actor":{"ID":"f00","Attributes":{"container":"8a7b6c5d"}},"time":1736762400}"#;
+        assert!(parse_event_line(network).unwrap().is_none());
+        assert!(parse_event_line("not json").is_err());
+    }
+}
@@ -0,0 +1,243 @@
+//! Live container and stack logs (`docker logs -f`, `docker-compose logs -f`)
+//!
+//! The follow commands run until the viewer drops the stream. Dropping it
+//! closes the SSH channel (or local pipe), but a follower of a quiet container
+//! would only notice on its next write, so the command is wrapped in a small
+//! script that watches its stdin and kills the follower when it closes.
+
+use crate::docker::commands::find_compose_dir;
+use crate::error::AppError;
+use crate::shell::{ContainerName, ShellCommand};
+use crate::ssh::{CommandStream, OutputChunk};
+use crate::transport::{CommandTransport, Target};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+
+/// Where to start following
+#[derive(Debug, Clone, Default)]
+pub struct FollowOptions {
+    /// `docker logs --since`: a duration (`10m`, `2h`) or a timestamp
+    pub since: Option<String>,
+    /// Lines of history before following; `None` for all of it
+    pub tail: Option<u32>,
+}
+
+impl FollowOptions {
+    /// Options from form values; empty values mean "not set"
+    pub fn parse(since: &str, tail: &str) -> Result<Self, AppError> {
+        let since = since.trim();
+        let valid_since = since.len() <= 40
+            && since
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || ":.+-".contains(c));
+        if !valid_since {
+            return Err(AppError::Validation(format!(
+                "Invalid --since (use 10m, 2h or 2025-01-13T10:00:00Z): {:?}",
+                since
+            )));
+        }
+        let tail = match tail.trim() {
+            "" | "all" => None,
+            tail => Some(
+                tail.parse()
+                    .map_err(|_| AppError::Validation(format!("Invalid --tail: {:?}", tail)))?,
+            ),
+        };
+        Ok(Self {
+            since: Some(since.to_string()).filter(|s| !s.is_empty()),
+            tail,
+        })
+    }
+
+    /// `--timestamps` (always, the viewer shows or hides them), `--tail` and `--since`
+    fn args(&self) -> Vec<String> {
+        let mut args = vec![
+            "-f".to_string(),
+            "--timestamps".to_string(),
+            "--tail".to_string(),
+            self.tail.map_or_else(|| "all".to_string(), |t| t.to_string()),
+        ];
+        if let Some(ref since) = self.since {
+            args.push("--since".to_string());
+            args.push(since.clone());
+        }
+        args
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+/// One line of followed log output
+#[derive(Debug, Clone, Serialize)]
+pub struct LogLine {
+    pub stream: LogStream,
+    pub timestamp: Option<DateTime<Utc>>,
+    /// Container of a stack's log line (the compose prefix)
+    pub source: Option<String>,
+    pub text: String,
+}
+
+impl LogLine {
+    /// Parse a line of `docker logs --timestamps` output, or of
+    /// `docker-compose logs --timestamps` (`db-1  | 2025-... text`) for a stack
+    pub fn parse(stream: LogStream, line: &str, stack: bool) -> Self {
+        let line = line.trim_end_matches(['\n', '\r']);
+        let (source, rest) = match line.split_once(" | ").or_else(|| line.split_once("| ")) {
+            Some((source, rest)) if stack && !source.trim().contains(' ') => (Some(source.trim().to_string()), rest),
+            _ => (None, line),
+        };
+        let (timestamp, text) = match rest.split_once(' ') {
+            Some((stamp, text)) => match DateTime::parse_from_rfc3339(stamp) {
+                Ok(t) => (Some(t.with_timezone(&Utc)), text),
+                Err(_) => (None, rest),
+            },
+            None => (None, rest),
+        };
+        Self {
+            stream,
+            timestamp,
+            source,
+            text: text.to_string(),
+        }
+    }
+
+    /// Fixed-width timestamp (nanoseconds), so they also sort as text
+    pub fn timestamp_text(&self) -> Option<String> {
+        self.timestamp.map(|t| t.to_rfc3339_opts(SecondsFormat::Nanos, true))
+    }
+
+    /// The line as written to a downloaded log file
+    pub fn to_text(&self) -> String {
+        let mut line = String::new();
+        if let Some(ts) = self.timestamp_text() {
+            line.push_str(&ts);
+            line.push(' ');
+        }
+        if let Some(ref source) = self.source {
+            line.push_str(source);
+            line.push_str(" | ");
+        }
+        if self.stream == LogStream::Stderr {
+            line.push_str("[stderr] ");
+        }
+        line.push_str(&self.text);
+        line
+    }
+}
+
+/// Split an output chunk into log lines; `None` for the exit chunk
+pub fn chunk_lines(chunk: &OutputChunk, stack: bool) -> Option<Vec<LogLine>> {
+    let (stream, text) = match chunk {
+        OutputChunk::Stdout(text) => (LogStream::Stdout, text),
+        OutputChunk::Stderr(text) => (LogStream::Stderr, text),
+        OutputChunk::Exit(_) => return None,
+    };
+    Some(text.lines().map(|line| LogLine::parse(stream, line, stack)).collect())
+}
+
+/// Run `follower` until it exits or stdin closes (the viewer went away)
+pub(crate) fn until_closed(follower: ShellCommand) -> String {
+    let script = format!(
+        "exec 3<&0; {} & pid=$!; {{ cat <&3; kill $pid; }} >/dev/null 2>&1 & wait $pid",
+        follower.build()
+    );
+    // Run by sh whatever the login shell is
+    ShellCommand::new("sh").arg("-c").arg(script).build()
+}
+
+/// Follow a container's logs; stdout and stderr stay apart
+pub async fn follow_container_logs(
+    transport: &dyn CommandTransport,
+    node_name: &str,
+    container: &str,
+    options: &FollowOptions,
+) -> Result<CommandStream, AppError> {
+    let container = ContainerName::parse(container)?;
+    let follower = ShellCommand::new("docker").arg("logs").args(options.args()).arg(&container);
+
+    tracing::info!("Following logs of {} on {}", container, node_name);
+    transport
+        .execute_streaming(Target::Node(node_name), &until_closed(follower))
+        .await
+}
+
+/// Follow the logs of a compose stack's containers. Compose interleaves the
+/// containers' stdout and stderr on stdout; `--since` needs Compose v2.
+pub async fn follow_stack_logs(
+    transport: &dyn CommandTransport,
+    node_name: &str,
+    project: &str,
+    options: &FollowOptions,
+) -> Result<CommandStream, AppError> {
+    let dir = find_compose_dir(transport, node_name, project).await?;
+    // `exec` so the watchdog's kill reaches docker-compose, not a subshell
+    let follower = ShellCommand::new("cd").path(&dir).and(
+        ShellCommand::new("exec")
+            .arg("docker-compose")
+            .args(["logs", "--no-color"])
+            .args(options.args()),
+    );
+
+    tracing::info!("Following logs of stack {} on {}", project, node_name);
+    transport
+        .execute_streaming(Target::Node(node_name), &until_closed(follower))
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ssh::collect_output;
+    use crate::transport::LocalTransport;
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_log_lines() {
+        let line = LogLine::parse(LogStream::Stderr, "2025-01-13T10:00:00.5Z [Warning] Aborted connection\n", false);
+        assert_eq!(line.timestamp_text().unwrap(), "2025-01-13T10:00:00.500000000Z");
+        assert_eq!(line.text, "[Warning] Aborted connection");
+        assert_eq!(line.source, None);
+
+        let line = LogLine::parse(LogStream::Stdout, "homelab-db-1  | 2025-01-13T10:00:00Z ready | accepting", true);
+        assert_eq!(line.source.as_deref(), Some("homelab-db-1"));
+        assert_eq!(line.text, "ready | accepting");
+        assert_eq!(line.to_text(), "2025-01-13T10:00:00.000000000Z homelab-db-1 | ready | accepting");
+
+        // No timestamp: the whole line is text
+        assert_eq!(LogLine::parse(LogStream::Stdout, "plain output", false).text, "plain output");
+
+        assert!(FollowOptions::parse("10m", "").is_ok());
+        assert_eq!(FollowOptions::parse("", "200").unwrap().tail, Some(200));
+        assert!(FollowOptions::parse("10m; reboot", "").is_err());
+        assert!(FollowOptions::parse("", "lots").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_follower_stops_with_viewer() {
+        // Ends on its own when the follower exits, although stdin stays open
+        let command = until_closed(ShellCommand::new("echo").arg("hi"));
+        let stream = LocalTransport.run_streaming(&command).await.unwrap();
+        let output = tokio::time::timeout(Duration::from_secs(5), collect_output(stream))
+            .await
+            .unwrap();
+        assert_eq!(output.stdout, "hi\n");
+        assert!(output.success());
+
+        // A quiet follower is killed once the stream is dropped. It is its
+        // own process, which killing the outer shell alone would leave running.
+        let marker = std::env::temp_dir().join(format!("follow-test-{}", std::process::id()));
+        let follower = ShellCommand::new("sh")
+            .arg("-c")
+            .arg(format!("sleep 1; touch {}", crate::shell::quote(&marker.display().to_string())));
+        let stream = LocalTransport.run_streaming(&until_closed(follower)).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        drop(stream);
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert!(!marker.exists());
+    }
+}
@@ -1,19 +1,216 @@
 //! Docker container management module
 
 pub mod commands;
+pub mod events;
+mod json;
+pub mod logs;
+pub mod problems;
+pub mod stats;
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::str::FromStr;
+use std::fmt;
 
-/// Docker container information
+/// Docker container information (`docker ps`, completed by `docker inspect`)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Container {
     pub id: String,
     pub name: String,
     pub image: String,
     pub status: ContainerStatus,
-    pub ports: String,
-    pub created: String,
+    /// Docker's status text, e.g. "Up 3 days (healthy)"
+    pub status_text: String,
+    /// Healthcheck state, if the container has a healthcheck
+    pub health: Option<HealthStatus>,
+    pub ports: Vec<PortMapping>,
+    pub created: Option<DateTime<Utc>>,
     pub project: Option<String>,  // docker-compose project name
+    pub labels: BTreeMap<String, String>,
+    /// Volume names and bind mount sources
+    pub mounts: Vec<String>,
+    pub networks: Vec<String>,
+    /// From `docker inspect` (`None` if it failed)
+    pub restart_policy: Option<RestartPolicy>,
+    /// Image ID (`sha256:...`) from `docker inspect`
+    pub image_id: Option<String>,
+    /// Restarts by the restart policy, from `docker inspect` (0 without it)
+    pub restart_count: u64,
+    /// Exit code of the last run; from the status text of an exited
+    /// container, or from `docker inspect`
+    pub exit_code: Option<i64>,
+    /// The last run was killed for running out of memory
+    pub oom_killed: bool,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl Container {
+    /// Published ports, without the IPv6 twin of each IPv4 mapping
+    pub fn published_ports(&self) -> Vec<&PortMapping> {
+        let mut published: Vec<&PortMapping> = Vec::new();
+        for port in self.ports.iter().filter(|p| p.host_port.is_some()) {
+            let twin = published.iter().any(|p| {
+                p.host_port == port.host_port && p.container_port == port.container_port && p.protocol == port.protocol
+            });
+            if !twin {
+                published.push(port);
+            }
+        }
+        published
+    }
+}
+
+/// A container port and where it is published
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortMapping {
+    pub host_ip: Option<String>,
+    /// First published port; `None` if the port is only exposed
+    pub host_port: Option<u16>,
+    pub container_port: u16,
+    /// Number of ports for a range (`8000-8010/tcp`), otherwise 1
+    pub range: u16,
+    pub protocol: String,
+}
+
+impl fmt::Display for PortMapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let span = |start: u16| match self.range {
+            0 | 1 => start.to_string(),
+            n => format!("{}-{}", start, start.saturating_add(n - 1)),
+        };
+        if let Some(host_port) = self.host_port {
+            match self.host_ip.as_deref() {
+                Some(ip) if ip.contains(':') => write!(f, "[{}]:", ip)?,
+                Some(ip) => write!(f, "{}:", ip)?,
+                None => {}
+            }
+            write!(f, "{}->", span(host_port))?;
+        }
+        write!(f, "{}/{}", span(self.container_port), self.protocol)
+    }
+}
+
+/// Healthcheck state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Starting,
+    Healthy,
+    Unhealthy,
+}
+
+impl HealthStatus {
+    /// Parse Docker's health status; `None` for "none" and unknown values
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "starting" | "health: starting" => Some(HealthStatus::Starting),
+            "healthy" => Some(HealthStatus::Healthy),
+            "unhealthy" => Some(HealthStatus::Unhealthy),
+            _ => None,
+        }
+    }
+
+    pub fn display(&self) -> &'static str {
+        match self {
+            HealthStatus::Starting => "starting",
+            HealthStatus::Healthy => "healthy",
+            HealthStatus::Unhealthy => "unhealthy",
+        }
+    }
+}
+
+/// `--restart` policy of a container
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RestartPolicy {
+    /// `no`, `always`, `unless-stopped` or `on-failure`
+    pub name: String,
+    /// Retries for `on-failure` (0 = unlimited)
+    pub maximum_retry_count: u32,
+}
+
+impl fmt::Display for RestartPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.name.as_str(), self.maximum_retry_count) {
+            ("", _) => write!(f, "no"),
+            ("on-failure", n) if n > 0 => write!(f, "on-failure:{}", n),
+            (name, _) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// Everything `docker inspect` tells about a container
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContainerDetails {
+    pub id: String,
+    pub name: String,
+    /// Image the container was created from, as given (`nextcloud:29-apache`)
+    pub image: String,
+    /// Image ID (`sha256:...`)
+    pub image_id: String,
+    /// Registry digests of the image (`nextcloud@sha256:...`)
+    pub repo_digests: Vec<String>,
+    pub created: Option<DateTime<Utc>>,
+    pub status: ContainerStatus,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub exit_code: i64,
+    /// Error Docker reported starting the container
+    pub error: Option<String>,
+    pub oom_killed: bool,
+    pub restart_count: u64,
+    pub restart_policy: RestartPolicy,
+    pub health: Option<Health>,
+    /// Entrypoint followed by the command
+    pub command: Vec<String>,
+    pub working_dir: Option<String>,
+    /// Names of the environment variables; values often hold secrets and
+    /// aren't kept
+    pub env: Vec<String>,
+    pub labels: BTreeMap<String, String>,
+    pub ports: Vec<PortMapping>,
+    pub mounts: Vec<Mount>,
+    pub networks: Vec<NetworkAttachment>,
+}
+
+/// Healthcheck state with the latest probe results
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Health {
+    pub status: HealthStatus,
+    pub failing_streak: u32,
+    /// Latest probes, oldest first
+    pub log: Vec<HealthProbe>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthProbe {
+    pub start: Option<DateTime<Utc>>,
+    pub exit_code: i64,
+    pub output: String,
+}
+
+/// A volume, bind mount or tmpfs of a container
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Mount {
+    /// `volume`, `bind` or `tmpfs`
+    pub kind: String,
+    /// Volume name
+    pub name: Option<String>,
+    pub source: String,
+    pub destination: String,
+    pub read_only: bool,
+}
+
+/// A network a container is connected to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkAttachment {
+    pub name: String,
+    pub ip_address: Option<String>,
+    pub gateway: Option<String>,
+    pub mac_address: Option<String>,
+    pub aliases: Vec<String>,
 }
 
 /// Docker compose stack/project
@@ -34,25 +231,52 @@ pub enum ContainerStatus {
     Paused,
     Restarting,
     Dead,
+    /// Created but never started
+    Created,
     Unknown,
 }
 
-impl ContainerStatus {
-    /// Parse status from docker ps output
-    pub fn from_str(s: &str) -> Self {
-        let s_lower = s.to_lowercase();
-        if s_lower.contains("up") || s_lower.starts_with("running") {
+/// Parse status from docker ps output
+///
+/// Only the start of the text counts: "Up 2 seconds" is running, but
+/// "Restarting (1) 5 seconds ago" isn't, and the health suffix
+/// ("(healthy)") is read separately into `Container::health`.
+impl FromStr for ContainerStatus {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s_lower = s.trim().to_lowercase();
+        Ok(if s_lower.starts_with("up") && s_lower.contains("(paused)") {
+            ContainerStatus::Paused
+        } else if s_lower.starts_with("up") || s_lower.starts_with("running") {
             ContainerStatus::Running
-        } else if s_lower.contains("exited") {
+        } else if s_lower.starts_with("exited") {
             ContainerStatus::Exited
-        } else if s_lower.contains("paused") {
+        } else if s_lower.starts_with("paused") {
             ContainerStatus::Paused
-        } else if s_lower.contains("restarting") {
+        } else if s_lower.starts_with("restarting") {
             ContainerStatus::Restarting
-        } else if s_lower.contains("dead") {
+        } else if s_lower.starts_with("dead") || s_lower.starts_with("removal") {
             ContainerStatus::Dead
+        } else if s_lower.starts_with("created") {
+            ContainerStatus::Created
         } else {
             ContainerStatus::Unknown
+        })
+    }
+}
+
+impl ContainerStatus {
+    /// Parse a state name (`docker ps` `State`, `docker inspect` `State.Status`)
+    pub fn from_state(state: &str) -> Self {
+        match state.trim().to_lowercase().as_str() {
+            "running" => ContainerStatus::Running,
+            "exited" => ContainerStatus::Exited,
+            "paused" => ContainerStatus::Paused,
+            "restarting" => ContainerStatus::Restarting,
+            "dead" | "removing" => ContainerStatus::Dead,
+            "created" => ContainerStatus::Created,
+            _ => ContainerStatus::Unknown,
         }
     }
 
@@ -64,6 +288,7 @@ impl ContainerStatus {
             ContainerStatus::Paused => "bg-yellow-600",
             ContainerStatus::Restarting => "bg-blue-600",
             ContainerStatus::Dead => "bg-gray-600",
+            ContainerStatus::Created => "bg-gray-600",
             ContainerStatus::Unknown => "bg-gray-500",
         }
     }
@@ -76,6 +301,7 @@ impl ContainerStatus {
             ContainerStatus::Paused => "Paused",
             ContainerStatus::Restarting => "Restarting",
             ContainerStatus::Dead => "Dead",
+            ContainerStatus::Created => "Created",
             ContainerStatus::Unknown => "Unknown",
         }
     }
@@ -91,8 +317,26 @@ impl ContainerStatus {
 pub struct NodeSummary {
     pub name: String,
     pub host: String,
+    /// Address the node was last reached on, if it has connected
+    pub address: Option<String>,
     pub total: usize,
     pub running: usize,
     pub stopped: usize,
+    /// Unhealthy, restart-looping and recently OOM-killed containers
+    pub problems: usize,
     pub online: bool,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_from_text() {
+        assert_eq!("Up 3 days (healthy)".parse(), Ok(ContainerStatus::Running));
+        assert_eq!("Up 2 hours (Paused)".parse(), Ok(ContainerStatus::Paused));
+        assert_eq!("Restarting (1) 5 seconds ago".parse(), Ok(ContainerStatus::Restarting));
+        assert_eq!("Exited (137) 2 hours ago".parse(), Ok(ContainerStatus::Exited));
+        assert_eq!("Created".parse(), Ok(ContainerStatus::Created));
+    }
+}
@@ -0,0 +1,150 @@
+//! Containers that need attention: failing healthchecks, restart loops and
+//! out-of-memory kills, across all docker nodes
+
+use crate::config::DockerNode;
+use crate::docker::commands::list_containers;
+use crate::docker::{Container, ContainerStatus, HealthStatus};
+use crate::transport::CommandTransport;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+/// Restarts after which a recently started container counts as looping
+const RESTART_LOOP_MIN_RESTARTS: u64 = 3;
+/// How recently a looping container was (re)started
+const RESTART_LOOP_WINDOW: Duration = Duration::minutes(10);
+/// How long an out-of-memory kill stays a problem
+const OOM_RECENT: Duration = Duration::hours(24);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProblemKind {
+    Unhealthy,
+    RestartLoop,
+    OomKilled,
+}
+
+impl ProblemKind {
+    pub fn display(&self) -> &'static str {
+        match self {
+            ProblemKind::Unhealthy => "Unhealthy",
+            ProblemKind::RestartLoop => "Restart loop",
+            ProblemKind::OomKilled => "OOM killed",
+        }
+    }
+}
+
+/// One thing wrong with a container
+#[derive(Debug, Clone, Serialize)]
+pub struct Problem {
+    pub container: String,
+    pub project: Option<String>,
+    pub kind: ProblemKind,
+    /// Status, restarts and exit code behind the problem
+    pub detail: String,
+}
+
+/// Problems on one node; `error` is set if it couldn't be listed
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeProblems {
+    pub node: String,
+    pub problems: Vec<Problem>,
+    pub error: Option<String>,
+}
+
+/// What is wrong with a container at `now` (nothing for a healthy one)
+pub fn container_problems(container: &Container, now: DateTime<Utc>) -> Vec<Problem> {
+    let problem = |kind, detail: String| Problem {
+        container: container.name.clone(),
+        project: container.project.clone(),
+        kind,
+        detail,
+    };
+    let exit_code = container
+        .exit_code
+        .map(|code| format!(", last exit code {}", code))
+        .unwrap_or_default();
+    let mut problems = Vec::new();
+
+    if container.health == Some(HealthStatus::Unhealthy) {
+        problems.push(problem(ProblemKind::Unhealthy, container.status_text.clone()));
+    }
+
+    let recently_started = container.started_at.is_some_and(|t| now - t < RESTART_LOOP_WINDOW);
+    if container.status == ContainerStatus::Restarting
+        || (container.restart_count >= RESTART_LOOP_MIN_RESTARTS && container.status.is_running() && recently_started)
+    {
+        problems.push(problem(
+            ProblemKind::RestartLoop,
+            format!("{}, {} restarts{}", container.status_text, container.restart_count, exit_code),
+        ));
+    }
+
+    // Without a finish time the kill can't be dated; better shown than missed
+    if container.oom_killed && container.finished_at.is_none_or(|t| now - t < OOM_RECENT) {
+        let when = container
+            .finished_at
+            .map(|t| format!(" at {}", t.format("%Y-%m-%d %H:%M UTC")))
+            .unwrap_or_default();
+        problems.push(problem(ProblemKind::OomKilled, format!("out of memory{}{}", when, exit_code)));
+    }
+
+    problems
+}
+
+/// Problems of every node, listed at the same time
+pub async fn find_problems(transport: &dyn CommandTransport, nodes: &[DockerNode]) -> Vec<NodeProblems> {
+    futures::future::join_all(nodes.iter().map(|node| async move {
+        match list_containers(transport, &node.name).await {
+            Ok(containers) => {
+                let now = Utc::now();
+                NodeProblems {
+                    node: node.name.clone(),
+                    problems: containers.iter().flat_map(|c| container_problems(c, now)).collect(),
+                    error: None,
+                }
+            }
+            Err(e) => NodeProblems {
+                node: node.name.clone(),
+                problems: Vec::new(),
+                error: Some(e.to_string()),
+            },
+        }
+    }))
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::FixtureTransport;
+
+    #[tokio::test]
+    async fn test_container_problems() {
+        let transport = FixtureTransport::from_toml(include_str!("../../fixtures/homelab.toml")).unwrap();
+        let containers = list_containers(&transport, "LXC_HOME").await.unwrap();
+        let now: DateTime<Utc> = "2025-01-13T12:00:00Z".parse().unwrap();
+
+        // unifi was OOM-killed four hours ago; the others are fine
+        assert!(container_problems(&containers[0], now).is_empty());
+        assert!(container_problems(&containers[1], now).is_empty());
+        let problems = container_problems(&containers[2], now);
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].kind, ProblemKind::OomKilled);
+        assert!(problems[0].detail.ends_with("last exit code 137"));
+        assert!(container_problems(&containers[2], now + Duration::days(2)).is_empty());
+
+        // Up a minute after its fifth restart
+        let mut looping = containers[0].clone();
+        looping.restart_count = 5;
+        looping.started_at = Some(now - Duration::minutes(1));
+        assert_eq!(container_problems(&looping, now)[0].kind, ProblemKind::RestartLoop);
+        looping.started_at = Some(now - Duration::hours(1));
+        assert!(container_problems(&looping, now).is_empty());
+
+        let mut unhealthy = containers[1].clone();
+        unhealthy.health = Some(HealthStatus::Unhealthy);
+        unhealthy.status = ContainerStatus::Restarting;
+        let kinds: Vec<_> = container_problems(&unhealthy, now).iter().map(|p| p.kind).collect();
+        assert_eq!(kinds, [ProblemKind::Unhealthy, ProblemKind::RestartLoop]);
+    }
+}
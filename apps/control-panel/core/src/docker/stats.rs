@@ -0,0 +1,354 @@
+//! Container resource usage: `docker stats --no-stream` sampled on every
+//! docker node each `stats.interval_secs`, the last `stats.history` samples
+//! of each container kept in memory for the sparklines
+//!
+//! Network and block IO are counters since the container started; the rates
+//! shown are the differences between consecutive samples.
+
+use crate::config::{DockerNode, SharedConfig};
+use crate::docker::commands::container_stats;
+use crate::transport::CommandTransport;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Shortest time between two samples of a node
+const MIN_INTERVAL_SECS: u64 = 5;
+
+/// One container's line of `docker stats`
+#[derive(Debug, Clone, Serialize)]
+pub struct ContainerStats {
+    pub id: String,
+    pub name: String,
+    /// Of one CPU, so up to 100 times the number of cores
+    pub cpu_percent: f64,
+    pub memory_bytes: u64,
+    pub memory_limit: u64,
+    pub memory_percent: f64,
+    pub net_rx: u64,
+    pub net_tx: u64,
+    pub block_read: u64,
+    pub block_write: u64,
+    pub pids: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Sample {
+    at: DateTime<Utc>,
+    stats: ContainerStats,
+}
+
+/// Recent usage of a container, or the sum over a stack's containers.
+/// Histories are oldest first; the rate histories have one point less than
+/// there are samples.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Usage {
+    /// Containers summed up
+    pub containers: usize,
+    pub cpu_percent: f64,
+    pub memory_bytes: u64,
+    /// The largest limit of the containers (they usually share the host's)
+    pub memory_limit: u64,
+    pub net_rx: u64,
+    pub net_tx: u64,
+    pub block_read: u64,
+    pub block_write: u64,
+    pub pids: u64,
+    /// Bytes per second received and sent, since the previous sample
+    pub net_rate: f64,
+    /// Bytes per second read and written, since the previous sample
+    pub block_rate: f64,
+    pub cpu_history: Vec<f64>,
+    pub memory_history: Vec<f64>,
+    pub net_history: Vec<f64>,
+    pub block_history: Vec<f64>,
+}
+
+impl Usage {
+    fn from_samples(samples: &VecDeque<Sample>) -> Option<Self> {
+        let latest = &samples.back()?.stats;
+        let rates = |total: fn(&ContainerStats) -> u64| -> Vec<f64> {
+            samples
+                .iter()
+                .zip(samples.iter().skip(1))
+                .map(|(prev, next)| {
+                    let secs = (next.at - prev.at).num_milliseconds() as f64 / 1000.0;
+                    // Counters restart with the container
+                    let bytes = total(&next.stats).saturating_sub(total(&prev.stats));
+                    if secs > 0.0 { bytes as f64 / secs } else { 0.0 }
+                })
+                .collect()
+        };
+        let net_history = rates(|s| s.net_rx + s.net_tx);
+        let block_history = rates(|s| s.block_read + s.block_write);
+
+        Some(Self {
+            containers: 1,
+            cpu_percent: latest.cpu_percent,
+            memory_bytes: latest.memory_bytes,
+            memory_limit: latest.memory_limit,
+            net_rx: latest.net_rx,
+            net_tx: latest.net_tx,
+            block_read: latest.block_read,
+            block_write: latest.block_write,
+            pids: latest.pids,
+            net_rate: net_history.last().copied().unwrap_or(0.0),
+            block_rate: block_history.last().copied().unwrap_or(0.0),
+            cpu_history: samples.iter().map(|s| s.stats.cpu_percent).collect(),
+            memory_history: samples.iter().map(|s| s.stats.memory_bytes as f64).collect(),
+            net_history,
+            block_history,
+        })
+    }
+
+    /// Add another container's usage. Histories are lined up on the latest
+    /// sample, as all containers of a node are sampled together.
+    fn add(&mut self, other: &Usage) {
+        fn add_history(sum: &mut Vec<f64>, other: &[f64]) {
+            if other.len() > sum.len() {
+                let missing = other.len() - sum.len();
+                sum.splice(0..0, std::iter::repeat_n(0.0, missing));
+            }
+            let offset = sum.len() - other.len();
+            for (total, value) in sum[offset..].iter_mut().zip(other) {
+                *total += value;
+            }
+        }
+
+        self.containers += other.containers;
+        self.cpu_percent += other.cpu_percent;
+        self.memory_bytes += other.memory_bytes;
+        self.memory_limit = self.memory_limit.max(other.memory_limit);
+        self.net_rx += other.net_rx;
+        self.net_tx += other.net_tx;
+        self.block_read += other.block_read;
+        self.block_write += other.block_write;
+        self.pids += other.pids;
+        self.net_rate += other.net_rate;
+        self.block_rate += other.block_rate;
+        add_history(&mut self.cpu_history, &other.cpu_history);
+        add_history(&mut self.memory_history, &other.memory_history);
+        add_history(&mut self.net_history, &other.net_history);
+        add_history(&mut self.block_history, &other.block_history);
+    }
+
+    /// Memory use as a percentage of the limit
+    pub fn memory_percent(&self) -> f64 {
+        if self.memory_limit == 0 {
+            0.0
+        } else {
+            self.memory_bytes as f64 * 100.0 / self.memory_limit as f64
+        }
+    }
+}
+
+/// Usage of the running containers of a node, by container name
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct NodeUsage {
+    /// Time of the last successful sample
+    pub updated: Option<DateTime<Utc>>,
+    /// Why the last sample failed; the older samples are kept
+    pub error: Option<String>,
+    pub containers: HashMap<String, Usage>,
+}
+
+impl NodeUsage {
+    pub fn container(&self, name: &str) -> Option<&Usage> {
+        self.containers.get(name)
+    }
+
+    /// Sum over the given containers; `None` if none of them was sampled
+    pub fn total<'a>(&self, names: impl IntoIterator<Item = &'a str>) -> Option<Usage> {
+        names
+            .into_iter()
+            .filter_map(|name| self.containers.get(name))
+            .fold(None, |sum: Option<Usage>, usage| {
+                let mut sum = sum.unwrap_or_default();
+                sum.add(usage);
+                Some(sum)
+            })
+    }
+
+    /// The container using the most memory
+    pub fn top_memory(&self) -> Option<(&str, &Usage)> {
+        self.containers
+            .iter()
+            .max_by_key(|(_, usage)| usage.memory_bytes)
+            .map(|(name, usage)| (name.as_str(), usage))
+    }
+}
+
+#[derive(Default)]
+struct NodeSamples {
+    updated: Option<DateTime<Utc>>,
+    error: Option<String>,
+    containers: HashMap<String, VecDeque<Sample>>,
+}
+
+/// Rolling in-memory store of the samples, shared by the collector and the
+/// views
+#[derive(Clone, Default)]
+pub struct StatsStore {
+    nodes: Arc<RwLock<HashMap<String, NodeSamples>>>,
+}
+
+impl StatsStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a sample of a node, keeping `history` per container. Containers
+    /// missing from it (stopped or removed) are dropped.
+    pub fn record(&self, node: &str, at: DateTime<Utc>, stats: Vec<ContainerStats>, history: usize) {
+        let Ok(mut nodes) = self.nodes.write() else { return };
+        let samples = nodes.entry(node.to_string()).or_default();
+        let mut previous = std::mem::take(&mut samples.containers);
+        for stats in stats {
+            let name = stats.name.clone();
+            let mut series = previous.remove(&name).unwrap_or_default();
+            series.push_back(Sample { at, stats });
+            while series.len() > history.max(1) {
+                series.pop_front();
+            }
+            samples.containers.insert(name, series);
+        }
+        samples.updated = Some(at);
+        samples.error = None;
+    }
+
+    /// Note a failed sample of a node
+    pub fn record_error(&self, node: &str, error: String) {
+        if let Ok(mut nodes) = self.nodes.write() {
+            nodes.entry(node.to_string()).or_default().error = Some(error);
+        }
+    }
+
+    /// Forget the nodes `keep` returns false for
+    pub fn retain(&self, keep: impl Fn(&str) -> bool) {
+        if let Ok(mut nodes) = self.nodes.write() {
+            nodes.retain(|name, _| keep(name));
+        }
+    }
+
+    /// Usage on a node; `None` before its first sample
+    pub fn node(&self, node: &str) -> Option<NodeUsage> {
+        let nodes = self.nodes.read().ok()?;
+        let samples = nodes.get(node)?;
+        Some(NodeUsage {
+            updated: samples.updated,
+            error: samples.error.clone(),
+            containers: samples
+                .containers
+                .iter()
+                .filter_map(|(name, series)| Some((name.clone(), Usage::from_samples(series)?)))
+                .collect(),
+        })
+    }
+}
+
+/// Sample the given nodes at the same time
+pub async fn collect(store: &StatsStore, transport: &dyn CommandTransport, nodes: &[DockerNode], history: usize) {
+    futures::future::join_all(nodes.iter().map(|node| async move {
+        match container_stats(transport, &node.name).await {
+            Ok(stats) => store.record(&node.name, Utc::now(), stats, history),
+            Err(e) => {
+                tracing::debug!("docker stats failed on {}: {}", node.name, e);
+                store.record_error(&node.name, e.to_string());
+            }
+        }
+    }))
+    .await;
+}
+
+/// Sample every docker node until the task is dropped. The configuration
+/// is read each round, so reloads (and `stats.enabled`) apply to the next.
+pub async fn run_collector(store: StatsStore, transport: impl CommandTransport, config: SharedConfig) {
+    loop {
+        let config = config.load();
+        if config.stats.enabled {
+            collect(&store, &transport, &config.docker_nodes, config.stats.history).await;
+            store.retain(|name| config.get_docker_node(name).is_some());
+        } else {
+            store.retain(|_| false);
+        }
+        let interval = config.stats.interval_secs.max(MIN_INTERVAL_SECS);
+        tokio::time::sleep(Duration::from_secs(interval)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::FixtureTransport;
+
+    #[tokio::test]
+    async fn test_collect_and_aggregate() {
+        let transport = FixtureTransport::from_toml(include_str!("../../fixtures/homelab.toml")).unwrap();
+        let home: Vec<DockerNode> = vec![toml::from_str("name = 'LXC_HOME'\nhost = '192.168.8.80'\nctid = 200").unwrap()];
+        let store = StatsStore::new();
+        collect(&store, &transport, &home, 3).await;
+
+        let usage = store.node("LXC_HOME").unwrap();
+        assert!(usage.error.is_none());
+        let db = usage.container("homelab-db-1").unwrap();
+        assert_eq!(db.memory_bytes, 160 * 1024 * 1024);
+        assert_eq!(db.net_rx + db.net_tx, 1_740_000);
+        assert_eq!(usage.top_memory().unwrap().0, "homelab-nextcloud-1");
+
+        // Two more samples, ten seconds apart; only three are kept
+        let start = usage.updated.unwrap();
+        for (i, rx) in [(1, 740_000), (2, 840_000)] {
+            let stats = ContainerStats {
+                id: "8a7b".to_string(),
+                name: "homelab-db-1".to_string(),
+                cpu_percent: 2.0 * i as f64,
+                memory_bytes: 200 << 20,
+                memory_limit: 8 << 30,
+                memory_percent: 2.5,
+                net_rx: rx,
+                net_tx: 1_100_000,
+                block_read: 210_000_000,
+                block_write: 3_400_000_000,
+                pids: 14,
+            };
+            store.record("LXC_HOME", start + chrono::Duration::seconds(10 * i), vec![stats], 3);
+        }
+        let db = store.node("LXC_HOME").unwrap().container("homelab-db-1").unwrap().clone();
+        assert_eq!(db.cpu_history, [1.02, 2.0, 4.0]);
+        assert_eq!(db.net_history, [10_000.0, 10_000.0]);
+        assert_eq!(db.net_rate, 10_000.0);
+        assert_eq!(db.block_rate, 0.0);
+
+        // nextcloud was missing from the later samples, so it is gone
+        let usage = store.node("LXC_HOME").unwrap();
+        assert!(usage.container("homelab-nextcloud-1").is_none());
+        let total = usage.total(["homelab-db-1", "unifi"]).unwrap();
+        assert_eq!(total.containers, 1);
+        assert_eq!(total.memory_bytes, 200 << 20);
+        assert!(usage.total(["unifi"]).is_none());
+    }
+
+    #[test]
+    fn test_stack_total_lines_up_histories() {
+        let mut sum = Usage {
+            containers: 1,
+            cpu_history: vec![1.0, 2.0, 3.0],
+            ..Default::default()
+        };
+        sum.add(&Usage {
+            containers: 1,
+            cpu_history: vec![10.0],
+            ..Default::default()
+        });
+        assert_eq!(sum.cpu_history, [1.0, 2.0, 13.0]);
+        sum.add(&Usage {
+            containers: 1,
+            cpu_history: vec![1.0, 1.0, 1.0, 1.0],
+            ..Default::default()
+        });
+        assert_eq!(sum.cpu_history, [1.0, 2.0, 3.0, 14.0]);
+        assert_eq!(sum.containers, 3);
+    }
+}
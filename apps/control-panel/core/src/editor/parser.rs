@@ -208,8 +208,8 @@ fn extract_comment(content: &str, target_line: usize) -> Option<String> {
     // Check for comment on previous line
     if target_line > 0 {
         let prev_line = lines[target_line - 1].trim();
-        if prev_line.starts_with('#') {
-            return Some(prev_line[1..].trim().to_string());
+        if let Some(comment) = prev_line.strip_prefix('#') {
+            return Some(comment.trim().to_string());
         }
     }
 
@@ -11,9 +11,21 @@ pub enum AppError {
     #[error("SSH connection failed: {0}")]
     SshConnection(String),
 
+    #[error("SSH authentication failed: {0}")]
+    SshAuth(String),
+
     #[error("SSH command failed: {0}")]
     SshCommand(String),
 
+    #[error("Host key for {host} does not match known_hosts (server presented {fingerprint})")]
+    HostKeyMismatch { host: String, fingerprint: String },
+
+    #[error("Host key for {host} is not trusted yet ({fingerprint})")]
+    HostKeyUnknown { host: String, fingerprint: String },
+
+    #[error("File transfer failed: {0}")]
+    Sftp(String),
+
     #[error("Docker error: {0}")]
     Docker(String),
 
@@ -1,7 +1,9 @@
 //! NixOS deployment management
 
 use crate::error::AppError;
-use crate::ssh::SshPool;
+use crate::shell::{ProfileName, ShellCommand};
+use crate::ssh::{collect_output, CommandStream};
+use crate::transport::{CommandTransport, Target};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 
@@ -38,20 +40,29 @@ impl DeployState {
 
 /// Perform a dry-run build for validation
 pub async fn dry_run(
-    ssh_pool: &mut SshPool,
+    transport: &dyn CommandTransport,
     _node_name: &str,
     profile: &str,
     dotfiles_path: &str,
 ) -> Result<DeploymentStatus, AppError> {
     let started_at = Utc::now();
 
+    let profile_name = ProfileName::parse(profile)?;
+
     // Build command for dry-run
-    let command = format!(
-        "cd {} && nix build .#nixosConfigurations.{}.config.system.build.toplevel --dry-run 2>&1",
-        dotfiles_path, profile
+    let command = in_dotfiles(
+        dotfiles_path,
+        ShellCommand::new("nix")
+            .arg("build")
+            .arg(format!(
+                ".#nixosConfigurations.{}.config.system.build.toplevel",
+                profile_name
+            ))
+            .arg("--dry-run")
+            .merge_stderr(),
     );
 
-    let output = ssh_pool.execute_on_profile(profile, &command).await?;
+    let output = transport.execute_read(Target::Profile(profile), &command).await?;
 
     let status = if output.success() {
         DeploymentStatus {
@@ -76,7 +87,7 @@ pub async fn dry_run(
 
 /// Deploy changes to a node
 pub async fn deploy(
-    ssh_pool: &mut SshPool,
+    transport: &dyn CommandTransport,
     _node_name: &str,
     profile: &str,
     dotfiles_path: &str,
@@ -84,29 +95,8 @@ pub async fn deploy(
     let started_at = Utc::now();
     let profile_name = profile.to_string();
 
-    // Step 1: Pull latest changes
-    tracing::info!("Pulling latest changes on {}", profile);
-    let pull_cmd = format!("cd {} && git pull", dotfiles_path);
-    let pull_output = ssh_pool.execute_on_profile(profile, &pull_cmd).await?;
-
-    if !pull_output.success() {
-        return Ok(DeploymentStatus {
-            profile: profile_name,
-            status: DeployState::Failed,
-            message: format!("Git pull failed: {}", pull_output.combined()),
-            started_at: Some(started_at),
-            finished_at: Some(Utc::now()),
-        });
-    }
-
-    // Step 2: Build and switch
-    tracing::info!("Building and switching on {}", profile);
-    let switch_cmd = format!(
-        "cd {} && sudo nixos-rebuild switch --flake .#system 2>&1",
-        dotfiles_path
-    );
-
-    let switch_output = ssh_pool.execute_on_profile(profile, &switch_cmd).await?;
+    let stream = deploy_stream(transport, profile, dotfiles_path).await?;
+    let switch_output = collect_output(stream).await;
 
     let status = if switch_output.success() {
         DeploymentStatus {
@@ -129,30 +119,57 @@ pub async fn deploy(
     Ok(status)
 }
 
+/// Deploy changes to a node, streaming git pull and nixos-rebuild output.
+/// Each step is announced with a `==> step` line.
+pub async fn deploy_stream(
+    transport: &dyn CommandTransport,
+    profile: &str,
+    dotfiles_path: &str,
+) -> Result<CommandStream, AppError> {
+    tracing::info!("Pulling latest changes and switching on {}", profile);
+    let command = in_dotfiles(
+        dotfiles_path,
+        step("git pull")
+            .and(ShellCommand::new("git").arg("pull").merge_stderr())
+            .and(step("nixos-rebuild switch"))
+            .and(
+                ShellCommand::new("sudo")
+                    .args(["nixos-rebuild", "switch", "--flake", ".#system"])
+                    .merge_stderr(),
+            ),
+    );
+
+    transport.execute_streaming(Target::Profile(profile), &command).await
+}
+
 /// Get deployment log (last N lines of journal)
 #[allow(dead_code)]
 pub async fn get_deployment_log(
-    ssh_pool: &mut SshPool,
+    transport: &dyn CommandTransport,
     profile: &str,
     lines: u32,
 ) -> Result<String, AppError> {
-    let command = format!(
-        "journalctl -u nixos-rebuild --no-pager -n {} 2>/dev/null || echo 'No rebuild logs available'",
-        lines
-    );
+    let command = ShellCommand::new("journalctl")
+        .args(["-u", "nixos-rebuild", "--no-pager", "-n", &lines.to_string()])
+        .raw("2>/dev/null")
+        .or(ShellCommand::new("echo").arg("No rebuild logs available"))
+        .build();
 
-    let output = ssh_pool.execute_on_profile(profile, &command).await?;
+    let output = transport.execute_read(Target::Profile(profile), &command).await?;
     Ok(output.combined())
 }
 
 /// Check if a profile is reachable and NixOS
 #[allow(dead_code)]
 pub async fn check_node_health(
-    ssh_pool: &mut SshPool,
+    transport: &dyn CommandTransport,
     profile: &str,
 ) -> Result<bool, AppError> {
-    let command = "nixos-version 2>/dev/null || echo 'not-nixos'";
-    let output = ssh_pool.execute_on_profile(profile, command).await?;
+    let command = ShellCommand::new("nixos-version")
+        .raw("2>/dev/null")
+        .or(ShellCommand::new("echo").arg("not-nixos"))
+        .build();
+    let output = transport.execute_read(Target::Profile(profile), &command).await?;
 
     Ok(output.success() && !output.stdout.contains("not-nixos"))
 }
@@ -175,15 +192,16 @@ pub struct DeployStepResult {
 /// 3. git reset --hard origin/main
 /// 4. ./install.sh {dotfiles_path} {profile} -s -u -q
 pub async fn deploy_lxc_node(
-    ssh_pool: &mut SshPool,
+    transport: &dyn CommandTransport,
     profile: &str,
     dotfiles_path: &str,
 ) -> Result<Vec<DeployStepResult>, AppError> {
+    let profile_name = ProfileName::parse(profile)?;
     let mut results = Vec::new();
 
     // Step 1: SSH connectivity check (implicit via first command)
-    let check_cmd = "echo ok";
-    let check_output = ssh_pool.execute_on_profile(profile, check_cmd).await;
+    let check_cmd = ShellCommand::new("echo").arg("ok").build();
+    let check_output = transport.execute_read(Target::Profile(profile), &check_cmd).await;
     match check_output {
         Ok(out) if out.success() => {
             results.push(DeployStepResult {
@@ -212,8 +230,8 @@ pub async fn deploy_lxc_node(
 
     // Step 2: git fetch origin
     tracing::info!("deploy-lxc: git fetch on {}", profile);
-    let fetch_cmd = format!("cd {} && git fetch origin 2>&1", dotfiles_path);
-    let fetch_output = ssh_pool.execute_on_profile(profile, &fetch_cmd).await;
+    let fetch_cmd = in_dotfiles(dotfiles_path, git_fetch());
+    let fetch_output = transport.execute(Target::Profile(profile), &fetch_cmd).await;
     match fetch_output {
         Ok(out) if out.success() || out.exit_code == 0 => {
             results.push(DeployStepResult {
@@ -242,8 +260,8 @@ pub async fn deploy_lxc_node(
 
     // Step 3: git reset --hard origin/main
     tracing::info!("deploy-lxc: git reset on {}", profile);
-    let reset_cmd = format!("cd {} && git reset --hard origin/main 2>&1", dotfiles_path);
-    let reset_output = ssh_pool.execute_on_profile(profile, &reset_cmd).await;
+    let reset_cmd = in_dotfiles(dotfiles_path, git_reset());
+    let reset_output = transport.execute(Target::Profile(profile), &reset_cmd).await;
     match reset_output {
         Ok(out) if out.success() => {
             results.push(DeployStepResult {
@@ -272,11 +290,8 @@ pub async fn deploy_lxc_node(
 
     // Step 4: ./install.sh
     tracing::info!("deploy-lxc: install.sh on {}", profile);
-    let install_cmd = format!(
-        "cd {} && ./install.sh {} {} -s -u -q 2>&1",
-        dotfiles_path, dotfiles_path, profile
-    );
-    let install_output = ssh_pool.execute_on_profile(profile, &install_cmd).await;
+    let install_cmd = in_dotfiles(dotfiles_path, install(dotfiles_path, &profile_name));
+    let install_output = transport.execute(Target::Profile(profile), &install_cmd).await;
     match install_output {
         Ok(out) if out.success() => {
             results.push(DeployStepResult {
@@ -303,3 +318,55 @@ pub async fn deploy_lxc_node(
 
     Ok(results)
 }
+
+/// Run the deploy-lxc.sh workflow as one streamed command
+/// (git fetch, git reset --hard origin/main, install.sh), announcing each
+/// step with a `==> step` line.
+pub async fn deploy_lxc_stream(
+    transport: &dyn CommandTransport,
+    profile: &str,
+    dotfiles_path: &str,
+) -> Result<CommandStream, AppError> {
+    let profile_name = ProfileName::parse(profile)?;
+
+    tracing::info!("deploy-lxc: streaming deploy on {}", profile);
+    let command = in_dotfiles(
+        dotfiles_path,
+        step("git fetch origin")
+            .and(git_fetch())
+            .and(step("git reset --hard origin/main"))
+            .and(git_reset())
+            .and(step("install.sh"))
+            .and(install(dotfiles_path, &profile_name)),
+    );
+
+    transport.execute_streaming(Target::Profile(profile), &command).await
+}
+
+/// Run commands in the dotfiles checkout
+fn in_dotfiles(dotfiles_path: &str, command: ShellCommand) -> String {
+    ShellCommand::new("cd").path(dotfiles_path).and(command).build()
+}
+
+/// `==> step` marker line for streamed output
+fn step(name: &str) -> ShellCommand {
+    ShellCommand::new("echo").arg(format!("==> {}", name))
+}
+
+fn git_fetch() -> ShellCommand {
+    ShellCommand::new("git").args(["fetch", "origin"]).merge_stderr()
+}
+
+fn git_reset() -> ShellCommand {
+    ShellCommand::new("git")
+        .args(["reset", "--hard", "origin/main"])
+        .merge_stderr()
+}
+
+fn install(dotfiles_path: &str, profile: &ProfileName) -> ShellCommand {
+    ShellCommand::new("./install.sh")
+        .path(dotfiles_path)
+        .arg(profile)
+        .args(["-s", "-u", "-q"])
+        .merge_stderr()
+}
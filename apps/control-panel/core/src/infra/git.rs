@@ -53,7 +53,7 @@ pub fn get_status(dotfiles_path: &str) -> Result<GitStatus, AppError> {
 
     let (ahead, behind) = if let Ok(output) = ab_output {
         let s = String::from_utf8_lossy(&output.stdout);
-        let parts: Vec<&str> = s.trim().split_whitespace().collect();
+        let parts: Vec<&str> = s.split_whitespace().collect();
         if parts.len() == 2 {
             (
                 parts[0].parse().unwrap_or(0),
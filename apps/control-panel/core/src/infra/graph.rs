@@ -56,6 +56,7 @@ pub fn generate_graph_data(config: &Config) -> GraphData {
         ("_cat_lxc", "LXC Containers", "#22c55e", "lxc"),      // green
         ("_cat_vm", "Virtual Machines", "#f59e0b", "vm"),      // amber
         ("_cat_darwin", "macOS", "#8b5cf6", "darwin"),         // violet
+        ("_cat_server", "Servers", "#64748b", "server"),       // slate
     ];
 
     for (id, label, color, group) in &categories {
@@ -136,6 +137,7 @@ pub fn generate_graph_data(config: &Config) -> GraphData {
                 "lxc" => "_cat_lxc",
                 "vm" => "_cat_vm",
                 "darwin" => "_cat_darwin",
+                "server" => "_cat_server",
                 _ => "_root",
             };
             links.push(GraphLink {
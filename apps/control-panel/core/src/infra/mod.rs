@@ -19,7 +19,7 @@ pub use deploy::{DeployState, DeployStepResult, DeploymentStatus};
 pub use git::GitStatus;
 pub use graph::{GraphData, GraphLink, GraphNode};
 pub use monitoring::get_dashboard_url;
-pub use proxmox::{BackupJob, ContainerInfo};
+pub use proxmox::{BackupJob, ContainerInfo, CtidCollision, EndpointContainers};
 
 /// Profile node for graph visualization
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,6 +33,9 @@ pub struct ProfileNode {
     pub base_profile: Option<String>,
 }
 
+/// Values of a profile's `type`, as written in config.toml
+pub const PROFILE_TYPES: &[&str] = &["lxc", "desktop", "laptop", "vm", "darwin", "server"];
+
 /// Profile type
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
@@ -42,9 +45,11 @@ pub enum ProfileType {
     Lxc,
     Vm,
     Darwin,
+    Server,
 }
 
 impl ProfileType {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Self {
         match s.to_lowercase().as_str() {
             "desktop" => ProfileType::Desktop,
@@ -52,6 +57,7 @@ impl ProfileType {
             "lxc" => ProfileType::Lxc,
             "vm" => ProfileType::Vm,
             "darwin" => ProfileType::Darwin,
+            "server" => ProfileType::Server,
             _ => ProfileType::Lxc,
         }
     }
@@ -63,6 +69,7 @@ impl ProfileType {
             ProfileType::Lxc => "#22c55e",     // green
             ProfileType::Vm => "#f59e0b",      // amber
             ProfileType::Darwin => "#8b5cf6",  // violet
+            ProfileType::Server => "#64748b",  // slate
         }
     }
 
@@ -73,6 +80,7 @@ impl ProfileType {
             ProfileType::Lxc => "LXC",
             ProfileType::Vm => "VM",
             ProfileType::Darwin => "macOS",
+            ProfileType::Server => "Server",
         }
     }
 }
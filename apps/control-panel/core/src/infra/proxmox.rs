@@ -1,7 +1,16 @@
 //! Proxmox VE integration via SSH
+//!
+//! Every function runs on one Proxmox endpoint (`[[proxmox]]` in the
+//! config), named by `endpoint`. `pct` only sees the containers of the node
+//! it runs on, so each node of a cluster is an endpoint of its own, tied
+//! together by `cluster`.
 
+use std::collections::BTreeMap;
+
+use crate::config::ProxmoxConfig;
 use crate::error::AppError;
-use crate::ssh::SshPool;
+use crate::shell::{BackupJobId, Ctid, ShellCommand, StorageName};
+use crate::transport::{CommandTransport, Target};
 use serde::Serialize;
 
 /// Container status from Proxmox
@@ -10,12 +19,36 @@ pub struct ContainerInfo {
     pub ctid: u32,
     pub name: String,
     pub status: String,
+    /// Endpoint the container was listed on
+    pub endpoint: String,
+}
+
+/// Containers of one Proxmox endpoint
+#[derive(Debug)]
+pub struct EndpointContainers {
+    pub endpoint: String,
+    pub cluster: String,
+    pub host: String,
+    /// The containers, or why they couldn't be listed
+    pub containers: Result<Vec<ContainerInfo>, AppError>,
+}
+
+/// A CTID in use on more than one endpoint
+#[derive(Debug, Clone, Serialize)]
+pub struct CtidCollision {
+    pub ctid: u32,
+    /// Endpoint and container name of each use
+    pub containers: Vec<(String, String)>,
+    /// The endpoints are in different clusters. Expected for separate
+    /// sites, but a host bound to the wrong endpoint acts on the other
+    /// container.
+    pub across_clusters: bool,
 }
 
 /// Start a container on Proxmox
-pub async fn start_container(ssh_pool: &mut SshPool, ctid: u32) -> Result<(), AppError> {
-    let command = format!("pct start {}", ctid);
-    let output = ssh_pool.execute_on_proxmox(&command).await?;
+pub async fn start_container(transport: &dyn CommandTransport, endpoint: &str, ctid: u32) -> Result<(), AppError> {
+    let command = ShellCommand::new("pct").arg("start").arg(Ctid::new(ctid)?.to_string()).build();
+    let output = transport.execute(Target::Proxmox(endpoint), &command).await?;
     if !output.success() {
         return Err(AppError::SshCommand(format!(
             "Failed to start container {}: {}",
@@ -23,14 +56,14 @@ pub async fn start_container(ssh_pool: &mut SshPool, ctid: u32) -> Result<(), Ap
             output.combined()
         )));
     }
-    tracing::info!("Started container {} on Proxmox", ctid);
+    tracing::info!("Started container {} on Proxmox {}", ctid, endpoint);
     Ok(())
 }
 
 /// Stop a container on Proxmox
-pub async fn stop_container(ssh_pool: &mut SshPool, ctid: u32) -> Result<(), AppError> {
-    let command = format!("pct stop {}", ctid);
-    let output = ssh_pool.execute_on_proxmox(&command).await?;
+pub async fn stop_container(transport: &dyn CommandTransport, endpoint: &str, ctid: u32) -> Result<(), AppError> {
+    let command = ShellCommand::new("pct").arg("stop").arg(Ctid::new(ctid)?.to_string()).build();
+    let output = transport.execute(Target::Proxmox(endpoint), &command).await?;
     if !output.success() {
         return Err(AppError::SshCommand(format!(
             "Failed to stop container {}: {}",
@@ -38,14 +71,14 @@ pub async fn stop_container(ssh_pool: &mut SshPool, ctid: u32) -> Result<(), App
             output.combined()
         )));
     }
-    tracing::info!("Stopped container {} on Proxmox", ctid);
+    tracing::info!("Stopped container {} on Proxmox {}", ctid, endpoint);
     Ok(())
 }
 
 /// Restart a container on Proxmox
-pub async fn restart_container(ssh_pool: &mut SshPool, ctid: u32) -> Result<(), AppError> {
-    let command = format!("pct restart {}", ctid);
-    let output = ssh_pool.execute_on_proxmox(&command).await?;
+pub async fn restart_container(transport: &dyn CommandTransport, endpoint: &str, ctid: u32) -> Result<(), AppError> {
+    let command = ShellCommand::new("pct").arg("restart").arg(Ctid::new(ctid)?.to_string()).build();
+    let output = transport.execute(Target::Proxmox(endpoint), &command).await?;
     if !output.success() {
         return Err(AppError::SshCommand(format!(
             "Failed to restart container {}: {}",
@@ -53,14 +86,18 @@ pub async fn restart_container(ssh_pool: &mut SshPool, ctid: u32) -> Result<(),
             output.combined()
         )));
     }
-    tracing::info!("Restarted container {} on Proxmox", ctid);
+    tracing::info!("Restarted container {} on Proxmox {}", ctid, endpoint);
     Ok(())
 }
 
 /// Get container status from Proxmox
-pub async fn get_container_status(ssh_pool: &mut SshPool, ctid: u32) -> Result<String, AppError> {
-    let command = format!("pct status {}", ctid);
-    let output = ssh_pool.execute_on_proxmox(&command).await?;
+pub async fn get_container_status(
+    transport: &dyn CommandTransport,
+    endpoint: &str,
+    ctid: u32,
+) -> Result<String, AppError> {
+    let command = ShellCommand::new("pct").arg("status").arg(Ctid::new(ctid)?.to_string()).build();
+    let output = transport.execute_read(Target::Proxmox(endpoint), &command).await?;
 
     // Parse "status: running" or "status: stopped"
     let status = output
@@ -74,33 +111,76 @@ pub async fn get_container_status(ssh_pool: &mut SshPool, ctid: u32) -> Result<S
 }
 
 /// List all LXC containers on Proxmox
-pub async fn list_containers(ssh_pool: &mut SshPool) -> Result<Vec<ContainerInfo>, AppError> {
-    let command = "pct list";
-    let output = ssh_pool.execute_on_proxmox(command).await?;
+pub async fn list_containers(transport: &dyn CommandTransport, endpoint: &str) -> Result<Vec<ContainerInfo>, AppError> {
+    let command = ShellCommand::new("pct").arg("list").build();
+    let output = transport.execute_read(Target::Proxmox(endpoint), &command).await?;
 
     let containers = output
         .stdout
         .lines()
         .skip(1) // Skip header
         .filter(|l| !l.trim().is_empty())
-        .filter_map(parse_pct_list_line)
+        .filter_map(|l| parse_pct_list_line(l, endpoint))
         .collect();
 
     Ok(containers)
 }
 
+/// Containers of every endpoint, queried concurrently, in config order
+pub async fn list_all_containers(
+    transport: &dyn CommandTransport,
+    endpoints: &[ProxmoxConfig],
+) -> Vec<EndpointContainers> {
+    futures::future::join_all(endpoints.iter().map(|endpoint| async move {
+        let containers = list_containers(transport, &endpoint.name).await;
+        if let Err(ref e) = containers {
+            tracing::warn!("Failed to list containers on Proxmox {}: {}", endpoint.name, e);
+        }
+        EndpointContainers {
+            endpoint: endpoint.name.clone(),
+            cluster: endpoint.cluster_name().to_string(),
+            host: endpoint.host.clone(),
+            containers,
+        }
+    }))
+    .await
+}
+
+/// CTIDs listed on more than one endpoint
+pub fn ctid_collisions(lists: &[EndpointContainers]) -> Vec<CtidCollision> {
+    let mut by_ctid: BTreeMap<u32, Vec<(&str, &ContainerInfo)>> = BTreeMap::new();
+    for list in lists {
+        for container in list.containers.iter().flatten() {
+            by_ctid.entry(container.ctid).or_default().push((&list.cluster, container));
+        }
+    }
+
+    by_ctid
+        .into_iter()
+        .filter(|(_, uses)| uses.len() > 1)
+        .map(|(ctid, uses)| CtidCollision {
+            ctid,
+            across_clusters: uses.iter().any(|(cluster, _)| *cluster != uses[0].0),
+            containers: uses
+                .iter()
+                .map(|(_, c)| (c.endpoint.clone(), c.name.clone()))
+                .collect(),
+        })
+        .collect()
+}
+
 /// List all VMs on Proxmox
 #[allow(dead_code)]
-pub async fn list_vms(ssh_pool: &mut SshPool) -> Result<Vec<ContainerInfo>, AppError> {
-    let command = "qm list";
-    let output = ssh_pool.execute_on_proxmox(command).await?;
+pub async fn list_vms(transport: &dyn CommandTransport, endpoint: &str) -> Result<Vec<ContainerInfo>, AppError> {
+    let command = ShellCommand::new("qm").arg("list").build();
+    let output = transport.execute_read(Target::Proxmox(endpoint), &command).await?;
 
     let vms = output
         .stdout
         .lines()
         .skip(1) // Skip header
         .filter(|l| !l.trim().is_empty())
-        .filter_map(parse_qm_list_line)
+        .filter_map(|l| parse_qm_list_line(l, endpoint))
         .collect();
 
     Ok(vms)
@@ -108,7 +188,7 @@ pub async fn list_vms(ssh_pool: &mut SshPool) -> Result<Vec<ContainerInfo>, AppE
 
 /// Parse a line from `pct list` output
 /// Format: VMID Status Lock Name
-fn parse_pct_list_line(line: &str) -> Option<ContainerInfo> {
+fn parse_pct_list_line(line: &str, endpoint: &str) -> Option<ContainerInfo> {
     let parts: Vec<&str> = line.split_whitespace().collect();
     if parts.len() < 3 {
         return None;
@@ -123,12 +203,17 @@ fn parse_pct_list_line(line: &str) -> Option<ContainerInfo> {
         parts[2].to_string()
     };
 
-    Some(ContainerInfo { ctid, name, status })
+    Some(ContainerInfo {
+        ctid,
+        name,
+        status,
+        endpoint: endpoint.to_string(),
+    })
 }
 
 /// Parse a line from `qm list` output
 /// Format: VMID NAME STATUS MEM(MB) BOOTDISK(GB) PID
-fn parse_qm_list_line(line: &str) -> Option<ContainerInfo> {
+fn parse_qm_list_line(line: &str, endpoint: &str) -> Option<ContainerInfo> {
     let parts: Vec<&str> = line.split_whitespace().collect();
     if parts.len() < 3 {
         return None;
@@ -138,13 +223,22 @@ fn parse_qm_list_line(line: &str) -> Option<ContainerInfo> {
     let name = parts[1].to_string();
     let status = parts[2].to_string();
 
-    Some(ContainerInfo { ctid, name, status })
+    Some(ContainerInfo {
+        ctid,
+        name,
+        status,
+        endpoint: endpoint.to_string(),
+    })
 }
 
 /// Check if a container is running
 #[allow(dead_code)]
-pub async fn is_container_running(ssh_pool: &mut SshPool, ctid: u32) -> Result<bool, AppError> {
-    let status = get_container_status(ssh_pool, ctid).await?;
+pub async fn is_container_running(
+    transport: &dyn CommandTransport,
+    endpoint: &str,
+    ctid: u32,
+) -> Result<bool, AppError> {
+    let status = get_container_status(transport, endpoint, ctid).await?;
     Ok(status == "running")
 }
 
@@ -155,6 +249,8 @@ pub async fn is_container_running(ssh_pool: &mut SshPool, ctid: u32) -> Result<b
 /// Backup job information
 #[derive(Debug, Clone, Serialize)]
 pub struct BackupJob {
+    /// Endpoint the job was listed on (jobs are defined per cluster)
+    pub endpoint: String,
     pub id: String,
     pub schedule: String,
     pub storage: String,
@@ -165,10 +261,14 @@ pub struct BackupJob {
 }
 
 /// List all backup jobs
-pub async fn list_backup_jobs(ssh_pool: &mut SshPool) -> Result<Vec<BackupJob>, AppError> {
+pub async fn list_backup_jobs(transport: &dyn CommandTransport, endpoint: &str) -> Result<Vec<BackupJob>, AppError> {
     // Read vzdump.cron and /etc/pve/jobs.cfg for scheduled backups
-    let command = "cat /etc/pve/jobs.cfg 2>/dev/null || echo ''";
-    let output = ssh_pool.execute_on_proxmox(command).await?;
+    let command = ShellCommand::new("cat")
+        .arg("/etc/pve/jobs.cfg")
+        .raw("2>/dev/null")
+        .or(ShellCommand::new("echo").arg(""))
+        .build();
+    let output = transport.execute_read(Target::Proxmox(endpoint), &command).await?;
 
     let mut jobs = Vec::new();
     let mut current_job: Option<BackupJob> = None;
@@ -184,6 +284,7 @@ pub async fn list_backup_jobs(ssh_pool: &mut SshPool) -> Result<Vec<BackupJob>,
             // Start new job
             let id = line.trim_start_matches("vzdump:").trim().to_string();
             current_job = Some(BackupJob {
+                endpoint: endpoint.to_string(),
                 id,
                 schedule: String::new(),
                 storage: String::new(),
@@ -216,10 +317,19 @@ pub async fn list_backup_jobs(ssh_pool: &mut SshPool) -> Result<Vec<BackupJob>,
 }
 
 /// Run a backup job manually
-pub async fn run_backup_job(ssh_pool: &mut SshPool, job_id: &str) -> Result<String, AppError> {
+pub async fn run_backup_job(
+    transport: &dyn CommandTransport,
+    endpoint: &str,
+    job_id: &str,
+) -> Result<String, AppError> {
+    let job_id = BackupJobId::parse(job_id)?;
+
     // Trigger the vzdump job
-    let command = format!("pvesh create /cluster/backup/{}/run", job_id);
-    let output = ssh_pool.execute_on_proxmox(&command).await?;
+    let command = ShellCommand::new("pvesh")
+        .arg("create")
+        .arg(format!("/cluster/backup/{}/run", job_id))
+        .build();
+    let output = transport.execute(Target::Proxmox(endpoint), &command).await?;
 
     if !output.success() {
         return Err(AppError::SshCommand(format!(
@@ -229,20 +339,31 @@ pub async fn run_backup_job(ssh_pool: &mut SshPool, job_id: &str) -> Result<Stri
         )));
     }
 
-    tracing::info!("Started backup job {} on Proxmox", job_id);
+    tracing::info!("Started backup job {} on Proxmox {}", job_id, endpoint);
     Ok(output.combined())
 }
 
 /// Backup a specific container/VM immediately
 #[allow(dead_code)]
 pub async fn backup_container(
-    ssh_pool: &mut SshPool,
+    transport: &dyn CommandTransport,
+    endpoint: &str,
     ctid: u32,
     storage: &str,
     mode: &str,
 ) -> Result<String, AppError> {
-    let command = format!("vzdump {} --storage {} --mode {} --compress zstd", ctid, storage, mode);
-    let output = ssh_pool.execute_on_proxmox(&command).await?;
+    let storage = StorageName::parse(storage)?;
+    if !matches!(mode, "snapshot" | "suspend" | "stop") {
+        return Err(AppError::Validation(format!("Invalid backup mode: {:?}", mode)));
+    }
+
+    let command = ShellCommand::new("vzdump")
+        .arg(Ctid::new(ctid)?.to_string())
+        .arg("--storage")
+        .arg(&storage)
+        .args(["--mode", mode, "--compress", "zstd"])
+        .build();
+    let output = transport.execute(Target::Proxmox(endpoint), &command).await?;
 
     if !output.success() {
         return Err(AppError::SshCommand(format!(
@@ -252,15 +373,26 @@ pub async fn backup_container(
         )));
     }
 
-    tracing::info!("Started backup for container {} on Proxmox", ctid);
+    tracing::info!("Started backup for container {} on Proxmox {}", ctid, endpoint);
     Ok(output.combined())
 }
 
 /// List recent backups for a container/VM
 #[allow(dead_code)]
-pub async fn list_backups(ssh_pool: &mut SshPool, storage: &str) -> Result<Vec<String>, AppError> {
-    let command = format!("pvesm list {} --content backup 2>/dev/null | tail -20", storage);
-    let output = ssh_pool.execute_on_proxmox(&command).await?;
+pub async fn list_backups(
+    transport: &dyn CommandTransport,
+    endpoint: &str,
+    storage: &str,
+) -> Result<Vec<String>, AppError> {
+    let storage = StorageName::parse(storage)?;
+    let command = ShellCommand::new("pvesm")
+        .arg("list")
+        .arg(&storage)
+        .args(["--content", "backup"])
+        .raw("2>/dev/null")
+        .pipe(ShellCommand::new("tail").arg("-20"))
+        .build();
+    let output = transport.execute_read(Target::Proxmox(endpoint), &command).await?;
 
     let backups: Vec<String> = output
         .stdout
@@ -272,3 +404,79 @@ pub async fn list_backups(ssh_pool: &mut SshPool, storage: &str) -> Result<Vec<S
 
     Ok(backups)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::fixture::Fixture;
+    use crate::transport::FixtureTransport;
+
+    fn homelab() -> FixtureTransport {
+        FixtureTransport::from_toml(include_str!("../../fixtures/homelab.toml")).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_list_containers_from_fixture() {
+        let containers = list_containers(&homelab(), "home").await.unwrap();
+        assert_eq!(containers.len(), 3);
+        assert_eq!(containers[0].ctid, 205);
+        assert_eq!(containers[0].name, "LXC-HOME");
+        assert_eq!(containers[2].status, "stopped");
+        assert_eq!(containers[2].name, "LXC-monitoring");
+        assert_eq!(containers[2].endpoint, "home");
+    }
+
+    #[tokio::test]
+    async fn test_list_backup_jobs_from_fixture() {
+        let jobs = list_backup_jobs(&homelab(), "home").await.unwrap();
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(jobs[0].id, "backup-5b1e6a4f-7d3e");
+        assert_eq!(jobs[0].vmids, "205,206,207");
+        assert!(jobs[0].enabled);
+        assert_eq!(jobs[1].vmids, "all");
+        assert!(!jobs[1].enabled);
+        assert_eq!(jobs[1].comment.as_deref(), Some("Weekly full backup"));
+    }
+
+    #[tokio::test]
+    async fn test_start_container_command() {
+        let transport = FixtureTransport::new().on("pct start 205", "");
+        start_container(&transport, "komi", 205).await.unwrap();
+        assert_eq!(transport.calls(), vec!["proxmox:komi: pct start 205"]);
+        assert!(start_container(&transport, "komi", 7).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ctid_collisions() {
+        let komi = Fixture {
+            target: Some("proxmox:komi".to_string()),
+            command: "pct list".to_string(),
+            stdout: "VMID       Status     Lock         Name\n205        running                 proxy\n300        running                 mail\n"
+                .to_string(),
+            stderr: String::new(),
+            exit_code: 0,
+        };
+        let endpoint = |name: &str, cluster: Option<&str>| ProxmoxConfig {
+            name: name.to_string(),
+            cluster: cluster.map(str::to_string),
+            ..ProxmoxConfig::default()
+        };
+        let endpoints = [endpoint("home", Some("home")), endpoint("komi", None), endpoint("offline", None)];
+
+        let lists = list_all_containers(&homelab().with(komi), &endpoints).await;
+        assert_eq!(lists[0].containers.as_ref().unwrap().len(), 3);
+        assert_eq!(lists[1].cluster, "komi");
+        assert_eq!(lists[1].containers.as_ref().unwrap().len(), 2);
+        // The homelab fixture answers `pct list` on any endpoint
+        assert_eq!(lists[2].containers.as_ref().unwrap().len(), 3);
+
+        let collisions = ctid_collisions(&lists[..2]);
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(collisions[0].ctid, 205);
+        assert!(collisions[0].across_clusters);
+        assert_eq!(
+            collisions[0].containers,
+            vec![("home".to_string(), "LXC-HOME".to_string()), ("komi".to_string(), "proxy".to_string())]
+        );
+    }
+}
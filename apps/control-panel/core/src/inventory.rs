@@ -0,0 +1,568 @@
+//! Fleet inventory import
+//!
+//! Profiles can be sourced from the files the dotfiles scripts already use,
+//! instead of repeating them in config.toml:
+//!
+//! - `deploy-servers.conf` (and `deploy-servers-private.conf`), read by
+//!   deploy.sh: `@GROUP|ICON` headers followed by
+//!   `PROFILE|USER|IPS|DESCRIPTION|COMMAND|SSH_TIMEOUT` lines
+//! - `profiles/registry.toml`: `config_file`, `profile_dir`, `hostname`,
+//!   `description` and `tags` per profile
+//!
+//! `@GROUP` headers become `[[groups]]` (with their icon) unless config.toml
+//! defines the group; registry tags are added to a profile's own.
+//!
+//! `[[profiles]]` entries in config.toml override imported values field by
+//! field; wherever config.toml and a source (or two sources) disagree, the
+//! difference is reported as a `Mismatch`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, GroupConfig, ProfileConfig, TransportKind};
+use crate::error::AppError;
+
+/// A server line of deploy-servers.conf
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeployServer {
+    pub profile: String,
+    pub user: String,
+    /// Addresses in the order deploy.sh tries them
+    pub addresses: Vec<String>,
+    pub description: String,
+    /// Install command (`{DIR}` and `{PROFILE}` placeholders unexpanded)
+    pub command: String,
+    pub ssh_timeout: Option<u64>,
+    /// Group header the line is under (`@GROUP|ICON`)
+    pub group: Option<String>,
+    pub group_icon: Option<String>,
+}
+
+/// A profile of profiles/registry.toml
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct RegistryEntry {
+    #[serde(default)]
+    pub config_file: Option<String>,
+    #[serde(default)]
+    pub profile_dir: Option<String>,
+    #[serde(default)]
+    pub hostname: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RegistryFile {
+    #[serde(default)]
+    profiles: BTreeMap<String, RegistryEntry>,
+}
+
+/// Where a profile value comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InventorySource {
+    ConfigToml,
+    DeployServers,
+    Registry,
+}
+
+impl fmt::Display for InventorySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InventorySource::ConfigToml => write!(f, "config.toml"),
+            InventorySource::DeployServers => write!(f, "deploy-servers.conf"),
+            InventorySource::Registry => write!(f, "registry.toml"),
+        }
+    }
+}
+
+/// A profile field the sources disagree on
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Mismatch {
+    pub profile: String,
+    pub field: String,
+    /// Value in each source (`None`: the source doesn't have the profile)
+    pub values: Vec<(InventorySource, Option<String>)>,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let values: Vec<String> = self
+            .values
+            .iter()
+            .map(|(source, value)| format!("{} = {}", source, value.as_deref().unwrap_or("(missing)")))
+            .collect();
+        write!(f, "{} {}: {}", self.profile, self.field, values.join(", "))
+    }
+}
+
+/// A source file that was read (or couldn't be)
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceStatus {
+    pub source: InventorySource,
+    pub path: PathBuf,
+    /// Profiles read from the file
+    pub entries: usize,
+    /// Why the file was skipped
+    pub error: Option<String>,
+}
+
+/// Outcome of an import
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct InventoryReport {
+    pub sources: Vec<SourceStatus>,
+    /// Profiles added that config.toml doesn't list
+    pub imported: Vec<String>,
+    pub mismatches: Vec<Mismatch>,
+}
+
+/// Group name and icon of a deploy-servers.conf `@` header
+pub type DeployGroup = (String, String);
+
+/// Parse deploy-servers.conf. Lines before the first group header have no
+/// group; `group` carries a group over from a previous file, like deploy.sh.
+pub fn parse_deploy_servers(
+    content: &str,
+    mut group: Option<DeployGroup>,
+) -> Result<(Vec<DeployServer>, Option<DeployGroup>), AppError> {
+    let mut servers = Vec::new();
+
+    for (number, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        if let Some(header) = trimmed.strip_prefix('@') {
+            let (name, icon) = header.split_once('|').unwrap_or((header, ""));
+            group = Some((name.trim().to_string(), icon.trim().to_string()));
+            continue;
+        }
+
+        // Like `read -r prof user ips desc cmd timeout`: the last field takes
+        // the rest of the line
+        let fields: Vec<&str> = trimmed.splitn(6, '|').map(str::trim).collect();
+        if fields.len() < 3 || fields[0].is_empty() {
+            return Err(AppError::Config(format!(
+                "deploy-servers line {}: expected PROFILE|USER|IPS|DESCRIPTION|COMMAND|SSH_TIMEOUT",
+                number + 1
+            )));
+        }
+        let field = |i: usize| fields.get(i).copied().unwrap_or_default().to_string();
+        let ssh_timeout = match fields.get(5).filter(|t| !t.is_empty()) {
+            Some(timeout) => Some(timeout.parse().map_err(|_| {
+                AppError::Config(format!(
+                    "deploy-servers line {}: SSH_TIMEOUT {:?} is not a number",
+                    number + 1,
+                    timeout
+                ))
+            })?),
+            None => None,
+        };
+
+        servers.push(DeployServer {
+            profile: field(0),
+            user: field(1),
+            addresses: fields[2]
+                .split(',')
+                .map(str::trim)
+                .filter(|a| !a.is_empty())
+                .map(String::from)
+                .collect(),
+            description: field(3),
+            command: field(4),
+            ssh_timeout,
+            group: group.as_ref().map(|(name, _)| name.clone()),
+            group_icon: group.as_ref().map(|(_, icon)| icon.clone()).filter(|i| !i.is_empty()),
+        });
+    }
+
+    Ok((servers, group))
+}
+
+/// Parse profiles/registry.toml
+pub fn parse_registry(content: &str) -> Result<BTreeMap<String, RegistryEntry>, AppError> {
+    let file: RegistryFile = toml::from_str(content)
+        .map_err(|e| AppError::Config(format!("Invalid registry.toml: {}", e)))?;
+    Ok(file.profiles)
+}
+
+/// Read a source file. git-crypt locked files are reported, not parsed.
+fn read_source(path: &Path) -> Result<String, String> {
+    let data = std::fs::read(path).map_err(|e| e.to_string())?;
+    if data.starts_with(b"\0GITCRYPT") {
+        return Err("encrypted (git-crypt is locked)".to_string());
+    }
+    String::from_utf8(data).map_err(|_| "not a text file".to_string())
+}
+
+/// Profile type guessed from the name and registry directory, for profiles
+/// config.toml doesn't list. Always one of `infra::PROFILE_TYPES`.
+fn guess_type(name: &str, registry: Option<&RegistryEntry>) -> String {
+    let dir = registry.and_then(|r| r.profile_dir.as_deref()).unwrap_or_default();
+    let upper = name.to_uppercase();
+    let kind = if dir == "darwin" || upper.starts_with("MACBOOK") {
+        "darwin"
+    } else if upper.contains("LXC") {
+        "lxc"
+    } else if upper.starts_with("LAPTOP") {
+        "laptop"
+    } else if upper.starts_with("DESK") {
+        "desktop"
+    } else if upper.starts_with("VM") || upper.starts_with("VPS") {
+        "vm"
+    } else {
+        "server"
+    };
+    kind.to_string()
+}
+
+fn is_ip(address: &str) -> bool {
+    address.parse::<std::net::IpAddr>().is_ok()
+}
+
+/// Profile built from the sources alone
+fn imported_profile(name: &str, server: Option<&DeployServer>, registry: Option<&RegistryEntry>) -> ProfileConfig {
+    let addresses = server.map(|s| s.addresses.clone()).unwrap_or_default();
+    let hostname = registry
+        .and_then(|r| r.hostname.clone())
+        .or_else(|| addresses.iter().find(|a| !is_ip(a)).cloned())
+        .unwrap_or_else(|| name.to_lowercase());
+
+    ProfileConfig {
+        name: name.to_string(),
+        profile_type: guess_type(name, registry),
+        hostname,
+        ip: addresses.first().cloned(),
+        ctid: None,
+        base_profile: None,
+        user: server.map(|s| s.user.clone()).filter(|u| !u.is_empty()),
+        port: None,
+        identity_file: None,
+        addresses: addresses.iter().skip(1).cloned().collect(),
+        ssh_timeout: server.and_then(|s| s.ssh_timeout),
+        site: None,
+        jump_host: None,
+        proxmox: None,
+        transport: TransportKind::default(),
+        description: server
+            .map(|s| s.description.clone())
+            .filter(|d| !d.is_empty())
+            .or_else(|| registry.and_then(|r| r.description.clone())),
+        group: server.and_then(|s| s.group.clone()),
+        tags: registry.map(|r| r.tags.clone()).unwrap_or_default(),
+        deploy_command: server.map(|s| s.command.clone()).filter(|c| !c.is_empty()),
+        config_file: registry.and_then(|r| r.config_file.clone()),
+    }
+}
+
+/// Fill the fields config.toml leaves unset from the imported profile,
+/// reporting fields both set differently
+fn merge_profile(profile: &mut ProfileConfig, imported: ProfileConfig, in_registry: bool, mismatches: &mut Vec<Mismatch>) {
+    let mut report = |field: &str, configured: String, source: InventorySource, value: String| {
+        if configured != value {
+            mismatches.push(Mismatch {
+                profile: imported.name.clone(),
+                field: field.to_string(),
+                values: vec![(InventorySource::ConfigToml, Some(configured)), (source, Some(value))],
+            });
+        }
+    };
+
+    if in_registry && profile.hostname != imported.hostname {
+        report("hostname", profile.hostname.clone(), InventorySource::Registry, imported.hostname.clone());
+    }
+
+    let imported_addresses = imported.all_addresses();
+    if profile.ip.is_some() && !imported_addresses.is_empty() {
+        report(
+            "addresses",
+            profile.all_addresses().join(","),
+            InventorySource::DeployServers,
+            imported_addresses.join(","),
+        );
+    }
+    if let (Some(user), Some(imported_user)) = (&profile.user, &imported.user) {
+        report("user", user.clone(), InventorySource::DeployServers, imported_user.clone());
+    }
+    if let (Some(timeout), Some(imported_timeout)) = (profile.ssh_timeout, imported.ssh_timeout) {
+        report(
+            "ssh_timeout",
+            timeout.to_string(),
+            InventorySource::DeployServers,
+            imported_timeout.to_string(),
+        );
+    }
+
+    if profile.ip.is_none() {
+        profile.ip = imported.ip;
+        if profile.addresses.is_empty() {
+            profile.addresses = imported.addresses;
+        }
+    }
+    profile.user = profile.user.take().or(imported.user);
+    profile.ssh_timeout = profile.ssh_timeout.or(imported.ssh_timeout);
+    profile.description = profile.description.take().or(imported.description);
+    profile.group = profile.group.take().or(imported.group);
+    for tag in imported.tags {
+        if !profile.tags.contains(&tag) {
+            profile.tags.push(tag);
+        }
+    }
+    profile.deploy_command = profile.deploy_command.take().or(imported.deploy_command);
+    profile.config_file = profile.config_file.take().or(imported.config_file);
+}
+
+/// Merge the inventory sources into `config.profiles`
+pub fn import(config: &mut Config) -> InventoryReport {
+    let base = crate::config::expand_home(&config.dotfiles.path);
+    let mut report = InventoryReport::default();
+
+    let mut servers: Vec<DeployServer> = Vec::new();
+    let mut group = None;
+    for file in &config.inventory.deploy_servers {
+        let path = base.join(file);
+        let parsed = read_source(&path).and_then(|content| {
+            parse_deploy_servers(&content, group.clone()).map_err(|e| e.to_string())
+        });
+        let (entries, error) = match parsed {
+            Ok((file_servers, last_group)) => {
+                group = last_group;
+                let count = file_servers.len();
+                servers.extend(file_servers);
+                (count, None)
+            }
+            Err(e) => (0, Some(e)),
+        };
+        report.sources.push(SourceStatus {
+            source: InventorySource::DeployServers,
+            path,
+            entries,
+            error,
+        });
+    }
+
+    let mut registry = BTreeMap::new();
+    if let Some(file) = config.inventory.registry.as_deref().filter(|f| !f.is_empty()) {
+        let path = base.join(file);
+        let parsed = read_source(&path).and_then(|content| parse_registry(&content).map_err(|e| e.to_string()));
+        let (entries, error) = match parsed {
+            Ok(entries) => {
+                let count = entries.len();
+                registry = entries;
+                (count, None)
+            }
+            Err(e) => (0, Some(e)),
+        };
+        report.sources.push(SourceStatus {
+            source: InventorySource::Registry,
+            path,
+            entries,
+            error,
+        });
+    }
+    for status in report.sources.iter().filter(|s| s.error.is_some()) {
+        tracing::warn!(
+            "Inventory source {} skipped: {}",
+            status.path.display(),
+            status.error.as_deref().unwrap_or_default()
+        );
+    }
+
+    // Every name from any source, deploy-servers order first
+    let mut names: Vec<String> = Vec::new();
+    for name in servers.iter().map(|s| &s.profile).chain(registry.keys()) {
+        if !names.contains(name) {
+            names.push(name.clone());
+        }
+    }
+
+    let registry_loaded = report
+        .sources
+        .iter()
+        .any(|s| s.source == InventorySource::Registry && s.error.is_none());
+    for name in &names {
+        // Later lines win, like a second deploy-servers file overriding
+        let server = servers.iter().rev().find(|s| &s.profile == name);
+        let entry = registry.get(name);
+        if let (Some(_), None, true) = (server, entry, registry_loaded) {
+            report.mismatches.push(Mismatch {
+                profile: name.clone(),
+                field: "profile".to_string(),
+                values: vec![
+                    (InventorySource::DeployServers, Some("listed".to_string())),
+                    (InventorySource::Registry, None),
+                ],
+            });
+        }
+        let imported = imported_profile(name, server, entry);
+        match config.profiles.iter_mut().find(|p| &p.name == name) {
+            Some(profile) => merge_profile(profile, imported, entry.is_some(), &mut report.mismatches),
+            None => {
+                report.imported.push(name.clone());
+                config.profiles.push(imported);
+            }
+        }
+    }
+
+    // Group headers, unless config.toml defines the group
+    for server in &servers {
+        let Some(ref name) = server.group else {
+            continue;
+        };
+        match config.groups.iter_mut().find(|g| &g.name == name) {
+            Some(group) => group.icon = group.icon.take().or_else(|| server.group_icon.clone()),
+            None => config.groups.push(GroupConfig {
+                name: name.clone(),
+                icon: server.group_icon.clone(),
+                members: Vec::new(),
+            }),
+        }
+    }
+
+    // Profiles only config.toml knows about
+    if !names.is_empty() {
+        for profile in &config.profiles {
+            if !names.contains(&profile.name) {
+                report.mismatches.push(Mismatch {
+                    profile: profile.name.clone(),
+                    field: "profile".to_string(),
+                    values: vec![
+                        (InventorySource::ConfigToml, Some("listed".to_string())),
+                        (InventorySource::DeployServers, None),
+                        (InventorySource::Registry, None),
+                    ],
+                });
+            }
+        }
+    }
+
+    tracing::info!(
+        "Inventory: {} profiles imported, {} mismatches",
+        report.imported.len(),
+        report.mismatches.len()
+    );
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::minimal_toml;
+    use crate::infra::PROFILE_TYPES;
+    use tempfile::TempDir;
+
+    const SERVERS: &str = "\
+# comment
+@Laptops|󰌢
+LAPTOP_X13|akunito|192.168.8.92,192.168.8.93,100.64.0.8,nixosx13aku|ThinkPad X13|./install.sh {DIR} {PROFILE} -s -u|5
+@VPS Servers|󰒋
+VPS_PROD|akunito|vps-prod,172.26.5.155|Netcup|./install.sh {DIR} {PROFILE} -s -u -d|15
+";
+
+    const REGISTRY: &str = r#"
+[profiles.LAPTOP_X13]
+config_file = "profiles/LAPTOP_X13-config.nix"
+profile_dir = "personal"
+hostname = "nixosx13aku"
+description = "AMD laptop"
+tags = ["critical", "role:dev"]
+
+[profiles.DESK]
+config_file = "profiles/DESK-config.nix"
+hostname = "nixosaku"
+"#;
+
+    #[test]
+    fn test_parse_deploy_servers() {
+        let (servers, group) = parse_deploy_servers(SERVERS, None).unwrap();
+        assert_eq!(servers.len(), 2);
+        assert_eq!(servers[0].profile, "LAPTOP_X13");
+        assert_eq!(servers[0].addresses.len(), 4);
+        assert_eq!(servers[0].ssh_timeout, Some(5));
+        assert_eq!(servers[0].group.as_deref(), Some("Laptops"));
+        assert_eq!(servers[1].command, "./install.sh {DIR} {PROFILE} -s -u -d");
+        assert_eq!(group.map(|g| g.0), Some("VPS Servers".to_string()));
+
+        // A private file continues the last group of the previous one
+        let (more, _) = parse_deploy_servers("VPS_TEST|root|10.0.0.1|Test|true|", Some(("VPS Servers".into(), String::new()))).unwrap();
+        assert_eq!(more[0].group.as_deref(), Some("VPS Servers"));
+        assert_eq!(more[0].ssh_timeout, None);
+
+        assert!(parse_deploy_servers("BROKEN", None).is_err());
+        assert!(parse_deploy_servers("X|u|1.2.3.4|d|c|soon", None).is_err());
+    }
+
+    #[test]
+    fn test_merge_with_overrides() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        std::fs::create_dir_all(dir.join("profiles")).unwrap();
+        std::fs::write(dir.join("deploy-servers.conf"), SERVERS).unwrap();
+        std::fs::write(dir.join("deploy-servers-private.conf"), b"\0GITCRYPT\0\x12\x34").unwrap();
+        std::fs::write(dir.join("profiles/registry.toml"), REGISTRY).unwrap();
+
+        let mut config: Config = toml::from_str(&minimal_toml(
+            dir,
+            r#"
+[inventory]
+enabled = true
+
+[[profiles]]
+name = "LAPTOP_X13"
+type = "laptop"
+hostname = "nixosx13aku"
+ip = "192.168.8.92"
+ssh_timeout = 3
+tags = ["role:dev"]
+
+[[groups]]
+name = "Laptops"
+members = ["DESK"]
+"#,
+        ))
+        .unwrap();
+
+        let report = import(&mut config);
+
+        assert!(report.sources[1].error.as_deref().unwrap().contains("git-crypt"));
+        assert_eq!(report.imported, vec!["VPS_PROD", "DESK"]);
+
+        let laptop = config.get_profile("LAPTOP_X13").unwrap();
+        assert_eq!(laptop.ssh_timeout, Some(3));
+        assert_eq!(laptop.user.as_deref(), Some("akunito"));
+        assert_eq!(laptop.group.as_deref(), Some("Laptops"));
+        assert_eq!(laptop.config_file.as_deref(), Some("profiles/LAPTOP_X13-config.nix"));
+        assert_eq!(laptop.tags, vec!["role:dev", "critical"]);
+
+        let groups: Vec<(&str, Option<&str>)> = config.groups.iter().map(|g| (g.name.as_str(), g.icon.as_deref())).collect();
+        assert_eq!(groups, vec![("Laptops", Some("󰌢")), ("VPS Servers", Some("󰒋"))]);
+        assert_eq!(config.groups[0].members, vec!["DESK"]);
+
+        let vps = config.get_profile("VPS_PROD").unwrap();
+        assert_eq!(vps.profile_type, "vm");
+        for profile in &config.profiles {
+            assert!(PROFILE_TYPES.contains(&profile.profile_type.as_str()), "{}", profile.profile_type);
+        }
+        assert_eq!(guess_type("NAS", None), "server");
+        assert_eq!(vps.all_addresses(), vec!["vps-prod", "172.26.5.155"]);
+
+        let fields: Vec<(&str, &str)> = report
+            .mismatches
+            .iter()
+            .map(|m| (m.profile.as_str(), m.field.as_str()))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                ("LAPTOP_X13", "addresses"),
+                ("LAPTOP_X13", "ssh_timeout"),
+                ("VPS_PROD", "profile"),
+            ]
+        );
+    }
+}
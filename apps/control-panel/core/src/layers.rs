@@ -0,0 +1,281 @@
+//! Layered configuration
+//!
+//! The configuration is merged from, lowest precedence first:
+//!
+//! 1. the system file, `/etc/control-panel/config.toml`
+//! 2. the user file: `CONFIG_PATH`, else `~/.config/control-panel/config.toml`,
+//!    else the dotfiles' `apps/control-panel/config.toml`
+//! 3. `CONTROL_PANEL_*` environment variables, one per key, with `__`
+//!    between the levels: `CONTROL_PANEL_SERVER__PORT=3200`,
+//!    `CONTROL_PANEL_AUTH__PASSWORD_FILE=/run/secrets/panel`,
+//!    `CONTROL_PANEL_DOCKER_NODES__0__HOST=192.168.8.81`
+//!
+//! Tables are merged key by key; any other value (arrays included) replaces
+//! the one from a lower layer. Environment values are TOML (`3200`, `true`,
+//! `["a", "b"]`) unless they replace a string, and plain strings otherwise.
+//! Variables without `__` (like `CONTROL_PANEL_SSH_PASSPHRASE`) are left
+//! alone.
+
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+use crate::config::{expand_home, Config};
+use crate::error::AppError;
+
+/// System-wide configuration file
+pub const SYSTEM_CONFIG: &str = "/etc/control-panel/config.toml";
+
+/// Prefix of configuration environment variables
+pub const ENV_PREFIX: &str = "CONTROL_PANEL_";
+
+/// Keys whose values are never shown
+const SECRET_KEYS: &[&str] = &["password", "password_hash"];
+
+/// Where the configuration is read from
+#[derive(Debug, Clone)]
+pub struct ConfigLayers {
+    /// Files, lowest precedence first; missing files are skipped
+    pub files: Vec<PathBuf>,
+    /// Apply `CONTROL_PANEL_*` variables on top
+    pub env: bool,
+}
+
+impl ConfigLayers {
+    /// System file, user file and environment
+    pub fn standard() -> Self {
+        Self {
+            files: vec![PathBuf::from(SYSTEM_CONFIG), user_config_path()],
+            env: true,
+        }
+    }
+
+    /// The most specific file (the one edits should go to)
+    pub fn user_file(&self) -> Option<&Path> {
+        self.files.last().map(PathBuf::as_path)
+    }
+
+    /// Files that exist
+    pub fn existing(&self) -> Vec<&Path> {
+        self.files.iter().map(PathBuf::as_path).filter(|p| p.is_file()).collect()
+    }
+
+    /// Read and merge the layers. Without any file, the built-in defaults
+    /// are the base the environment applies to.
+    pub fn load(&self) -> anyhow::Result<Config> {
+        self.load_with(None)
+    }
+
+    /// Like `load`, with `content` in place of the file at `path`, to check
+    /// an edit before it is written
+    pub fn load_edited(&self, path: &Path, content: &str) -> anyhow::Result<Config> {
+        self.load_with(Some((path, content)))
+    }
+
+    fn load_with(&self, edited: Option<(&Path, &str)>) -> anyhow::Result<Config> {
+        let mut merged = Table::new();
+        let mut sources = Vec::new();
+
+        for path in self.existing() {
+            let content = match edited {
+                Some((edited_path, content)) if edited_path == path => content.to_string(),
+                _ => std::fs::read_to_string(path)?,
+            };
+            let table: Table = toml::from_str(&content).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+            merge(&mut merged, table);
+            sources.push(path.display().to_string());
+        }
+        if sources.is_empty() {
+            if let Value::Table(defaults) = Value::try_from(Config::default())? {
+                merged = defaults;
+            }
+            sources.push("built-in defaults".to_string());
+        }
+
+        if self.env {
+            for name in apply_env(&mut merged, std::env::vars())? {
+                sources.push(format!("env {}", name));
+            }
+        }
+
+        let mut config = Config::from_table(merged)?;
+        config.sources = sources;
+        Ok(config)
+    }
+}
+
+/// `CONFIG_PATH`, else the user config directory, else the dotfiles copy
+fn user_config_path() -> PathBuf {
+    if let Ok(path) = std::env::var("CONFIG_PATH") {
+        return PathBuf::from(path);
+    }
+    let user = expand_home("~/.config/control-panel/config.toml");
+    if user.is_file() {
+        return user;
+    }
+    expand_home("~/.dotfiles/apps/control-panel/config.toml")
+}
+
+/// Merge `overlay` into `base`: tables key by key, other values replaced
+pub fn merge(base: &mut Table, overlay: Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(existing)), Value::Table(table)) => merge(existing, table),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Apply `CONTROL_PANEL_*` variables to `table`, returning the names used
+pub fn apply_env(
+    table: &mut Table,
+    vars: impl Iterator<Item = (String, String)>,
+) -> Result<Vec<String>, AppError> {
+    let mut applied: Vec<String> = Vec::new();
+    let mut vars: Vec<(String, String)> = vars
+        .filter(|(name, _)| name.starts_with(ENV_PREFIX) && name.contains("__"))
+        .collect();
+    vars.sort();
+
+    for (name, raw) in vars {
+        let path: Vec<String> = name[ENV_PREFIX.len()..]
+            .split("__")
+            .map(|part| part.to_lowercase())
+            .collect();
+        if path.iter().any(String::is_empty) {
+            return Err(AppError::Config(format!("{}: empty key", name)));
+        }
+        set_path(table, &path, &raw).map_err(|e| AppError::Config(format!("{}: {}", name, e)))?;
+        applied.push(name);
+    }
+    Ok(applied)
+}
+
+/// Set `path` (table keys and array indexes) to `raw`
+fn set_path(table: &mut Table, path: &[String], raw: &str) -> Result<(), String> {
+    let (last, parents) = path.split_last().ok_or("empty key")?;
+
+    let mut current = table
+        .entry(parents.first().unwrap_or(last).clone())
+        .or_insert_with(|| Value::Table(Table::new()));
+    if parents.is_empty() {
+        *current = parse_value(raw, Some(current));
+        return Ok(());
+    }
+
+    for key in parents[1..].iter().chain(std::iter::once(last)) {
+        current = match current {
+            Value::Table(t) => t.entry(key.clone()).or_insert_with(|| Value::Table(Table::new())),
+            Value::Array(items) => {
+                let index: usize = key.parse().map_err(|_| format!("{} is not an array index", key))?;
+                let len = items.len();
+                items
+                    .get_mut(index)
+                    .ok_or_else(|| format!("index {} out of range ({} entries)", index, len))?
+            }
+            _ => return Err(format!("{} is not a table", key)),
+        };
+    }
+    let previous = current.clone();
+    *current = parse_value(raw, Some(&previous));
+    Ok(())
+}
+
+/// A string stays a string where the value it replaces is one; otherwise
+/// the value is read as TOML, falling back to a string
+fn parse_value(raw: &str, previous: Option<&Value>) -> Value {
+    if let Some(Value::String(_)) = previous {
+        return Value::String(raw.to_string());
+    }
+    toml::from_str::<Table>(&format!("value = {}", raw))
+        .ok()
+        .and_then(|mut t| t.remove("value"))
+        .unwrap_or_else(|| Value::String(raw.to_string()))
+}
+
+/// The configuration as TOML with passwords and hashes masked
+pub fn masked(config: &Config) -> String {
+    let mut value = match Value::try_from(config) {
+        Ok(value) => value,
+        Err(e) => return format!("# Can't show the configuration: {}", e),
+    };
+    mask(&mut value);
+    toml::to_string_pretty(&value).unwrap_or_default()
+}
+
+fn mask(value: &mut Value) {
+    match value {
+        Value::Table(table) => {
+            for (key, value) in table.iter_mut() {
+                if SECRET_KEYS.contains(&key.as_str()) {
+                    *value = Value::String("********".to_string());
+                } else {
+                    mask(value);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(mask),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layers_and_env() {
+        let mut system: Table = toml::from_str(
+            r#"
+[server]
+host = "0.0.0.0"
+port = 3100
+[auth]
+username = "admin"
+password = "system"
+[[docker_nodes]]
+name = "LXC_HOME"
+host = "192.168.8.80"
+ctid = 100
+"#,
+        )
+        .unwrap();
+        let user: Table = toml::from_str("[server]\nport = 3200\n[auth]\npassword_hash = \"$argon2id$x\"\n").unwrap();
+        merge(&mut system, user);
+        assert_eq!(system["server"]["host"].as_str(), Some("0.0.0.0"));
+        assert_eq!(system["server"]["port"].as_integer(), Some(3200));
+        assert_eq!(system["auth"]["password"].as_str(), Some("system"));
+
+        let vars = [
+            ("CONTROL_PANEL_SERVER__PORT", "3300"),
+            ("CONTROL_PANEL_AUTH__PASSWORD", "1234"),
+            ("CONTROL_PANEL_DOCKER_NODES__0__HOST", "192.168.8.81"),
+            ("CONTROL_PANEL_TUNNELS__BIND_ADDRESS", "0.0.0.0"),
+            ("CONTROL_PANEL_SSH_PASSPHRASE", "not config"),
+            ("HOME", "/root"),
+        ];
+        let applied = apply_env(&mut system, vars.iter().map(|(k, v)| (k.to_string(), v.to_string()))).unwrap();
+        assert_eq!(applied.len(), 4);
+        assert_eq!(system["server"]["port"].as_integer(), Some(3300));
+        // Replaces a string, so stays one
+        assert_eq!(system["auth"]["password"].as_str(), Some("1234"));
+        assert_eq!(system["docker_nodes"][0]["host"].as_str(), Some("192.168.8.81"));
+        assert_eq!(system["tunnels"]["bind_address"].as_str(), Some("0.0.0.0"));
+        assert!(!system.contains_key("ssh_passphrase"));
+
+        let out_of_range = [("CONTROL_PANEL_DOCKER_NODES__3__HOST".to_string(), "x".to_string())];
+        assert!(apply_env(&mut system, out_of_range.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_masked() {
+        let mut config = Config::default();
+        config.auth.password_hash = Some("$argon2id$v=19$secret".to_string());
+        let shown = masked(&config);
+        assert!(shown.contains("username = \"admin\""));
+        assert!(!shown.contains("secret"));
+        assert!(!shown.contains("\"admin\"\npassword = \"admin\""));
+        assert_eq!(shown.matches("********").count(), 2);
+    }
+}
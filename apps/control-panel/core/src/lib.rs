@@ -6,26 +6,59 @@
 //! # Modules
 //!
 //! - `config` - Configuration management and parsing
+//! - `credentials` - Login password hashes and password files
+//! - `layers` - Layered config (system file, user file, `CONTROL_PANEL_*` env)
+//! - `inventory` - Profile import from deploy-servers.conf and registry.toml
+//! - `reload` - Hot reload of config.toml (file watcher, validated swap)
+//! - `config_edit` - Comment-preserving inventory edits written to config.toml
 //! - `ssh` - SSH connection pool for remote command execution
+//! - `transport` - Command transports (SSH, local process, fixtures)
 //! - `error` - Error types for the application
 //! - `docker` - Docker container management
 //! - `infra` - Infrastructure control (Proxmox, deploy, git, graph)
+//! - `validate` - Configuration checks with severities (`--check-config`)
+//! - `selector` - Host selectors (`tag:lxc,!critical`) for batch operations
+//! - `batch` - Health checks, commands and prunes on many hosts at once
+//! - `shell` - Safe remote command construction and identifier validation
 //! - `editor` - Profile configuration editing
 
+pub mod batch;
 pub mod config;
+pub mod config_edit;
+pub mod credentials;
 pub mod docker;
 pub mod editor;
 pub mod error;
 pub mod infra;
+pub mod inventory;
+pub mod layers;
+pub mod reload;
+pub mod selector;
+pub mod shell;
 pub mod ssh;
+pub mod transport;
+pub mod validate;
 
 // Re-export commonly used types
-pub use config::{Config, DockerNode, GrafanaDashboard, GrafanaConfig, ProfileConfig as ProfileEntry};
-pub use docker::{ComposeStack, Container, ContainerStatus, NodeSummary};
+pub use batch::{HostResult, PruneKind};
+pub use config::{Config, DockerNode, GroupConfig, HostKeyPolicy, ProxmoxConfig, SharedConfig, TransportKind, GrafanaDashboard, GrafanaConfig, ProfileConfig as ProfileEntry};
+pub use docker::{ComposeStack, Container, ContainerDetails, ContainerStatus, HealthStatus, NodeSummary, PortMapping, RestartPolicy};
+pub use docker::logs::{FollowOptions, LogLine, LogStream};
+pub use docker::problems::{NodeProblems, Problem, ProblemKind};
+pub use docker::events::{ContainerInventory, InventoryChange, NodeInventory};
+pub use docker::stats::{ContainerStats, NodeUsage, StatsStore, Usage};
 pub use editor::{ConfigEntry, ConfigValue, EntryType, ProfileConfig};
 pub use error::AppError;
+pub use inventory::{InventoryReport, Mismatch};
+pub use layers::ConfigLayers;
+pub use selector::{Labels, Selector};
 pub use infra::{
-    BackupJob, ContainerInfo as ProxmoxContainer, DeployState, DeployStepResult, DeploymentStatus,
-    GitStatus, GraphData, GraphLink, GraphNode, NodeStatus, ProfileNode, ProfileType,
+    BackupJob, ContainerInfo as ProxmoxContainer, CtidCollision, DeployState, DeployStepResult, DeploymentStatus,
+    EndpointContainers, GitStatus, GraphData, GraphLink, GraphNode, NodeStatus, ProfileNode, ProfileType,
 };
-pub use ssh::{CommandOutput, SshPool};
+pub use ssh::{
+    CommandOutput, CommandStream, FileEntry, FileKind, OutputChunk, PendingHostKey, Sftp, SshPool,
+    TerminalInput, TerminalOutput, TerminalSession, TerminalTarget, TunnelInfo,
+};
+pub use transport::{CommandTransport, FixtureTransport, LocalTransport, Target};
+pub use validate::{Finding, Severity};
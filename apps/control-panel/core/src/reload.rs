@@ -0,0 +1,217 @@
+//! Hot reload of config.toml
+//!
+//! `watch` follows the config files (see `layers`) through their
+//! directories, so editors that save by writing a new file and renaming it
+//! over the old one are seen too. Environment overrides are applied again on
+//! every reload but can't change while the panel runs.
+//! A changed file is parsed and validated before it is swapped into the
+//! `SharedConfig`; a file that doesn't parse or has validation errors the
+//! running configuration didn't have is rejected with a logged error, and
+//! the running configuration stays. After a
+//! swap the SSH pool drops connections to removed or changed hosts and
+//! connects to new docker nodes.
+//!
+//! A few settings are read once at startup (listen address, upload limits);
+//! changing them logs that a restart is needed.
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::config::{Config, SharedConfig};
+use crate::error::AppError;
+use crate::layers::ConfigLayers;
+use crate::ssh::SshPool;
+use crate::validate::{has_errors, Finding, Severity};
+
+/// Editors write a file in several steps; wait for them to settle
+const SETTLE: Duration = Duration::from_millis(300);
+
+/// Watches the config file while it is alive
+pub struct ConfigWatcher {
+    _watcher: RecommendedWatcher,
+    task: JoinHandle<()>,
+}
+
+impl Drop for ConfigWatcher {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Reload the configuration from its layers and swap it in unless the edit
+/// adds validation errors. Returns the findings of the new configuration.
+pub fn reload(layers: &ConfigLayers, shared: &SharedConfig, pool: &SshPool) -> Result<Vec<Finding>, AppError> {
+    let config = layers.load().map_err(|e| AppError::Config(e.to_string()))?;
+
+    let findings = config.validate();
+    reject_added_errors(&findings, &shared.load())?;
+
+    let previous = shared.store(config);
+    let current = shared.load();
+    for setting in restart_required(&previous, &current) {
+        tracing::warn!("Config reload: {} changed, restart to apply it", setting);
+    }
+    pool.sync_connections(&previous);
+
+    Ok(findings)
+}
+
+/// Fail with the validation errors in `findings` that `running` doesn't
+/// have; errors the running config already has don't block other edits
+pub fn reject_added_errors(findings: &[Finding], running: &Config) -> Result<(), AppError> {
+    if !has_errors(findings) {
+        return Ok(());
+    }
+    let known = running.validate();
+    let added: Vec<String> = findings
+        .iter()
+        .filter(|f| f.severity == Severity::Error && !known.contains(f))
+        .map(|f| format!("{}: {}", f.subject, f.message))
+        .collect();
+    if added.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::Validation(added.join("; ")))
+    }
+}
+
+/// Settings read only at startup that differ between two configurations
+pub fn restart_required(previous: &Config, current: &Config) -> Vec<&'static str> {
+    let mut changed = Vec::new();
+    if previous.server.host != current.server.host || previous.server.port != current.server.port {
+        changed.push("[server]");
+    }
+    if previous.files.max_edit_bytes != current.files.max_edit_bytes
+        || previous.files.max_transfer_bytes != current.files.max_transfer_bytes
+    {
+        changed.push("files size limits");
+    }
+    changed
+}
+
+/// Reload the configuration whenever one of its files changes
+///
+/// Needs a tokio runtime. Watching stops when the returned watcher is
+/// dropped. Directories that don't exist (no system config) are skipped.
+pub fn watch(layers: ConfigLayers, shared: SharedConfig, pool: SshPool) -> Result<ConfigWatcher, AppError> {
+    let files: Vec<PathBuf> = layers.files.iter().map(PathBuf::as_path).map(absolute).collect();
+    let mut directories: Vec<PathBuf> = files
+        .iter()
+        .filter_map(|path| path.parent().map(Path::to_path_buf))
+        .filter(|dir| dir.is_dir())
+        .collect();
+    directories.dedup();
+    if directories.is_empty() {
+        return Err(AppError::Config("no config directory to watch".to_string()));
+    }
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let watched = files.clone();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let Ok(event) = event else {
+            return;
+        };
+        let relevant = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+            && event.paths.iter().any(|p| watched.contains(p));
+        if relevant {
+            let _ = tx.send(());
+        }
+    })
+    .map_err(|e| AppError::Internal(format!("Can't watch the config: {}", e)))?;
+    for directory in &directories {
+        watcher
+            .watch(directory, RecursiveMode::NonRecursive)
+            .map_err(|e| AppError::Internal(format!("Can't watch {}: {}", directory.display(), e)))?;
+    }
+
+    let names: Vec<String> = files.iter().map(|path| path.display().to_string()).collect();
+    tracing::info!("Watching {} for changes", names.join(", "));
+    let task = tokio::spawn(async move {
+        while rx.recv().await.is_some() {
+            tokio::time::sleep(SETTLE).await;
+            while rx.try_recv().is_ok() {}
+
+            match reload(&layers, &shared, &pool) {
+                Ok(findings) => {
+                    let warnings = findings.iter().filter(|f| f.severity == Severity::Warning).count();
+                    tracing::info!("Reloaded config from {} ({} warnings)", shared.load().sources.join(", "), warnings);
+                }
+                Err(e) => tracing::error!("Config change rejected, keeping the running config: {}", e),
+            }
+        }
+    });
+
+    Ok(ConfigWatcher { _watcher: watcher, task })
+}
+
+/// Event paths are absolute; relative config paths must be too to match
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{minimal_toml, HostKeyPolicy};
+    use tempfile::TempDir;
+
+    const HOSTS: &str = r#"
+[[docker_nodes]]
+name = "LXC_HOME"
+host = "127.0.0.1"
+ctid = 100
+transport = "local"
+"#;
+
+    #[tokio::test]
+    async fn test_reload_rejects_bad_edits() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("profiles")).unwrap();
+        let path = dir.path().join("config.toml");
+        let content = minimal_toml(dir.path(), HOSTS);
+        std::fs::write(&path, &content).unwrap();
+
+        let layers = ConfigLayers { files: vec![path.clone()], env: false };
+        let shared = SharedConfig::new(layers.load().unwrap());
+        let pool = SshPool::new(&shared).unwrap();
+
+        // Syntax error
+        std::fs::write(&path, content.replace("ctid = 100", "ctid = ")).unwrap();
+        assert!(reload(&layers, &shared, &pool).is_err());
+
+        // Parses, but two docker nodes share a CTID
+        let duplicate = "\n[[docker_nodes]]\nname = \"LXC_plane\"\nhost = \"127.0.0.2\"\nctid = 100\ntransport = \"local\"\n";
+        std::fs::write(&path, format!("{}{}", content, duplicate)).unwrap();
+        assert!(matches!(reload(&layers, &shared, &pool), Err(AppError::Validation(_))));
+        assert_eq!(shared.load().docker_nodes.len(), 1);
+
+        let added = duplicate.replace("ctid = 100", "ctid = 186");
+        std::fs::write(&path, format!("{}{}", content, added)).unwrap();
+        let findings = reload(&layers, &shared, &pool).unwrap();
+        assert!(!has_errors(&findings));
+        assert_eq!(pool.config().docker_nodes.len(), 2);
+        assert!(pool.config().get_docker_node("LXC_plane").is_some());
+
+        // An error the running config already has doesn't block other edits
+        shared.store(toml::from_str(&format!("{}{}", content, duplicate)).unwrap());
+        let other = added.replace("LXC_plane", "LXC_mailer").replace("186", "189").replace(".2", ".3");
+        std::fs::write(&path, format!("{}{}{}", content, duplicate, other)).unwrap();
+        let findings = reload(&layers, &shared, &pool).unwrap();
+        assert!(has_errors(&findings));
+        assert!(pool.config().get_docker_node("LXC_mailer").is_some());
+    }
+
+    #[test]
+    fn test_restart_required() {
+        let previous = Config::default();
+        let mut current = Config::default();
+        current.server.port += 1;
+        current.ssh.connect_timeout_secs += 1;
+        current.ssh.idle_timeout_secs = 0;
+        current.ssh.host_key_policy = HostKeyPolicy::Strict;
+        assert_eq!(restart_required(&previous, &current), vec!["[server]"]);
+    }
+}
@@ -0,0 +1,332 @@
+//! Host selectors for batch operations
+//!
+//! A selector is a comma-separated list of terms, e.g. `tag:lxc,!critical`:
+//!
+//! - `tag:NAME` - hosts with the tag
+//! - `group:NAME` or `@NAME` - members of a group
+//! - `name:NAME` - the host itself
+//! - `NAME` - a host name or a tag (`critical`, `site:komi`)
+//! - `*` - every host
+//!
+//! `!` in front of a term excludes the hosts it matches. A host is selected
+//! when it matches any of the other terms (every host if there are none)
+//! and no excluded one. Names may contain `*` wildcards (`LXC_*`);
+//! matching ignores case. An empty selector selects every host.
+//!
+//! Besides its `tags`, a host carries its profile type (`lxc`, `desktop`,
+//! ...) and `site:<site>` as tags. A docker node also has the tags and
+//! groups of the profile with the same name. Group members are the hosts
+//! listed in `[[groups]] members` and those naming the group in `group`.
+
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::config::{Config, DockerNode, ProfileConfig};
+use crate::error::AppError;
+use crate::transport::Target;
+
+/// A parsed selector expression
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Selector {
+    include: Vec<Term>,
+    exclude: Vec<Term>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Term {
+    All,
+    Tag(String),
+    Group(String),
+    Name(String),
+    /// A name or a tag
+    Bare(String),
+}
+
+/// What a selector looks at on a host
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Labels {
+    pub name: String,
+    pub tags: Vec<String>,
+    pub groups: Vec<String>,
+}
+
+impl Selector {
+    /// Parse a selector expression
+    pub fn parse(expression: &str) -> Result<Self, AppError> {
+        let mut selector = Selector::default();
+        for item in expression.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+            let (negated, term) = match item.strip_prefix('!') {
+                Some(rest) => (true, rest.trim()),
+                None => (false, item),
+            };
+            let term = Term::parse(term).map_err(|e| AppError::Validation(format!("selector {:?}: {}", item, e)))?;
+            if negated {
+                selector.exclude.push(term);
+            } else {
+                selector.include.push(term);
+            }
+        }
+        Ok(selector)
+    }
+
+    /// Selects every host
+    pub fn is_all(&self) -> bool {
+        self.exclude.is_empty() && (self.include.is_empty() || self.include.contains(&Term::All))
+    }
+
+    pub fn matches(&self, labels: &Labels) -> bool {
+        let included = self.include.is_empty() || self.include.iter().any(|t| t.matches(labels));
+        included && !self.exclude.iter().any(|t| t.matches(labels))
+    }
+}
+
+impl FromStr for Selector {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Selector::parse(s)
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let terms: Vec<String> = self
+            .include
+            .iter()
+            .map(Term::to_string)
+            .chain(self.exclude.iter().map(|t| format!("!{}", t)))
+            .collect();
+        write!(f, "{}", terms.join(","))
+    }
+}
+
+impl Term {
+    fn parse(term: &str) -> Result<Self, &'static str> {
+        if term.is_empty() {
+            return Err("empty term");
+        }
+        if term == "*" {
+            return Ok(Term::All);
+        }
+        let (kind, value): (fn(String) -> Term, &str) = if let Some(value) = term.strip_prefix('@') {
+            (Term::Group, value)
+        } else if let Some(value) = term.strip_prefix("group:") {
+            (Term::Group, value)
+        } else if let Some(value) = term.strip_prefix("tag:") {
+            (Term::Tag, value)
+        } else if let Some(value) = term.strip_prefix("name:") {
+            (Term::Name, value)
+        } else {
+            (Term::Bare, term)
+        };
+        match value.trim() {
+            "" => Err("missing a name after the prefix"),
+            value => Ok(kind(value.to_string())),
+        }
+    }
+
+    fn matches(&self, labels: &Labels) -> bool {
+        let any = |values: &[String], pattern: &str| values.iter().any(|v| glob_match(pattern, v));
+        match self {
+            Term::All => true,
+            Term::Tag(tag) => any(&labels.tags, tag),
+            Term::Group(group) => any(&labels.groups, group),
+            Term::Name(name) => glob_match(name, &labels.name),
+            Term::Bare(value) => glob_match(value, &labels.name) || any(&labels.tags, value),
+        }
+    }
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Term::All => write!(f, "*"),
+            Term::Tag(tag) => write!(f, "tag:{}", tag),
+            Term::Group(group) => write!(f, "@{}", group),
+            Term::Name(name) => write!(f, "name:{}", name),
+            Term::Bare(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// Case-insensitive match with `*` standing for any run of characters
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    let text = text.to_lowercase();
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !text.starts_with(first) || text.len() < first.len() + last.len() || !text.ends_with(last) {
+        return false;
+    }
+    let mut rest = &text[first.len()..text.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+impl Config {
+    /// Tags and groups of a profile
+    pub fn profile_labels(&self, profile: &ProfileConfig) -> Labels {
+        let mut labels = Labels {
+            name: profile.name.clone(),
+            tags: profile.tags.clone(),
+            groups: Vec::new(),
+        };
+        push_unique(&mut labels.tags, profile.profile_type.clone());
+        if let Some(ref site) = profile.site {
+            push_unique(&mut labels.tags, format!("site:{}", site));
+        }
+        if let Some(ref group) = profile.group {
+            push_unique(&mut labels.groups, group.clone());
+        }
+        self.add_listed_groups(&mut labels);
+        labels
+    }
+
+    /// Tags and groups of a docker node, with those of its profile
+    pub fn node_labels(&self, node: &DockerNode) -> Labels {
+        let mut labels = match self.get_profile(&node.name) {
+            Some(profile) => self.profile_labels(profile),
+            None => Labels {
+                name: node.name.clone(),
+                ..Labels::default()
+            },
+        };
+        for tag in &node.tags {
+            push_unique(&mut labels.tags, tag.clone());
+        }
+        if let Some(ref site) = node.site {
+            push_unique(&mut labels.tags, format!("site:{}", site));
+        }
+        self.add_listed_groups(&mut labels);
+        labels
+    }
+
+    /// Hosts a selector matches: profiles, then docker nodes without a
+    /// profile of the same name
+    pub fn select_hosts(&self, selector: &Selector) -> Vec<Target<'_>> {
+        let profiles = self.select_profiles(selector).into_iter().map(|p| Target::Profile(&p.name));
+        let nodes = self
+            .select_docker_nodes(selector)
+            .into_iter()
+            .filter(|n| self.get_profile(&n.name).is_none())
+            .map(|n| Target::Node(&n.name));
+        profiles.chain(nodes).collect()
+    }
+
+    fn add_listed_groups(&self, labels: &mut Labels) {
+        for group in self.groups.iter().filter(|g| g.members.contains(&labels.name)) {
+            push_unique(&mut labels.groups, group.name.clone());
+        }
+    }
+
+    /// Profiles a selector matches, in config order
+    pub fn select_profiles(&self, selector: &Selector) -> Vec<&ProfileConfig> {
+        self.profiles
+            .iter()
+            .filter(|p| selector.matches(&self.profile_labels(p)))
+            .collect()
+    }
+
+    /// Docker nodes a selector matches, in config order
+    pub fn select_docker_nodes(&self, selector: &Selector) -> Vec<&DockerNode> {
+        self.docker_nodes
+            .iter()
+            .filter(|n| selector.matches(&self.node_labels(n)))
+            .collect()
+    }
+}
+
+fn push_unique(values: &mut Vec<String>, value: String) {
+    if !values.contains(&value) {
+        values.push(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::minimal_toml;
+    use std::path::Path;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("LXC_*", "lxc_home"));
+        assert!(glob_match("*db*", "role:db"));
+        assert!(glob_match("a*b*c", "aXbYc"));
+        assert!(!glob_match("a*b*c", "aXcYb"));
+        assert!(!glob_match("ab*ba", "aba"));
+        assert!(!glob_match("critical", "critical-ish"));
+    }
+
+    #[test]
+    fn test_select() {
+        let config: Config = toml::from_str(&minimal_toml(
+            Path::new("/nonexistent"),
+            r#"
+[[groups]]
+name = "Homelab"
+members = ["LXC_HOME", "LXC_database"]
+
+[[docker_nodes]]
+name = "LXC_HOME"
+host = "192.168.8.80"
+ctid = 100
+tags = ["compose"]
+
+[[profiles]]
+name = "LXC_HOME"
+type = "lxc"
+hostname = "nixos-home"
+
+[[profiles]]
+name = "LXC_database"
+type = "lxc"
+hostname = "nixos-db"
+tags = ["critical", "role:db"]
+
+[[profiles]]
+name = "VPS_PROD"
+type = "vm"
+hostname = "vps"
+site = "netcup"
+group = "VPS Servers"
+tags = ["critical"]
+"#,
+        ))
+        .unwrap();
+
+        let names = |expression: &str| -> Vec<String> {
+            let selector = Selector::parse(expression).unwrap();
+            config.select_profiles(&selector).iter().map(|p| p.name.clone()).collect()
+        };
+        assert_eq!(names("tag:lxc,!critical"), vec!["LXC_HOME"]);
+        assert_eq!(names("critical"), vec!["LXC_database", "VPS_PROD"]);
+        assert_eq!(names("@homelab"), vec!["LXC_HOME", "LXC_database"]);
+        assert_eq!(names("group:VPS Servers,name:LXC_H*"), vec!["LXC_HOME", "VPS_PROD"]);
+        assert_eq!(names("site:netcup"), vec!["VPS_PROD"]);
+        assert_eq!(names("!tag:role:*"), vec!["LXC_HOME", "VPS_PROD"]);
+        assert_eq!(names("").len(), 3);
+        assert!(Selector::parse(" * ").unwrap().is_all());
+
+        let node = &config.docker_nodes[0];
+        let labels = config.node_labels(node);
+        assert_eq!(labels.tags, vec!["lxc", "compose"]);
+        assert_eq!(labels.groups, vec!["Homelab"]);
+        assert_eq!(config.select_docker_nodes(&"tag:compose".parse().unwrap()).len(), 1);
+        assert_eq!(config.select_hosts(&"@homelab".parse().unwrap()).len(), 2);
+
+        assert!(Selector::parse("tag:").is_err());
+        assert!(Selector::parse("lxc,!").is_err());
+        assert_eq!(Selector::parse("tag:lxc, !critical ,").unwrap().to_string(), "tag:lxc,!critical");
+    }
+}
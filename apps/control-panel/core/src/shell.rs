@@ -0,0 +1,317 @@
+//! Safe shell command construction
+//!
+//! Remote commands are run by the login shell on the target, so any value
+//! pasted into a command line must be quoted. `ShellCommand` builds command
+//! lines from trusted literals (program names, redirections) and quoted
+//! arguments, and the identifier types reject values from requests (URL path
+//! segments, form fields) that don't look like what they claim to be before
+//! they get anywhere near a command.
+
+use std::fmt;
+
+use crate::error::AppError;
+
+/// Quote a value for a POSIX shell. Plain words are left as they are,
+/// everything else is single-quoted.
+pub fn quote(value: &str) -> String {
+    let plain = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c));
+    if plain {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', r"'\''"))
+    }
+}
+
+/// Quote a path, keeping a leading `~/` as a reference to the remote home
+fn quote_path(path: &str) -> String {
+    if path == "~" {
+        "\"$HOME\"".to_string()
+    } else if let Some(rest) = path.strip_prefix("~/") {
+        format!("\"$HOME\"/{}", quote(rest))
+    } else {
+        quote(path)
+    }
+}
+
+/// A shell command line built from literals and quoted arguments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShellCommand {
+    line: String,
+}
+
+impl ShellCommand {
+    /// Start a command. The program is a literal, never user input.
+    pub fn new(program: &'static str) -> Self {
+        Self {
+            line: program.to_string(),
+        }
+    }
+
+    /// Append a quoted argument
+    pub fn arg(mut self, arg: impl AsRef<str>) -> Self {
+        self.line.push(' ');
+        self.line.push_str(&quote(arg.as_ref()));
+        self
+    }
+
+    /// Append several quoted arguments
+    pub fn args<I, S>(self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        args.into_iter().fold(self, |cmd, arg| cmd.arg(arg))
+    }
+
+    /// Append a quoted path argument; a leading `~/` still refers to the
+    /// remote user's home directory
+    pub fn path(mut self, path: impl AsRef<str>) -> Self {
+        self.line.push(' ');
+        self.line.push_str(&quote_path(path.as_ref()));
+        self
+    }
+
+    /// Append the output of another command as a single argument (`"$(...)"`)
+    pub fn output_of(mut self, inner: ShellCommand) -> Self {
+        self.line.push_str(" \"$(");
+        self.line.push_str(&inner.line);
+        self.line.push_str(")\"");
+        self
+    }
+
+    /// Append a literal fragment such as a redirection (`2>&1`)
+    pub fn raw(mut self, fragment: &'static str) -> Self {
+        self.line.push(' ');
+        self.line.push_str(fragment);
+        self
+    }
+
+    /// Redirect stderr into stdout
+    pub fn merge_stderr(self) -> Self {
+        self.raw("2>&1")
+    }
+
+    /// Run `next` only if this command succeeds
+    pub fn and(self, next: ShellCommand) -> Self {
+        self.join("&&", next)
+    }
+
+    /// Run `next` only if this command fails
+    pub fn or(self, next: ShellCommand) -> Self {
+        self.join("||", next)
+    }
+
+    /// Pipe this command's output into `next`
+    pub fn pipe(self, next: ShellCommand) -> Self {
+        self.join("|", next)
+    }
+
+    /// Wrap the command line in a subshell, e.g. to group an `||` branch
+    pub fn group(self) -> Self {
+        Self {
+            line: format!("({})", self.line),
+        }
+    }
+
+    fn join(mut self, operator: &str, next: ShellCommand) -> Self {
+        self.line.push(' ');
+        self.line.push_str(operator);
+        self.line.push(' ');
+        self.line.push_str(&next.line);
+        self
+    }
+
+    /// The finished command line
+    pub fn build(self) -> String {
+        self.line
+    }
+}
+
+impl fmt::Display for ShellCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.line)
+    }
+}
+
+/// Check that a value is a plain name: starts with a letter or digit and
+/// contains only letters, digits and the given punctuation
+fn validate_name(kind: &str, value: &str, punctuation: &str, max_len: usize) -> Result<(), AppError> {
+    let valid = value.len() <= max_len
+        && value.chars().next().is_some_and(|c| c.is_ascii_alphanumeric())
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || punctuation.contains(c));
+
+    if valid {
+        Ok(())
+    } else {
+        Err(AppError::Validation(format!("Invalid {}: {:?}", kind, value)))
+    }
+}
+
+macro_rules! identifier {
+    ($(#[$doc:meta])* $name:ident, $kind:literal, $punctuation:literal, $max_len:literal) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub struct $name(String);
+
+        impl $name {
+            /// Validate a value
+            pub fn parse(value: &str) -> Result<Self, AppError> {
+                validate_name($kind, value, $punctuation, $max_len)?;
+                Ok(Self(value.to_string()))
+            }
+
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+    };
+}
+
+identifier!(
+    /// Docker container name or ID
+    ContainerName, "container name", "_.-", 128
+);
+identifier!(
+    /// Docker Compose project name
+    ProjectName, "compose project", "_.-", 128
+);
+identifier!(
+    /// Profile / flake configuration name (`LXC_HOME`, `MACBOOK-KOMI`)
+    ProfileName, "profile name", "_.-", 64
+);
+identifier!(
+    /// Proxmox backup job ID (`backup-5b1e6a4f-7d3e`)
+    BackupJobId, "backup job ID", "_-", 64
+);
+identifier!(
+    /// Proxmox storage name
+    StorageName, "storage name", "_.-", 64
+);
+
+/// Proxmox guest ID (CTID / VMID), 100 to 999999999
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ctid(u32);
+
+impl Ctid {
+    pub fn new(ctid: u32) -> Result<Self, AppError> {
+        if (100..=999_999_999).contains(&ctid) {
+            Ok(Self(ctid))
+        } else {
+            Err(AppError::Validation(format!("Invalid CTID: {}", ctid)))
+        }
+    }
+
+    pub fn get(self) -> u32 {
+        self.0
+    }
+}
+
+impl fmt::Display for Ctid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOSTILE: &[&str] = &[
+        "x;rm -rf ~",
+        "x && reboot",
+        "$(reboot)",
+        "`reboot`",
+        "x|sh",
+        "x\nreboot",
+        "-rm",
+        "--format={{.Config.Env}}",
+        "../etc/passwd",
+        "x y",
+        "it's",
+        "",
+    ];
+
+    #[test]
+    fn test_identifiers_reject_hostile_input() {
+        for value in HOSTILE {
+            assert!(ContainerName::parse(value).is_err(), "container {:?}", value);
+            assert!(ProjectName::parse(value).is_err(), "project {:?}", value);
+            assert!(ProfileName::parse(value).is_err(), "profile {:?}", value);
+            assert!(BackupJobId::parse(value).is_err(), "job {:?}", value);
+            assert!(StorageName::parse(value).is_err(), "storage {:?}", value);
+        }
+        assert!(Ctid::new(0).is_err());
+        assert!(Ctid::new(99).is_err());
+    }
+
+    #[test]
+    fn test_identifiers_accept_real_names() {
+        assert!(ContainerName::parse("homelab-nextcloud-1").is_ok());
+        assert!(ContainerName::parse("3f4e2a1b9c0d").is_ok());
+        assert!(ProjectName::parse("homelab").is_ok());
+        assert!(ProfileName::parse("LXC_HOME").is_ok());
+        assert!(ProfileName::parse("MACBOOK-KOMI").is_ok());
+        assert!(BackupJobId::parse("backup-5b1e6a4f-7d3e").is_ok());
+        assert!(StorageName::parse("local-zfs").is_ok());
+        assert_eq!(Ctid::new(205).unwrap().get(), 205);
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("homelab"), "homelab");
+        assert_eq!(quote(""), "''");
+        assert_eq!(quote("x;rm -rf ~"), "'x;rm -rf ~'");
+        assert_eq!(quote("$(reboot)"), "'$(reboot)'");
+        assert_eq!(quote("it's"), r"'it'\''s'");
+        assert_eq!(quote("a\nb"), "'a\nb'");
+    }
+
+    #[test]
+    fn test_command_quotes_hostile_arguments() {
+        for value in HOSTILE {
+            let line = ShellCommand::new("docker").arg("start").arg(value).build();
+            let expected = format!("docker start {}", quote(value));
+            assert_eq!(line, expected);
+            // Anything with shell metacharacters ends up single-quoted
+            if value.chars().any(|c| " ;&|$`\n'~{}".contains(c)) {
+                assert!(line.ends_with('\''), "{}", line);
+            }
+        }
+    }
+
+    #[test]
+    fn test_command_composition() {
+        let line = ShellCommand::new("cd")
+            .path("~/.homelab/my stack")
+            .and(ShellCommand::new("docker-compose").args(["up", "-d"]).merge_stderr())
+            .build();
+        assert_eq!(line, "cd \"$HOME\"/'.homelab/my stack' && docker-compose up -d 2>&1");
+
+        let line = ShellCommand::new("docker")
+            .arg("pull")
+            .output_of(ShellCommand::new("docker").args(["inspect", "--format", "{{.Config.Image}}", "web"]))
+            .or(ShellCommand::new("echo").arg("failed").group())
+            .build();
+        assert_eq!(
+            line,
+            "docker pull \"$(docker inspect --format '{{.Config.Image}}' web)\" || (echo failed)"
+        );
+    }
+}
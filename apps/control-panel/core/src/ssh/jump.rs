@@ -0,0 +1,117 @@
+//! Jump host (ProxyJump) specifications
+//!
+//! A jump host is written like OpenSSH's `ProxyJump`: one or more
+//! comma-separated hops, each `[user@]host[:port]`, connected in order.
+//! IPv6 addresses with a port go in brackets (`[fd00::1]:2222`).
+
+use crate::error::AppError;
+
+/// One hop of a jump host chain, as written in the config
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hop {
+    pub user: Option<String>,
+    pub host: String,
+    pub port: Option<u16>,
+}
+
+impl Hop {
+    /// A bare name (no user or port), which may refer to a profile or docker node
+    pub fn name(&self) -> Option<&str> {
+        if self.user.is_none() && self.port.is_none() {
+            Some(&self.host)
+        } else {
+            None
+        }
+    }
+}
+
+/// Parse a jump host specification into its hops, first hop first
+pub fn parse_jump_spec(spec: &str) -> Result<Vec<Hop>, AppError> {
+    let hops = spec
+        .split(',')
+        .map(str::trim)
+        .filter(|hop| !hop.is_empty())
+        .map(parse_hop)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if hops.is_empty() {
+        return Err(AppError::Config(format!("Empty jump host: {:?}", spec)));
+    }
+    Ok(hops)
+}
+
+fn parse_hop(hop: &str) -> Result<Hop, AppError> {
+    let invalid = || AppError::Config(format!("Invalid jump host: {:?}", hop));
+
+    let (user, address) = match hop.rsplit_once('@') {
+        Some((user, address)) if !user.is_empty() => (Some(user.to_string()), address),
+        Some(_) => return Err(invalid()),
+        None => (None, hop),
+    };
+
+    let (host, port) = if let Some(rest) = address.strip_prefix('[') {
+        let (host, rest) = rest.split_once(']').ok_or_else(invalid)?;
+        match rest {
+            "" => (host, None),
+            _ => {
+                let port = rest.strip_prefix(':').ok_or_else(invalid)?;
+                (host, Some(port.parse::<u16>().map_err(|_| invalid())?))
+            }
+        }
+    } else {
+        match address.split_once(':') {
+            Some((host, port)) => (host, Some(port.parse::<u16>().map_err(|_| invalid())?)),
+            None => (address, None),
+        }
+    };
+
+    if host.is_empty() || host.contains(char::is_whitespace) {
+        return Err(invalid());
+    }
+
+    Ok(Hop {
+        user,
+        host: host.to_string(),
+        port,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_jump_spec() {
+        let hops = parse_jump_spec("akunito@100.64.0.5:2222, LXC_tailscale,[fd00::1]").unwrap();
+        assert_eq!(
+            hops,
+            vec![
+                Hop {
+                    user: Some("akunito".to_string()),
+                    host: "100.64.0.5".to_string(),
+                    port: Some(2222),
+                },
+                Hop {
+                    user: None,
+                    host: "LXC_tailscale".to_string(),
+                    port: None,
+                },
+                Hop {
+                    user: None,
+                    host: "fd00::1".to_string(),
+                    port: None,
+                },
+            ]
+        );
+        assert_eq!(hops[1].name(), Some("LXC_tailscale"));
+        assert_eq!(hops[0].name(), None);
+    }
+
+    #[test]
+    fn test_parse_jump_spec_rejects_invalid() {
+        assert!(parse_jump_spec("").is_err());
+        assert!(parse_jump_spec("@host").is_err());
+        assert!(parse_jump_spec("host:notaport").is_err());
+        assert!(parse_jump_spec("[fd00::1").is_err());
+    }
+}
@@ -0,0 +1,341 @@
+//! Host key verification
+//!
+//! Server keys are checked against the panel's own known_hosts file first and
+//! then against the user's `~/.ssh/known_hosts` (plain and hashed entries).
+//! A key recorded in the panel file takes precedence, which is how an operator
+//! replaces a changed key without touching the user's file. The panel file
+//! and the policy are read from the current configuration on every check, so
+//! a reload applies to the next handshake.
+
+use russh_keys::known_hosts::{known_host_keys_path, learn_known_hosts_path};
+use russh_keys::{HashAlg, PublicKey};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::config::{HostKeyPolicy, SharedConfig};
+use crate::error::AppError;
+
+/// Why a host key was rejected
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RejectReason {
+    /// No key recorded and the policy is strict
+    Unknown,
+    /// A different key is recorded for this host
+    Changed,
+}
+
+/// A host key that was rejected during a handshake and awaits a decision
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingHostKey {
+    pub host: String,
+    pub port: u16,
+    pub fingerprint: String,
+    pub algorithm: String,
+    pub reason: RejectReason,
+    #[serde(skip)]
+    key: PublicKey,
+}
+
+/// Result of looking a key up in a single known_hosts file
+enum Lookup {
+    Match,
+    Mismatch,
+    NotFound,
+}
+
+/// Known host key store shared by every connection of an `SshPool`
+pub struct HostKeyStore {
+    /// Source of `ssh.known_hosts_file` and `ssh.host_key_policy`
+    config: SharedConfig,
+    user_path: Option<PathBuf>,
+    pending: Mutex<HashMap<(String, u16), PendingHostKey>>,
+}
+
+impl HostKeyStore {
+    /// Create a store following the SSH section of the config
+    pub fn new(config: &SharedConfig) -> Self {
+        let user_path = std::env::var("HOME")
+            .ok()
+            .map(|home| PathBuf::from(home).join(".ssh").join("known_hosts"));
+
+        Self {
+            config: config.clone(),
+            user_path,
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Verify a server key, pinning it on first use when the policy allows
+    pub fn verify(&self, host: &str, port: u16, key: &PublicKey) -> Result<(), AppError> {
+        let fingerprint = fingerprint(key);
+        let config = self.config.load();
+        let panel_path = config.known_hosts_path();
+
+        let mut lookup = lookup_file(&panel_path, host, port, key)?;
+        if let (Lookup::NotFound, Some(ref user_path)) = (&lookup, &self.user_path) {
+            lookup = lookup_file(user_path, host, port, key)?;
+        }
+
+        match lookup {
+            Lookup::Match => {
+                self.clear_pending(host, port);
+                Ok(())
+            }
+            Lookup::Mismatch => {
+                tracing::error!(
+                    "Host key for {}:{} changed! Server presented {}",
+                    host,
+                    port,
+                    fingerprint
+                );
+                self.record_pending(host, port, key, RejectReason::Changed);
+                Err(AppError::HostKeyMismatch {
+                    host: host.to_string(),
+                    fingerprint,
+                })
+            }
+            Lookup::NotFound => match config.ssh.host_key_policy {
+                HostKeyPolicy::Tofu => {
+                    tracing::warn!(
+                        "Pinning new host key for {}:{} ({})",
+                        host,
+                        port,
+                        fingerprint
+                    );
+                    learn_known_hosts_path(host, port, key, &panel_path).map_err(|e| {
+                        AppError::Internal(format!("Failed to record host key: {}", e))
+                    })?;
+                    Ok(())
+                }
+                HostKeyPolicy::Strict => {
+                    self.record_pending(host, port, key, RejectReason::Unknown);
+                    Err(AppError::HostKeyUnknown {
+                        host: host.to_string(),
+                        fingerprint,
+                    })
+                }
+            },
+        }
+    }
+
+    /// Keys rejected so far that an operator can accept
+    pub fn pending(&self) -> Vec<PendingHostKey> {
+        let mut keys: Vec<PendingHostKey> = self
+            .pending
+            .lock()
+            .map(|p| p.values().cloned().collect())
+            .unwrap_or_default();
+        keys.sort_by(|a, b| (&a.host, a.port).cmp(&(&b.host, b.port)));
+        keys
+    }
+
+    /// Trust the pending key for a host, replacing any key pinned in the panel file.
+    /// `fingerprint` is the one the operator was shown; if the server has since
+    /// presented a different key, nothing is accepted.
+    pub fn accept(&self, host: &str, port: u16, fingerprint: &str) -> Result<PendingHostKey, AppError> {
+        let pending = {
+            let mut pending = self
+                .pending
+                .lock()
+                .map_err(|_| AppError::Internal("Host key store poisoned".to_string()))?;
+            let entry = (host.to_string(), port);
+            match pending.get(&entry) {
+                None => {
+                    return Err(AppError::Validation(format!(
+                        "No pending host key for {}:{}",
+                        host, port
+                    )))
+                }
+                Some(key) if key.fingerprint != fingerprint => {
+                    return Err(AppError::Validation(format!(
+                        "Host key for {}:{} is now {}, not {}; review it again",
+                        host, port, key.fingerprint, fingerprint
+                    )))
+                }
+                Some(_) => pending.remove(&entry).expect("pending key checked above"),
+            }
+        };
+
+        let panel_path = self.config.load().known_hosts_path();
+        remove_host_entries(&panel_path, host, port)?;
+        learn_known_hosts_path(host, port, &pending.key, &panel_path)
+            .map_err(|e| AppError::Internal(format!("Failed to record host key: {}", e)))?;
+
+        tracing::info!(
+            "Accepted host key for {}:{} ({})",
+            host,
+            port,
+            pending.fingerprint
+        );
+        Ok(pending)
+    }
+
+    fn record_pending(&self, host: &str, port: u16, key: &PublicKey, reason: RejectReason) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.insert(
+                (host.to_string(), port),
+                PendingHostKey {
+                    host: host.to_string(),
+                    port,
+                    fingerprint: fingerprint(key),
+                    algorithm: key.algorithm().to_string(),
+                    reason,
+                    key: key.clone(),
+                },
+            );
+        }
+    }
+
+    fn clear_pending(&self, host: &str, port: u16) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.remove(&(host.to_string(), port));
+        }
+    }
+}
+
+/// SHA256 fingerprint in OpenSSH notation (`SHA256:...`)
+pub fn fingerprint(key: &PublicKey) -> String {
+    key.fingerprint(HashAlg::Sha256).to_string()
+}
+
+/// Compare a key against every entry recorded for a host in one file.
+/// Entries with a different algorithm don't count as a mismatch, since servers
+/// offer several key types. An unreadable file is an error rather than "not
+/// found", so a corrupt file can't lead to a key being silently re-pinned.
+fn lookup_file(path: &Path, host: &str, port: u16, key: &PublicKey) -> Result<Lookup, AppError> {
+    let recorded = known_host_keys_path(host, port, path)
+        .map_err(|e| AppError::Config(format!("Failed to read {}: {}", path.display(), e)))?;
+
+    let mut result = Lookup::NotFound;
+    for (_, recorded_key) in recorded {
+        if recorded_key.algorithm() != key.algorithm() {
+            continue;
+        }
+        if recorded_key == *key {
+            return Ok(Lookup::Match);
+        }
+        result = Lookup::Mismatch;
+    }
+    Ok(result)
+}
+
+/// Remove plain entries for a host from a known_hosts file
+fn remove_host_entries(path: &Path, host: &str, port: u16) -> Result<(), AppError> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let pattern = if port == 22 {
+        host.to_string()
+    } else {
+        format!("[{}]:{}", host, port)
+    };
+
+    let kept: Vec<&str> = content
+        .lines()
+        .filter(|line| {
+            let hosts = line.split_whitespace().next().unwrap_or("");
+            !hosts.split(',').any(|h| h == pattern)
+        })
+        .collect();
+
+    let mut new_content = kept.join("\n");
+    if !new_content.is_empty() {
+        new_content.push('\n');
+    }
+    std::fs::write(path, new_content)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use tempfile::TempDir;
+
+    const KEY_A: &str = "AAAAC3NzaC1lZDI1NTE5AAAAIJdD7y3aLq454yWBdwLWbieU1ebz9/cu7/QEXn9OIeZJ";
+    const KEY_B: &str = "AAAAC3NzaC1lZDI1NTE5AAAAIA6rWI3G1sz07DnfFlrouTcysQlj2P+jpNSOEWD9OJ3X";
+
+    fn config(dir: &Path, policy: HostKeyPolicy) -> Config {
+        let mut config = Config::default();
+        config.ssh.known_hosts_file = Some(dir.join("known_hosts").display().to_string());
+        config.ssh.host_key_policy = policy;
+        config
+    }
+
+    fn store(dir: &Path, policy: HostKeyPolicy) -> HostKeyStore {
+        HostKeyStore {
+            config: SharedConfig::new(config(dir, policy)),
+            user_path: None,
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    #[test]
+    fn test_tofu_pins_then_detects_change() {
+        let dir = TempDir::new().unwrap();
+        let store = store(dir.path(), HostKeyPolicy::Tofu);
+        let key_a = russh_keys::parse_public_key_base64(KEY_A).unwrap();
+        let key_b = russh_keys::parse_public_key_base64(KEY_B).unwrap();
+
+        assert!(store.verify("192.168.8.80", 22, &key_a).is_ok());
+        assert!(store.verify("192.168.8.80", 22, &key_a).is_ok());
+        assert!(matches!(
+            store.verify("192.168.8.80", 22, &key_b),
+            Err(AppError::HostKeyMismatch { .. })
+        ));
+
+        store.accept("192.168.8.80", 22, &fingerprint(&key_b)).unwrap();
+        assert!(store.verify("192.168.8.80", 22, &key_b).is_ok());
+        assert!(store.verify("192.168.8.80", 22, &key_a).is_err());
+    }
+
+    #[test]
+    fn test_strict_rejects_unknown() {
+        let dir = TempDir::new().unwrap();
+        let store = store(dir.path(), HostKeyPolicy::Strict);
+        let key_a = russh_keys::parse_public_key_base64(KEY_A).unwrap();
+        let key_b = russh_keys::parse_public_key_base64(KEY_B).unwrap();
+
+        assert!(matches!(
+            store.verify("192.168.8.102", 2222, &key_a),
+            Err(AppError::HostKeyUnknown { .. })
+        ));
+        assert_eq!(store.pending().len(), 1);
+
+        // Only the key the operator was shown can be accepted
+        assert!(store.accept("192.168.8.102", 2222, &fingerprint(&key_b)).is_err());
+        assert_eq!(store.pending().len(), 1);
+        store.accept("192.168.8.102", 2222, &fingerprint(&key_a)).unwrap();
+        assert!(store.verify("192.168.8.102", 2222, &key_a).is_ok());
+        assert!(store.pending().is_empty());
+    }
+
+    #[test]
+    fn test_reload_applies_policy_and_file() {
+        let dir = TempDir::new().unwrap();
+        let store = store(dir.path(), HostKeyPolicy::Tofu);
+        let key_a = russh_keys::parse_public_key_base64(KEY_A).unwrap();
+        let key_b = russh_keys::parse_public_key_base64(KEY_B).unwrap();
+        assert!(store.verify("192.168.8.80", 22, &key_a).is_ok());
+
+        store.config.store(config(dir.path(), HostKeyPolicy::Strict));
+        assert!(matches!(
+            store.verify("192.168.8.81", 22, &key_b),
+            Err(AppError::HostKeyUnknown { .. })
+        ));
+
+        // A new, empty file: the key pinned in the old one is unknown there
+        let other = TempDir::new().unwrap();
+        store.config.store(config(other.path(), HostKeyPolicy::Strict));
+        assert!(matches!(
+            store.verify("192.168.8.80", 22, &key_a),
+            Err(AppError::HostKeyUnknown { .. })
+        ));
+    }
+}
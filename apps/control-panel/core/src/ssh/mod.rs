@@ -0,0 +1,1481 @@
+//! SSH connection pool and command execution
+//!
+//! Authentication tries the host's identity file first (`identity_file`, else
+//! `ssh.private_key_path`; encrypted keys are decrypted with the configured
+//! passphrase), then every key in the SSH agent.
+//!
+//! Server host keys are verified against known_hosts (see `known_hosts`).
+//!
+//! The pool is cheap to clone and shared between tasks. Connections are
+//! cached per (host, user, port), so docker nodes, profiles and the Proxmox
+//! host that resolve to the same target share one session. Each target has its
+//! own slot, locked only while connecting; every command runs on its own
+//! channel, so commands to different hosts (and several commands to one host)
+//! run in parallel.
+//!
+//! Cached sessions send keepalives and are replaced when found closed. A
+//! command whose connection broke before it started is retried once on a new
+//! connection; the `*_read` variants also retry when the connection drops
+//! mid-command, which is only safe for idempotent commands. Idle connections
+//! are closed after `ssh.idle_timeout_secs` (0: never).
+//!
+//! Hosts with a jump host (see `jump`) are reached through a direct-tcpip
+//! channel on the (cached) bastion connection. Host keys and authentication
+//! are checked for every hop.
+//!
+//! A host may have several addresses (LAN, wifi, tailscale). They are probed
+//! in parallel and the first one in configured order that answers wins; the
+//! working address is remembered and tried first on the next connect.
+//!
+//! The pool implements `CommandTransport`. Hosts configured with
+//! `transport = "local"` are the panel's own machine; their commands run as
+//! local processes instead.
+//!
+//! LXC containers can also be reached without their own SSH, through
+//! `pct exec <ctid> -- sh -c ...` on the Proxmox host: always with
+//! `transport = "pct"`, or as a fallback when the container's SSH can't be
+//! reached (`ssh.pct_fallback`). Commands sent that way run as root inside
+//! the container. The fallback only covers network failures, and SSH is
+//! retried a couple of minutes after it last failed.
+//!
+//! Interactive PTY sessions (login shells, `docker exec -it`, `pct enter`)
+//! are in `terminal`; SFTP file access is in `sftp` and local port forwards
+//! in `tunnel`.
+
+pub mod jump;
+pub mod known_hosts;
+pub mod sftp;
+pub mod terminal;
+#[cfg(test)]
+mod testing;
+pub mod tunnel;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use russh::keys::agent::client::AgentClient;
+use russh::keys::decode_secret_key;
+use russh::{client, ChannelMsg};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+use tokio::net::{TcpStream, UnixStream};
+use tokio::sync::{mpsc, Mutex};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::StreamExt;
+
+use crate::config::{expand_home, Config, DockerNode, ProfileConfig, ProxmoxConfig, SharedConfig, TransportKind};
+use crate::error::AppError;
+use crate::shell::{Ctid, ShellCommand};
+use crate::transport::{CommandTransport, LocalTransport, Target};
+
+pub use known_hosts::{HostKeyStore, PendingHostKey, RejectReason};
+pub use sftp::{FileEntry, FileKind, Sftp};
+pub use terminal::{TerminalInput, TerminalOutput, TerminalSession, TerminalTarget};
+pub use tunnel::TunnelInfo;
+
+/// Private keys loaded from disk, by path
+type KeyCache = std::sync::Mutex<HashMap<PathBuf, Arc<ssh_key::PrivateKey>>>;
+
+/// Port used unless a host sets its own
+const SSH_PORT: u16 = 22;
+
+/// Longest allowed chain of jump hosts (also catches jump host loops)
+const MAX_JUMPS: usize = 8;
+
+/// After a container's SSH can't be reached, its commands go straight to
+/// `pct exec` for this long before SSH is tried again
+const PCT_FALLBACK_RETRY: Duration = Duration::from_secs(120);
+
+/// SSH connection pool for managing connections to multiple nodes
+#[derive(Clone)]
+pub struct SshPool {
+    config: SharedConfig,
+    keys: Arc<KeyCache>,
+    host_keys: Arc<HostKeyStore>,
+    connections: Arc<ConnectionCache>,
+    /// Last address that worked for each target
+    working_addresses: Arc<std::sync::Mutex<HashMap<ConnectionKey, String>>>,
+    /// When SSH to a container last failed and `pct exec` took over
+    unreachable: Arc<std::sync::Mutex<HashMap<ConnectionKey, Instant>>>,
+    tunnels: Arc<tunnel::Tunnels>,
+}
+
+/// How commands reach a target
+enum Route {
+    Ssh(ConnectionKey),
+    Local,
+    Pct(Ctid),
+}
+
+/// Where a connection goes
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ConnectionKey {
+    /// Addresses of the host in preference order (never empty)
+    addresses: Vec<String>,
+    user: String,
+    port: u16,
+    /// Private key for this host, if not the default one
+    identity_file: Option<String>,
+    /// Connect timeout for each address, in seconds
+    timeout_secs: u64,
+    /// Bastion this connection is tunnelled through
+    jump: Option<Box<ConnectionKey>>,
+}
+
+impl fmt::Display for ConnectionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}:{}", self.user, self.addresses.join(","), self.port)?;
+        if let Some(ref jump) = self.jump {
+            write!(f, " via {}", jump)?;
+        }
+        Ok(())
+    }
+}
+
+/// Per-target connection slot. The lock is only held while connecting.
+type HostSlot = Mutex<Option<Arc<SshConnection>>>;
+
+/// Connection slots by target
+type ConnectionCache = std::sync::Mutex<HashMap<ConnectionKey, Arc<HostSlot>>>;
+
+/// A single SSH connection to a node
+struct SshConnection {
+    session: client::Handle<SshClient>,
+    last_used: std::sync::Mutex<Instant>,
+    /// Bastion connection carrying this one, kept alive (and counted as in
+    /// use) for as long as this connection exists
+    _jump: Option<Arc<SshConnection>>,
+}
+
+impl SshConnection {
+    fn touch(&self) {
+        if let Ok(mut last_used) = self.last_used.lock() {
+            *last_used = Instant::now();
+        }
+    }
+
+    fn idle_for(&self) -> Duration {
+        self.last_used
+            .lock()
+            .map(|last_used| last_used.elapsed())
+            .unwrap_or_default()
+    }
+}
+
+/// A command that failed because of its connection
+struct RunFailure {
+    error: AppError,
+    /// The command may already have run on the remote host
+    started: bool,
+}
+
+/// SSH client handler
+struct SshClient {
+    host: String,
+    port: u16,
+    host_keys: Arc<HostKeyStore>,
+    /// Why the server key was rejected, picked up by `connect` after the
+    /// handshake fails (russh only sees a `false` from `check_server_key`)
+    rejection: Arc<std::sync::Mutex<Option<AppError>>>,
+}
+
+#[async_trait::async_trait]
+impl client::Handler for SshClient {
+    type Error = russh::Error;
+
+    async fn check_server_key(
+        &mut self,
+        server_public_key: &russh_keys::PublicKey,
+    ) -> Result<bool, Self::Error> {
+        match self.host_keys.verify(&self.host, self.port, server_public_key) {
+            Ok(()) => Ok(true),
+            Err(e) => {
+                if let Ok(mut rejection) = self.rejection.lock() {
+                    *rejection = Some(e);
+                }
+                Ok(false)
+            }
+        }
+    }
+}
+
+impl SshPool {
+    /// Create a new SSH connection pool
+    ///
+    /// Loads the default key up front so problems show in the log at startup;
+    /// per-host keys are loaded on first use. If the key can't be loaded,
+    /// authentication falls back to the SSH agent.
+    ///
+    /// The pool reads `config` on every use, so a reloaded configuration
+    /// applies to the next command; see `sync_connections`.
+    pub fn new(shared: &SharedConfig) -> Result<Self> {
+        let config = shared.load();
+        let pool = Self {
+            config: shared.clone(),
+            keys: Arc::default(),
+            host_keys: Arc::new(HostKeyStore::new(shared)),
+            connections: Arc::default(),
+            working_addresses: Arc::default(),
+            unreachable: Arc::default(),
+            tunnels: Arc::default(),
+        };
+
+        match pool.identity(&expand_home(&config.ssh.private_key_path)) {
+            Ok(_) => tracing::info!("SSH key loaded successfully from file"),
+            Err(e) => {
+                tracing::warn!("Failed to load SSH key from file: {}", e);
+                if std::env::var("SSH_AUTH_SOCK").is_ok() {
+                    tracing::info!("SSH agent detected, will use agent authentication");
+                } else {
+                    tracing::warn!(
+                        "No SSH agent available. SSH operations will fail until key is available."
+                    );
+                }
+            }
+        }
+
+        // Close idle connections in the background, even if nothing asks for
+        // a connection again
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(reap_idle_connections(Arc::downgrade(&pool.connections), shared.clone()));
+        }
+
+        Ok(pool)
+    }
+
+    /// Load a private key (cached), decrypting it with the configured
+    /// passphrase if it is encrypted
+    fn identity(&self, path: &Path) -> Result<Arc<ssh_key::PrivateKey>, AppError> {
+        if let Some(key) = self.keys.lock().ok().and_then(|keys| keys.get(path).cloned()) {
+            return Ok(key);
+        }
+
+        let key_content = std::fs::read_to_string(path).map_err(|e| {
+            AppError::SshConnection(format!("Failed to read key file {}: {}", path.display(), e))
+        })?;
+
+        let key = match decode_secret_key(&key_content, None) {
+            Ok(key) => key,
+            Err(e) => {
+                let passphrase = self.config().key_passphrase().ok_or_else(|| {
+                    AppError::SshConnection(format!(
+                        "Failed to decode key {} (encrypted keys need ssh.passphrase_env or ssh.passphrase_file): {}",
+                        path.display(),
+                        e
+                    ))
+                })?;
+                decode_secret_key(&key_content, Some(&passphrase)).map_err(|e| {
+                    AppError::SshConnection(format!("Failed to decrypt key {}: {}", path.display(), e))
+                })?
+            }
+        };
+
+        let key = Arc::new(key);
+        if let Ok(mut keys) = self.keys.lock() {
+            keys.insert(path.to_path_buf(), key.clone());
+        }
+        Ok(key)
+    }
+
+    /// Authenticate with the host's identity file, then with the agent
+    async fn authenticate(
+        &self,
+        session: &mut client::Handle<SshClient>,
+        key: &ConnectionKey,
+    ) -> Result<(), AppError> {
+        let user = key.user.as_str();
+        let identity_path = expand_home(
+            key.identity_file
+                .as_deref()
+                .unwrap_or(&self.config().ssh.private_key_path),
+        );
+        let mut attempts = Vec::new();
+
+        match self.identity(&identity_path) {
+            Ok(private_key) => {
+                tracing::debug!("Authenticating with key file {}", identity_path.display());
+                let accepted = session
+                    .authenticate_publickey(user, private_key)
+                    .await
+                    .map_err(|e| AppError::SshAuth(format!("Key authentication failed: {}", e)))?;
+                if accepted {
+                    return Ok(());
+                }
+                attempts.push(format!("key {} rejected", identity_path.display()));
+            }
+            Err(e) => {
+                tracing::debug!("{}", e);
+                attempts.push(e.to_string());
+            }
+        }
+
+        if std::env::var("SSH_AUTH_SOCK").is_ok() {
+            if Self::authenticate_agent(session, user).await? {
+                return Ok(());
+            }
+            attempts.push("no agent key accepted".to_string());
+        } else {
+            attempts.push("no SSH agent".to_string());
+        }
+
+        Err(AppError::SshAuth(format!(
+            "Authentication rejected for {}: {}",
+            user,
+            attempts.join("; ")
+        )))
+    }
+
+    /// Try each key from the SSH agent
+    async fn authenticate_agent(session: &mut client::Handle<SshClient>, user: &str) -> Result<bool, AppError> {
+        tracing::debug!("Authenticating with SSH agent");
+        let mut agent = Self::connect_agent().await?;
+
+        // Get identities from agent
+        let identities = agent.request_identities().await.map_err(|e| {
+            AppError::SshAuth(format!("Failed to get identities from agent: {}", e))
+        })?;
+
+        if identities.is_empty() {
+            tracing::debug!("No keys available in SSH agent. Run: ssh-add");
+        }
+
+        for identity in identities {
+            match session
+                .authenticate_publickey_with(user, identity.clone(), &mut agent)
+                .await
+            {
+                Ok(true) => return Ok(true),
+                Ok(false) => continue,
+                Err(e) => {
+                    tracing::debug!("Agent key failed: {}", e);
+                    continue;
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    /// Connect to SSH agent
+    async fn connect_agent() -> Result<AgentClient<UnixStream>, AppError> {
+        let socket_path = std::env::var("SSH_AUTH_SOCK").map_err(|_| {
+            AppError::SshAuth("SSH_AUTH_SOCK not set".to_string())
+        })?;
+
+        let stream = UnixStream::connect(&socket_path).await.map_err(|e| {
+            AppError::SshAuth(format!("Failed to connect to SSH agent: {}", e))
+        })?;
+
+        Ok(AgentClient::connect(stream))
+    }
+
+    /// How to reach a target: over SSH, as a local process, or through
+    /// `pct exec`, as set by the host's `transport`
+    fn route(&self, target: Target<'_>) -> Result<Route, AppError> {
+        let kind = match target {
+            Target::Node(name) => self.config().get_docker_node(name).map(|n| n.transport),
+            Target::Profile(name) => match self.config().get_docker_node(name) {
+                Some(node) => Some(node.transport),
+                None => self.config().get_profile(name).map(|p| p.transport),
+            },
+            Target::Proxmox(_) => None,
+        };
+
+        match kind {
+            Some(TransportKind::Local) => return Ok(Route::Local),
+            Some(TransportKind::Pct) => {
+                let ctid = self.container_id(target).ok_or_else(|| {
+                    AppError::Config(format!("{} uses transport = \"pct\" but has no ctid", target))
+                })??;
+                return Ok(Route::Pct(ctid));
+            }
+            Some(TransportKind::Ssh) | None => {}
+        }
+
+        let key = match target {
+            Target::Node(name) => self.node_key(name)?,
+            Target::Profile(name) => self.profile_key(name)?,
+            Target::Proxmox(name) => self.proxmox_key(name)?,
+        };
+        Ok(Route::Ssh(key))
+    }
+
+    /// The LXC container behind a target: the docker node's `ctid`, else the
+    /// profile's
+    fn container_id(&self, target: Target<'_>) -> Option<Result<Ctid, AppError>> {
+        let ctid = match target {
+            Target::Node(name) => self.config().get_docker_node(name).map(|n| n.ctid),
+            Target::Profile(name) => match self.config().get_docker_node(name) {
+                Some(node) => Some(node.ctid),
+                None => self.config().get_profile(name).and_then(|p| p.ctid),
+            },
+            Target::Proxmox(_) => None,
+        };
+        ctid.map(Ctid::new)
+    }
+
+    /// Connect to a container over SSH, or pick the container to use with
+    /// `pct exec` instead when it can't be reached. Only network errors fall
+    /// back; host key, authentication and other errors are returned, not
+    /// bypassed. A failure is remembered for `PCT_FALLBACK_RETRY`, so an
+    /// unreachable container doesn't cost a connect timeout per command.
+    async fn pct_fallback(&self, target: Target<'_>, key: &ConnectionKey) -> Result<Option<Ctid>, AppError> {
+        let ctid = match self.container_id(target) {
+            Some(Ok(ctid)) if self.config().ssh.pct_fallback => Some(ctid),
+            _ => None,
+        };
+
+        if let (Some(ctid), Some(failed)) = (ctid, self.unreachable_since(key)) {
+            if failed.elapsed() < PCT_FALLBACK_RETRY {
+                return Ok(Some(ctid));
+            }
+        }
+
+        match self.connection(key).await {
+            Ok(_) => {
+                if let Ok(mut unreachable) = self.unreachable.lock() {
+                    unreachable.remove(key);
+                }
+                Ok(None)
+            }
+            Err(AppError::SshConnection(e)) => match ctid {
+                Some(ctid) => {
+                    tracing::warn!(
+                        "{} unreachable over SSH ({}), using pct exec {} for the next {}s",
+                        key,
+                        e,
+                        ctid,
+                        PCT_FALLBACK_RETRY.as_secs()
+                    );
+                    if let Ok(mut unreachable) = self.unreachable.lock() {
+                        unreachable.insert(key.clone(), Instant::now());
+                    }
+                    if let Ok(mut working) = self.working_addresses.lock() {
+                        working.insert(key.clone(), format!("pct exec {}", ctid));
+                    }
+                    Ok(Some(ctid))
+                }
+                None => Err(AppError::SshConnection(e)),
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    fn unreachable_since(&self, key: &ConnectionKey) -> Option<Instant> {
+        self.unreachable.lock().ok()?.get(key).copied()
+    }
+
+    async fn dispatch(&self, target: Target<'_>, command: &str, idempotent: bool) -> Result<CommandOutput, AppError> {
+        match self.route(target)? {
+            Route::Ssh(key) => match self.pct_fallback(target, &key).await? {
+                Some(ctid) => self.run(&self.container_host_key(target)?, &pct_exec(ctid, command), idempotent).await,
+                None => self.run(&key, command, idempotent).await,
+            },
+            Route::Local => LocalTransport.run(command).await,
+            Route::Pct(ctid) => self.run(&self.container_host_key(target)?, &pct_exec(ctid, command), idempotent).await,
+        }
+    }
+
+    /// Connection target for a docker node
+    fn node_key(&self, node_name: &str) -> Result<ConnectionKey, AppError> {
+        let config = self.config();
+        let node = config
+            .get_docker_node(node_name)
+            .ok_or_else(|| AppError::NodeNotFound(node_name.to_string()))?;
+        self.docker_node_key(node, 0)
+    }
+
+    /// Connection target for a profile: the docker node of the same name if
+    /// there is one, otherwise the profile's own addresses, user and key
+    fn profile_key(&self, profile_name: &str) -> Result<ConnectionKey, AppError> {
+        if self.config().get_docker_node(profile_name).is_some() {
+            return self.node_key(profile_name);
+        }
+
+        match self.config().get_profile(profile_name) {
+            Some(profile) => self.profile_entry_key(profile, 0),
+            None => Err(AppError::NodeNotFound(format!(
+                "Profile {} not found or has no IP",
+                profile_name
+            ))),
+        }
+    }
+
+    /// Connection target for a Proxmox endpoint, by name
+    fn proxmox_key(&self, name: &str) -> Result<ConnectionKey, AppError> {
+        let config = self.config();
+        let endpoint = config
+            .get_proxmox(name)
+            .ok_or_else(|| AppError::Config(format!("No Proxmox endpoint named {}", name)))?;
+        self.proxmox_endpoint_key(endpoint)
+    }
+
+    fn proxmox_endpoint_key(&self, endpoint: &ProxmoxConfig) -> Result<ConnectionKey, AppError> {
+        Ok(ConnectionKey {
+            addresses: vec![endpoint.host.clone()],
+            user: endpoint.user.clone(),
+            port: endpoint.port.unwrap_or(SSH_PORT),
+            identity_file: endpoint.identity_file.clone(),
+            timeout_secs: self.config().ssh.connect_timeout_secs,
+            jump: self.jump_chain(self.config().get_proxmox_jump_host(endpoint), 0)?,
+        })
+    }
+
+    /// Connection to the Proxmox endpoint hosting a target's container, for
+    /// `pct exec` and `pct enter`
+    fn container_host_key(&self, target: Target<'_>) -> Result<ConnectionKey, AppError> {
+        let config = self.config();
+        let endpoint = match target {
+            Target::Node(name) => config.get_docker_node(name).and_then(|n| config.get_node_proxmox(n)),
+            Target::Profile(name) => match config.get_docker_node(name) {
+                Some(node) => config.get_node_proxmox(node),
+                None => config.get_profile(name).and_then(|p| config.get_profile_proxmox(p)),
+            },
+            Target::Proxmox(name) => config.get_proxmox(name),
+        };
+        let endpoint = endpoint.ok_or_else(|| AppError::Config(format!("No Proxmox endpoint for {}", target)))?;
+        self.proxmox_endpoint_key(endpoint)
+    }
+
+    fn docker_node_key(&self, node: &DockerNode, depth: usize) -> Result<ConnectionKey, AppError> {
+        let addresses = node.all_addresses();
+        if addresses.is_empty() {
+            return Err(AppError::NodeNotFound(format!("Docker node {} has no address", node.name)));
+        }
+
+        Ok(ConnectionKey {
+            addresses,
+            user: self.config().get_ssh_user(node).to_string(),
+            port: node.port.unwrap_or(SSH_PORT),
+            identity_file: node.identity_file.clone(),
+            timeout_secs: node.ssh_timeout.unwrap_or(self.config().ssh.connect_timeout_secs),
+            jump: self.jump_chain(self.config().get_node_jump_host(node), depth)?,
+        })
+    }
+
+    fn profile_entry_key(&self, profile: &ProfileConfig, depth: usize) -> Result<ConnectionKey, AppError> {
+        let addresses = profile.all_addresses();
+        if addresses.is_empty() {
+            return Err(AppError::NodeNotFound(format!("Profile {} has no IP", profile.name)));
+        }
+
+        Ok(ConnectionKey {
+            addresses,
+            user: self.config().get_profile_user(profile).to_string(),
+            port: profile.port.unwrap_or(SSH_PORT),
+            identity_file: profile.identity_file.clone(),
+            timeout_secs: profile.ssh_timeout.unwrap_or(self.config().ssh.connect_timeout_secs),
+            jump: self.jump_chain(self.config().get_profile_jump_host(profile), depth)?,
+        })
+    }
+
+    /// Resolve a jump host specification into the chain of bastions, last
+    /// hop outermost. A hop naming a docker node or profile uses that host's
+    /// address and user; as the first hop it also keeps that host's own jump
+    /// host, so chains can be built up from per-host settings.
+    fn jump_chain(&self, spec: Option<&str>, depth: usize) -> Result<Option<Box<ConnectionKey>>, AppError> {
+        let Some(spec) = spec else {
+            return Ok(None);
+        };
+        if depth >= MAX_JUMPS {
+            return Err(AppError::Config(format!(
+                "Jump host chain too long (loop?) at {:?}",
+                spec
+            )));
+        }
+
+        let mut previous: Option<Box<ConnectionKey>> = None;
+        for hop in jump::parse_jump_spec(spec)? {
+            let named = match hop.name() {
+                Some(name) => {
+                    if let Some(node) = self.config().get_docker_node(name) {
+                        Some(self.docker_node_key(node, depth + 1)?)
+                    } else if let Some(profile) =
+                        self.config().get_profile(name).filter(|p| !p.all_addresses().is_empty())
+                    {
+                        Some(self.profile_entry_key(profile, depth + 1)?)
+                    } else {
+                        None
+                    }
+                }
+                None => None,
+            };
+
+            let key = match named {
+                Some(mut key) => {
+                    if previous.is_some() {
+                        key.jump = previous.take();
+                    }
+                    key
+                }
+                None => ConnectionKey {
+                    addresses: vec![hop.host],
+                    user: hop.user.unwrap_or_else(|| self.config().ssh.default_user.clone()),
+                    port: hop.port.unwrap_or(SSH_PORT),
+                    identity_file: None,
+                    timeout_secs: self.config().ssh.connect_timeout_secs,
+                    jump: previous.take(),
+                },
+            };
+            previous = Some(Box::new(key));
+        }
+
+        Ok(previous)
+    }
+
+    /// Run a buffered command on a cached connection, reconnecting once if
+    /// the connection turns out to be broken
+    async fn run(&self, key: &ConnectionKey, command: &str, idempotent: bool) -> Result<CommandOutput, AppError> {
+        let connection = self.connection(key).await?;
+        let failure = match Self::run_command(&connection, command).await {
+            Ok(output) => return Ok(output),
+            Err(failure) => failure,
+        };
+
+        self.evict(key, &connection).await;
+        if failure.started && !idempotent {
+            return Err(failure.error);
+        }
+
+        tracing::warn!("Connection to {} failed ({}), reconnecting", key, failure.error);
+        let connection = self.connection(key).await?;
+        match Self::run_command(&connection, command).await {
+            Ok(output) => Ok(output),
+            Err(failure) => {
+                self.evict(key, &connection).await;
+                Err(failure.error)
+            }
+        }
+    }
+
+    /// Start a streaming command on a cached connection. A stream that fails
+    /// to start never ran, so it is always safe to retry on a new connection.
+    async fn run_streaming(&self, key: &ConnectionKey, command: &str) -> Result<CommandStream, AppError> {
+        let connection = self.connection(key).await?;
+        let error = match Self::run_command_streaming(&connection, command).await {
+            Ok(stream) => return Ok(stream),
+            Err(error) => error,
+        };
+
+        self.evict(key, &connection).await;
+        tracing::warn!("Connection to {} failed ({}), reconnecting", key, error);
+        let connection = self.connection(key).await?;
+        let result = Self::run_command_streaming(&connection, command).await;
+        if result.is_err() {
+            self.evict(key, &connection).await;
+        }
+        result
+    }
+
+    /// Get the cached connection for a target, connecting if there is none
+    /// or the cached session has closed
+    async fn connection(&self, key: &ConnectionKey) -> Result<Arc<SshConnection>, AppError> {
+        let slot = {
+            let mut cache = self
+                .connections
+                .lock()
+                .map_err(|_| AppError::Internal("SSH connection cache poisoned".to_string()))?;
+            cache.entry(key.clone()).or_default().clone()
+        };
+
+        // Concurrent callers for the same target wait here instead of each
+        // opening their own connection; other targets are unaffected
+        let mut guard = slot.lock().await;
+        if let Some(ref connection) = *guard {
+            if !connection.session.is_closed() {
+                connection.touch();
+                return Ok(connection.clone());
+            }
+            tracing::info!("Connection to {} closed, reconnecting", key);
+        }
+
+        let connection = Arc::new(self.connect(key).await?);
+        *guard = Some(connection.clone());
+        Ok(connection)
+    }
+
+    /// Drop a broken connection from the cache, unless another task has
+    /// already replaced it
+    async fn evict(&self, key: &ConnectionKey, connection: &Arc<SshConnection>) {
+        let slot = match self.connections.lock() {
+            Ok(cache) => cache.get(key).cloned(),
+            Err(_) => None,
+        };
+        if let Some(slot) = slot {
+            let mut guard = slot.lock().await;
+            if guard.as_ref().is_some_and(|cached| Arc::ptr_eq(cached, connection)) {
+                *guard = None;
+            }
+        }
+    }
+
+    /// Addresses to try for a target, the last one that worked first
+    fn candidate_addresses(&self, key: &ConnectionKey) -> Vec<String> {
+        let mut addresses = key.addresses.clone();
+        let working = self
+            .working_addresses
+            .lock()
+            .ok()
+            .and_then(|working| working.get(key).cloned());
+        if let Some(position) = working.and_then(|w| addresses.iter().position(|a| *a == w)) {
+            let address = addresses.remove(position);
+            addresses.insert(0, address);
+        }
+        addresses
+    }
+
+    /// Handler that verifies the host key recorded for the address in use
+    fn client_handler(
+        &self,
+        address: &str,
+        port: u16,
+        rejection: &Arc<std::sync::Mutex<Option<AppError>>>,
+    ) -> SshClient {
+        SshClient {
+            host: address.to_string(),
+            port,
+            host_keys: self.host_keys.clone(),
+            rejection: rejection.clone(),
+        }
+    }
+
+    /// Open a direct-tcpip channel through a bastion to the first address
+    /// the bastion can reach, trying them in order
+    async fn open_jump_channel(
+        &self,
+        jump_key: &ConnectionKey,
+        jump: &Arc<SshConnection>,
+        addresses: &[String],
+        port: u16,
+        per_address: Duration,
+    ) -> Result<(String, russh::Channel<client::Msg>), AppError> {
+        let mut errors = Vec::new();
+        for address in addresses {
+            let open = jump
+                .session
+                .channel_open_direct_tcpip(address.clone(), port as u32, "127.0.0.1", 0);
+            match tokio::time::timeout(per_address, open).await {
+                Ok(Ok(channel)) => return Ok((address.clone(), channel)),
+                Ok(Err(e)) => errors.push(format!("{}: {}", address, e)),
+                Err(_) => errors.push(format!("{}: timeout", address)),
+            }
+            if jump.session.is_closed() {
+                self.evict(jump_key, jump).await;
+                break;
+            }
+        }
+
+        Err(AppError::SshConnection(format!(
+            "No address reachable via {}: {}",
+            jump_key,
+            errors.join("; ")
+        )))
+    }
+
+    /// Address of the current (or last) connection to a docker node
+    pub fn node_address(&self, node_name: &str) -> Option<String> {
+        self.address(Target::Node(node_name))
+    }
+
+    /// Address of the current (or last) connection to a profile
+    pub fn profile_address(&self, profile_name: &str) -> Option<String> {
+        self.address(Target::Profile(profile_name))
+    }
+
+    fn working_address(&self, key: &ConnectionKey) -> Option<String> {
+        self.working_addresses.lock().ok()?.get(key).cloned()
+    }
+
+    /// Create a new SSH connection
+    async fn connect(&self, key: &ConnectionKey) -> Result<SshConnection, AppError> {
+        use tokio::time::timeout;
+
+        let config = client::Config {
+            // 0 turns keepalives off; russh would otherwise send them in a busy loop
+            keepalive_interval: Some(self.config().ssh.keepalive_interval_secs)
+                .filter(|&secs| secs > 0)
+                .map(Duration::from_secs),
+            keepalive_max: 3,
+            ..Default::default()
+        };
+        let config = Arc::new(config);
+
+        tracing::info!("Connecting to {}", key);
+
+        // Through a bastion, the SSH session runs over a direct-tcpip channel
+        // on the bastion's own (cached, verified) connection
+        let jump = match key.jump {
+            Some(ref jump_key) => Some(Box::pin(self.connection(jump_key)).await?),
+            None => None,
+        };
+
+        let per_address = Duration::from_secs(key.timeout_secs);
+        let addresses = self.candidate_addresses(key);
+        let rejection = Arc::new(std::sync::Mutex::new(None));
+
+        let (address, handshake) = match (&key.jump, &jump) {
+            (Some(jump_key), Some(jump)) => {
+                let (address, channel) = self.open_jump_channel(jump_key, jump, &addresses, key.port, per_address).await?;
+                let handler = self.client_handler(&address, key.port, &rejection);
+                let handshake = timeout(per_address, client::connect_stream(config, channel.into_stream(), handler)).await;
+                (address, handshake)
+            }
+            _ => {
+                let (address, stream) = probe_addresses(&addresses, key.port, per_address).await?;
+                let handler = self.client_handler(&address, key.port, &rejection);
+                let handshake = timeout(per_address, client::connect_stream(config, stream, handler)).await;
+                (address, handshake)
+            }
+        };
+
+        let mut session = match handshake {
+            Ok(Ok(session)) => session,
+            Ok(Err(e)) => {
+                if let Some(host_key_error) = rejection.lock().ok().and_then(|mut r| r.take()) {
+                    return Err(host_key_error);
+                }
+                return Err(AppError::SshConnection(format!("Failed to connect to {}: {}", address, e)));
+            }
+            Err(_) => return Err(AppError::SshConnection(format!("Connection timeout to {}", address))),
+        };
+
+        self.authenticate(&mut session, key).await?;
+
+        tracing::info!("Connected to {} on {}", key, address);
+        if let Ok(mut working) = self.working_addresses.lock() {
+            working.insert(key.clone(), address.clone());
+        }
+
+        Ok(SshConnection {
+            session,
+            last_used: std::sync::Mutex::new(Instant::now()),
+            _jump: jump,
+        })
+    }
+
+    /// Run a command on an established connection
+    async fn run_command(connection: &SshConnection, command: &str) -> Result<CommandOutput, RunFailure> {
+        let not_started = |error| RunFailure { error, started: false };
+
+        let mut channel = connection
+            .session
+            .channel_open_session()
+            .await
+            .map_err(|e| not_started(AppError::SshCommand(format!("Failed to open channel: {}", e))))?;
+
+        channel
+            .exec(true, command)
+            .await
+            .map_err(|e| not_started(AppError::SshCommand(format!("Failed to execute command: {}", e))))?;
+
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        let mut exit_code = None;
+        let mut eof = false;
+
+        loop {
+            match channel.wait().await {
+                Some(ChannelMsg::Data { data }) => {
+                    stdout.extend_from_slice(&data);
+                }
+                Some(ChannelMsg::ExtendedData { data, ext: 1 }) => {
+                    // stderr
+                    stderr.extend_from_slice(&data);
+                }
+                Some(ChannelMsg::ExitStatus { exit_status }) => {
+                    exit_code = Some(exit_status);
+                    if eof {
+                        break;
+                    }
+                }
+                // Exit status may arrive after EOF
+                Some(ChannelMsg::Eof) => {
+                    eof = true;
+                    if exit_code.is_some() {
+                        break;
+                    }
+                }
+                None => break,
+                _ => {}
+            }
+        }
+        connection.touch();
+
+        // The channel went away without EOF or an exit status: the session died
+        if !eof && exit_code.is_none() {
+            return Err(RunFailure {
+                error: AppError::SshConnection("Connection lost before the command finished".to_string()),
+                started: true,
+            });
+        }
+
+        Ok(CommandOutput {
+            stdout: String::from_utf8_lossy(&stdout).to_string(),
+            stderr: String::from_utf8_lossy(&stderr).to_string(),
+            exit_code: exit_code.unwrap_or(0),
+        })
+    }
+
+    /// Start a command on an established connection and stream its output.
+    ///
+    /// The channel is handed to a background task. Output is forwarded line by line
+    /// (so multi-byte characters are never split) and ends with
+    /// `OutputChunk::Exit`. Dropping the stream closes the remote channel,
+    /// also while the command is quiet (`docker logs -f` of an idle container).
+    /// If the connection is lost mid-command the stream ends with an error
+    /// line and exit code 255, like `ssh` itself.
+    async fn run_command_streaming(
+        connection: &Arc<SshConnection>,
+        command: &str,
+    ) -> Result<CommandStream, AppError> {
+        let mut channel = connection
+            .session
+            .channel_open_session()
+            .await
+            .map_err(|e| AppError::SshCommand(format!("Failed to open channel: {}", e)))?;
+
+        channel
+            .exec(true, command)
+            .await
+            .map_err(|e| AppError::SshCommand(format!("Failed to execute command: {}", e)))?;
+
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+        // Held by the task so the connection counts as in use until the
+        // command finishes
+        let connection = connection.clone();
+
+        tokio::spawn(async move {
+            let mut stdout = LineBuffer::default();
+            let mut stderr = LineBuffer::default();
+            let mut exit_code = None;
+            let mut eof = false;
+
+            loop {
+                let msg = tokio::select! {
+                    msg = channel.wait() => msg,
+                    _ = tx.closed() => {
+                        let _ = channel.close().await;
+                        return;
+                    }
+                };
+                let chunk = match msg {
+                    Some(ChannelMsg::Data { data }) => stdout.push(&data).map(OutputChunk::Stdout),
+                    Some(ChannelMsg::ExtendedData { data, ext: 1 }) => {
+                        stderr.push(&data).map(OutputChunk::Stderr)
+                    }
+                    Some(ChannelMsg::ExitStatus { exit_status }) => {
+                        exit_code = Some(exit_status);
+                        None
+                    }
+                    Some(ChannelMsg::Eof) => {
+                        eof = true;
+                        break;
+                    }
+                    None => break,
+                    _ => None,
+                };
+
+                if let Some(chunk) = chunk {
+                    if tx.send(chunk).await.is_err() {
+                        // Receiver dropped: stop the remote process
+                        let _ = channel.close().await;
+                        return;
+                    }
+                }
+            }
+
+            // Exit status may arrive after EOF
+            if eof && exit_code.is_none() {
+                while let Some(msg) = channel.wait().await {
+                    if let ChannelMsg::ExitStatus { exit_status } = msg {
+                        exit_code = Some(exit_status);
+                        break;
+                    }
+                }
+            }
+
+            if let Some(rest) = stdout.finish() {
+                let _ = tx.send(OutputChunk::Stdout(rest)).await;
+            }
+            if let Some(rest) = stderr.finish() {
+                let _ = tx.send(OutputChunk::Stderr(rest)).await;
+            }
+            if !eof && exit_code.is_none() {
+                let _ = tx
+                    .send(OutputChunk::Stderr(
+                        "Connection lost before the command finished\n".to_string(),
+                    ))
+                    .await;
+                exit_code = Some(255);
+            }
+            connection.touch();
+            let _ = tx.send(OutputChunk::Exit(exit_code.unwrap_or(0))).await;
+        });
+
+        Ok(ReceiverStream::new(rx))
+    }
+
+    /// Close connection to a specific node (useful for reconnecting)
+    #[allow(dead_code)]
+    pub async fn close_connection(&self, node_name: &str) {
+        let Ok(key) = self.node_key(node_name) else {
+            return;
+        };
+        let slot = match self.connections.lock() {
+            Ok(cache) => cache.get(&key).cloned(),
+            Err(_) => None,
+        };
+        if let Some(slot) = slot {
+            let mut guard = slot.lock().await;
+            *guard = None;
+        }
+    }
+
+    /// Host key store (pending keys, operator accept)
+    pub fn host_keys(&self) -> &Arc<HostKeyStore> {
+        &self.host_keys
+    }
+
+    /// Get the current configuration
+    pub fn config(&self) -> Arc<Config> {
+        self.config.load()
+    }
+
+    /// Bring cached connections in line with a reloaded configuration.
+    ///
+    /// Connections no host uses anymore (the host was removed, or its
+    /// address, user, port, key or jump host changed) are dropped from the
+    /// cache and closed once running commands finish; tunnels over them are
+    /// closed. Docker nodes that are new since `previous` are connected in
+    /// the background so problems show in the log right away.
+    pub fn sync_connections(&self, previous: &Config) {
+        let config = self.config();
+
+        let mut wanted = HashSet::new();
+        let mut add = |key: ConnectionKey| {
+            let mut hop = Some(Box::new(key));
+            while let Some(mut key) = hop {
+                hop = key.jump.take();
+                let mut full = *key;
+                full.jump = hop.clone();
+                wanted.insert(full);
+            }
+        };
+        for endpoint in &config.proxmox {
+            if let Ok(key) = self.proxmox_endpoint_key(endpoint) {
+                add(key);
+            }
+        }
+        for node in &config.docker_nodes {
+            if let Ok(Route::Ssh(key)) = self.route(Target::Node(&node.name)) {
+                add(key);
+            }
+        }
+        for profile in &config.profiles {
+            if let Ok(Route::Ssh(key)) = self.route(Target::Profile(&profile.name)) {
+                add(key);
+            }
+        }
+
+        let removed: Vec<(ConnectionKey, Arc<HostSlot>)> = match self.connections.lock() {
+            Ok(mut cache) => {
+                let stale: Vec<ConnectionKey> = cache.keys().filter(|k| !wanted.contains(*k)).cloned().collect();
+                stale
+                    .into_iter()
+                    .filter_map(|key| cache.remove(&key).map(|slot| (key, slot)))
+                    .collect()
+            }
+            Err(_) => Vec::new(),
+        };
+        if let Ok(mut working) = self.working_addresses.lock() {
+            working.retain(|key, _| wanted.contains(key));
+        }
+        self.close_stale_tunnels(&wanted);
+
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        for (key, slot) in removed {
+            runtime.spawn(close_when_unused(key, slot));
+        }
+
+        for node in config.docker_nodes.iter().filter(|n| previous.get_docker_node(&n.name).is_none()) {
+            let Ok(Route::Ssh(key)) = self.route(Target::Node(&node.name)) else {
+                continue;
+            };
+            let pool = self.clone();
+            let name = node.name.clone();
+            runtime.spawn(async move {
+                match pool.connection(&key).await {
+                    Ok(_) => tracing::info!("Connected to new docker node {} ({})", name, key),
+                    Err(e) => tracing::warn!("New docker node {} is not reachable: {}", name, e),
+                }
+            });
+        }
+    }
+}
+
+/// Close a connection dropped from the cache once nothing uses it
+async fn close_when_unused(key: ConnectionKey, slot: Arc<HostSlot>) {
+    let Some(connection) = slot.lock().await.take() else {
+        return;
+    };
+    while Arc::strong_count(&connection) > 1 {
+        tokio::time::sleep(Duration::from_secs(5)).await;
+    }
+    tracing::info!("Closing connection to {} (removed from config)", key);
+    let _ = connection
+        .session
+        .disconnect(russh::Disconnect::ByApplication, "config changed", "en")
+        .await;
+}
+
+#[async_trait]
+impl CommandTransport for SshPool {
+    async fn execute(&self, target: Target<'_>, command: &str) -> Result<CommandOutput, AppError> {
+        self.dispatch(target, command, false).await
+    }
+
+    /// Retried once if the connection drops mid-command
+    async fn execute_read(&self, target: Target<'_>, command: &str) -> Result<CommandOutput, AppError> {
+        self.dispatch(target, command, true).await
+    }
+
+    async fn execute_streaming(&self, target: Target<'_>, command: &str) -> Result<CommandStream, AppError> {
+        match self.route(target)? {
+            Route::Ssh(key) => match self.pct_fallback(target, &key).await? {
+                Some(ctid) => self.run_streaming(&self.container_host_key(target)?, &pct_exec(ctid, command)).await,
+                None => self.run_streaming(&key, command).await,
+            },
+            Route::Local => LocalTransport.run_streaming(command).await,
+            Route::Pct(ctid) => self.run_streaming(&self.container_host_key(target)?, &pct_exec(ctid, command)).await,
+        }
+    }
+
+    fn address(&self, target: Target<'_>) -> Option<String> {
+        match self.route(target).ok()? {
+            Route::Ssh(key) => self.working_address(&key),
+            Route::Local => Some("local".to_string()),
+            Route::Pct(ctid) => Some(format!("pct exec {}", ctid)),
+        }
+    }
+}
+
+/// Wrap a command line to run inside an LXC container from the Proxmox host
+fn pct_exec(ctid: Ctid, command: &str) -> String {
+    ShellCommand::new("pct")
+        .arg("exec")
+        .arg(ctid.to_string())
+        .args(["--", "sh", "-c", command])
+        .build()
+}
+
+/// Open TCP connections to all addresses at once and take the first address,
+/// in preference order, that accepts. Later addresses only matter if every
+/// earlier one fails, so a dead LAN address costs at most one timeout.
+async fn probe_addresses(
+    addresses: &[String],
+    port: u16,
+    per_address: Duration,
+) -> Result<(String, TcpStream), AppError> {
+    let probes: Vec<_> = addresses
+        .iter()
+        .map(|address| {
+            let target = (address.clone(), port);
+            tokio::spawn(async move { tokio::time::timeout(per_address, TcpStream::connect(target)).await })
+        })
+        .collect();
+
+    let mut found = None;
+    let mut errors = Vec::new();
+    for (address, probe) in addresses.iter().zip(probes) {
+        if found.is_some() {
+            probe.abort();
+            continue;
+        }
+        match probe.await {
+            Ok(Ok(Ok(stream))) => found = Some((address.clone(), stream)),
+            Ok(Ok(Err(e))) => errors.push(format!("{}: {}", address, e)),
+            Ok(Err(_)) => errors.push(format!("{}: timeout", address)),
+            Err(e) => errors.push(format!("{}: {}", address, e)),
+        }
+    }
+
+    found.ok_or_else(|| {
+        AppError::SshConnection(format!("No address reachable: {}", errors.join("; ")))
+    })
+}
+
+/// Periodically close cached connections that have been idle (and have no
+/// running command) for longer than `ssh.idle_timeout_secs`, read on every
+/// round so a reload applies; 0 keeps them open. Stops once the pool is gone.
+async fn reap_idle_connections(connections: Weak<ConnectionCache>, config: SharedConfig) {
+    loop {
+        let idle_timeout = Duration::from_secs(config.load().ssh.idle_timeout_secs);
+        tokio::time::sleep((idle_timeout / 4).max(Duration::from_secs(5))).await;
+        let Some(connections) = connections.upgrade() else {
+            return;
+        };
+        let idle_timeout = Duration::from_secs(config.load().ssh.idle_timeout_secs);
+        if idle_timeout.is_zero() {
+            continue;
+        }
+
+        let idle: Vec<(ConnectionKey, Arc<SshConnection>)> = match connections.lock() {
+            Ok(mut cache) => {
+                let mut idle = Vec::new();
+                cache.retain(|key, slot| {
+                    // A locked slot is connecting right now
+                    let Ok(mut guard) = slot.try_lock() else {
+                        return true;
+                    };
+                    let expired = guard.as_ref().is_some_and(|connection| {
+                        Arc::strong_count(connection) == 1 && connection.idle_for() >= idle_timeout
+                    });
+                    if expired {
+                        if let Some(connection) = guard.take() {
+                            idle.push((key.clone(), connection));
+                        }
+                    }
+                    guard.is_some() || Arc::strong_count(slot) > 1
+                });
+                idle
+            }
+            Err(_) => return,
+        };
+
+        for (key, connection) in idle {
+            tracing::info!("Closing idle connection to {}", key);
+            let _ = connection
+                .session
+                .disconnect(russh::Disconnect::ByApplication, "idle", "en")
+                .await;
+        }
+    }
+}
+
+/// Number of output chunks buffered before a slow reader applies backpressure
+pub(crate) const STREAM_BUFFER: usize = 256;
+
+/// A piece of output from a streaming command
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputChunk {
+    /// One or more complete lines of stdout (including trailing newlines)
+    Stdout(String),
+    /// One or more complete lines of stderr (including trailing newlines)
+    Stderr(String),
+    /// The command finished with this exit code (always the last chunk)
+    Exit(u32),
+}
+
+/// Live output of a running command
+pub type CommandStream = ReceiverStream<OutputChunk>;
+
+/// Drain a command stream into a buffered `CommandOutput`
+pub async fn collect_output(mut stream: CommandStream) -> CommandOutput {
+    let mut stdout = String::new();
+    let mut stderr = String::new();
+    let mut exit_code = 0;
+
+    while let Some(chunk) = stream.next().await {
+        match chunk {
+            OutputChunk::Stdout(s) => stdout.push_str(&s),
+            OutputChunk::Stderr(s) => stderr.push_str(&s),
+            OutputChunk::Exit(code) => exit_code = code,
+        }
+    }
+
+    CommandOutput {
+        stdout,
+        stderr,
+        exit_code,
+    }
+}
+
+/// Longest partial line a `LineBuffer` holds back (progress bars, binary
+/// output) before handing it out without a newline
+const MAX_PARTIAL_LINE: usize = 64 * 1024;
+
+/// Accumulates raw channel data and hands out complete lines
+#[derive(Default)]
+pub(crate) struct LineBuffer {
+    pending: Vec<u8>,
+}
+
+impl LineBuffer {
+    /// Add data, returning everything up to and including the last newline,
+    /// or the partial line once it reaches `MAX_PARTIAL_LINE`
+    pub(crate) fn push(&mut self, data: &[u8]) -> Option<String> {
+        self.pending.extend_from_slice(data);
+        let end = match self.pending.iter().rposition(|&b| b == b'\n') {
+            Some(newline) => newline + 1,
+            None if self.pending.len() >= MAX_PARTIAL_LINE => utf8_boundary(&self.pending),
+            None => return None,
+        };
+        if end == 0 {
+            return None;
+        }
+        let lines: Vec<u8> = self.pending.drain(..end).collect();
+        Some(String::from_utf8_lossy(&lines).to_string())
+    }
+
+    /// Whatever is left after the last newline
+    pub(crate) fn finish(&mut self) -> Option<String> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        let rest = std::mem::take(&mut self.pending);
+        Some(String::from_utf8_lossy(&rest).to_string())
+    }
+}
+
+/// Length of `data` without a character cut off at its end
+fn utf8_boundary(data: &[u8]) -> usize {
+    match std::str::from_utf8(data) {
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        _ => data.len(),
+    }
+}
+
+/// Output from an SSH command
+#[derive(Debug, Clone)]
+pub struct CommandOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_code: u32,
+}
+
+impl CommandOutput {
+    /// Check if the command succeeded (exit code 0)
+    pub fn success(&self) -> bool {
+        self.exit_code == 0
+    }
+
+    /// Get combined output (stdout + stderr)
+    pub fn combined(&self) -> String {
+        if self.stderr.is_empty() {
+            self.stdout.clone()
+        } else if self.stdout.is_empty() {
+            self.stderr.clone()
+        } else {
+            format!("{}\n{}", self.stdout, self.stderr)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::TestServer;
+    use super::*;
+    use std::sync::atomic::Ordering;
+
+    #[test]
+    fn test_pct_exec_quotes_command() {
+        let ctid = Ctid::new(205).unwrap();
+        assert_eq!(
+            pct_exec(ctid, "cd ~/.homelab && docker ps"),
+            "pct exec 205 -- sh -c 'cd ~/.homelab && docker ps'"
+        );
+        // Expanded inside the container, not on the Proxmox host
+        assert_eq!(pct_exec(ctid, "echo $HOME"), "pct exec 205 -- sh -c 'echo $HOME'");
+    }
+
+    #[tokio::test]
+    async fn test_run_reconnects_after_eviction() {
+        let server = TestServer::start().await;
+        let pool = SshPool::new(&server.config("")).unwrap();
+        let key = pool.profile_key("loopback").unwrap();
+
+        assert_eq!(pool.run(&key, "uptime", false).await.unwrap().stdout, "uptime\n");
+        assert_eq!(pool.run(&key, "uptime", false).await.unwrap().stdout, "uptime\n");
+        assert_eq!(server.state.connections(), 1);
+
+        // The command never reached the host, so even a non-idempotent one
+        // is sent again, on a new connection
+        server.state.refuse_channels.store(1, Ordering::Relaxed);
+        assert_eq!(pool.run(&key, "docker restart web", false).await.unwrap().stdout, "docker restart web\n");
+        assert_eq!(server.state.runs("docker restart web"), 1);
+        assert_eq!(server.state.connections(), 2);
+
+        // A failed retry leaves nothing broken in the cache
+        server.state.refuse_channels.store(2, Ordering::Relaxed);
+        assert!(pool.run(&key, "uptime", false).await.is_err());
+        assert_eq!(pool.run(&key, "uptime", false).await.unwrap().stdout, "uptime\n");
+    }
+
+    #[tokio::test]
+    async fn test_run_retries_only_idempotent_commands() {
+        let server = TestServer::start().await;
+        let pool = SshPool::new(&server.config("")).unwrap();
+        let key = pool.profile_key("loopback").unwrap();
+
+        // Connection lost after the command started: it may have run
+        assert!(pool.run(&key, "drop", false).await.is_err());
+        assert_eq!(server.state.runs("drop"), 1);
+        assert_eq!(server.state.connections(), 1);
+
+        assert!(pool.run(&key, "drop", true).await.is_err());
+        assert_eq!(server.state.runs("drop"), 3);
+        assert_eq!(server.state.connections(), 3);
+
+        // Both broken connections were evicted
+        assert_eq!(pool.run(&key, "uptime", false).await.unwrap().stdout, "uptime\n");
+        assert_eq!(server.state.connections(), 4);
+    }
+
+    #[test]
+    fn test_line_buffer_joins_chunks() {
+        let mut buffer = LineBuffer::default();
+        assert_eq!(buffer.push(b"first li"), None);
+        assert_eq!(buffer.push(b"ne\nsecond"), Some("first line\n".to_string()));
+        assert_eq!(buffer.push(b" line\nthird\npart"), Some("second line\nthird\n".to_string()));
+        // Trailing partial line only comes out at the end
+        assert_eq!(buffer.finish(), Some("part".to_string()));
+        assert_eq!(buffer.finish(), None);
+    }
+
+    #[test]
+    fn test_line_buffer_keeps_split_characters() {
+        let mut buffer = LineBuffer::default();
+        let text = "größe: 5 €\n".as_bytes();
+        // Split inside the two-byte 'ö' and the three-byte '€'
+        let euro = text.len() - 3;
+        assert_eq!(buffer.push(&text[..3]), None);
+        assert_eq!(buffer.push(&text[3..euro + 1]), None);
+        assert_eq!(buffer.push(&text[euro + 1..]), Some("größe: 5 €\n".to_string()));
+        assert_eq!(buffer.finish(), None);
+    }
+
+    #[test]
+    fn test_line_buffer_flushes_long_partial_lines() {
+        let mut buffer = LineBuffer::default();
+        let progress = "#".repeat(MAX_PARTIAL_LINE - 1);
+        assert_eq!(buffer.push(progress.as_bytes()), None);
+        // Flushed at the cap, keeping the '€' that isn't complete yet
+        let euro = "€".as_bytes();
+        assert_eq!(buffer.push(&[b'#', euro[0]]), Some(format!("{}#", progress)));
+        assert_eq!(buffer.push(&euro[1..]), None);
+        assert_eq!(buffer.finish(), Some("€".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_collect_output() {
+        let (tx, rx) = tokio::sync::mpsc::channel(8);
+        for chunk in [
+            OutputChunk::Stdout("one\n".to_string()),
+            OutputChunk::Stderr("warning\n".to_string()),
+            OutputChunk::Stdout("two\nthree".to_string()),
+            OutputChunk::Exit(3),
+        ] {
+            tx.send(chunk).await.unwrap();
+        }
+        drop(tx);
+
+        let output = collect_output(ReceiverStream::new(rx)).await;
+        assert_eq!(output.stdout, "one\ntwo\nthree");
+        assert_eq!(output.stderr, "warning\n");
+        assert_eq!(output.exit_code, 3);
+        assert!(!output.success());
+    }
+}
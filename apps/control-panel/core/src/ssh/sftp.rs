@@ -0,0 +1,409 @@
+//! Remote files over SFTP
+//!
+//! Each `Sftp` session is an SFTP subsystem channel on the host's cached
+//! connection, so opening one is cheap. Hosts are profiles or docker nodes;
+//! SFTP needs a direct SSH connection, so `local` and `pct` transports (and
+//! the `pct exec` fallback) don't apply.
+
+use chrono::{DateTime, Local, Utc};
+use russh_sftp::client::fs::File;
+use russh_sftp::client::SftpSession;
+use russh_sftp::protocol::{FileAttributes, FileType, OpenFlags};
+use serde::Serialize;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt, ReadBuf};
+
+use super::{Route, SshConnection, SshPool};
+use crate::error::AppError;
+use crate::transport::Target;
+
+/// Type of a directory entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileKind {
+    File,
+    Dir,
+    Symlink,
+    Other,
+}
+
+/// A remote file or directory
+#[derive(Debug, Clone, Serialize)]
+pub struct FileEntry {
+    pub name: String,
+    pub path: String,
+    pub kind: FileKind,
+    pub size: u64,
+    /// Permission bits (`0o644`)
+    pub mode: u32,
+    pub modified: Option<DateTime<Utc>>,
+}
+
+impl FileEntry {
+    fn new(path: String, name: String, attrs: &russh_sftp::protocol::FileAttributes) -> Self {
+        let kind = match attrs.file_type() {
+            FileType::Dir => FileKind::Dir,
+            FileType::File => FileKind::File,
+            FileType::Symlink => FileKind::Symlink,
+            FileType::Other => FileKind::Other,
+        };
+        Self {
+            name,
+            path,
+            kind,
+            size: attrs.size.unwrap_or(0),
+            mode: attrs.permissions.unwrap_or(0) & 0o7777,
+            modified: attrs.mtime.and_then(|t| DateTime::from_timestamp(t as i64, 0)),
+        }
+    }
+
+    /// Permissions in `ls -l` notation (`rw-r--r--`)
+    pub fn mode_string(&self) -> String {
+        let flags = ['r', 'w', 'x'];
+        (0..9)
+            .map(|i| {
+                if self.mode & (0o400 >> i) != 0 {
+                    flags[i % 3]
+                } else {
+                    '-'
+                }
+            })
+            .collect()
+    }
+}
+
+/// An SFTP session on a host. Keeps the host's connection open while in use.
+pub struct Sftp {
+    session: SftpSession,
+    host: String,
+    _connection: Arc<SshConnection>,
+}
+
+impl SshPool {
+    /// Open an SFTP session to a profile or docker node
+    pub async fn sftp(&self, host: &str) -> Result<Sftp, AppError> {
+        let key = match self.route(Target::Profile(host))? {
+            Route::Ssh(key) => key,
+            Route::Local | Route::Pct(_) => {
+                return Err(AppError::NotImplemented(format!(
+                    "File access to {} needs transport = \"ssh\"",
+                    host
+                )))
+            }
+        };
+
+        let connection = self.connection(&key).await?;
+        let channel = connection
+            .session
+            .channel_open_session()
+            .await
+            .map_err(|e| AppError::SshCommand(format!("Failed to open channel: {}", e)))?;
+        channel
+            .request_subsystem(true, "sftp")
+            .await
+            .map_err(|e| AppError::Sftp(format!("{}: subsystem request failed: {}", host, e)))?;
+        let session = SftpSession::new(channel.into_stream())
+            .await
+            .map_err(|e| AppError::Sftp(format!("{}: {}", host, e)))?;
+
+        connection.touch();
+        Ok(Sftp {
+            session,
+            host: host.to_string(),
+            _connection: connection,
+        })
+    }
+}
+
+impl Sftp {
+    fn error(&self, path: &str, e: impl std::fmt::Display) -> AppError {
+        AppError::Sftp(format!("{}:{}: {}", self.host, path, e))
+    }
+
+    /// Absolute path of the login directory
+    pub async fn home(&self) -> Result<String, AppError> {
+        self.session.canonicalize(".").await.map_err(|e| self.error(".", e))
+    }
+
+    /// Directory contents, directories first, then by name
+    pub async fn list(&self, path: &str) -> Result<Vec<FileEntry>, AppError> {
+        let dir = self.session.read_dir(path).await.map_err(|e| self.error(path, e))?;
+        let mut entries: Vec<FileEntry> = dir
+            .map(|entry| {
+                let name = entry.file_name();
+                FileEntry::new(join(path, &name), name, &entry.metadata())
+            })
+            .collect();
+        entries.sort_by(|a, b| {
+            (a.kind != FileKind::Dir, &a.name).cmp(&(b.kind != FileKind::Dir, &b.name))
+        });
+        Ok(entries)
+    }
+
+    /// File or directory information (following symlinks)
+    pub async fn stat(&self, path: &str) -> Result<FileEntry, AppError> {
+        let attrs = self.session.metadata(path).await.map_err(|e| self.error(path, e))?;
+        Ok(FileEntry::new(path.to_string(), file_name(path), &attrs))
+    }
+
+    /// Read a whole file, refusing files larger than `max_size`
+    pub async fn read(&self, path: &str, max_size: u64) -> Result<Vec<u8>, AppError> {
+        let entry = self.stat(path).await?;
+        if entry.kind == FileKind::Dir {
+            return Err(AppError::Validation(format!("{} is a directory", path)));
+        }
+        if entry.size > max_size {
+            return Err(too_large(path, entry.size, max_size));
+        }
+
+        let file = self.session.open(path).await.map_err(|e| self.error(path, e))?;
+        // Files can grow between stat and read (logs)
+        let mut data = Vec::with_capacity(entry.size as usize);
+        file.take(max_size + 1)
+            .read_to_end(&mut data)
+            .await
+            .map_err(|e| self.error(path, e))?;
+        if data.len() as u64 > max_size {
+            return Err(AppError::Validation(format!(
+                "{} grew over the {} byte limit",
+                path, max_size
+            )));
+        }
+        Ok(data)
+    }
+
+    /// Open a file for streaming, refusing files larger than `max_size`.
+    /// The reader keeps the session open and stops at the size seen when
+    /// opening, so it matches `RemoteFile::size` even if the file grows.
+    pub async fn into_reader(self, path: &str, max_size: u64) -> Result<RemoteFile, AppError> {
+        let entry = self.stat(path).await?;
+        if entry.kind == FileKind::Dir {
+            return Err(AppError::Validation(format!("{} is a directory", path)));
+        }
+        if entry.size > max_size {
+            return Err(too_large(path, entry.size, max_size));
+        }
+
+        let file = self.session.open(path).await.map_err(|e| self.error(path, e))?;
+        Ok(RemoteFile {
+            size: entry.size,
+            file: file.take(entry.size),
+            _sftp: self,
+        })
+    }
+
+    /// Write a whole file. With `backup`, an existing file is first copied
+    /// to `<path>.bak-<timestamp>`, whose path is returned; originals larger
+    /// than `max_backup` are refused rather than copied.
+    pub async fn write(
+        &self,
+        path: &str,
+        data: &[u8],
+        backup: bool,
+        max_backup: u64,
+    ) -> Result<Option<String>, AppError> {
+        let exists = self.session.try_exists(path).await.map_err(|e| self.error(path, e))?;
+
+        let backup_path = if backup && exists {
+            Some(self.backup(path, max_backup).await?)
+        } else {
+            None
+        };
+
+        // Truncating in place keeps the file's owner and permissions
+        self.write_all(path, data).await?;
+        tracing::info!("Wrote {} bytes to {}:{}", data.len(), self.host, path);
+        Ok(backup_path)
+    }
+
+    /// Copy a file next to itself with the same permissions (0600 if the
+    /// server doesn't report them), so a backup of a secret stays private
+    async fn backup(&self, path: &str, max_size: u64) -> Result<String, AppError> {
+        let entry = self.stat(path).await?;
+        if entry.kind == FileKind::Dir {
+            return Err(AppError::Validation(format!("{} is a directory", path)));
+        }
+        if entry.size > max_size {
+            return Err(too_large(path, entry.size, max_size));
+        }
+
+        let backup_path = format!("{}.bak-{}", path, Local::now().format("%Y%m%d-%H%M%S"));
+        let mut attrs = FileAttributes::empty();
+        attrs.permissions = Some(if entry.mode == 0 { 0o600 } else { entry.mode });
+
+        let mut source = self.session.open(path).await.map_err(|e| self.error(path, e))?;
+        let mut target = self
+            .session
+            .open_with_flags_and_attributes(
+                &backup_path,
+                OpenFlags::CREATE | OpenFlags::EXCLUDE | OpenFlags::WRITE,
+                attrs.clone(),
+            )
+            .await
+            .map_err(|e| self.error(&backup_path, e))?;
+        // The mode given at creation is subject to the server's umask
+        target.set_metadata(attrs).await.map_err(|e| self.error(&backup_path, e))?;
+        tokio::io::copy(&mut source, &mut target)
+            .await
+            .map_err(|e| self.error(&backup_path, e))?;
+        target.shutdown().await.map_err(|e| self.error(&backup_path, e))?;
+        Ok(backup_path)
+    }
+
+    async fn write_all(&self, path: &str, data: &[u8]) -> Result<(), AppError> {
+        let mut file = self.session.create(path).await.map_err(|e| self.error(path, e))?;
+        file.write_all(data).await.map_err(|e| self.error(path, e))?;
+        file.shutdown().await.map_err(|e| self.error(path, e))?;
+        Ok(())
+    }
+
+    /// Copy a local file to the host, returning the number of bytes sent
+    pub async fn upload(&self, local: &Path, remote: &str, max_size: u64) -> Result<u64, AppError> {
+        let mut source = tokio::fs::File::open(local).await?;
+        let size = source.metadata().await?.len();
+        if size > max_size {
+            return Err(too_large(&local.display().to_string(), size, max_size));
+        }
+        let mut target = self.session.create(remote).await.map_err(|e| self.error(remote, e))?;
+        let bytes = tokio::io::copy(&mut source, &mut target)
+            .await
+            .map_err(|e| self.error(remote, e))?;
+        target.shutdown().await.map_err(|e| self.error(remote, e))?;
+        tracing::info!("Uploaded {} to {}:{} ({} bytes)", local.display(), self.host, remote, bytes);
+        Ok(bytes)
+    }
+
+    /// Copy a file from the host to a local path, returning the number of
+    /// bytes received
+    pub async fn download(&self, remote: &str, local: &Path, max_size: u64) -> Result<u64, AppError> {
+        let entry = self.stat(remote).await?;
+        if entry.size > max_size {
+            return Err(too_large(remote, entry.size, max_size));
+        }
+        let mut source = self.session.open(remote).await.map_err(|e| self.error(remote, e))?;
+        let mut target = tokio::fs::File::create(local).await?;
+        let bytes = tokio::io::copy(&mut source, &mut target).await?;
+        target.flush().await?;
+        tracing::info!("Downloaded {}:{} to {} ({} bytes)", self.host, remote, local.display(), bytes);
+        Ok(bytes)
+    }
+}
+
+/// A remote file being read, see `Sftp::into_reader`
+pub struct RemoteFile {
+    /// Size in bytes when the file was opened
+    pub size: u64,
+    file: tokio::io::Take<File>,
+    _sftp: Sftp,
+}
+
+impl AsyncRead for RemoteFile {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.file).poll_read(cx, buf)
+    }
+}
+
+fn too_large(path: &str, size: u64, max_size: u64) -> AppError {
+    AppError::Validation(format!("{} is {} bytes, over the {} byte limit", path, size, max_size))
+}
+
+/// Join a directory and a name into a remote path
+pub fn join(dir: &str, name: &str) -> String {
+    if dir.ends_with('/') {
+        format!("{}{}", dir, name)
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+/// Parent directory of a remote path (`/` for top-level entries)
+pub fn parent(path: &str) -> String {
+    match path.trim_end_matches('/').rsplit_once('/') {
+        Some(("", _)) | None => "/".to_string(),
+        Some((parent, _)) => parent.to_string(),
+    }
+}
+
+fn file_name(path: &str) -> String {
+    path.trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or(path)
+        .to_string()
+}
+
+/// Whether data looks like text that can be edited
+pub fn is_text(data: &[u8]) -> bool {
+    !data[..data.len().min(8192)].contains(&0) && std::str::from_utf8(data).is_ok()
+}
+
+/// Syntax highlighting language for a file, by name (highlight.js names)
+pub fn syntax_for_path(path: &str) -> &'static str {
+    let name = file_name(path).to_lowercase();
+    if name == "dockerfile" || name.starts_with("dockerfile.") {
+        return "dockerfile";
+    }
+    if name == "nginx.conf" || path.contains("/nginx/") {
+        return "nginx";
+    }
+    match name.rsplit_once('.').map(|(_, ext)| ext) {
+        Some("yml" | "yaml") => "yaml",
+        Some("nix") => "nix",
+        Some("toml") => "toml",
+        Some("json") => "json",
+        Some("sh" | "bash" | "zsh") => "bash",
+        Some("conf" | "cfg" | "ini" | "env" | "service" | "timer") => "ini",
+        Some("xml" | "html") => "xml",
+        Some("md") => "markdown",
+        Some("py") => "python",
+        Some("rs") => "rust",
+        Some("js") => "javascript",
+        Some("sql") => "sql",
+        _ => "plaintext",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paths() {
+        assert_eq!(join("/etc", "hosts"), "/etc/hosts");
+        assert_eq!(join("/", "etc"), "/etc");
+        assert_eq!(parent("/etc/nginx/nginx.conf"), "/etc/nginx");
+        assert_eq!(parent("/etc"), "/");
+        assert_eq!(parent("/"), "/");
+        assert_eq!(file_name("/home/akunito/.homelab/docker-compose.yml"), "docker-compose.yml");
+    }
+
+    #[test]
+    fn test_syntax_and_text() {
+        assert_eq!(syntax_for_path("/opt/homelab/docker-compose.yml"), "yaml");
+        assert_eq!(syntax_for_path("/etc/nixos/configuration.nix"), "nix");
+        assert_eq!(syntax_for_path("/srv/app/Dockerfile"), "dockerfile");
+        assert_eq!(syntax_for_path("/var/log/syslog"), "plaintext");
+        assert!(is_text(b"services:\n  web:\n"));
+        assert!(!is_text(b"\x7fELF\x00\x01"));
+    }
+
+    #[test]
+    fn test_mode_string() {
+        let entry = FileEntry {
+            name: "x".to_string(),
+            path: "/x".to_string(),
+            kind: FileKind::File,
+            size: 0,
+            mode: 0o754,
+            modified: None,
+        };
+        assert_eq!(entry.mode_string(), "rwxr-xr--");
+    }
+}
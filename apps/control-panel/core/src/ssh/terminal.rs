@@ -0,0 +1,309 @@
+//! Interactive terminal sessions
+//!
+//! A session is a PTY channel on a cached connection: a login shell on a
+//! host, `docker exec -it` into a container, or `pct enter` into an LXC on the
+//! Proxmox host. A task owns the channel and relays keyboard input, resizes
+//! and output through the session's channels. Dropping the input sender
+//! closes the channel.
+//!
+//! Sessions without keyboard input for `terminal.idle_timeout_secs` (unless
+//! 0) are closed. Output (what the user saw, which excludes unechoed input such as
+//! passwords) is recorded to a transcript file per session.
+
+use chrono::Local;
+use russh::ChannelMsg;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+use super::{pct_exec, ConnectionKey, Route, SshConnection, SshPool};
+use crate::error::AppError;
+use crate::shell::{ContainerName, Ctid, ShellCommand};
+use crate::transport::Target;
+
+/// Terminal type announced to the remote side
+const TERM: &str = "xterm-256color";
+
+/// What a terminal session is attached to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TerminalTarget {
+    /// Login shell on a profile or docker node
+    Host(String),
+    /// Shell inside a docker container on a node
+    Container { node: String, container: String },
+    /// Console of an LXC container, from its Proxmox endpoint
+    Lxc { proxmox: String, ctid: u32 },
+}
+
+impl TerminalTarget {
+    /// File name friendly form, for transcripts
+    fn slug(&self) -> String {
+        let raw = match self {
+            TerminalTarget::Host(name) => name.clone(),
+            TerminalTarget::Container { node, container } => format!("{}-{}", node, container),
+            TerminalTarget::Lxc { proxmox, ctid } => format!("lxc-{}-{}", proxmox, ctid),
+        };
+        raw.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect()
+    }
+}
+
+impl fmt::Display for TerminalTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TerminalTarget::Host(name) => write!(f, "{}", name),
+            TerminalTarget::Container { node, container } => write!(f, "{} on {}", container, node),
+            TerminalTarget::Lxc { proxmox, ctid } => write!(f, "LXC {} on {}", ctid, proxmox),
+        }
+    }
+}
+
+/// Input to a terminal session
+#[derive(Debug, Clone)]
+pub enum TerminalInput {
+    /// Keyboard input
+    Data(Vec<u8>),
+    /// The terminal was resized
+    Resize { cols: u32, rows: u32 },
+}
+
+/// Output of a terminal session
+#[derive(Debug, Clone, PartialEq)]
+pub enum TerminalOutput {
+    /// Raw terminal output
+    Data(Vec<u8>),
+    /// The remote shell exited with this code (last message)
+    Exit(u32),
+    /// Closed after `terminal.idle_timeout_secs` without input (last message)
+    IdleTimeout,
+}
+
+/// A running terminal session
+pub struct TerminalSession {
+    pub input: mpsc::Sender<TerminalInput>,
+    pub output: mpsc::Receiver<TerminalOutput>,
+}
+
+impl SshPool {
+    /// Open an interactive terminal. `opened_by` is recorded in the transcript.
+    pub async fn open_terminal(
+        &self,
+        target: &TerminalTarget,
+        cols: u32,
+        rows: u32,
+        opened_by: &str,
+    ) -> Result<TerminalSession, AppError> {
+        let (key, command) = self.terminal_route(target).await?;
+        let connection = self.connection(&key).await?;
+
+        let channel = connection
+            .session
+            .channel_open_session()
+            .await
+            .map_err(|e| AppError::SshCommand(format!("Failed to open channel: {}", e)))?;
+        channel
+            .request_pty(false, TERM, cols, rows, 0, 0, &[])
+            .await
+            .map_err(|e| AppError::SshCommand(format!("Failed to request PTY: {}", e)))?;
+        match command {
+            Some(ref command) => channel.exec(true, command.as_str()).await,
+            None => channel.request_shell(true).await,
+        }
+        .map_err(|e| AppError::SshCommand(format!("Failed to start shell: {}", e)))?;
+
+        tracing::info!("Terminal to {} opened by {}", target, opened_by);
+        let transcript = if self.config().terminal.transcripts {
+            match Transcript::create(self.config().transcript_dir(), target, opened_by).await {
+                Ok(transcript) => Some(transcript),
+                Err(e) => {
+                    tracing::warn!("Terminal transcript for {} not recorded: {}", target, e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let (input_tx, input_rx) = mpsc::channel(64);
+        let (output_tx, output_rx) = mpsc::channel(super::STREAM_BUFFER);
+        // 0 turns the idle timeout off
+        let idle_timeout = match self.config().terminal.idle_timeout_secs {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        };
+        tokio::spawn(relay(connection, channel, input_rx, output_tx, idle_timeout, transcript));
+
+        Ok(TerminalSession {
+            input: input_tx,
+            output: output_rx,
+        })
+    }
+
+    /// Connection and command (none for a login shell) for a terminal target
+    async fn terminal_route(&self, target: &TerminalTarget) -> Result<(ConnectionKey, Option<String>), AppError> {
+        let local_error = || {
+            AppError::NotImplemented(format!(
+                "Terminals to {} (transport = \"local\") aren't supported",
+                target
+            ))
+        };
+
+        match target {
+            TerminalTarget::Host(name) => {
+                let target = Target::Profile(name);
+                match self.route(target)? {
+                    Route::Ssh(key) => match self.pct_fallback(target, &key).await? {
+                        Some(ctid) => Ok((self.container_host_key(target)?, Some(pct_enter(ctid)))),
+                        None => Ok((key, None)),
+                    },
+                    Route::Pct(ctid) => Ok((self.container_host_key(target)?, Some(pct_enter(ctid)))),
+                    Route::Local => Err(local_error()),
+                }
+            }
+            TerminalTarget::Container { node, container } => {
+                let container = ContainerName::parse(container)?;
+                let docker = ShellCommand::new("docker")
+                    .args(["exec", "-it", "-e"])
+                    .arg(format!("TERM={}", TERM))
+                    .arg(&container)
+                    .args(["sh", "-c", "command -v bash >/dev/null && exec bash || exec sh"])
+                    .build();
+
+                let target = Target::Node(node);
+                match self.route(target)? {
+                    Route::Ssh(key) => match self.pct_fallback(target, &key).await? {
+                        Some(ctid) => Ok((self.container_host_key(target)?, Some(pct_exec(ctid, &docker)))),
+                        None => Ok((key, Some(docker))),
+                    },
+                    Route::Pct(ctid) => Ok((self.container_host_key(target)?, Some(pct_exec(ctid, &docker)))),
+                    Route::Local => Err(local_error()),
+                }
+            }
+            TerminalTarget::Lxc { proxmox, ctid } => Ok((self.proxmox_key(proxmox)?, Some(pct_enter(Ctid::new(*ctid)?)))),
+        }
+    }
+}
+
+fn pct_enter(ctid: Ctid) -> String {
+    ShellCommand::new("pct").arg("enter").arg(ctid.to_string()).build()
+}
+
+/// Relay input, output and resizes until the shell exits, the client goes
+/// away or the session idles out
+async fn relay(
+    connection: Arc<SshConnection>,
+    mut channel: russh::Channel<russh::client::Msg>,
+    mut input: mpsc::Receiver<TerminalInput>,
+    output: mpsc::Sender<TerminalOutput>,
+    idle_timeout: Option<Duration>,
+    mut transcript: Option<Transcript>,
+) {
+    let mut last_input = Instant::now();
+    let mut exit_code = None;
+
+    let reason = loop {
+        tokio::select! {
+            msg = channel.wait() => match msg {
+                Some(ChannelMsg::Data { ref data }) | Some(ChannelMsg::ExtendedData { ref data, .. }) => {
+                    if let Some(ref mut transcript) = transcript {
+                        transcript.write(data).await;
+                    }
+                    if output.send(TerminalOutput::Data(data.to_vec())).await.is_err() {
+                        break "client disconnected";
+                    }
+                }
+                Some(ChannelMsg::ExitStatus { exit_status }) => exit_code = Some(exit_status),
+                Some(_) => {}
+                None => break "shell exited",
+            },
+            msg = input.recv() => match msg {
+                Some(TerminalInput::Data(data)) => {
+                    last_input = Instant::now();
+                    if channel.data(&data[..]).await.is_err() {
+                        break "channel closed";
+                    }
+                }
+                Some(TerminalInput::Resize { cols, rows }) => {
+                    let _ = channel.window_change(cols, rows, 0, 0).await;
+                }
+                None => break "client disconnected",
+            },
+            _ = tokio::time::sleep_until(last_input + idle_timeout.unwrap_or_default()), if idle_timeout.is_some() => {
+                let _ = output.send(TerminalOutput::IdleTimeout).await;
+                break "idle timeout";
+            }
+        }
+    };
+
+    let _ = channel.close().await;
+    connection.touch();
+    if let Some(code) = exit_code {
+        let _ = output.send(TerminalOutput::Exit(code)).await;
+    }
+
+    tracing::info!("Terminal session ended: {}", reason);
+    if let Some(transcript) = transcript {
+        transcript.finish(reason, exit_code).await;
+    }
+}
+
+/// Session output log, one file per session
+struct Transcript {
+    file: tokio::fs::File,
+    path: PathBuf,
+}
+
+impl Transcript {
+    async fn create(dir: PathBuf, target: &TerminalTarget, opened_by: &str) -> std::io::Result<Self> {
+        tokio::fs::create_dir_all(&dir).await?;
+        let now = Local::now();
+        let path = dir.join(format!("{}-{}.log", now.format("%Y%m%d-%H%M%S"), target.slug()));
+
+        let mut options = tokio::fs::OpenOptions::new();
+        options.create(true).append(true).mode(0o600);
+        let mut file = options.open(&path).await?;
+        let header = format!(
+            "# Terminal to {} opened by {} at {}\n",
+            target,
+            opened_by,
+            now.to_rfc3339()
+        );
+        file.write_all(header.as_bytes()).await?;
+
+        Ok(Self { file, path })
+    }
+
+    async fn write(&mut self, data: &[u8]) {
+        if let Err(e) = self.file.write_all(data).await {
+            tracing::warn!("Failed to write transcript {}: {}", self.path.display(), e);
+        }
+    }
+
+    async fn finish(mut self, reason: &str, exit_code: Option<u32>) {
+        let code = exit_code.map(|c| format!(", exit {}", c)).unwrap_or_default();
+        let footer = format!("\n# Closed at {} ({}{})\n", Local::now().to_rfc3339(), reason, code);
+        self.write(footer.as_bytes()).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_slug() {
+        let target = TerminalTarget::Container {
+            node: "LXC_HOME".to_string(),
+            container: "homelab-db-1".to_string(),
+        };
+        assert_eq!(target.slug(), "LXC_HOME-homelab-db-1");
+        assert_eq!(TerminalTarget::Host("../etc".to_string()).slug(), "___etc");
+        let lxc = TerminalTarget::Lxc { proxmox: "home".to_string(), ctid: 205 };
+        assert_eq!(lxc.slug(), "lxc-home-205");
+    }
+}
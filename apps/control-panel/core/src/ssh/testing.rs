@@ -0,0 +1,166 @@
+//! Loopback SSH server for tests
+//!
+//! Accepts any public key, forwards direct-tcpip channels to the requested
+//! address and counts connections. Commands are echoed back with exit code
+//! 0, except `drop`, which closes the connection before answering. Its host
+//! key and the client key it is used with are in `fixtures/ssh`.
+
+use russh::server::{self, Auth, Msg, Session};
+use russh::{Channel, ChannelId, CryptoVec};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::config::{minimal_toml, Config, SharedConfig};
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fixtures").join("ssh").join(name)
+}
+
+/// What the server has seen, and how it misbehaves
+#[derive(Default)]
+pub(crate) struct ServerState {
+    /// Connections accepted so far
+    pub connections: AtomicUsize,
+    /// Commands received, in order
+    pub commands: Mutex<Vec<String>>,
+    /// Refuse this many of the next session channels
+    pub refuse_channels: AtomicUsize,
+}
+
+impl ServerState {
+    pub fn connections(&self) -> usize {
+        self.connections.load(Ordering::Relaxed)
+    }
+
+    /// How often a command was received
+    pub fn runs(&self, command: &str) -> usize {
+        self.commands.lock().unwrap().iter().filter(|c| *c == command).count()
+    }
+}
+
+/// SSH server on a random loopback port, stopped when dropped
+pub(crate) struct TestServer {
+    pub port: u16,
+    pub state: Arc<ServerState>,
+    /// Holds the known_hosts file of `config`
+    dir: TempDir,
+    accept: tokio::task::JoinHandle<()>,
+}
+
+impl TestServer {
+    pub async fn start() -> Self {
+        let host_key = std::fs::read_to_string(fixture("host_key")).unwrap();
+        let config = Arc::new(server::Config {
+            keys: vec![russh_keys::decode_secret_key(&host_key, None).unwrap()],
+            ..Default::default()
+        });
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let state = Arc::new(ServerState::default());
+
+        let shared = state.clone();
+        let accept = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                shared.connections.fetch_add(1, Ordering::Relaxed);
+                let handler = TestHandler { state: shared.clone() };
+                if let Ok(session) = server::run_stream(config.clone(), stream, handler).await {
+                    tokio::spawn(session);
+                }
+            }
+        });
+
+        Self {
+            port,
+            state,
+            dir: TempDir::new().unwrap(),
+            accept,
+        }
+    }
+
+    /// Configuration with a `loopback` profile pointing at this server
+    pub fn config(&self, extra: &str) -> SharedConfig {
+        let hosts = format!(
+            r#"
+[[profiles]]
+name = "loopback"
+type = "vm"
+hostname = "loopback"
+ip = "127.0.0.1"
+port = {port}
+{extra}
+"#,
+            port = self.port,
+        );
+        let mut config: Config = toml::from_str(&minimal_toml(self.dir.path(), &hosts)).unwrap();
+        config.ssh.private_key_path = fixture("client_key").display().to_string();
+        config.ssh.known_hosts_file = Some(self.dir.path().join("known_hosts").display().to_string());
+        config.ssh.connect_timeout_secs = 5;
+        SharedConfig::new(config)
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.accept.abort();
+    }
+}
+
+struct TestHandler {
+    state: Arc<ServerState>,
+}
+
+#[async_trait::async_trait]
+impl server::Handler for TestHandler {
+    type Error = russh::Error;
+
+    async fn auth_publickey(&mut self, _user: &str, _key: &russh_keys::PublicKey) -> Result<Auth, Self::Error> {
+        Ok(Auth::Accept)
+    }
+
+    async fn channel_open_session(&mut self, _channel: Channel<Msg>, _session: &mut Session) -> Result<bool, Self::Error> {
+        let refuse = self
+            .state
+            .refuse_channels
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+            .is_ok();
+        Ok(!refuse)
+    }
+
+    async fn exec_request(&mut self, channel: ChannelId, data: &[u8], session: &mut Session) -> Result<(), Self::Error> {
+        let command = String::from_utf8_lossy(data).to_string();
+        self.state.commands.lock().unwrap().push(command.clone());
+        if command == "drop" {
+            return Err(russh::Error::Disconnect);
+        }
+
+        session.channel_success(channel)?;
+        session.data(channel, CryptoVec::from_slice(format!("{}\n", command).as_bytes()))?;
+        session.exit_status_request(channel, 0)?;
+        session.eof(channel)?;
+        session.close(channel)?;
+        Ok(())
+    }
+
+    async fn channel_open_direct_tcpip(
+        &mut self,
+        channel: Channel<Msg>,
+        host_to_connect: &str,
+        port_to_connect: u32,
+        _originator_address: &str,
+        _originator_port: u32,
+        _session: &mut Session,
+    ) -> Result<bool, Self::Error> {
+        let Ok(mut target) = TcpStream::connect((host_to_connect, port_to_connect as u16)).await else {
+            return Ok(false);
+        };
+        tokio::spawn(async move {
+            let mut stream = channel.into_stream();
+            let _ = tokio::io::copy_bidirectional(&mut stream, &mut target).await;
+        });
+        Ok(true)
+    }
+}
@@ -0,0 +1,395 @@
+//! Local port forwards (SSH tunnels)
+//!
+//! A tunnel listens on a local port (`tunnels.bind_address`, port chosen by
+//! the OS) and forwards each accepted connection over a direct-tcpip channel
+//! on the host's cached connection, like `ssh -L`. The remote end is resolved
+//! by the host, so `localhost` is the host itself.
+//!
+//! Opening the same forward twice returns the running tunnel. A tunnel
+//! without open connections for `tunnels.idle_timeout_secs` is closed.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Notify;
+use tokio::task::JoinSet;
+use tokio::time::Instant;
+
+use super::{ConnectionKey, Route, SshPool};
+use crate::error::AppError;
+use crate::transport::Target;
+
+/// An open tunnel
+#[derive(Debug, Clone, Serialize)]
+pub struct TunnelInfo {
+    pub id: u64,
+    /// Profile or docker node the tunnel goes through
+    pub host: String,
+    /// Destination, as seen from the host
+    pub remote_host: String,
+    pub remote_port: u16,
+    /// Where the tunnel listens
+    pub local_address: SocketAddr,
+    pub opened_at: DateTime<Utc>,
+    /// Connections open right now
+    pub connections: usize,
+    /// Connections accepted so far
+    pub total_connections: u64,
+    /// Traffic of closed connections
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    /// Seconds since the last connection closed (0 while in use)
+    pub idle_secs: u64,
+}
+
+/// Open tunnels, shared by all clones of a pool
+#[derive(Default)]
+pub(crate) struct Tunnels {
+    next_id: AtomicU64,
+    open: Mutex<HashMap<u64, Tunnel>>,
+}
+
+struct Tunnel {
+    host: String,
+    key: ConnectionKey,
+    remote_host: String,
+    remote_port: u16,
+    local_address: SocketAddr,
+    opened_at: DateTime<Utc>,
+    stats: Arc<TunnelStats>,
+    close: Arc<Notify>,
+}
+
+impl Tunnel {
+    fn forwards(&self, host: &str, remote_host: &str, remote_port: u16) -> bool {
+        self.host == host && self.remote_host == remote_host && self.remote_port == remote_port
+    }
+
+    fn info(&self, id: u64) -> TunnelInfo {
+        let connections = self.stats.active.load(Ordering::Relaxed);
+        TunnelInfo {
+            id,
+            host: self.host.clone(),
+            remote_host: self.remote_host.clone(),
+            remote_port: self.remote_port,
+            local_address: self.local_address,
+            opened_at: self.opened_at,
+            connections,
+            total_connections: self.stats.total.load(Ordering::Relaxed),
+            bytes_sent: self.stats.sent.load(Ordering::Relaxed),
+            bytes_received: self.stats.received.load(Ordering::Relaxed),
+            idle_secs: if connections > 0 { 0 } else { self.stats.idle_for().as_secs() },
+        }
+    }
+}
+
+#[derive(Default)]
+struct TunnelStats {
+    active: AtomicUsize,
+    total: AtomicU64,
+    sent: AtomicU64,
+    received: AtomicU64,
+    last_active: Mutex<Option<Instant>>,
+}
+
+impl TunnelStats {
+    fn touch(&self) {
+        if let Ok(mut last_active) = self.last_active.lock() {
+            *last_active = Some(Instant::now());
+        }
+    }
+
+    fn last_active(&self) -> Instant {
+        self.last_active
+            .lock()
+            .ok()
+            .and_then(|last_active| *last_active)
+            .unwrap_or_else(Instant::now)
+    }
+
+    fn idle_for(&self) -> Duration {
+        self.last_active().elapsed()
+    }
+}
+
+impl SshPool {
+    /// Forward a local port to `remote_host:remote_port` as seen from a
+    /// profile or docker node
+    pub async fn open_tunnel(&self, host: &str, remote_host: &str, remote_port: u16) -> Result<TunnelInfo, AppError> {
+        if remote_host.is_empty() || remote_port == 0 {
+            return Err(AppError::Validation("Tunnel needs a remote host and port".to_string()));
+        }
+
+        let key = match self.route(Target::Profile(host))? {
+            Route::Ssh(key) => key,
+            Route::Local => {
+                return Err(AppError::Validation(format!(
+                    "{} is this machine (transport = \"local\"); connect to port {} directly",
+                    host, remote_port
+                )))
+            }
+            Route::Pct(_) => {
+                return Err(AppError::NotImplemented(format!(
+                    "Tunnels to {} need transport = \"ssh\"",
+                    host
+                )))
+            }
+        };
+
+        if let Some(info) = self.find_tunnel(host, remote_host, remote_port) {
+            return Ok(info);
+        }
+
+        // Fail now rather than on the first forwarded connection
+        self.connection(&key).await?;
+
+        let listener = TcpListener::bind((self.config().tunnels.bind_address.as_str(), 0)).await?;
+        let local_address = listener.local_addr()?;
+        let tunnel = Tunnel {
+            host: host.to_string(),
+            key: key.clone(),
+            remote_host: remote_host.to_string(),
+            remote_port,
+            local_address,
+            opened_at: Utc::now(),
+            stats: Arc::default(),
+            close: Arc::default(),
+        };
+        tunnel.stats.touch();
+
+        let task = TunnelTask {
+            pool: self.clone(),
+            key,
+            remote_host: remote_host.to_string(),
+            remote_port,
+            stats: tunnel.stats.clone(),
+            close: tunnel.close.clone(),
+        };
+        let (id, info) = {
+            let mut open = self
+                .tunnels
+                .open
+                .lock()
+                .map_err(|_| AppError::Internal("Tunnel list poisoned".to_string()))?;
+            // Another request may have opened the same forward while this one
+            // was connecting; keep that one and drop our listener
+            if let Some((id, existing)) = open.iter().find(|(_, t)| t.forwards(host, remote_host, remote_port)) {
+                return Ok(existing.info(*id));
+            }
+            let id = self.tunnels.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+            let info = tunnel.info(id);
+            open.insert(id, tunnel);
+            (id, info)
+        };
+
+        tracing::info!(
+            "Tunnel {} opened: {} -> {}:{} via {}",
+            id,
+            local_address,
+            remote_host,
+            remote_port,
+            host
+        );
+        let idle_timeout = Duration::from_secs(self.config().tunnels.idle_timeout_secs);
+        tokio::spawn(task.run(id, listener, idle_timeout));
+        Ok(info)
+    }
+
+    /// Open tunnels, oldest first
+    pub fn tunnels(&self) -> Vec<TunnelInfo> {
+        let mut tunnels: Vec<TunnelInfo> = match self.tunnels.open.lock() {
+            Ok(open) => open.iter().map(|(id, tunnel)| tunnel.info(*id)).collect(),
+            Err(_) => Vec::new(),
+        };
+        tunnels.sort_by_key(|t| t.id);
+        tunnels
+    }
+
+    /// Close a tunnel and its connections. Returns false if it wasn't open.
+    pub fn close_tunnel(&self, id: u64) -> bool {
+        match self.tunnels.open.lock().ok().and_then(|mut open| open.remove(&id)) {
+            Some(tunnel) => {
+                tunnel.close.notify_one();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Close tunnels whose connection isn't in `wanted` (config reload)
+    pub(super) fn close_stale_tunnels(&self, wanted: &HashSet<ConnectionKey>) {
+        let stale: Vec<u64> = match self.tunnels.open.lock() {
+            Ok(open) => open
+                .iter()
+                .filter(|(_, t)| !wanted.contains(&t.key))
+                .map(|(id, _)| *id)
+                .collect(),
+            Err(_) => return,
+        };
+        for id in stale {
+            tracing::info!("Closing tunnel {}: its host changed or was removed", id);
+            self.close_tunnel(id);
+        }
+    }
+
+    fn find_tunnel(&self, host: &str, remote_host: &str, remote_port: u16) -> Option<TunnelInfo> {
+        let open = self.tunnels.open.lock().ok()?;
+        open.iter()
+            .find(|(_, t)| t.forwards(host, remote_host, remote_port))
+            .map(|(id, t)| t.info(*id))
+    }
+}
+
+/// Accept loop of one tunnel
+struct TunnelTask {
+    pool: SshPool,
+    key: ConnectionKey,
+    remote_host: String,
+    remote_port: u16,
+    stats: Arc<TunnelStats>,
+    close: Arc<Notify>,
+}
+
+impl TunnelTask {
+    async fn run(self, id: u64, listener: TcpListener, idle_timeout: Duration) {
+        // Dropping the set closes the forwarded connections with the tunnel
+        let mut forwards = JoinSet::new();
+        let this = Arc::new(self);
+
+        let reason = loop {
+            tokio::select! {
+                _ = this.close.notified() => break "closed",
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, peer)) => {
+                        this.stats.total.fetch_add(1, Ordering::Relaxed);
+                        this.stats.active.fetch_add(1, Ordering::Relaxed);
+                        this.stats.touch();
+                        forwards.spawn(this.clone().forward(stream, peer));
+                    }
+                    Err(e) => {
+                        tracing::warn!("Tunnel {} failed to accept: {}", id, e);
+                    }
+                },
+                Some(_) = forwards.join_next() => {}
+                _ = tokio::time::sleep_until(this.stats.last_active() + idle_timeout) => {
+                    if this.stats.active.load(Ordering::Relaxed) == 0 {
+                        break "idle timeout";
+                    }
+                    this.stats.touch();
+                }
+            }
+        };
+
+        if let Ok(mut open) = this.pool.tunnels.open.lock() {
+            open.remove(&id);
+        }
+        tracing::info!("Tunnel {} closed ({})", id, reason);
+    }
+
+    /// Relay one accepted connection over a direct-tcpip channel
+    async fn forward(self: Arc<Self>, mut stream: TcpStream, peer: SocketAddr) {
+        let result = async {
+            let connection = self.pool.connection(&self.key).await?;
+            let channel = connection
+                .session
+                .channel_open_direct_tcpip(
+                    self.remote_host.clone(),
+                    self.remote_port as u32,
+                    peer.ip().to_string(),
+                    peer.port() as u32,
+                )
+                .await
+                .map_err(|e| {
+                    AppError::SshCommand(format!(
+                        "{}:{} refused the forward: {}",
+                        self.remote_host, self.remote_port, e
+                    ))
+                })?;
+
+            let mut remote = channel.into_stream();
+            let copied = tokio::io::copy_bidirectional(&mut stream, &mut remote).await;
+            connection.touch();
+            Ok::<_, AppError>(copied)
+        }
+        .await;
+
+        match result {
+            Ok(Ok((sent, received))) => {
+                self.stats.sent.fetch_add(sent, Ordering::Relaxed);
+                self.stats.received.fetch_add(received, Ordering::Relaxed);
+            }
+            Ok(Err(e)) => tracing::debug!("Tunnel connection from {} ended: {}", peer, e),
+            Err(e) => tracing::warn!("Tunnel connection from {} failed: {}", peer, e),
+        }
+        self.stats.active.fetch_sub(1, Ordering::Relaxed);
+        self.stats.touch();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::TestServer;
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// TCP server answering each connection with what it receives
+    async fn echo_server() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let (mut read, mut write) = stream.split();
+                    let _ = tokio::io::copy(&mut read, &mut write).await;
+                });
+            }
+        });
+        port
+    }
+
+    async fn round_trip(address: SocketAddr) -> Vec<u8> {
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        stream.write_all(b"ping").await.unwrap();
+        let mut reply = [0u8; 4];
+        stream.read_exact(&mut reply).await.unwrap();
+        reply.to_vec()
+    }
+
+    #[tokio::test]
+    async fn test_tunnel_open_reuse_and_close() {
+        let server = TestServer::start().await;
+        let pool = SshPool::new(&server.config("[tunnels]\nidle_timeout_secs = 1\nbind_address = \"127.0.0.1\"")).unwrap();
+        let echo = echo_server().await;
+
+        // Concurrent opens of the same forward end up with one tunnel
+        let (first, second) = tokio::join!(
+            pool.open_tunnel("loopback", "127.0.0.1", echo),
+            pool.open_tunnel("loopback", "127.0.0.1", echo)
+        );
+        let (first, second) = (first.unwrap(), second.unwrap());
+        assert_eq!(first.id, second.id);
+        assert_eq!(first.local_address, second.local_address);
+        assert_eq!(pool.tunnels().len(), 1);
+
+        assert_eq!(round_trip(first.local_address).await, b"ping");
+        let reused = pool.open_tunnel("loopback", "127.0.0.1", echo).await.unwrap();
+        assert_eq!(reused.id, first.id);
+        assert_eq!(server.state.connections(), 1);
+
+        // Closed after a second without connections
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+        assert!(pool.tunnels().is_empty());
+        assert!(TcpStream::connect(first.local_address).await.is_err());
+
+        // A config reload that drops the host closes its tunnels
+        let reopened = pool.open_tunnel("loopback", "127.0.0.1", echo).await.unwrap();
+        assert_ne!(reopened.id, first.id);
+        pool.close_stale_tunnels(&HashSet::new());
+        assert!(pool.tunnels().is_empty());
+    }
+}
@@ -0,0 +1,189 @@
+//! Fixture (replay) transport
+//!
+//! Serves canned outputs for known command lines, so the command modules can
+//! be exercised without live hosts. Fixtures are matched on the exact command
+//! line and, optionally, the target; commands without a fixture fail. Every
+//! command sent is recorded for assertions.
+//!
+//! Fixture files are TOML:
+//!
+//! ```toml
+//! [[fixture]]
+//! target = "proxmox"          # optional: "node:NAME", "profile:NAME",
+//!                             # "proxmox:NAME" or "proxmox" (any endpoint)
+//! command = "pct list"
+//! stdout = """
+//! VMID       Status     Lock         Name
+//! 205        running                 LXC-HOME
+//! """
+//! exit_code = 0               # optional
+//! ```
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Mutex;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+use super::{CommandTransport, Target};
+use crate::error::AppError;
+use crate::ssh::{CommandOutput, CommandStream, OutputChunk};
+
+/// One canned command output
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Fixture {
+    /// Only match this target (`node:NAME`, `profile:NAME`, `proxmox:NAME`,
+    /// or `proxmox` for every endpoint)
+    #[serde(default)]
+    pub target: Option<String>,
+    pub command: String,
+    #[serde(default)]
+    pub stdout: String,
+    #[serde(default)]
+    pub stderr: String,
+    #[serde(default)]
+    pub exit_code: u32,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct FixtureFile {
+    #[serde(default)]
+    fixture: Vec<Fixture>,
+}
+
+/// Replays canned command outputs
+#[derive(Debug, Default)]
+pub struct FixtureTransport {
+    fixtures: Vec<Fixture>,
+    calls: Mutex<Vec<String>>,
+}
+
+impl FixtureTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse fixtures from TOML
+    pub fn from_toml(content: &str) -> Result<Self, AppError> {
+        let file: FixtureFile = toml::from_str(content)
+            .map_err(|e| AppError::Config(format!("Invalid fixture file: {}", e)))?;
+        Ok(Self {
+            fixtures: file.fixture,
+            calls: Mutex::default(),
+        })
+    }
+
+    /// Load fixtures from a TOML file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, AppError> {
+        Self::from_toml(&std::fs::read_to_string(path)?)
+    }
+
+    /// Answer `command` on any target with this stdout and exit code 0
+    pub fn on(self, command: impl Into<String>, stdout: impl Into<String>) -> Self {
+        self.with(Fixture {
+            target: None,
+            command: command.into(),
+            stdout: stdout.into(),
+            stderr: String::new(),
+            exit_code: 0,
+        })
+    }
+
+    /// Add a fixture. Later fixtures take precedence over earlier ones.
+    pub fn with(mut self, fixture: Fixture) -> Self {
+        self.fixtures.push(fixture);
+        self
+    }
+
+    /// Commands sent so far, as `target: command`
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().map(|calls| calls.clone()).unwrap_or_default()
+    }
+
+    fn lookup(&self, target: Target<'_>, command: &str) -> Result<CommandOutput, AppError> {
+        let target = target.to_string();
+        if let Ok(mut calls) = self.calls.lock() {
+            calls.push(format!("{}: {}", target, command));
+        }
+
+        self.fixtures
+            .iter()
+            .rev()
+            .find(|f| f.command == command && f.target.as_deref().is_none_or(|t| target_matches(t, &target)))
+            .map(|f| CommandOutput {
+                stdout: f.stdout.clone(),
+                stderr: f.stderr.clone(),
+                exit_code: f.exit_code,
+            })
+            .ok_or_else(|| AppError::SshCommand(format!("No fixture for {}: {}", target, command)))
+    }
+}
+
+fn target_matches(fixture: &str, target: &str) -> bool {
+    fixture == target || (fixture == "proxmox" && target.starts_with("proxmox:"))
+}
+
+#[async_trait]
+impl CommandTransport for FixtureTransport {
+    async fn execute(&self, target: Target<'_>, command: &str) -> Result<CommandOutput, AppError> {
+        self.lookup(target, command)
+    }
+
+    async fn execute_streaming(&self, target: Target<'_>, command: &str) -> Result<CommandStream, AppError> {
+        let output = self.lookup(target, command)?;
+
+        let (tx, rx) = mpsc::channel(3);
+        if !output.stdout.is_empty() {
+            let _ = tx.try_send(OutputChunk::Stdout(output.stdout));
+        }
+        if !output.stderr.is_empty() {
+            let _ = tx.try_send(OutputChunk::Stderr(output.stderr));
+        }
+        let _ = tx.try_send(OutputChunk::Exit(output.exit_code));
+        Ok(ReceiverStream::new(rx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fixture_matching() {
+        let transport = FixtureTransport::from_toml(
+            r#"
+[[fixture]]
+command = "uptime"
+stdout = "up 3 days\n"
+
+[[fixture]]
+target = "node:LXC_HOME"
+command = "uptime"
+stdout = "up 1 day\n"
+
+[[fixture]]
+command = "false"
+exit_code = 1
+"#,
+        )
+        .unwrap();
+
+        let output = transport.execute(Target::Node("LXC_HOME"), "uptime").await.unwrap();
+        assert_eq!(output.stdout, "up 1 day\n");
+        let output = transport.execute(Target::Proxmox("home"), "uptime").await.unwrap();
+        assert_eq!(output.stdout, "up 3 days\n");
+        assert_eq!(transport.execute(Target::Proxmox("home"), "false").await.unwrap().exit_code, 1);
+        assert!(transport.execute(Target::Proxmox("home"), "reboot").await.is_err());
+
+        assert_eq!(
+            transport.calls(),
+            vec![
+                "node:LXC_HOME: uptime",
+                "proxmox:home: uptime",
+                "proxmox:home: false",
+                "proxmox:home: reboot",
+            ]
+        );
+    }
+}
@@ -0,0 +1,172 @@
+//! Local process transport
+//!
+//! Runs command lines with `sh -c` on the machine the panel runs on, so the
+//! panel can manage its own host without an SSH round trip. The target is
+//! ignored: every target is this machine.
+
+use async_trait::async_trait;
+use std::os::unix::process::ExitStatusExt;
+use std::process::{ExitStatus, Stdio};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Command;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+use super::{CommandTransport, Target};
+use crate::error::AppError;
+use crate::ssh::{CommandOutput, CommandStream, LineBuffer, OutputChunk, STREAM_BUFFER};
+
+/// Runs commands as local processes
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LocalTransport;
+
+impl LocalTransport {
+    pub fn new() -> Self {
+        Self
+    }
+
+    fn command(command: &str) -> Command {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg(command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        cmd
+    }
+
+    /// Run a command and wait for it to finish
+    pub async fn run(&self, command: &str) -> Result<CommandOutput, AppError> {
+        tracing::debug!("Running locally: {}", command);
+        let output = Self::command(command)
+            .output()
+            .await
+            .map_err(|e| AppError::SshCommand(format!("Failed to run local command: {}", e)))?;
+
+        Ok(CommandOutput {
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+            exit_code: exit_code(output.status),
+        })
+    }
+
+    /// Run a command, streaming its output. Dropping the stream kills the
+    /// process. Its stdin stays open until then, like an SSH channel's, so
+    /// commands can watch it to stop their own children.
+    pub async fn run_streaming(&self, command: &str) -> Result<CommandStream, AppError> {
+        tracing::debug!("Streaming locally: {}", command);
+        let mut child = Self::command(command)
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|e| AppError::SshCommand(format!("Failed to run local command: {}", e)))?;
+        let stdin = child.stdin.take();
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+        tokio::spawn(async move {
+            let closed = tx.clone();
+            let run = async {
+                let (out, err) = tokio::join!(
+                    forward(stdout, &tx, OutputChunk::Stdout),
+                    forward(stderr, &tx, OutputChunk::Stderr),
+                );
+                if !(out && err) {
+                    return;
+                }
+                let code = match child.wait().await {
+                    Ok(status) => exit_code(status),
+                    Err(_) => 255,
+                };
+                let _ = tx.send(OutputChunk::Exit(code)).await;
+            };
+
+            // The child is killed when this task ends early
+            tokio::select! {
+                _ = closed.closed() => {}
+                _ = run => {}
+            }
+            drop(stdin);
+        });
+
+        Ok(ReceiverStream::new(rx))
+    }
+}
+
+/// Send a pipe's output line by line, returning false if the reader is gone
+async fn forward<R>(
+    reader: Option<R>,
+    tx: &mpsc::Sender<OutputChunk>,
+    wrap: fn(String) -> OutputChunk,
+) -> bool
+where
+    R: AsyncRead + Unpin,
+{
+    let Some(mut reader) = reader else {
+        return true;
+    };
+    let mut lines = LineBuffer::default();
+    let mut buf = [0u8; 8192];
+
+    loop {
+        let n = match reader.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        if let Some(text) = lines.push(&buf[..n]) {
+            if tx.send(wrap(text)).await.is_err() {
+                return false;
+            }
+        }
+    }
+    match lines.finish() {
+        Some(rest) => tx.send(wrap(rest)).await.is_ok(),
+        None => true,
+    }
+}
+
+/// Exit code as a shell reports it (128 + signal for killed processes)
+fn exit_code(status: ExitStatus) -> u32 {
+    status
+        .code()
+        .or_else(|| status.signal().map(|signal| 128 + signal))
+        .unwrap_or(255) as u32
+}
+
+#[async_trait]
+impl CommandTransport for LocalTransport {
+    async fn execute(&self, _target: Target<'_>, command: &str) -> Result<CommandOutput, AppError> {
+        self.run(command).await
+    }
+
+    async fn execute_streaming(&self, _target: Target<'_>, command: &str) -> Result<CommandStream, AppError> {
+        self.run_streaming(command).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ssh::collect_output;
+
+    #[tokio::test]
+    async fn test_local_execute() {
+        let local = LocalTransport::new();
+        let output = local
+            .execute(Target::Proxmox("default"), "echo out; echo err >&2; exit 3")
+            .await
+            .unwrap();
+        assert_eq!(output.stdout, "out\n");
+        assert_eq!(output.stderr, "err\n");
+        assert_eq!(output.exit_code, 3);
+
+        let stream = local
+            .execute_streaming(Target::Node("local"), "printf 'a\\nb'")
+            .await
+            .unwrap();
+        let output = collect_output(stream).await;
+        assert_eq!(output.stdout, "a\nb");
+        assert!(output.success());
+    }
+}
@@ -0,0 +1,73 @@
+//! Command transports
+//!
+//! The docker, Proxmox and deploy commands are written against
+//! `CommandTransport` rather than a concrete connection type:
+//!
+//! - `SshPool` runs commands over SSH (and locally for hosts configured
+//!   with `transport = "local"`)
+//! - `LocalTransport` runs every command as a local process
+//! - `FixtureTransport` replays canned outputs, for tests and offline demos
+
+pub mod fixture;
+pub mod local;
+
+use async_trait::async_trait;
+use std::fmt;
+
+use crate::error::AppError;
+use crate::ssh::{CommandOutput, CommandStream};
+
+pub use fixture::FixtureTransport;
+pub use local::LocalTransport;
+
+/// Where a command runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Target<'a> {
+    /// A docker node, by name
+    Node(&'a str),
+    /// A profile, by name (or the docker node of the same name)
+    Profile(&'a str),
+    /// A Proxmox endpoint, by name
+    Proxmox(&'a str),
+}
+
+impl<'a> Target<'a> {
+    /// Name of the node, profile or endpoint
+    pub fn name(&self) -> &'a str {
+        match self {
+            Target::Node(name) | Target::Profile(name) | Target::Proxmox(name) => name,
+        }
+    }
+}
+
+impl fmt::Display for Target<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Node(name) => write!(f, "node:{}", name),
+            Target::Profile(name) => write!(f, "profile:{}", name),
+            Target::Proxmox(name) => write!(f, "proxmox:{}", name),
+        }
+    }
+}
+
+/// Runs shell command lines on a target
+#[async_trait]
+pub trait CommandTransport: Send + Sync {
+    /// Run a command and wait for it to finish
+    async fn execute(&self, target: Target<'_>, command: &str) -> Result<CommandOutput, AppError>;
+
+    /// Run an idempotent command. Transports that can lose a connection
+    /// mid-command may retry these.
+    async fn execute_read(&self, target: Target<'_>, command: &str) -> Result<CommandOutput, AppError> {
+        self.execute(target, command).await
+    }
+
+    /// Run a command, streaming its output as it arrives. Dropping the
+    /// stream stops the command.
+    async fn execute_streaming(&self, target: Target<'_>, command: &str) -> Result<CommandStream, AppError>;
+
+    /// Address the target was last reached at, if the transport has one
+    fn address(&self, _target: Target<'_>) -> Option<String> {
+        None
+    }
+}
@@ -0,0 +1,487 @@
+//! Configuration validation
+//!
+//! `Config::load` only checks that config.toml parses. `Config::validate`
+//! checks what the panel can't act on correctly: two hosts behind the same
+//! address or CTID, base profiles and profile files that don't exist in the
+//! dotfiles, docker nodes without a profile, key files that are missing,
+//! group members and tags selectors can't use, and login passwords that are
+//! unreadable or stored in plaintext.
+//! Each finding has a severity; errors are the ones that make the panel act
+//! on the wrong host or fail outright.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use crate::config::{expand_home, Config, ProxmoxConfig};
+use crate::credentials::{check_hash, SecretKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The panel would act on the wrong host or can't work as configured
+    Error,
+    /// Something will fail or fall back when it is used
+    Warning,
+    /// Worth a look, may be intended
+    Info,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        })
+    }
+}
+
+/// One problem found in the configuration
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Finding {
+    pub severity: Severity,
+    /// Config entry the finding is about, e.g. `profiles.LAPTOP_X13`
+    pub subject: String,
+    pub message: String,
+    /// What to change to fix it
+    pub hint: Option<String>,
+}
+
+impl Finding {
+    fn new(severity: Severity, subject: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            subject: subject.into(),
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:<7} {}: {}", self.severity, self.subject, self.message)?;
+        if let Some(ref hint) = self.hint {
+            write!(f, "\n        hint: {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+/// True if any finding is an error
+pub fn has_errors(findings: &[Finding]) -> bool {
+    findings.iter().any(|f| f.severity == Severity::Error)
+}
+
+impl Config {
+    /// Check the configuration for problems, most severe first
+    pub fn validate(&self) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        check_auth(self, &mut findings);
+        check_proxmox(self, &mut findings);
+        check_duplicates(self, &mut findings);
+        check_docker_nodes(self, &mut findings);
+        check_groups(self, &mut findings);
+        check_key_files(self, &mut findings);
+        check_dotfiles(self, &mut findings);
+        findings.sort_by_key(|f| f.severity);
+        findings
+    }
+}
+
+/// Names sharing one value, for values with more than one name
+fn shared<K: Ord>(entries: impl Iterator<Item = (K, String)>) -> Vec<(K, Vec<String>)> {
+    let mut groups: BTreeMap<K, Vec<String>> = BTreeMap::new();
+    for (key, name) in entries {
+        groups.entry(key).or_default().push(name);
+    }
+    groups.into_iter().filter(|(_, names)| names.len() > 1).collect()
+}
+
+fn check_auth(config: &Config, findings: &mut Vec<Finding>) {
+    let auth = &config.auth;
+    if let Some(ref hash) = auth.password_hash {
+        if let Err(e) = check_hash(hash) {
+            findings.push(
+                Finding::new(Severity::Error, "auth.password_hash", format!("not a usable hash: {}", e))
+                    .hint("Generate one with `control-panel-web --hash-password`"),
+            );
+        }
+    }
+
+    match auth.secret() {
+        Err(e) => findings.push(
+            Finding::new(Severity::Error, "auth", format!("{}; every login is refused", e))
+                .hint("Set password_hash, or password_file to a readable file"),
+        ),
+        Ok(secret) if SecretKind::of(&secret) == SecretKind::Plaintext => {
+            let subject = if auth.password_file.is_some() { "auth.password_file" } else { "auth.password" };
+            findings.push(
+                Finding::new(Severity::Warning, subject, "password is stored in plaintext")
+                    .hint("Replace it with an argon2 hash (`control-panel-web --hash-password`) in password_hash"),
+            );
+        }
+        Ok(_) => {}
+    }
+}
+
+fn check_duplicates(config: &Config, findings: &mut Vec<Finding>) {
+    // The same private IP can exist on the LAN behind another jump host
+    let profile_ips = config.profiles.iter().filter_map(|p| {
+        let ip = p.ip.as_deref()?.trim();
+        let network = config.get_profile_jump_host(p).unwrap_or_default();
+        (!ip.is_empty()).then(|| ((network.to_string(), ip.to_string()), p.name.clone()))
+    });
+    for ((network, ip), names) in shared(profile_ips) {
+        let via = if network.is_empty() { String::new() } else { format!(" (via {})", network) };
+        findings.push(
+            Finding::new(
+                Severity::Error,
+                format!("profiles.{}", names.join(", profiles.")),
+                format!("{} profiles use ip {}{}; deploys and commands for one reach the other", names.len(), ip, via),
+            )
+            .hint("Give each profile its own ip, or remove the profile of a host that no longer exists"),
+        );
+    }
+
+    // CTIDs are unique per Proxmox cluster
+    let cluster = |endpoint: Option<&ProxmoxConfig>| endpoint.map(|e| e.cluster_name().to_string()).unwrap_or_default();
+    let profile_ctids = config
+        .profiles
+        .iter()
+        .filter_map(|p| Some(((cluster(config.get_profile_proxmox(p)), p.ctid?), p.name.clone())));
+    for ((_, ctid), names) in shared(profile_ctids) {
+        findings.push(
+            Finding::new(
+                Severity::Error,
+                format!("profiles.{}", names.join(", profiles.")),
+                format!("{} profiles use CTID {}", names.len(), ctid),
+            )
+            .hint("Check `pct list` on the Proxmox host and fix the ctid, or bind the profiles to their own `proxmox` endpoint"),
+        );
+    }
+
+    let node_hosts = config.docker_nodes.iter().map(|n| {
+        let network = config.get_node_jump_host(n).unwrap_or_default().to_string();
+        ((network, n.host.trim().to_string()), n.name.clone())
+    });
+    for ((_, host), names) in shared(node_hosts) {
+        findings.push(Finding::new(
+            Severity::Error,
+            format!("docker_nodes.{}", names.join(", docker_nodes.")),
+            format!("{} docker nodes use host {}", names.len(), host),
+        ));
+    }
+
+    let node_ctids = config
+        .docker_nodes
+        .iter()
+        .map(|n| ((cluster(config.get_node_proxmox(n)), n.ctid), n.name.clone()));
+    for ((_, ctid), names) in shared(node_ctids) {
+        findings.push(Finding::new(
+            Severity::Error,
+            format!("docker_nodes.{}", names.join(", docker_nodes.")),
+            format!("{} docker nodes use CTID {}", names.len(), ctid),
+        ));
+    }
+}
+
+fn check_proxmox(config: &Config, findings: &mut Vec<Finding>) {
+    if config.proxmox.is_empty() {
+        findings.push(Finding::new(Severity::Error, "proxmox", "no Proxmox endpoint configured"));
+    }
+    for (name, hosts) in shared(config.proxmox.iter().map(|p| (p.name.clone(), p.host.clone()))) {
+        findings.push(
+            Finding::new(
+                Severity::Error,
+                format!("proxmox.{}", name),
+                format!("{} endpoints share this name ({})", hosts.len(), hosts.join(", ")),
+            )
+            .hint("Give each [[proxmox]] entry its own name"),
+        );
+    }
+
+    let bindings = config
+        .docker_nodes
+        .iter()
+        .map(|n| (format!("docker_nodes.{}", n.name), &n.proxmox))
+        .chain(config.profiles.iter().map(|p| (format!("profiles.{}", p.name), &p.proxmox)));
+    for (subject, name) in bindings {
+        if let Some(name) = name.as_deref().filter(|name| config.get_proxmox(name).is_none()) {
+            findings.push(
+                Finding::new(Severity::Error, subject, format!("proxmox = {:?} names no [[proxmox]] endpoint", name))
+                    .hint("pct exec, consoles and container controls need the endpoint hosting the container"),
+            );
+        }
+    }
+}
+
+fn check_docker_nodes(config: &Config, findings: &mut Vec<Finding>) {
+    for node in &config.docker_nodes {
+        let subject = format!("docker_nodes.{}", node.name);
+        let Some(profile) = config.get_profile(&node.name) else {
+            findings.push(
+                Finding::new(Severity::Warning, subject, "no profile with this name")
+                    .hint("Add a [[profiles]] entry so the host shows up in the infrastructure views and can be deployed"),
+            );
+            continue;
+        };
+
+        if profile.ip.as_deref().is_some_and(|ip| ip != node.host) {
+            findings.push(Finding::new(
+                Severity::Info,
+                subject.clone(),
+                format!(
+                    "host {} differs from the profile's ip {}",
+                    node.host,
+                    profile.ip.as_deref().unwrap_or_default()
+                ),
+            ));
+        }
+        if profile.ctid.is_some_and(|ctid| ctid != node.ctid) {
+            findings.push(Finding::new(
+                Severity::Warning,
+                subject,
+                format!(
+                    "ctid {} differs from the profile's ctid {}",
+                    node.ctid,
+                    profile.ctid.unwrap_or_default()
+                ),
+            ));
+        }
+    }
+}
+
+fn check_groups(config: &Config, findings: &mut Vec<Finding>) {
+    for (name, entries) in shared(config.groups.iter().map(|g| (g.name.clone(), g.name.clone()))) {
+        findings.push(Finding::new(
+            Severity::Error,
+            format!("groups.{}", name),
+            format!("{} groups share this name", entries.len()),
+        ));
+    }
+    for group in &config.groups {
+        let unknown: Vec<&str> = group
+            .members
+            .iter()
+            .filter(|m| config.get_profile(m).is_none() && config.get_docker_node(m).is_none())
+            .map(String::as_str)
+            .collect();
+        if !unknown.is_empty() {
+            findings.push(Finding::new(
+                Severity::Warning,
+                format!("groups.{}", group.name),
+                format!("members {} name no profile or docker node", unknown.join(", ")),
+            ));
+        }
+    }
+
+    // Selectors split on commas and read a leading ! or @ as an operator
+    let tags = config
+        .profiles
+        .iter()
+        .map(|p| (format!("profiles.{}", p.name), &p.tags))
+        .chain(config.docker_nodes.iter().map(|n| (format!("docker_nodes.{}", n.name), &n.tags)));
+    for (subject, tags) in tags {
+        for tag in tags.iter().filter(|t| t.trim().is_empty() || t.contains(',') || t.starts_with(['!', '@'])) {
+            findings.push(
+                Finding::new(Severity::Warning, subject.clone(), format!("tag {:?} can't be used in a selector", tag))
+                    .hint("Tags can't be empty, contain commas or start with ! or @"),
+            );
+        }
+    }
+}
+
+fn check_key_files(config: &Config, findings: &mut Vec<Finding>) {
+    if !expand_home(&config.ssh.private_key_path).exists() {
+        findings.push(
+            Finding::new(
+                Severity::Warning,
+                "ssh.private_key_path",
+                format!("{} does not exist; only SSH agent keys will be tried", config.ssh.private_key_path),
+            )
+            .hint("Point private_key_path at an existing key or load the key into ssh-agent"),
+        );
+    }
+
+    if let Some(ref file) = config.ssh.passphrase_file {
+        if !expand_home(file).exists() {
+            findings.push(Finding::new(
+                Severity::Warning,
+                "ssh.passphrase_file",
+                format!("{} does not exist; encrypted keys can't be decrypted", file),
+            ));
+        }
+    }
+
+    let identity_files = config
+        .proxmox
+        .iter()
+        .map(|p| (format!("proxmox.{}", p.name), &p.identity_file))
+        .chain(config.docker_nodes.iter().map(|n| (format!("docker_nodes.{}", n.name), &n.identity_file)))
+        .chain(config.profiles.iter().map(|p| (format!("profiles.{}", p.name), &p.identity_file)));
+    for (subject, file) in identity_files {
+        if let Some(file) = file.as_deref().filter(|f| !expand_home(f).exists()) {
+            findings.push(Finding::new(
+                Severity::Warning,
+                subject,
+                format!("identity_file {} does not exist; only SSH agent keys will be tried", file),
+            ));
+        }
+    }
+}
+
+fn check_dotfiles(config: &Config, findings: &mut Vec<Finding>) {
+    let dotfiles = expand_home(&config.dotfiles.path);
+    let profiles_dir = dotfiles.join("profiles");
+    if !profiles_dir.is_dir() {
+        findings.push(
+            Finding::new(
+                Severity::Error,
+                "dotfiles.path",
+                format!("{} has no profiles directory", dotfiles.display()),
+            )
+            .hint("Set dotfiles.path to the dotfiles checkout; profile files can't be checked until then"),
+        );
+        return;
+    }
+
+    for profile in &config.profiles {
+        let subject = format!("profiles.{}", profile.name);
+
+        if let Some(ref base) = profile.base_profile {
+            if !base_profile_exists(&profiles_dir, base) {
+                findings.push(
+                    Finding::new(
+                        Severity::Warning,
+                        subject.clone(),
+                        format!("base_profile {} matches no file in profiles/", base),
+                    )
+                    .hint(format!("Expected profiles/{0}.nix or profiles/{0}-config.nix", base)),
+                );
+            }
+        }
+
+        let config_file = profile
+            .config_file
+            .clone()
+            .unwrap_or_else(|| format!("profiles/{}-config.nix", profile.name));
+        if !dotfiles.join(&config_file).is_file() {
+            findings.push(
+                Finding::new(Severity::Warning, subject, format!("{} does not exist; the profile can't be deployed", config_file))
+                    .hint("Remove the profile if the host was migrated or retired, or set config_file"),
+            );
+        }
+    }
+}
+
+fn base_profile_exists(profiles_dir: &Path, base: &str) -> bool {
+    [format!("{}.nix", base), format!("{}-config.nix", base)]
+        .iter()
+        .any(|name| profiles_dir.join(name).is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::minimal_toml;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_validate() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        std::fs::create_dir_all(dir.join("profiles")).unwrap();
+        for file in ["LXC-base-config.nix", "LAPTOP_X13-config.nix", "LXC_HOME-config.nix"] {
+            std::fs::write(dir.join("profiles").join(file), "{ }").unwrap();
+        }
+        std::fs::write(dir.join("id_test"), "key").unwrap();
+
+        let config: Config = toml::from_str(&minimal_toml(
+            dir,
+            &format!(
+                r#"
+[[groups]]
+name = "Homelab"
+members = ["LXC_HOME", "LXC_gone"]
+
+[[docker_nodes]]
+name = "LXC_HOME"
+host = "192.168.8.80"
+ctid = 100
+
+[[docker_nodes]]
+name = "LXC_plane"
+host = "192.168.8.86"
+ctid = 186
+
+[[profiles]]
+name = "LAPTOP_L15"
+type = "laptop"
+hostname = "nixolaptopaku"
+ip = "192.168.8.92"
+tags = ["laptop", "!critical"]
+
+[[profiles]]
+name = "LAPTOP_X13"
+type = "laptop"
+hostname = "nixosx13aku"
+ip = "192.168.8.92"
+identity_file = "{dir}/id_missing"
+
+[[profiles]]
+name = "LXC_HOME"
+type = "lxc"
+hostname = "nixosLabaku"
+ip = "192.168.8.80"
+ctid = 100
+base_profile = "LXC-base-config"
+
+[[profiles]]
+name = "KOMI_LXC_proxy"
+type = "lxc"
+hostname = "proxy"
+ip = "192.168.8.92"
+ctid = 100
+base_profile = "KOMI-base"
+config_file = "profiles/LXC_HOME-config.nix"
+jump_host = "LXC_tailscale"
+"#,
+                dir = dir.display()
+            ),
+        ))
+        .unwrap();
+
+        let findings = config.validate();
+
+        let found: Vec<(Severity, &str)> = findings
+            .iter()
+            .map(|f| (f.severity, f.subject.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                // KOMI_LXC_proxy's .92 is on another LAN
+                (Severity::Error, "profiles.LAPTOP_L15, profiles.LAPTOP_X13"),
+                (Severity::Error, "profiles.LXC_HOME, profiles.KOMI_LXC_proxy"),
+                (Severity::Warning, "auth.password"),
+                (Severity::Warning, "docker_nodes.LXC_plane"),
+                (Severity::Warning, "groups.Homelab"),
+                (Severity::Warning, "profiles.LAPTOP_L15"),
+                (Severity::Warning, "profiles.LAPTOP_X13"),
+                (Severity::Warning, "profiles.LAPTOP_L15"),
+                (Severity::Warning, "profiles.KOMI_LXC_proxy"),
+            ]
+        );
+        assert!(has_errors(&findings));
+        assert!(findings[4].message.contains("LXC_gone"));
+        assert!(findings[8].message.contains("KOMI-base"));
+    }
+}
@@ -1,11 +1,9 @@
 //! Main application state and logic
 
-use control_panel_core::{Config, SshPool};
+use control_panel_core::{Config, ConfigLayers, OutputChunk, SharedConfig, SshPool};
 use egui::{Context, Ui};
-use std::sync::Arc;
 use tokio::runtime::Runtime;
 use tokio::sync::mpsc;
-use tokio::sync::RwLock;
 
 // =============================================================================
 // Async Command System
@@ -21,14 +19,27 @@ pub enum AsyncCommand {
     StartContainer { node: String, container: String },
     RestartContainer { node: String, container: String },
     FetchLogs { node: String, container: String },
+    InspectContainer { node: String, container: String },
+    PullContainer { node: String, container: String },
+    RebuildStack { node: String, project: String },
+    PruneNodes { nodes: Vec<String>, kind: control_panel_core::PruneKind },
+    FindProblems { nodes: Vec<String> },
+    /// Follow logs until `stopped` changes or its sender is dropped
+    FollowLogs {
+        id: u64,
+        node: String,
+        target: crate::ui::logs::LogTarget,
+        options: control_panel_core::FollowOptions,
+        stopped: tokio::sync::watch::Receiver<bool>,
+    },
 
     // Proxmox commands
     RefreshProxmox,
-    ProxmoxStart { ctid: u32 },
-    ProxmoxStop { ctid: u32 },
-    ProxmoxRestart { ctid: u32 },
+    ProxmoxStart { endpoint: String, ctid: u32 },
+    ProxmoxStop { endpoint: String, ctid: u32 },
+    ProxmoxRestart { endpoint: String, ctid: u32 },
     RefreshBackupJobs,
-    RunBackupJob { job_id: String },
+    RunBackupJob { endpoint: String, job_id: String },
 
     // Infrastructure commands
     GitPull,
@@ -36,6 +47,29 @@ pub enum AsyncCommand {
     GitCommit { message: String, files: Vec<String> },
     DeployDryRun { profile: String },
     Deploy { profile: String },
+    /// Deploy one profile after the other, stopping at the first failure
+    DeployMany { profiles: Vec<String> },
+    BatchHealth { select: String },
+    RunCommand { select: String, command: String },
+
+    // File commands
+    ListDir { host: String, path: String },
+    OpenFile { host: String, path: String },
+    SaveFile { host: String, path: String, content: String },
+    DownloadFile { host: String, path: String },
+    UploadFile { host: String, local: std::path::PathBuf, remote: String },
+
+    // Inventory commands
+    SaveEntry {
+        section: control_panel_core::config_edit::Section,
+        original: Option<String>,
+        values: std::collections::HashMap<String, String>,
+    },
+    DeleteEntry { section: control_panel_core::config_edit::Section, name: String },
+
+    // SSH host key commands
+    RefreshHostKeys,
+    AcceptHostKey { host: String, port: u16, fingerprint: String },
 }
 
 /// Results from async operations
@@ -52,6 +86,11 @@ pub enum AsyncResult {
         container: String,
         logs: String,
     },
+    ContainerDetails {
+        node: String,
+        container: String,
+        details: Result<control_panel_core::ContainerDetails, String>,
+    },
     DockerOperationSuccess {
         node: String,
         container: String,
@@ -63,6 +102,12 @@ pub enum AsyncResult {
         operation: String,
         error: String,
     },
+    /// Live output of a pull/rebuild shown in the docker output view
+    DockerOutput { title: String, chunk: OutputChunk },
+    PruneDone(crate::ui::infra::BatchReport),
+    DockerProblems(Vec<control_panel_core::NodeProblems>),
+    LogLines { id: u64, lines: Vec<control_panel_core::LogLine> },
+    LogEnded { id: u64, status: String },
 
     // Proxmox results
     ProxmoxContainers(Vec<control_panel_core::ProxmoxContainer>),
@@ -78,6 +123,28 @@ pub enum AsyncResult {
     GitCommitSuccess { branch: String },
     GitOperationError { operation: String, error: String },
     DeploymentStatus(control_panel_core::DeploymentStatus),
+    DeployOutput { profile: String, chunk: OutputChunk },
+    BatchDone(crate::ui::infra::BatchReport),
+
+    // File results
+    DirListing {
+        host: String,
+        path: String,
+        entries: Vec<control_panel_core::FileEntry>,
+    },
+    FileContent { host: String, path: String, content: String },
+    FileSaved { path: String, backup: Option<String> },
+    FileTransferDone { message: String },
+    FileError { error: String },
+
+    // Inventory results
+    EntrySaved { message: String, warnings: usize },
+    EntryError { error: String },
+
+    // SSH host key results
+    PendingHostKeys(Vec<control_panel_core::PendingHostKey>),
+    HostKeyAccepted { host: String, port: u16 },
+    HostKeyError { error: String },
 }
 
 /// Channel sender for async commands - can be cloned for each panel
@@ -98,7 +165,9 @@ pub enum Panel {
     Proxmox,
     Monitoring,
     Editor,
+    Files,
     Infrastructure,
+    Inventory,
 }
 
 impl Panel {
@@ -109,7 +178,9 @@ impl Panel {
             Panel::Proxmox => "Proxmox",
             Panel::Monitoring => "Monitoring",
             Panel::Editor => "Editor",
+            Panel::Files => "Files",
             Panel::Infrastructure => "Infrastructure",
+            Panel::Inventory => "Inventory",
         }
     }
 
@@ -120,15 +191,17 @@ impl Panel {
             Panel::Proxmox,
             Panel::Monitoring,
             Panel::Editor,
+            Panel::Files,
             Panel::Infrastructure,
+            Panel::Inventory,
         ]
     }
 }
 
 /// Main application state
 pub struct ControlPanelApp {
-    /// Application configuration
-    config: Arc<Config>,
+    /// Application configuration, swapped when config.toml is edited
+    config: SharedConfig,
 
     /// Active panel
     active_panel: Panel,
@@ -152,11 +225,14 @@ pub struct ControlPanelApp {
     proxmox_state: crate::ui::proxmox::ProxmoxPanelState,
     monitoring_state: crate::ui::monitoring::MonitoringPanelState,
     editor_state: crate::ui::editor::EditorPanelState,
+    files_state: crate::ui::files::FilesPanelState,
     infra_state: crate::ui::infra::InfraPanelState,
+    inventory_state: crate::ui::inventory::InventoryPanelState,
 }
 
 impl ControlPanelApp {
-    pub fn new(config: Config) -> Self {
+    /// The files of `layers` are watched for changes
+    pub fn new(config: Config, layers: ConfigLayers) -> Self {
         let runtime = Runtime::new().expect("Failed to create tokio runtime");
 
         // Check if Sway is available
@@ -178,10 +254,23 @@ impl ControlPanelApp {
         let (command_tx, command_rx) = mpsc::unbounded_channel::<AsyncCommand>();
         let (result_tx, result_rx) = mpsc::unbounded_channel::<AsyncResult>();
 
-        let config = Arc::new(config);
+        let config = SharedConfig::new(config);
+
+        // Container resource usage, sampled by the handler's collector, and
+        // container lists kept current by its docker events watcher
+        let stats = control_panel_core::StatsStore::new();
+        let inventory = control_panel_core::ContainerInventory::new();
 
         // Spawn the background async handler
-        spawn_async_handler(&runtime, config.clone(), command_rx, result_tx);
+        spawn_async_handler(
+            &runtime,
+            config.clone(),
+            layers,
+            stats.clone(),
+            inventory.clone(),
+            command_rx,
+            result_tx,
+        );
 
         Self {
             config,
@@ -191,11 +280,17 @@ impl ControlPanelApp {
             command_tx,
             result_rx,
             sway_state: Default::default(),
-            docker_state: Default::default(),
+            docker_state: crate::ui::docker::DockerPanelState {
+                stats,
+                inventory,
+                ..Default::default()
+            },
             proxmox_state: Default::default(),
             monitoring_state: Default::default(),
             editor_state: Default::default(),
+            files_state: Default::default(),
             infra_state: Default::default(),
+            inventory_state: Default::default(),
         }
     }
 
@@ -220,6 +315,11 @@ impl ControlPanelApp {
                         self.docker_state.logs = logs;
                     }
                 }
+                AsyncResult::ContainerDetails { node, container, details } => {
+                    if self.docker_state.details_for == Some((node, container)) {
+                        self.docker_state.details = Some(details);
+                    }
+                }
                 AsyncResult::DockerOperationSuccess {
                     node,
                     container,
@@ -240,6 +340,49 @@ impl ControlPanelApp {
                 } => {
                     tracing::error!("{} {} on {} failed: {}", operation, container, node, error);
                     self.docker_state.error = Some(format!("{} failed: {}", operation, error));
+                    // A rejected host key shows up in the infra panel
+                    let _ = self.command_tx.send(AsyncCommand::RefreshHostKeys);
+                }
+
+                AsyncResult::DockerOutput { title, chunk } => {
+                    if self.docker_state.output_title.as_deref() != Some(title.as_str()) {
+                        self.docker_state.output_title = Some(title);
+                        self.docker_state.output.clear();
+                    }
+                    match chunk {
+                        OutputChunk::Stdout(text) | OutputChunk::Stderr(text) => {
+                            self.docker_state.output.push_str(&text);
+                        }
+                        OutputChunk::Exit(code) => {
+                            self.docker_state
+                                .output
+                                .push_str(&format!("[exit {}]\n", code));
+                        }
+                    }
+                }
+
+                AsyncResult::PruneDone(report) => {
+                    self.docker_state.output = report.to_text();
+                    self.docker_state.output_title = Some(report.title);
+                    let _ = self.command_tx.send(AsyncCommand::RefreshDocker);
+                }
+                AsyncResult::LogLines { id, lines } => {
+                    if let Some(ref mut view) = self.docker_state.follow {
+                        if view.id == id {
+                            view.push(lines);
+                        }
+                    }
+                }
+                AsyncResult::LogEnded { id, status } => {
+                    if let Some(ref mut view) = self.docker_state.follow {
+                        if view.id == id {
+                            view.ended(status);
+                        }
+                    }
+                }
+                AsyncResult::DockerProblems(problems) => {
+                    self.docker_state.problems = Some(problems);
+                    self.docker_state.problems_loading = false;
                 }
 
                 // Proxmox results
@@ -279,7 +422,7 @@ impl ControlPanelApp {
                     self.infra_state.loading = false;
                     // Refresh git status
                     if let Ok(status) =
-                        control_panel_core::infra::git::get_status(&self.config.dotfiles.path)
+                        control_panel_core::infra::git::get_status(&self.config.load().dotfiles.path)
                     {
                         self.infra_state.git_status = Some(status);
                     }
@@ -289,7 +432,7 @@ impl ControlPanelApp {
                     self.infra_state.loading = false;
                     // Refresh git status
                     if let Ok(status) =
-                        control_panel_core::infra::git::get_status(&self.config.dotfiles.path)
+                        control_panel_core::infra::git::get_status(&self.config.load().dotfiles.path)
                     {
                         self.infra_state.git_status = Some(status);
                     }
@@ -300,7 +443,7 @@ impl ControlPanelApp {
                     self.infra_state.commit_message.clear();
                     // Refresh git status
                     if let Ok(status) =
-                        control_panel_core::infra::git::get_status(&self.config.dotfiles.path)
+                        control_panel_core::infra::git::get_status(&self.config.load().dotfiles.path)
                     {
                         self.infra_state.git_status = Some(status);
                     }
@@ -316,6 +459,90 @@ impl ControlPanelApp {
                         .insert(status.profile.clone(), status);
                     self.infra_state.loading = false;
                 }
+                AsyncResult::DeployOutput { profile, chunk } => {
+                    let log = self.infra_state.deploy_logs.entry(profile).or_default();
+                    match chunk {
+                        OutputChunk::Stdout(text) | OutputChunk::Stderr(text) => log.push_str(&text),
+                        OutputChunk::Exit(code) => log.push_str(&format!("[exit {}]\n", code)),
+                    }
+                }
+                AsyncResult::BatchDone(report) => {
+                    self.infra_state.loading = false;
+                    self.infra_state.batch_reports.insert(0, report);
+                }
+
+                // File results
+                AsyncResult::DirListing { host, path, entries } => {
+                    let state = &mut self.files_state;
+                    if state.host.as_deref() == Some(host.as_str()) {
+                        state.path_input = path.clone();
+                        state.path = path;
+                        state.entries = entries;
+                        state.loading = false;
+                    }
+                }
+                AsyncResult::FileContent { host, path, content } => {
+                    let state = &mut self.files_state;
+                    if state.host.as_deref() == Some(host.as_str()) {
+                        state.open_file = Some(crate::ui::files::OpenFile::new(path, content));
+                        state.loading = false;
+                    }
+                }
+                AsyncResult::FileSaved { path, backup } => {
+                    let state = &mut self.files_state;
+                    state.loading = false;
+                    if let Some(file) = state.open_file.as_mut().filter(|f| f.path == path) {
+                        file.original = file.content.clone();
+                    }
+                    state.success = Some(match backup {
+                        Some(backup) => format!("Saved {} (previous version in {})", path, backup),
+                        None => format!("Saved {}", path),
+                    });
+                }
+                AsyncResult::FileTransferDone { message } => {
+                    let state = &mut self.files_state;
+                    state.loading = false;
+                    state.success = Some(message);
+                    if let Some(host) = state.host.clone() {
+                        let _ = self.command_tx.send(AsyncCommand::ListDir {
+                            host,
+                            path: state.path.clone(),
+                        });
+                    }
+                }
+                AsyncResult::FileError { error } => {
+                    self.files_state.loading = false;
+                    self.files_state.error = Some(error);
+                }
+
+                // Inventory results
+                AsyncResult::EntrySaved { message, warnings } => {
+                    let state = &mut self.inventory_state;
+                    state.saving = false;
+                    state.editing = false;
+                    state.error = None;
+                    state.message = Some(match warnings {
+                        0 => message,
+                        n => format!("{} ({} warnings, see --check-config)", message, n),
+                    });
+                }
+                AsyncResult::EntryError { error } => {
+                    self.inventory_state.saving = false;
+                    self.inventory_state.message = None;
+                    self.inventory_state.error = Some(error);
+                }
+
+                // SSH host key results
+                AsyncResult::PendingHostKeys(keys) => {
+                    self.infra_state.pending_host_keys = keys;
+                }
+                AsyncResult::HostKeyAccepted { host, port } => {
+                    tracing::info!("Host key for {}:{} accepted", host, port);
+                    let _ = self.command_tx.send(AsyncCommand::RefreshHostKeys);
+                }
+                AsyncResult::HostKeyError { error } => {
+                    self.infra_state.error = Some(error);
+                }
             }
         }
     }
@@ -355,6 +582,7 @@ impl ControlPanelApp {
 
     /// Render the active panel content
     fn render_panel(&mut self, ctx: &Context, ui: &mut Ui) {
+        let config = self.config.load();
         match self.active_panel {
             Panel::Sway => {
                 crate::ui::sway::render(ctx, ui, &mut self.sway_state);
@@ -364,7 +592,7 @@ impl ControlPanelApp {
                     ctx,
                     ui,
                     &mut self.docker_state,
-                    &self.config,
+                    &config,
                     &self.command_tx,
                 );
             }
@@ -373,7 +601,7 @@ impl ControlPanelApp {
                     ctx,
                     ui,
                     &mut self.proxmox_state,
-                    &self.config,
+                    &config,
                     &self.command_tx,
                 );
             }
@@ -382,7 +610,7 @@ impl ControlPanelApp {
                     ctx,
                     ui,
                     &mut self.monitoring_state,
-                    &self.config,
+                    &config,
                 );
             }
             Panel::Editor => {
@@ -390,7 +618,16 @@ impl ControlPanelApp {
                     ctx,
                     ui,
                     &mut self.editor_state,
-                    &self.config,
+                    &config,
+                );
+            }
+            Panel::Files => {
+                crate::ui::files::render(
+                    ctx,
+                    ui,
+                    &mut self.files_state,
+                    &config,
+                    &self.command_tx,
                 );
             }
             Panel::Infrastructure => {
@@ -398,7 +635,16 @@ impl ControlPanelApp {
                     ctx,
                     ui,
                     &mut self.infra_state,
-                    &self.config,
+                    &config,
+                    &self.command_tx,
+                );
+            }
+            Panel::Inventory => {
+                crate::ui::inventory::render(
+                    ctx,
+                    ui,
+                    &mut self.inventory_state,
+                    &config,
                     &self.command_tx,
                 );
             }
@@ -444,30 +690,64 @@ fn check_sway_available() -> bool {
 /// Spawn the background task handler for async operations
 fn spawn_async_handler(
     runtime: &Runtime,
-    config: Arc<Config>,
+    config: SharedConfig,
+    layers: ConfigLayers,
+    stats: control_panel_core::StatsStore,
+    inventory: control_panel_core::ContainerInventory,
     mut command_rx: mpsc::UnboundedReceiver<AsyncCommand>,
     result_tx: mpsc::UnboundedSender<AsyncResult>,
 ) {
-    let config = config.clone();
-
     runtime.spawn(async move {
         // Create SSH pool for remote operations
         let ssh_pool = match SshPool::new(&config) {
-            Ok(pool) => Arc::new(RwLock::new(pool)),
+            Ok(pool) => pool,
             Err(e) => {
                 tracing::error!("Failed to create SSH pool: {}. SSH operations will fail.", e);
                 // Create a dummy pool that will fail on first use
                 match SshPool::new(&config) {
-                    Ok(pool) => Arc::new(RwLock::new(pool)),
+                    Ok(pool) => pool,
                     Err(_) => return, // Give up if we can't create a pool at all
                 }
             }
         };
 
+        // Apply edits to config.toml without a restart (stops with the handler)
+        let _watcher = control_panel_core::reload::watch(layers.clone(), config.clone(), ssh_pool.clone())
+            .map_err(|e| tracing::warn!("Config hot reload disabled: {}", e))
+            .ok();
+
+        tokio::spawn(control_panel_core::docker::stats::run_collector(
+            stats,
+            ssh_pool.clone(),
+            config.clone(),
+        ));
+
+        tokio::spawn(control_panel_core::docker::events::run_watcher(
+            inventory.clone(),
+            ssh_pool.clone(),
+            config.clone(),
+        ));
+
+        // Push the lists the events change to the Docker panel
+        let changes_tx = result_tx.clone();
+        tokio::spawn(async move {
+            let mut changes = inventory.changes(None).into_inner();
+            while let Some(change) = changes.recv().await {
+                if let Some(containers) = inventory.containers(&change.node) {
+                    let _ = changes_tx.send(AsyncResult::DockerContainers {
+                        node: change.node,
+                        containers,
+                    });
+                }
+            }
+        });
+
         tracing::info!("Async handler started, waiting for commands...");
 
         while let Some(command) = command_rx.recv().await {
-            let config = config.clone();
+            let shared = config.clone();
+            let layers = layers.clone();
+            let config = config.load();
             let ssh_pool = ssh_pool.clone();
             let result_tx = result_tx.clone();
 
@@ -514,25 +794,53 @@ fn spawn_async_handler(
                     AsyncCommand::FetchLogs { node, container } => {
                         handle_fetch_logs(&node, &container, &ssh_pool, &result_tx).await;
                     }
+                    AsyncCommand::InspectContainer { node, container } => {
+                        let details = control_panel_core::docker::commands::inspect_container(&ssh_pool, &node, &container)
+                            .await
+                            .map_err(|e| e.to_string());
+                        let _ = result_tx.send(AsyncResult::ContainerDetails { node, container, details });
+                    }
+                    AsyncCommand::PullContainer { node, container } => {
+                        handle_pull_container(&node, &container, &ssh_pool, &result_tx).await;
+                    }
+                    AsyncCommand::RebuildStack { node, project } => {
+                        handle_rebuild_stack(&node, &project, &ssh_pool, &result_tx).await;
+                    }
+                    AsyncCommand::PruneNodes { nodes, kind } => {
+                        handle_prune_nodes(&nodes, kind, &ssh_pool, &result_tx).await;
+                    }
+                    AsyncCommand::FollowLogs { id, node, target, options, stopped } => {
+                        handle_follow_logs(id, &node, &target, &options, stopped, &ssh_pool, &result_tx).await;
+                    }
+                    AsyncCommand::FindProblems { nodes } => {
+                        let nodes: Vec<_> = config
+                            .docker_nodes
+                            .iter()
+                            .filter(|n| nodes.contains(&n.name))
+                            .cloned()
+                            .collect();
+                        let problems = control_panel_core::docker::problems::find_problems(&ssh_pool, &nodes).await;
+                        let _ = result_tx.send(AsyncResult::DockerProblems(problems));
+                    }
 
                     // Proxmox commands
                     AsyncCommand::RefreshProxmox => {
-                        handle_refresh_proxmox(&ssh_pool, &result_tx).await;
+                        handle_refresh_proxmox(&config, &ssh_pool, &result_tx).await;
                     }
-                    AsyncCommand::ProxmoxStart { ctid } => {
-                        handle_proxmox_operation(ctid, "Start", &ssh_pool, &result_tx).await;
+                    AsyncCommand::ProxmoxStart { endpoint, ctid } => {
+                        handle_proxmox_operation(&endpoint, ctid, "Start", &ssh_pool, &result_tx).await;
                     }
-                    AsyncCommand::ProxmoxStop { ctid } => {
-                        handle_proxmox_operation(ctid, "Stop", &ssh_pool, &result_tx).await;
+                    AsyncCommand::ProxmoxStop { endpoint, ctid } => {
+                        handle_proxmox_operation(&endpoint, ctid, "Stop", &ssh_pool, &result_tx).await;
                     }
-                    AsyncCommand::ProxmoxRestart { ctid } => {
-                        handle_proxmox_operation(ctid, "Restart", &ssh_pool, &result_tx).await;
+                    AsyncCommand::ProxmoxRestart { endpoint, ctid } => {
+                        handle_proxmox_operation(&endpoint, ctid, "Restart", &ssh_pool, &result_tx).await;
                     }
                     AsyncCommand::RefreshBackupJobs => {
-                        handle_refresh_backup_jobs(&ssh_pool, &result_tx).await;
+                        handle_refresh_backup_jobs(&config, &ssh_pool, &result_tx).await;
                     }
-                    AsyncCommand::RunBackupJob { job_id } => {
-                        handle_run_backup_job(&job_id, &ssh_pool, &result_tx).await;
+                    AsyncCommand::RunBackupJob { endpoint, job_id } => {
+                        handle_run_backup_job(&endpoint, &job_id, &ssh_pool, &result_tx).await;
                     }
 
                     // Infrastructure commands
@@ -551,6 +859,52 @@ fn spawn_async_handler(
                     AsyncCommand::Deploy { profile } => {
                         handle_deploy(&config, &profile, &ssh_pool, &result_tx).await;
                     }
+                    AsyncCommand::DeployMany { profiles } => {
+                        for profile in &profiles {
+                            if !handle_deploy(&config, profile, &ssh_pool, &result_tx).await {
+                                break;
+                            }
+                        }
+                    }
+                    AsyncCommand::BatchHealth { select } => {
+                        handle_batch_health(&config, &select, &ssh_pool, &result_tx).await;
+                    }
+                    AsyncCommand::RunCommand { select, command } => {
+                        handle_run_command(&config, &select, &command, &ssh_pool, &result_tx).await;
+                    }
+
+                    // File commands
+                    AsyncCommand::ListDir { host, path } => {
+                        handle_list_dir(&host, &path, &ssh_pool, &result_tx).await;
+                    }
+                    AsyncCommand::OpenFile { host, path } => {
+                        handle_open_file(&config, &host, &path, &ssh_pool, &result_tx).await;
+                    }
+                    AsyncCommand::SaveFile { host, path, content } => {
+                        handle_save_file(&config, &host, &path, &content, &ssh_pool, &result_tx).await;
+                    }
+                    AsyncCommand::DownloadFile { host, path } => {
+                        handle_download_file(&config, &host, &path, &ssh_pool, &result_tx).await;
+                    }
+                    AsyncCommand::UploadFile { host, local, remote } => {
+                        handle_upload_file(&config, &host, &local, &remote, &ssh_pool, &result_tx).await;
+                    }
+
+                    // Inventory commands
+                    AsyncCommand::SaveEntry { section, original, values } => {
+                        handle_save_entry(&layers, &shared, section, original.as_deref(), &values, &ssh_pool, &result_tx);
+                    }
+                    AsyncCommand::DeleteEntry { section, name } => {
+                        handle_delete_entry(&layers, &shared, section, &name, &ssh_pool, &result_tx);
+                    }
+
+                    // SSH host key commands
+                    AsyncCommand::RefreshHostKeys => {
+                        handle_refresh_host_keys(&ssh_pool, &result_tx).await;
+                    }
+                    AsyncCommand::AcceptHostKey { host, port, fingerprint } => {
+                        handle_accept_host_key(&host, port, &fingerprint, &ssh_pool, &result_tx).await;
+                    }
                 }
             });
         }
@@ -563,45 +917,45 @@ fn spawn_async_handler(
 
 async fn handle_refresh_docker(
     config: &Config,
-    ssh_pool: &Arc<RwLock<SshPool>>,
+    ssh_pool: &SshPool,
     result_tx: &mpsc::UnboundedSender<AsyncResult>,
 ) {
-    let mut summaries = Vec::new();
-
-    for node in &config.docker_nodes {
-        let mut pool = ssh_pool.write().await;
-        let summary =
-            control_panel_core::docker::commands::get_node_summary(&mut pool, &node.name, &node.host)
-                .await;
-        summaries.push(summary);
-    }
-
+    // Query every node at once; a slow node only delays its own results
+    let summaries = control_panel_core::docker::commands::get_all_node_summaries(ssh_pool, &config.docker_nodes).await;
     let _ = result_tx.send(AsyncResult::DockerNodeSummaries(summaries));
 
-    // Also fetch containers for each online node
-    for node in &config.docker_nodes {
-        let mut pool = ssh_pool.write().await;
-        match control_panel_core::docker::commands::list_containers(&mut pool, &node.name).await {
-            Ok(containers) => {
-                let _ = result_tx.send(AsyncResult::DockerContainers {
-                    node: node.name.clone(),
-                    containers,
-                });
-            }
-            Err(e) => {
-                tracing::warn!("Failed to list containers on {}: {}", node.name, e);
-            }
-        }
+    // Also fetch containers for each node, again concurrently
+    let tasks: Vec<_> = config
+        .docker_nodes
+        .iter()
+        .map(|node| {
+            let node = node.name.clone();
+            let ssh_pool = ssh_pool.clone();
+            let result_tx = result_tx.clone();
+            tokio::spawn(async move {
+                match control_panel_core::docker::commands::list_containers(&ssh_pool, &node).await {
+                    Ok(containers) => {
+                        let _ = result_tx.send(AsyncResult::DockerContainers { node, containers });
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to list containers on {}: {}", node, e);
+                    }
+                }
+            })
+        })
+        .collect();
+
+    for task in tasks {
+        let _ = task.await;
     }
 }
 
 async fn handle_refresh_docker_node(
     node: &str,
-    ssh_pool: &Arc<RwLock<SshPool>>,
+    ssh_pool: &SshPool,
     result_tx: &mpsc::UnboundedSender<AsyncResult>,
 ) {
-    let mut pool = ssh_pool.write().await;
-    match control_panel_core::docker::commands::list_containers(&mut pool, node).await {
+    match control_panel_core::docker::commands::list_containers(ssh_pool, node).await {
         Ok(containers) => {
             let _ = result_tx.send(AsyncResult::DockerContainers {
                 node: node.to_string(),
@@ -623,19 +977,18 @@ async fn handle_docker_operation(
     node: &str,
     container: &str,
     operation: &str,
-    ssh_pool: &Arc<RwLock<SshPool>>,
+    ssh_pool: &SshPool,
     result_tx: &mpsc::UnboundedSender<AsyncResult>,
 ) {
-    let mut pool = ssh_pool.write().await;
 
     let result = match operation {
-        "Stop" => control_panel_core::docker::commands::stop_container(&mut pool, node, container)
+        "Stop" => control_panel_core::docker::commands::stop_container(ssh_pool, node, container)
             .await,
         "Start" => {
-            control_panel_core::docker::commands::start_container(&mut pool, node, container).await
+            control_panel_core::docker::commands::start_container(ssh_pool, node, container).await
         }
         "Restart" => {
-            control_panel_core::docker::commands::restart_container(&mut pool, node, container)
+            control_panel_core::docker::commands::restart_container(ssh_pool, node, container)
                 .await
         }
         _ => return,
@@ -663,11 +1016,10 @@ async fn handle_docker_operation(
 async fn handle_fetch_logs(
     node: &str,
     container: &str,
-    ssh_pool: &Arc<RwLock<SshPool>>,
+    ssh_pool: &SshPool,
     result_tx: &mpsc::UnboundedSender<AsyncResult>,
 ) {
-    let mut pool = ssh_pool.write().await;
-    match control_panel_core::docker::commands::get_container_logs(&mut pool, node, container, 100)
+    match control_panel_core::docker::commands::get_container_logs(ssh_pool, node, container, 100)
         .await
     {
         Ok(logs) => {
@@ -687,52 +1039,155 @@ async fn handle_fetch_logs(
     }
 }
 
+/// Forward a command stream to the UI as `DockerOutput` chunks
+async fn forward_docker_output(
+    title: String,
+    stream: Result<control_panel_core::CommandStream, control_panel_core::AppError>,
+    result_tx: &mpsc::UnboundedSender<AsyncResult>,
+) {
+    let mut rx = match stream {
+        Ok(stream) => stream.into_inner(),
+        Err(e) => {
+            let _ = result_tx.send(AsyncResult::DockerOutput {
+                title,
+                chunk: OutputChunk::Stderr(format!("{}\n", e)),
+            });
+            return;
+        }
+    };
+
+    while let Some(chunk) = rx.recv().await {
+        let _ = result_tx.send(AsyncResult::DockerOutput {
+            title: title.clone(),
+            chunk,
+        });
+    }
+}
+
+/// Forward followed log lines until the follower ends or the view stops it.
+/// Returning drops the stream, which stops the remote `docker logs -f`.
+async fn handle_follow_logs(
+    id: u64,
+    node: &str,
+    target: &crate::ui::logs::LogTarget,
+    options: &control_panel_core::FollowOptions,
+    mut stopped: tokio::sync::watch::Receiver<bool>,
+    ssh_pool: &SshPool,
+    result_tx: &mpsc::UnboundedSender<AsyncResult>,
+) {
+    use control_panel_core::docker::logs;
+    use crate::ui::logs::LogTarget;
+
+    let stack = matches!(target, LogTarget::Stack(_));
+    let stream = match target {
+        LogTarget::Container(container) => logs::follow_container_logs(ssh_pool, node, container, options).await,
+        LogTarget::Stack(project) => logs::follow_stack_logs(ssh_pool, node, project, options).await,
+    };
+    let mut rx = match stream {
+        Ok(stream) => stream.into_inner(),
+        Err(e) => {
+            let _ = result_tx.send(AsyncResult::LogEnded { id, status: e.to_string() });
+            return;
+        }
+    };
+
+    loop {
+        tokio::select! {
+            chunk = rx.recv() => match chunk {
+                Some(OutputChunk::Exit(code)) => {
+                    let _ = result_tx.send(AsyncResult::LogEnded { id, status: format!("Ended (exit {})", code) });
+                    return;
+                }
+                Some(chunk) => {
+                    let lines = logs::chunk_lines(&chunk, stack).unwrap_or_default();
+                    let _ = result_tx.send(AsyncResult::LogLines { id, lines });
+                }
+                None => return,
+            },
+            _ = stopped.changed() => {
+                tracing::info!("Stopped following logs of {} on {}", target.name(), node);
+                return;
+            }
+        }
+    }
+}
+
+async fn handle_pull_container(
+    node: &str,
+    container: &str,
+    ssh_pool: &SshPool,
+    result_tx: &mpsc::UnboundedSender<AsyncResult>,
+) {
+    let stream = control_panel_core::docker::commands::pull_container_stream(ssh_pool, node, container).await;
+    forward_docker_output(format!("Pull {} on {}", container, node), stream, result_tx).await;
+}
+
+async fn handle_rebuild_stack(
+    node: &str,
+    project: &str,
+    ssh_pool: &SshPool,
+    result_tx: &mpsc::UnboundedSender<AsyncResult>,
+) {
+    let stream = control_panel_core::docker::commands::stack_rebuild_stream(ssh_pool, node, project).await;
+    forward_docker_output(format!("Rebuild stack {} on {}", project, node), stream, result_tx)
+        .await;
+}
+
+async fn handle_prune_nodes(
+    nodes: &[String],
+    kind: control_panel_core::PruneKind,
+    ssh_pool: &SshPool,
+    result_tx: &mpsc::UnboundedSender<AsyncResult>,
+) {
+    let names: Vec<&str> = nodes.iter().map(String::as_str).collect();
+    let results = control_panel_core::batch::prune(ssh_pool, &names, kind).await;
+    let title = format!("Prune {} on {} nodes", kind, nodes.len());
+    let _ = result_tx.send(AsyncResult::PruneDone(batch_report(title, results, |result| match result {
+        Ok(output) => (true, "OK", output),
+        Err(e) => (false, "ERROR", e.to_string()),
+    })));
+}
+
 // =============================================================================
 // Proxmox Handlers
 // =============================================================================
 
 async fn handle_refresh_proxmox(
-    ssh_pool: &Arc<RwLock<SshPool>>,
+    config: &Config,
+    ssh_pool: &SshPool,
     result_tx: &mpsc::UnboundedSender<AsyncResult>,
 ) {
-    let mut pool = ssh_pool.write().await;
-
-    match control_panel_core::infra::proxmox::list_containers(&mut pool).await {
-        Ok(containers) => {
-            let _ = result_tx.send(AsyncResult::ProxmoxContainers(containers));
-        }
-        Err(e) => {
-            let _ = result_tx.send(AsyncResult::ProxmoxOperationError {
-                ctid: 0,
-                operation: "Refresh".to_string(),
-                error: e.to_string(),
-            });
+    let mut containers = Vec::new();
+    for list in control_panel_core::infra::proxmox::list_all_containers(ssh_pool, &config.proxmox).await {
+        match list.containers {
+            Ok(listed) => containers.extend(listed),
+            Err(e) => {
+                let _ = result_tx.send(AsyncResult::ProxmoxOperationError {
+                    ctid: 0,
+                    operation: format!("Refresh {}", list.endpoint),
+                    error: e.to_string(),
+                });
+            }
         }
     }
+    let _ = result_tx.send(AsyncResult::ProxmoxContainers(containers));
 
     // Also fetch backup jobs
-    match control_panel_core::infra::proxmox::list_backup_jobs(&mut pool).await {
-        Ok(jobs) => {
-            let _ = result_tx.send(AsyncResult::BackupJobs(jobs));
-        }
-        Err(e) => {
-            tracing::warn!("Failed to list backup jobs: {}", e);
-        }
-    }
+    handle_refresh_backup_jobs(config, ssh_pool, result_tx).await;
 }
 
 async fn handle_proxmox_operation(
+    endpoint: &str,
     ctid: u32,
     operation: &str,
-    ssh_pool: &Arc<RwLock<SshPool>>,
+    ssh_pool: &SshPool,
     result_tx: &mpsc::UnboundedSender<AsyncResult>,
 ) {
-    let mut pool = ssh_pool.write().await;
 
     let result = match operation {
-        "Start" => control_panel_core::infra::proxmox::start_container(&mut pool, ctid).await,
-        "Stop" => control_panel_core::infra::proxmox::stop_container(&mut pool, ctid).await,
-        "Restart" => control_panel_core::infra::proxmox::restart_container(&mut pool, ctid).await,
+        "Start" => control_panel_core::infra::proxmox::start_container(ssh_pool, endpoint, ctid).await,
+        "Stop" => control_panel_core::infra::proxmox::stop_container(ssh_pool, endpoint, ctid).await,
+        "Restart" => control_panel_core::infra::proxmox::restart_container(ssh_pool, endpoint, ctid).await,
         _ => return,
     };
 
@@ -753,28 +1208,36 @@ async fn handle_proxmox_operation(
     }
 }
 
+/// Backup jobs of every cluster (jobs.cfg is shared by a cluster's nodes)
 async fn handle_refresh_backup_jobs(
-    ssh_pool: &Arc<RwLock<SshPool>>,
+    config: &Config,
+    ssh_pool: &SshPool,
     result_tx: &mpsc::UnboundedSender<AsyncResult>,
 ) {
-    let mut pool = ssh_pool.write().await;
-    match control_panel_core::infra::proxmox::list_backup_jobs(&mut pool).await {
-        Ok(jobs) => {
-            let _ = result_tx.send(AsyncResult::BackupJobs(jobs));
+    let mut clusters: Vec<&str> = Vec::new();
+    let mut jobs = Vec::new();
+    for endpoint in &config.proxmox {
+        if clusters.contains(&endpoint.cluster_name()) {
+            continue;
         }
-        Err(e) => {
-            tracing::warn!("Failed to list backup jobs: {}", e);
+        clusters.push(endpoint.cluster_name());
+        match control_panel_core::infra::proxmox::list_backup_jobs(ssh_pool, &endpoint.name).await {
+            Ok(listed) => jobs.extend(listed),
+            Err(e) => {
+                tracing::warn!("Failed to list backup jobs on {}: {}", endpoint.name, e);
+            }
         }
     }
+    let _ = result_tx.send(AsyncResult::BackupJobs(jobs));
 }
 
 async fn handle_run_backup_job(
+    endpoint: &str,
     job_id: &str,
-    ssh_pool: &Arc<RwLock<SshPool>>,
+    ssh_pool: &SshPool,
     result_tx: &mpsc::UnboundedSender<AsyncResult>,
 ) {
-    let mut pool = ssh_pool.write().await;
-    match control_panel_core::infra::proxmox::run_backup_job(&mut pool, job_id).await {
+    match control_panel_core::infra::proxmox::run_backup_job(ssh_pool, endpoint, job_id).await {
         Ok(_) => {
             let _ = result_tx.send(AsyncResult::BackupJobStarted {
                 job_id: job_id.to_string(),
@@ -867,12 +1330,11 @@ async fn handle_git_commit(
 async fn handle_deploy_dry_run(
     config: &Config,
     profile: &str,
-    ssh_pool: &Arc<RwLock<SshPool>>,
+    ssh_pool: &SshPool,
     result_tx: &mpsc::UnboundedSender<AsyncResult>,
 ) {
-    let mut pool = ssh_pool.write().await;
     match control_panel_core::infra::deploy::dry_run(
-        &mut pool,
+        ssh_pool,
         profile,
         profile,
         &config.dotfiles.path,
@@ -896,24 +1358,18 @@ async fn handle_deploy_dry_run(
     }
 }
 
+/// Returns whether the deploy succeeded
 async fn handle_deploy(
     config: &Config,
     profile: &str,
-    ssh_pool: &Arc<RwLock<SshPool>>,
+    ssh_pool: &SshPool,
     result_tx: &mpsc::UnboundedSender<AsyncResult>,
-) {
-    let mut pool = ssh_pool.write().await;
-    match control_panel_core::infra::deploy::deploy(
-        &mut pool,
-        profile,
-        profile,
-        &config.dotfiles.path,
-    )
-    .await
-    {
-        Ok(status) => {
-            let _ = result_tx.send(AsyncResult::DeploymentStatus(status));
-        }
+) -> bool {
+    let started_at = chrono::Utc::now();
+    let stream = control_panel_core::infra::deploy::deploy_stream(ssh_pool, profile, &config.dotfiles.path).await;
+
+    let mut rx = match stream {
+        Ok(stream) => stream.into_inner(),
         Err(e) => {
             let _ = result_tx.send(AsyncResult::DeploymentStatus(
                 control_panel_core::DeploymentStatus {
@@ -924,6 +1380,358 @@ async fn handle_deploy(
                     finished_at: None,
                 },
             ));
+            return false;
+        }
+    };
+
+    let _ = result_tx.send(AsyncResult::DeploymentStatus(
+        control_panel_core::DeploymentStatus {
+            profile: profile.to_string(),
+            status: control_panel_core::DeployState::Switching,
+            message: "Deploying...".to_string(),
+            started_at: Some(started_at),
+            finished_at: None,
+        },
+    ));
+
+    let mut exit_code = None;
+    while let Some(chunk) = rx.recv().await {
+        if let OutputChunk::Exit(code) = chunk {
+            exit_code = Some(code);
+        }
+        let _ = result_tx.send(AsyncResult::DeployOutput {
+            profile: profile.to_string(),
+            chunk,
+        });
+    }
+
+    let (status, message) = match exit_code {
+        Some(0) => (
+            control_panel_core::DeployState::Success,
+            "Deployment successful".to_string(),
+        ),
+        Some(code) => (
+            control_panel_core::DeployState::Failed,
+            format!("Deployment failed (exit {})", code),
+        ),
+        None => (
+            control_panel_core::DeployState::Failed,
+            "Deployment interrupted".to_string(),
+        ),
+    };
+
+    let _ = result_tx.send(AsyncResult::DeploymentStatus(
+        control_panel_core::DeploymentStatus {
+            profile: profile.to_string(),
+            status,
+            message,
+            started_at: Some(started_at),
+            finished_at: Some(chrono::Utc::now()),
+        },
+    ));
+    exit_code == Some(0)
+}
+
+// =============================================================================
+// Batch Handlers
+// =============================================================================
+
+/// Turn per-host results into a report
+fn batch_report<T>(
+    title: String,
+    results: Vec<control_panel_core::HostResult<T>>,
+    describe: impl Fn(Result<T, control_panel_core::AppError>) -> (bool, &'static str, String),
+) -> crate::ui::infra::BatchReport {
+    let lines = results
+        .into_iter()
+        .map(|r| {
+            let (ok, status, output) = describe(r.result);
+            crate::ui::infra::BatchLine { host: r.host, ok, status, output }
+        })
+        .collect();
+    crate::ui::infra::BatchReport { title, lines }
+}
+
+fn selector_error(result_tx: &mpsc::UnboundedSender<AsyncResult>, title: String, error: control_panel_core::AppError) {
+    let _ = result_tx.send(AsyncResult::BatchDone(crate::ui::infra::BatchReport {
+        title,
+        lines: vec![crate::ui::infra::BatchLine {
+            host: "selector".to_string(),
+            ok: false,
+            status: "ERROR",
+            output: error.to_string(),
+        }],
+    }));
+}
+
+async fn handle_batch_health(
+    config: &Config,
+    select: &str,
+    ssh_pool: &SshPool,
+    result_tx: &mpsc::UnboundedSender<AsyncResult>,
+) {
+    let selector = match control_panel_core::Selector::parse(select) {
+        Ok(selector) => selector,
+        Err(e) => return selector_error(result_tx, "Health check".to_string(), e),
+    };
+    let results = control_panel_core::batch::health(ssh_pool, &config.select_hosts(&selector)).await;
+    let title = if selector.is_all() {
+        "Health check on every host".to_string()
+    } else {
+        format!("Health check on {}", selector)
+    };
+    let _ = result_tx.send(AsyncResult::BatchDone(batch_report(title, results, |result| match result {
+        Ok(Some(version)) => (true, "OK", format!("NixOS {}", version)),
+        Ok(None) => (true, "NOT NIXOS", String::new()),
+        Err(e) => (false, "UNREACHABLE", e.to_string()),
+    })));
+}
+
+async fn handle_run_command(
+    config: &Config,
+    select: &str,
+    command: &str,
+    ssh_pool: &SshPool,
+    result_tx: &mpsc::UnboundedSender<AsyncResult>,
+) {
+    let selector = match control_panel_core::Selector::parse(select) {
+        Ok(selector) => selector,
+        Err(e) => return selector_error(result_tx, command.to_string(), e),
+    };
+    let results = control_panel_core::batch::run_command(ssh_pool, &config.select_hosts(&selector), command).await;
+    let title = format!("{} on {}", command, selector);
+    let _ = result_tx.send(AsyncResult::BatchDone(batch_report(title, results, |result| match result {
+        Ok(output) if output.success() => (true, "OK", output.combined()),
+        Ok(output) => (false, "FAIL", format!("[exit {}]\n{}", output.exit_code, output.combined())),
+        Err(e) => (false, "ERROR", e.to_string()),
+    })));
+}
+
+// =============================================================================
+// File Handlers
+// =============================================================================
+
+fn send_file_error(result_tx: &mpsc::UnboundedSender<AsyncResult>, error: control_panel_core::AppError) {
+    let _ = result_tx.send(AsyncResult::FileError {
+        error: error.to_string(),
+    });
+}
+
+async fn handle_list_dir(
+    host: &str,
+    path: &str,
+    ssh_pool: &SshPool,
+    result_tx: &mpsc::UnboundedSender<AsyncResult>,
+) {
+    let listing = async {
+        let sftp = ssh_pool.sftp(host).await?;
+        // An empty path means the login directory
+        let path = if path.is_empty() { sftp.home().await? } else { path.to_string() };
+        let entries = sftp.list(&path).await?;
+        Ok::<_, control_panel_core::AppError>((path, entries))
+    }
+    .await;
+
+    match listing {
+        Ok((path, entries)) => {
+            let _ = result_tx.send(AsyncResult::DirListing {
+                host: host.to_string(),
+                path,
+                entries,
+            });
+        }
+        Err(e) => send_file_error(result_tx, e),
+    }
+}
+
+async fn handle_open_file(
+    config: &Config,
+    host: &str,
+    path: &str,
+    ssh_pool: &SshPool,
+    result_tx: &mpsc::UnboundedSender<AsyncResult>,
+) {
+    let data = async {
+        let sftp = ssh_pool.sftp(host).await?;
+        sftp.read(path, config.files.max_edit_bytes).await
+    }
+    .await;
+
+    match data {
+        Ok(data) if control_panel_core::ssh::sftp::is_text(&data) => {
+            let _ = result_tx.send(AsyncResult::FileContent {
+                host: host.to_string(),
+                path: path.to_string(),
+                content: String::from_utf8_lossy(&data).to_string(),
+            });
+        }
+        Ok(_) => {
+            let _ = result_tx.send(AsyncResult::FileError {
+                error: format!("{} is a binary file; download it instead", path),
+            });
+        }
+        Err(e) => send_file_error(result_tx, e),
+    }
+}
+
+async fn handle_save_file(
+    config: &Config,
+    host: &str,
+    path: &str,
+    content: &str,
+    ssh_pool: &SshPool,
+    result_tx: &mpsc::UnboundedSender<AsyncResult>,
+) {
+    let result = async {
+        let sftp = ssh_pool.sftp(host).await?;
+        sftp.write(path, content.as_bytes(), config.files.backup, config.files.max_edit_bytes)
+            .await
+    }
+    .await;
+
+    match result {
+        Ok(backup) => {
+            let _ = result_tx.send(AsyncResult::FileSaved {
+                path: path.to_string(),
+                backup,
+            });
+        }
+        Err(e) => send_file_error(result_tx, e),
+    }
+}
+
+async fn handle_download_file(
+    config: &Config,
+    host: &str,
+    path: &str,
+    ssh_pool: &SshPool,
+    result_tx: &mpsc::UnboundedSender<AsyncResult>,
+) {
+    let name = path.rsplit('/').next().unwrap_or("download");
+    let downloads = control_panel_core::config::expand_home("~/Downloads");
+    let local = downloads.join(name);
+
+    let result = async {
+        tokio::fs::create_dir_all(&downloads).await?;
+        let sftp = ssh_pool.sftp(host).await?;
+        sftp.download(path, &local, config.files.max_transfer_bytes).await
+    }
+    .await;
+
+    match result {
+        Ok(bytes) => {
+            let _ = result_tx.send(AsyncResult::FileTransferDone {
+                message: format!("Downloaded {} to {} ({} bytes)", path, local.display(), bytes),
+            });
+        }
+        Err(e) => send_file_error(result_tx, e),
+    }
+}
+
+async fn handle_upload_file(
+    config: &Config,
+    host: &str,
+    local: &std::path::Path,
+    remote: &str,
+    ssh_pool: &SshPool,
+    result_tx: &mpsc::UnboundedSender<AsyncResult>,
+) {
+    let result = async {
+        let sftp = ssh_pool.sftp(host).await?;
+        sftp.upload(local, remote, config.files.max_transfer_bytes).await
+    }
+    .await;
+
+    match result {
+        Ok(bytes) => {
+            let _ = result_tx.send(AsyncResult::FileTransferDone {
+                message: format!("Uploaded {} to {} ({} bytes)", local.display(), remote, bytes),
+            });
+        }
+        Err(e) => send_file_error(result_tx, e),
+    }
+}
+
+// =============================================================================
+// Inventory Handlers
+// =============================================================================
+
+fn send_entry_result(
+    result_tx: &mpsc::UnboundedSender<AsyncResult>,
+    message: String,
+    result: Result<Vec<control_panel_core::Finding>, control_panel_core::AppError>,
+) {
+    let _ = result_tx.send(match result {
+        Ok(findings) => AsyncResult::EntrySaved {
+            message,
+            warnings: findings
+                .iter()
+                .filter(|f| f.severity == control_panel_core::Severity::Warning)
+                .count(),
+        },
+        Err(e) => AsyncResult::EntryError { error: e.to_string() },
+    });
+}
+
+fn handle_save_entry(
+    layers: &ConfigLayers,
+    shared: &SharedConfig,
+    section: control_panel_core::config_edit::Section,
+    original: Option<&str>,
+    values: &std::collections::HashMap<String, String>,
+    ssh_pool: &SshPool,
+    result_tx: &mpsc::UnboundedSender<AsyncResult>,
+) {
+    let result = control_panel_core::config_edit::form_to_entry(section, values).and_then(|entry| {
+        control_panel_core::config_edit::save_entry(layers, shared, ssh_pool, section, original, &entry)
+    });
+    let name = values.get("name").map(|n| n.trim()).unwrap_or_default();
+    send_entry_result(result_tx, format!("Saved {} {}", section.label(), name), result);
+}
+
+fn handle_delete_entry(
+    layers: &ConfigLayers,
+    shared: &SharedConfig,
+    section: control_panel_core::config_edit::Section,
+    name: &str,
+    ssh_pool: &SshPool,
+    result_tx: &mpsc::UnboundedSender<AsyncResult>,
+) {
+    let result = control_panel_core::config_edit::delete_entry(layers, shared, ssh_pool, section, name);
+    send_entry_result(result_tx, format!("Removed {} {}", section.label(), name), result);
+}
+
+// =============================================================================
+// SSH Host Key Handlers
+// =============================================================================
+
+async fn handle_refresh_host_keys(
+    ssh_pool: &SshPool,
+    result_tx: &mpsc::UnboundedSender<AsyncResult>,
+) {
+    let pending = ssh_pool.host_keys().pending();
+    let _ = result_tx.send(AsyncResult::PendingHostKeys(pending));
+}
+
+async fn handle_accept_host_key(
+    host: &str,
+    port: u16,
+    fingerprint: &str,
+    ssh_pool: &SshPool,
+    result_tx: &mpsc::UnboundedSender<AsyncResult>,
+) {
+    let host_keys = ssh_pool.host_keys().clone();
+    match host_keys.accept(host, port, fingerprint) {
+        Ok(_) => {
+            let _ = result_tx.send(AsyncResult::HostKeyAccepted {
+                host: host.to_string(),
+                port,
+            });
+        }
+        Err(e) => {
+            let _ = result_tx.send(AsyncResult::HostKeyError {
+                error: e.to_string(),
+            });
         }
     }
 }
@@ -7,7 +7,7 @@ mod theme;
 mod ui;
 
 use anyhow::Result;
-use std::path::PathBuf;
+use control_panel_core::ConfigLayers;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 fn main() -> Result<()> {
@@ -20,30 +20,37 @@ fn main() -> Result<()> {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    tracing::info!("Starting NixOS Control Panel");
+    let flag = |name: &str| std::env::args().skip(1).any(|arg| arg == name);
+
+    // System file, user file (CONFIG_PATH), CONTROL_PANEL_* overrides
+    let layers = ConfigLayers::standard();
 
-    // Determine config path
-    let config_path = std::env::var("CONFIG_PATH")
-        .map(PathBuf::from)
-        .unwrap_or_else(|_| {
-            // Try common locations
-            let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-            let dotfiles = format!("{}/.dotfiles", home);
-            PathBuf::from(format!("{}/apps/control-panel/config.toml", dotfiles))
-        });
+    if flag("--check-config") {
+        return check_config_file(&layers);
+    }
+    if flag("--print-config") {
+        let config = layers.load()?;
+        println!("# Sources: {}", config.sources.join(", "));
+        print!("{}", control_panel_core::layers::masked(&config));
+        return Ok(());
+    }
+
+    tracing::info!("Starting NixOS Control Panel");
 
     // Load configuration
-    let config = if config_path.exists() {
-        tracing::info!("Loading config from: {:?}", config_path);
-        let content = std::fs::read_to_string(&config_path)?;
-        toml::from_str(&content)?
-    } else {
-        tracing::warn!("Config not found at {:?}, using defaults", config_path);
-        control_panel_core::Config::default()
-    };
+    if layers.existing().is_empty() {
+        tracing::warn!("No config file found at {:?}, using defaults", layers.files);
+    }
+    let config = layers.load()?;
+    tracing::info!("Config loaded from: {}", config.sources.join(", "));
+    for finding in config.validate() {
+        if finding.severity != control_panel_core::Severity::Info {
+            tracing::warn!("Config {} {}: {}", finding.severity, finding.subject, finding.message);
+        }
+    }
 
     // Create application state
-    let app = app::ControlPanelApp::new(config);
+    let app = app::ControlPanelApp::new(config, layers);
 
     // Run the native GUI
     let native_options = eframe::NativeOptions {
@@ -69,3 +76,26 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+/// `--check-config`: print the configuration findings, fail on errors
+fn check_config_file(layers: &ConfigLayers) -> Result<()> {
+    let config = layers.load()?;
+    let findings = config.validate();
+
+    for finding in &findings {
+        println!("{}", finding);
+    }
+    let count = |severity| findings.iter().filter(|f| f.severity == severity).count();
+    println!(
+        "{}: {} errors, {} warnings, {} notes",
+        config.sources.join(", "),
+        count(control_panel_core::Severity::Error),
+        count(control_panel_core::Severity::Warning),
+        count(control_panel_core::Severity::Info)
+    );
+
+    if control_panel_core::validate::has_errors(&findings) {
+        std::process::exit(1);
+    }
+    Ok(())
+}
@@ -86,6 +86,7 @@ pub mod colors {
     pub const LXC: Color32 = Color32::from_rgb(34, 197, 94); // green-500
     pub const VM: Color32 = Color32::from_rgb(245, 158, 11); // amber-500
     pub const DARWIN: Color32 = Color32::from_rgb(139, 92, 246); // violet-500
+    pub const SERVER: Color32 = Color32::from_rgb(100, 116, 139); // slate-500
 
     // UI colors
     #[allow(dead_code)]
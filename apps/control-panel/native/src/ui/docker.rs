@@ -1,7 +1,8 @@
 //! Docker panel - Container management across LXC nodes
 
 use crate::app::{AsyncCommand, CommandSender};
-use control_panel_core::Config;
+use crate::ui::logs::{LogFollowView, LogTarget};
+use control_panel_core::{Config, NodeUsage, Usage};
 use egui::{Context, Ui};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -23,6 +24,30 @@ pub struct DockerPanelState {
     pub selected_container: Option<(String, String)>, // (node, container_name)
     /// Container logs
     pub logs: String,
+    /// Live log follow
+    pub follow: Option<crate::ui::logs::LogFollowView>,
+    /// Id of the latest follow
+    pub follow_id: u64,
+    /// Container shown in the details view
+    pub details_for: Option<(String, String)>, // (node, container_name)
+    /// `docker inspect` of that container (`None` while loading)
+    pub details: Option<Result<control_panel_core::ContainerDetails, String>>,
+    /// Title of the running/last pull or rebuild
+    pub output_title: Option<String>,
+    /// Live output of the running/last pull or rebuild
+    pub output: String,
+    /// Selector filtering the nodes (`tag:lxc,!critical`)
+    pub selector: String,
+    /// What the prune button removes
+    pub prune_kind: control_panel_core::PruneKind,
+    /// Container resource usage, filled by the stats collector
+    pub stats: control_panel_core::StatsStore,
+    /// Container lists kept current by `docker events`
+    pub inventory: control_panel_core::ContainerInventory,
+    /// Problems of the filtered nodes, once checked
+    pub problems: Option<Vec<control_panel_core::NodeProblems>>,
+    /// Waiting for a problems check
+    pub problems_loading: bool,
     /// Last refresh time
     #[allow(dead_code)]
     pub last_refresh: Option<std::time::Instant>,
@@ -59,6 +84,60 @@ pub fn render(
 
     ui.add_space(12.0);
 
+    // Node filter and prune of the filtered nodes
+    ui.horizontal(|ui| {
+        ui.label("Filter:");
+        ui.add(egui::TextEdit::singleline(&mut state.selector).hint_text("tag:lxc,!critical"));
+
+        egui::ComboBox::from_id_salt("prune_kind")
+            .selected_text(state.prune_kind.to_string())
+            .show_ui(ui, |ui| {
+                for kind in control_panel_core::PruneKind::all() {
+                    ui.selectable_value(&mut state.prune_kind, *kind, kind.to_string());
+                }
+            });
+    });
+    let nodes = match control_panel_core::Selector::parse(&state.selector) {
+        Ok(selector) => config.select_docker_nodes(&selector),
+        Err(e) => {
+            ui.colored_label(crate::theme::colors::OFFLINE, e.to_string());
+            Vec::new()
+        }
+    };
+    ui.horizontal(|ui| {
+        ui.colored_label(
+            crate::theme::colors::MUTED,
+            format!("{} of {} nodes", nodes.len(), config.docker_nodes.len()),
+        );
+        ui.add_enabled_ui(!nodes.is_empty(), |ui| {
+            if ui.button(format!("🧹 Prune {}", nodes.len())).clicked() {
+                tracing::info!("Prune {} on {}", state.prune_kind, state.selector);
+                state.output_title = Some(format!("Prune {}", state.prune_kind));
+                state.output = "Pruning...\n".to_string();
+                let _ = command_tx.send(AsyncCommand::PruneNodes {
+                    nodes: nodes.iter().map(|n| n.name.clone()).collect(),
+                    kind: state.prune_kind,
+                });
+            }
+            if ui.button("⚠ Problems").clicked() {
+                state.problems_loading = true;
+                let _ = command_tx.send(AsyncCommand::FindProblems {
+                    nodes: nodes.iter().map(|n| n.name.clone()).collect(),
+                });
+            }
+        });
+        if state.problems_loading {
+            ui.spinner();
+        }
+    });
+
+    if let Some(problems) = state.problems.clone() {
+        ui.add_space(8.0);
+        render_problems(ui, state, &problems);
+    }
+
+    ui.add_space(12.0);
+
     // Node selector
     ui.horizontal(|ui| {
         ui.label("Node:");
@@ -71,7 +150,7 @@ pub fn render(
             )
             .show_ui(ui, |ui| {
                 ui.selectable_value(&mut state.selected_node, None, "All Nodes");
-                for node in &config.docker_nodes {
+                for node in &nodes {
                     ui.selectable_value(
                         &mut state.selected_node,
                         Some(node.name.clone()),
@@ -91,7 +170,7 @@ pub fn render(
 
             if state.node_summaries.is_empty() {
                 // Show configured nodes with placeholder data
-                for node in &config.docker_nodes {
+                for node in &nodes {
                     ui.horizontal(|ui| {
                         ui.colored_label(crate::theme::colors::UNKNOWN, "○");
                         ui.label(&node.name);
@@ -104,6 +183,9 @@ pub fn render(
                 ui.label("Click Refresh to load container data");
             } else {
                 for summary in &state.node_summaries {
+                    let Some(node) = nodes.iter().find(|n| n.name == summary.name) else {
+                        continue;
+                    };
                     ui.horizontal(|ui| {
                         let status_color = if summary.online {
                             crate::theme::colors::ONLINE
@@ -114,15 +196,28 @@ pub fn render(
                         ui.colored_label(status_color, if summary.online { "●" } else { "○" });
                         ui.strong(&summary.name);
                         ui.label(format!("({})", summary.host));
+                        if let Some(ref address) = summary.address {
+                            if *address != summary.host {
+                                ui.colored_label(crate::theme::colors::MUTED, format!("via {}", address));
+                            }
+                        }
 
                         if summary.online {
                             ui.label(format!(
                                 "{} running / {} total",
                                 summary.running, summary.total
                             ));
+                            if summary.problems > 0 {
+                                ui.colored_label(
+                                    crate::theme::colors::WARNING,
+                                    format!("⚠ {} with problems", summary.problems),
+                                );
+                            }
                         } else {
                             ui.colored_label(crate::theme::colors::MUTED, "Offline");
                         }
+
+                        crate::ui::infra::render_labels(ui, &config.node_labels(node));
                     });
                 }
             }
@@ -135,18 +230,43 @@ pub fn render(
         let containers_clone = state.containers.get(node_name.as_str()).cloned();
 
         ui.group(|ui| {
-            ui.heading(format!("Containers on {}", node_name));
+            ui.horizontal(|ui| {
+                ui.heading(format!("Containers on {}", node_name));
+                match state.inventory.node(node_name) {
+                    Some(inventory) if inventory.live => {
+                        ui.colored_label(crate::theme::colors::ONLINE, "● live")
+                            .on_hover_text("Updated by docker events");
+                    }
+                    Some(inventory) => {
+                        ui.colored_label(crate::theme::colors::MUTED, "not live").on_hover_text(format!(
+                            "docker events stream down, Refresh to update: {}",
+                            inventory.error.unwrap_or_default()
+                        ));
+                    }
+                    None => {}
+                }
+            });
             ui.add_space(4.0);
 
+            let usage = config.stats.enabled.then(|| state.stats.node(node_name).unwrap_or_default());
             if let Some(containers) = containers_clone {
                 if containers.is_empty() {
                     ui.label("No containers found");
                 } else {
+                    if let Some(ref usage) = usage {
+                        render_node_usage(ui, usage, &containers);
+                    }
                     egui::ScrollArea::vertical()
                         .max_height(400.0)
                         .show(ui, |ui| {
                             for container in &containers {
                                 render_container_row(ui, state, &node_name, container, command_tx);
+                                if let Some(u) = usage.as_ref().and_then(|u| u.container(&container.name)) {
+                                    ui.horizontal(|ui| {
+                                        ui.add_space(16.0);
+                                        render_usage(ui, u);
+                                    });
+                                }
                             }
                         });
                 }
@@ -156,6 +276,81 @@ pub fn render(
         });
     }
 
+    // Container details section
+    if let Some((node, container)) = state.details_for.clone() {
+        ui.add_space(12.0);
+        ui.group(|ui| {
+            ui.horizontal(|ui| {
+                ui.heading(format!("Details: {} ({})", container, node));
+                if ui.button("🔄 Refresh").clicked() {
+                    state.details = None;
+                    let _ = command_tx.send(AsyncCommand::InspectContainer {
+                        node: node.clone(),
+                        container: container.clone(),
+                    });
+                }
+                if ui.button("✕ Close").clicked() {
+                    state.details_for = None;
+                    state.details = None;
+                }
+            });
+
+            ui.add_space(4.0);
+
+            match state.details {
+                None => {
+                    ui.spinner();
+                }
+                Some(Err(ref error)) => {
+                    ui.colored_label(crate::theme::colors::OFFLINE, format!("⚠️ {}", error));
+                }
+                Some(Ok(ref details)) => {
+                    egui::ScrollArea::vertical()
+                        .id_salt("docker_details")
+                        .max_height(300.0)
+                        .show(ui, |ui| render_details(ui, details));
+                }
+            }
+        });
+    }
+
+    // Live log follow section
+    if let Some(mut view) = state.follow.take() {
+        ui.add_space(12.0);
+        if crate::ui::logs::render(ui, &mut view, &mut state.follow_id, command_tx) {
+            state.follow = Some(view);
+        }
+    }
+
+    // Pull / rebuild output section
+    if let Some(title) = state.output_title.clone() {
+        ui.add_space(12.0);
+        ui.group(|ui| {
+            ui.horizontal(|ui| {
+                ui.heading(format!("Output: {}", title));
+                if ui.button("✕ Close").clicked() {
+                    state.output_title = None;
+                    state.output.clear();
+                }
+            });
+
+            ui.add_space(4.0);
+
+            egui::ScrollArea::vertical()
+                .id_salt("docker_output")
+                .max_height(200.0)
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    ui.add(
+                        egui::TextEdit::multiline(&mut state.output.as_str())
+                            .font(egui::TextStyle::Monospace)
+                            .desired_width(f32::INFINITY)
+                            .desired_rows(10),
+                    );
+                });
+        });
+    }
+
     // Container logs section
     if let Some((ref node, ref container)) = state.selected_container.clone() {
         ui.add_space(12.0);
@@ -191,6 +386,259 @@ pub fn render(
     }
 }
 
+/// Follow a container's or stack's logs, replacing (and stopping) the
+/// current follow
+fn start_follow(state: &mut DockerPanelState, node: &str, target: LogTarget, command_tx: &CommandSender) {
+    tracing::info!("Follow logs of {} on {}", target.name(), node);
+    state.follow_id += 1;
+    state.follow = Some(LogFollowView::start(state.follow_id, node, target, "", "200", command_tx));
+}
+
+/// Unhealthy, restart-looping and OOM-killed containers of the checked nodes
+fn render_problems(ui: &mut Ui, state: &mut DockerPanelState, problems: &[control_panel_core::NodeProblems]) {
+    use control_panel_core::ProblemKind;
+
+    ui.group(|ui| {
+        ui.horizontal(|ui| {
+            ui.heading("Container Problems");
+            if ui.button("✕ Close").clicked() {
+                state.problems = None;
+            }
+        });
+        ui.add_space(4.0);
+
+        if problems.iter().all(|n| n.problems.is_empty() && n.error.is_none()) {
+            ui.colored_label(
+                crate::theme::colors::ONLINE,
+                format!("No problems on {} nodes", problems.len()),
+            );
+            return;
+        }
+
+        egui::Grid::new("docker_problems")
+            .num_columns(4)
+            .spacing([12.0, 4.0])
+            .striped(true)
+            .show(ui, |ui| {
+                for node in problems {
+                    if let Some(ref error) = node.error {
+                        ui.strong(&node.node);
+                        ui.label("");
+                        ui.colored_label(crate::theme::colors::OFFLINE, "Unreachable");
+                        ui.colored_label(crate::theme::colors::MUTED, error);
+                        ui.end_row();
+                    }
+                    for problem in &node.problems {
+                        if ui.link(&node.node).clicked() {
+                            state.selected_node = Some(node.node.clone());
+                        }
+                        match problem.project {
+                            Some(ref project) => ui.label(format!("{} [{}]", problem.container, project)),
+                            None => ui.label(&problem.container),
+                        };
+                        let color = match problem.kind {
+                            ProblemKind::Unhealthy => crate::theme::colors::WARNING,
+                            ProblemKind::RestartLoop | ProblemKind::OomKilled => crate::theme::colors::OFFLINE,
+                        };
+                        ui.colored_label(color, problem.kind.display());
+                        ui.colored_label(crate::theme::colors::MUTED, &problem.detail);
+                        ui.end_row();
+                    }
+                }
+            });
+    });
+}
+
+fn health_color(health: control_panel_core::HealthStatus) -> egui::Color32 {
+    match health {
+        control_panel_core::HealthStatus::Healthy => crate::theme::colors::ONLINE,
+        control_panel_core::HealthStatus::Starting => crate::theme::colors::WARNING,
+        control_panel_core::HealthStatus::Unhealthy => crate::theme::colors::OFFLINE,
+    }
+}
+
+/// Render the `docker inspect` view of a container
+fn render_details(ui: &mut Ui, details: &control_panel_core::ContainerDetails) {
+    let time = |t: Option<chrono::DateTime<chrono::Utc>>| {
+        t.map(|t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string()).unwrap_or_else(|| "-".to_string())
+    };
+    let lines = |items: Vec<String>| if items.is_empty() { "-".to_string() } else { items.join("\n") };
+
+    let mut state_text = details.status.display().to_string();
+    if !details.status.is_running() {
+        state_text.push_str(&format!(" (exit code {})", details.exit_code));
+    }
+    if details.oom_killed {
+        state_text.push_str(", killed: out of memory");
+    }
+
+    egui::Grid::new("container_details")
+        .num_columns(2)
+        .spacing([12.0, 4.0])
+        .striped(true)
+        .show(ui, |ui| {
+            let mut row = |label: &str, value: String| {
+                ui.colored_label(crate::theme::colors::MUTED, label);
+                ui.monospace(value);
+                ui.end_row();
+            };
+
+            row("State", state_text);
+            if let Some(ref error) = details.error {
+                row("Error", error.clone());
+            }
+            row(
+                "Health",
+                match details.health {
+                    Some(ref health) => format!(
+                        "{} (failing streak {})\n{}",
+                        health.status.display(),
+                        health.failing_streak,
+                        lines(
+                            health
+                                .log
+                                .iter()
+                                .rev()
+                                .map(|p| format!("{} exit {}: {}", time(p.start), p.exit_code, p.output))
+                                .collect()
+                        )
+                    ),
+                    None => "no healthcheck".to_string(),
+                },
+            );
+            row("Image", details.image.clone());
+            row("Image ID", details.image_id.clone());
+            row("Digests", lines(details.repo_digests.clone()));
+            row("Created", time(details.created));
+            row("Started", time(details.started_at));
+            row("Finished", time(details.finished_at));
+            row("Restarts", format!("{} (policy {})", details.restart_count, details.restart_policy));
+            row("Command", details.command.join(" "));
+            row("Working dir", details.working_dir.clone().unwrap_or_else(|| "-".to_string()));
+            row("Ports", lines(details.ports.iter().map(|p| p.to_string()).collect()));
+            row(
+                "Mounts",
+                lines(
+                    details
+                        .mounts
+                        .iter()
+                        .map(|m| {
+                            format!(
+                                "{} {} -> {}{}",
+                                m.kind,
+                                m.name.as_deref().unwrap_or(&m.source),
+                                m.destination,
+                                if m.read_only { " (ro)" } else { "" }
+                            )
+                        })
+                        .collect(),
+                ),
+            );
+            row(
+                "Networks",
+                lines(
+                    details
+                        .networks
+                        .iter()
+                        .map(|n| format!("{} {} ({})", n.name, n.ip_address.as_deref().unwrap_or("-"), n.aliases.join(", ")))
+                        .collect(),
+                ),
+            );
+            row("Environment", lines(details.env.clone()));
+            row("Labels", lines(details.labels.iter().map(|(k, v)| format!("{}={}", k, v)).collect()));
+        });
+
+    ui.colored_label(crate::theme::colors::MUTED, "Environment values are not shown.");
+}
+
+/// Sample time, the container using the most memory and each stack's total
+fn render_node_usage(ui: &mut Ui, usage: &NodeUsage, containers: &[control_panel_core::Container]) {
+    ui.horizontal(|ui| {
+        if let Some(updated) = usage.updated {
+            ui.colored_label(
+                crate::theme::colors::MUTED,
+                format!("Usage sampled {}", updated.with_timezone(&chrono::Local).format("%H:%M:%S")),
+            );
+        }
+        if let Some((name, top)) = usage.top_memory() {
+            ui.colored_label(
+                crate::theme::colors::MUTED,
+                format!("· most memory: {} ({})", name, format_size(top.memory_bytes)),
+            );
+        }
+        if let Some(ref error) = usage.error {
+            ui.colored_label(crate::theme::colors::WARNING, format!("Last sample failed: {}", error));
+        }
+    });
+
+    let mut stacks: Vec<&str> = containers.iter().filter_map(|c| c.project.as_deref()).collect();
+    stacks.sort_unstable();
+    stacks.dedup();
+    for stack in stacks {
+        let names = containers
+            .iter()
+            .filter(|c| c.project.as_deref() == Some(stack))
+            .map(|c| c.name.as_str());
+        if let Some(total) = usage.total(names) {
+            ui.horizontal(|ui| {
+                ui.strong(format!("[{}]", stack));
+                ui.colored_label(crate::theme::colors::MUTED, format!("{} running", total.containers));
+                render_usage(ui, &total);
+            });
+        }
+    }
+    ui.add_space(4.0);
+}
+
+/// CPU, memory, network and block IO rates with their sparklines
+fn render_usage(ui: &mut Ui, usage: &Usage) {
+    ui.label(format!("CPU {:.1}%", usage.cpu_percent));
+    sparkline(ui, &usage.cpu_history, crate::theme::colors::ACCENT);
+    ui.label(format!("Mem {} ({:.1}%)", format_size(usage.memory_bytes), usage.memory_percent()));
+    sparkline(ui, &usage.memory_history, crate::theme::colors::DARWIN);
+    ui.label(format!("Net {}/s", format_size(usage.net_rate as u64)))
+        .on_hover_text(format!("↓ {}  ↑ {}", format_size(usage.net_rx), format_size(usage.net_tx)));
+    sparkline(ui, &usage.net_history, crate::theme::colors::ONLINE);
+    ui.label(format!("Block {}/s", format_size(usage.block_rate as u64)))
+        .on_hover_text(format!("read {}  written {}", format_size(usage.block_read), format_size(usage.block_write)));
+    sparkline(ui, &usage.block_history, crate::theme::colors::WARNING);
+}
+
+/// A small line of the history, scaled to its largest value
+fn sparkline(ui: &mut Ui, values: &[f64], color: egui::Color32) {
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(60.0, 14.0), egui::Sense::hover());
+    if values.len() < 2 {
+        return;
+    }
+    let max = values.iter().copied().fold(0.0, f64::max);
+    let step = rect.width() / (values.len() - 1) as f32;
+    let points = values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| {
+            let height = if max > 0.0 { (v / max) as f32 * (rect.height() - 1.0) } else { 0.0 };
+            egui::pos2(rect.left() + i as f32 * step, rect.bottom() - height)
+        })
+        .collect();
+    ui.painter().add(egui::Shape::line(points, egui::Stroke::new(1.5, color)));
+}
+
+/// Human readable size
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
 /// Render a single container row
 fn render_container_row(
     ui: &mut Ui,
@@ -212,6 +660,10 @@ fn render_container_row(
         // Container name
         ui.strong(&container.name);
 
+        if let Some(health) = container.health {
+            ui.colored_label(health_color(health), health.display());
+        }
+
         // Image (truncated)
         let image_display = if container.image.len() > 30 {
             format!("{}...", &container.image[..27])
@@ -225,8 +677,68 @@ fn render_container_row(
             ui.label(format!("[{}]", project));
         }
 
+        if container.restart_count > 0 {
+            let last_exit = container
+                .exit_code
+                .map(|code| format!(", exit {}", code))
+                .unwrap_or_default();
+            ui.colored_label(
+                crate::theme::colors::WARNING,
+                format!("↻ {}{}", container.restart_count, last_exit),
+            );
+        }
+
+        let ports = container.published_ports();
+        if !ports.is_empty() {
+            ui.colored_label(
+                crate::theme::colors::MUTED,
+                ports.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", "),
+            );
+        }
+
         // Action buttons
         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            if let Some(ref project) = container.project {
+                if ui.small_button("🔨 Rebuild Stack").clicked() {
+                    tracing::info!("Rebuild stack: {}", project);
+                    state.output_title = Some(format!("Rebuild stack {} on {}", project, node_name));
+                    state.output.clear();
+                    let _ = command_tx.send(AsyncCommand::RebuildStack {
+                        node: node_name.to_string(),
+                        project: project.clone(),
+                    });
+                }
+            }
+
+            if let Some(ref project) = container.project {
+                if ui.small_button("📜 Follow Stack").clicked() {
+                    start_follow(state, node_name, LogTarget::Stack(project.clone()), command_tx);
+                }
+            }
+
+            if ui.small_button("⬇ Pull").clicked() {
+                tracing::info!("Pull image for container: {}", container.name);
+                state.output_title = Some(format!("Pull {} on {}", container.name, node_name));
+                state.output.clear();
+                let _ = command_tx.send(AsyncCommand::PullContainer {
+                    node: node_name.to_string(),
+                    container: container.name.clone(),
+                });
+            }
+
+            if ui.small_button("ℹ Details").clicked() {
+                state.details_for = Some((node_name.to_string(), container.name.clone()));
+                state.details = None;
+                let _ = command_tx.send(AsyncCommand::InspectContainer {
+                    node: node_name.to_string(),
+                    container: container.name.clone(),
+                });
+            }
+
+            if ui.small_button("📜 Follow").clicked() {
+                start_follow(state, node_name, LogTarget::Container(container.name.clone()), command_tx);
+            }
+
             if ui.small_button("📋 Logs").clicked() {
                 state.selected_container = Some((node_name.to_string(), container.name.clone()));
                 state.logs = "Loading logs...".to_string();
@@ -0,0 +1,336 @@
+//! Files panel - Remote file browser and editor (SFTP)
+
+use crate::app::{AsyncCommand, CommandSender};
+use control_panel_core::ssh::sftp;
+use control_panel_core::{Config, FileEntry, FileKind};
+use egui::{Context, Ui};
+use std::sync::Arc;
+
+/// State for the Files panel
+#[derive(Default)]
+pub struct FilesPanelState {
+    /// Selected host (profile or docker node)
+    pub host: Option<String>,
+    /// Directory being shown
+    pub path: String,
+    /// Path field contents
+    pub path_input: String,
+    /// Directory contents
+    pub entries: Vec<FileEntry>,
+    /// File open in the editor
+    pub open_file: Option<OpenFile>,
+    /// Local file to upload into the current directory
+    pub upload_path: String,
+    /// Loading state
+    pub loading: bool,
+    /// Error message
+    pub error: Option<String>,
+    /// Success message
+    pub success: Option<String>,
+}
+
+/// A text file loaded into the editor
+pub struct OpenFile {
+    pub path: String,
+    pub content: String,
+    /// Content as loaded, to tell whether there are unsaved edits
+    pub original: String,
+    pub editing: bool,
+}
+
+impl OpenFile {
+    pub fn new(path: String, content: String) -> Self {
+        Self {
+            path,
+            original: content.clone(),
+            content,
+            editing: false,
+        }
+    }
+
+    fn modified(&self) -> bool {
+        self.content != self.original
+    }
+}
+
+/// Highlighter language for a file. egui_extras only ships a few
+/// highlighters; the TOML one (comments and strings) suits most config files.
+fn highlight_language(path: &str) -> &'static str {
+    match sftp::syntax_for_path(path) {
+        "python" => "py",
+        "rust" => "rs",
+        "toml" | "yaml" | "nix" | "bash" | "ini" | "dockerfile" | "nginx" => "toml",
+        _ => "",
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+    } else if bytes >= 1024 {
+        format!("{:.1} KiB", bytes as f64 / 1024.0)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+/// Render the Files panel
+pub fn render(
+    _ctx: &Context,
+    ui: &mut Ui,
+    state: &mut FilesPanelState,
+    config: &Arc<Config>,
+    command_tx: &CommandSender,
+) {
+    ui.heading("📁 Remote Files");
+    ui.add_space(8.0);
+
+    let mut hosts: Vec<&str> = config.docker_nodes.iter().map(|n| n.name.as_str()).collect();
+    for profile in &config.profiles {
+        if !hosts.contains(&profile.name.as_str()) && !profile.all_addresses().is_empty() {
+            hosts.push(&profile.name);
+        }
+    }
+
+    ui.horizontal(|ui| {
+        ui.label("Host:");
+        egui::ComboBox::from_id_salt("files_host")
+            .selected_text(state.host.as_deref().unwrap_or("Select a host..."))
+            .show_ui(ui, |ui| {
+                for host in &hosts {
+                    if ui
+                        .selectable_label(state.host.as_deref() == Some(*host), *host)
+                        .clicked()
+                    {
+                        state.host = Some(host.to_string());
+                        state.path.clear();
+                        state.open_file = None;
+                        list_dir(state, command_tx, String::new());
+                    }
+                }
+            });
+
+        if state.host.is_some() {
+            ui.separator();
+            ui.label("Path:");
+            let response = ui.text_edit_singleline(&mut state.path_input);
+            if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                let path = state.path_input.clone();
+                list_dir(state, command_tx, path);
+            }
+            if ui.button("🔄 Refresh").clicked() {
+                let path = state.path.clone();
+                list_dir(state, command_tx, path);
+            }
+        }
+
+        if state.loading {
+            ui.spinner();
+        }
+    });
+
+    if let Some(ref error) = state.error {
+        ui.colored_label(crate::theme::colors::OFFLINE, format!("⚠️ {}", error));
+    }
+    if let Some(ref success) = state.success {
+        ui.colored_label(crate::theme::colors::ONLINE, format!("✓ {}", success));
+    }
+
+    let Some(host) = state.host.clone() else {
+        ui.add_space(12.0);
+        ui.label(format!(
+            "Pick a host to browse its files over SFTP. Editing is limited to {}, transfers to {}.",
+            format_size(config.files.max_edit_bytes),
+            format_size(config.files.max_transfer_bytes)
+        ));
+        return;
+    };
+
+    ui.add_space(12.0);
+
+    if state.open_file.is_some() {
+        render_editor(ui, state, &host, config, command_tx);
+    } else {
+        render_listing(ui, state, &host, command_tx);
+    }
+}
+
+fn list_dir(state: &mut FilesPanelState, command_tx: &CommandSender, path: String) {
+    let Some(host) = state.host.clone() else {
+        return;
+    };
+    state.loading = true;
+    state.error = None;
+    state.success = None;
+    let _ = command_tx.send(AsyncCommand::ListDir { host, path });
+}
+
+fn render_listing(
+    ui: &mut Ui,
+    state: &mut FilesPanelState,
+    host: &str,
+    command_tx: &CommandSender,
+) {
+    ui.horizontal(|ui| {
+        ui.label("Upload local file:");
+        ui.text_edit_singleline(&mut state.upload_path);
+        let name = std::path::Path::new(state.upload_path.trim())
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string());
+        if ui
+            .add_enabled(name.is_some() && !state.path.is_empty(), egui::Button::new("⬆ Upload here"))
+            .clicked()
+        {
+            if let Some(name) = name {
+                state.loading = true;
+                state.error = None;
+                let _ = command_tx.send(AsyncCommand::UploadFile {
+                    host: host.to_string(),
+                    local: control_panel_core::config::expand_home(state.upload_path.trim()),
+                    remote: sftp::join(&state.path, &name),
+                });
+            }
+        }
+    });
+    ui.add_space(8.0);
+
+    let mut navigate = None;
+    let mut open = None;
+
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        egui::Grid::new("files_listing")
+            .num_columns(5)
+            .striped(true)
+            .spacing([16.0, 4.0])
+            .show(ui, |ui| {
+                if !state.path.is_empty() && state.path != "/" && ui.link("⬆ ..").clicked() {
+                    navigate = Some(sftp::parent(&state.path));
+                }
+                ui.end_row();
+
+                for entry in state.entries.iter().filter(|e| e.name != "." && e.name != "..") {
+                    let icon = match entry.kind {
+                        FileKind::Dir => "📁",
+                        FileKind::Symlink => "🔗",
+                        _ => "📄",
+                    };
+                    if ui.link(format!("{} {}", icon, entry.name)).clicked() {
+                        if entry.kind == FileKind::Dir {
+                            navigate = Some(entry.path.clone());
+                        } else {
+                            open = Some(entry.path.clone());
+                        }
+                    }
+                    if entry.kind == FileKind::Dir {
+                        ui.label("");
+                    } else {
+                        ui.label(format_size(entry.size));
+                    }
+                    ui.monospace(entry.mode_string());
+                    ui.label(
+                        entry
+                            .modified
+                            .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+                            .unwrap_or_default(),
+                    );
+                    if entry.kind != FileKind::Dir && ui.small_button("⬇ Download").clicked() {
+                        state.loading = true;
+                        state.error = None;
+                        let _ = command_tx.send(AsyncCommand::DownloadFile {
+                            host: host.to_string(),
+                            path: entry.path.clone(),
+                        });
+                    }
+                    ui.end_row();
+                }
+            });
+    });
+
+    if let Some(path) = navigate {
+        list_dir(state, command_tx, path);
+    }
+    if let Some(path) = open {
+        state.loading = true;
+        state.error = None;
+        state.success = None;
+        let _ = command_tx.send(AsyncCommand::OpenFile {
+            host: host.to_string(),
+            path,
+        });
+    }
+}
+
+fn render_editor(
+    ui: &mut Ui,
+    state: &mut FilesPanelState,
+    host: &str,
+    config: &Arc<Config>,
+    command_tx: &CommandSender,
+) {
+    let Some(file) = state.open_file.as_mut() else {
+        return;
+    };
+
+    let mut close = false;
+    let mut save = false;
+    ui.horizontal(|ui| {
+        if ui.button("⬅ Back").clicked() {
+            close = true;
+        }
+        ui.strong(&file.path);
+        if file.modified() {
+            ui.colored_label(crate::theme::colors::WARNING, "● modified");
+        }
+        ui.separator();
+        ui.checkbox(&mut file.editing, "Edit");
+        if ui.add_enabled(file.modified(), egui::Button::new("💾 Save")).clicked() {
+            save = true;
+        }
+        if config.files.backup {
+            ui.label("The current file is kept as a .bak copy");
+        }
+    });
+    ui.add_space(8.0);
+
+    let language = highlight_language(&file.path);
+    egui::ScrollArea::both().show(ui, |ui| {
+        if file.editing {
+            let mut layouter = |ui: &Ui, text: &str, wrap_width: f32| {
+                let theme = egui_extras::syntax_highlighting::CodeTheme::from_memory(ui.ctx(), ui.style());
+                let mut job = egui_extras::syntax_highlighting::highlight(ui.ctx(), ui.style(), &theme, text, language);
+                job.wrap.max_width = wrap_width;
+                ui.fonts(|f| f.layout_job(job))
+            };
+            ui.add(
+                egui::TextEdit::multiline(&mut file.content)
+                    .code_editor()
+                    .desired_width(f32::INFINITY)
+                    .desired_rows(30)
+                    .layouter(&mut layouter),
+            );
+        } else {
+            let theme = egui_extras::syntax_highlighting::CodeTheme::from_memory(ui.ctx(), ui.style());
+            egui_extras::syntax_highlighting::code_view_ui(ui, &theme, &file.content, language);
+        }
+    });
+
+    if save {
+        if file.content.len() as u64 > config.files.max_edit_bytes {
+            state.error = Some(format!(
+                "Content is over the {} edit limit",
+                format_size(config.files.max_edit_bytes)
+            ));
+        } else {
+            state.loading = true;
+            state.error = None;
+            let _ = command_tx.send(AsyncCommand::SaveFile {
+                host: host.to_string(),
+                path: file.path.clone(),
+                content: file.content.clone(),
+            });
+        }
+    }
+    if close {
+        state.open_file = None;
+    }
+}
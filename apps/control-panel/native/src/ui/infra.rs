@@ -23,6 +23,49 @@ pub struct InfraPanelState {
     pub error: Option<String>,
     /// Commit message input
     pub commit_message: String,
+    /// Live deploy output per profile
+    pub deploy_logs: std::collections::HashMap<String, String>,
+    /// Host keys rejected during SSH handshakes
+    pub pending_host_keys: Vec<control_panel_core::PendingHostKey>,
+    /// Selector filtering the profiles (`tag:lxc,!critical`)
+    pub selector: String,
+    /// Command to run on the selected hosts
+    pub batch_command: String,
+    /// Results of health checks and commands, newest first
+    pub batch_reports: Vec<BatchReport>,
+}
+
+/// Outcome of a batch operation, one line per host
+#[derive(Debug, Clone)]
+pub struct BatchReport {
+    pub title: String,
+    pub lines: Vec<BatchLine>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BatchLine {
+    pub host: String,
+    pub ok: bool,
+    pub status: &'static str,
+    pub output: String,
+}
+
+impl BatchReport {
+    pub fn failed(&self) -> usize {
+        self.lines.iter().filter(|l| !l.ok).count()
+    }
+
+    /// Plain text for the console views
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{} ({} hosts, {} failed)\n", self.title, self.lines.len(), self.failed());
+        for line in &self.lines {
+            text.push_str(&format!("[{}] {}\n", line.status, line.host));
+            for output in line.output.trim_end().lines() {
+                text.push_str(&format!("    {}\n", output));
+            }
+        }
+        text
+    }
 }
 
 /// Render the Infrastructure panel
@@ -64,6 +107,7 @@ pub fn render(
             }
 
             state.loading = false;
+            let _ = command_tx.send(AsyncCommand::RefreshHostKeys);
         }
 
         if state.loading {
@@ -206,11 +250,34 @@ pub fn render(
 
     ui.add_space(12.0);
 
+    // The selector filters the deploy targets and the profile overview
+    let selector = control_panel_core::Selector::parse(&state.selector);
+    let profiles = match selector {
+        Ok(ref selector) => config.select_profiles(selector),
+        Err(_) => Vec::new(),
+    };
+
     // Deployment section
     ui.group(|ui| {
         ui.heading("🚀 Deployment");
         ui.add_space(4.0);
 
+        // Host filter shared by the deploy, health and command actions
+        ui.horizontal(|ui| {
+            ui.label("Hosts:");
+            ui.add(egui::TextEdit::singleline(&mut state.selector).hint_text("tag:lxc,!critical"));
+        });
+        if let Err(ref e) = selector {
+            ui.colored_label(crate::theme::colors::OFFLINE, e.to_string());
+        }
+        let hosts = selector.as_ref().map(|s| config.select_hosts(s).len()).unwrap_or(0);
+        ui.colored_label(
+            crate::theme::colors::MUTED,
+            format!("{} profiles, {} hosts selected", profiles.len(), hosts),
+        );
+
+        ui.add_space(4.0);
+
         // Profile selector for deployment
         ui.horizontal(|ui| {
             ui.label("Target Profile:");
@@ -222,7 +289,7 @@ pub fn render(
                         .unwrap_or("Select profile..."),
                 )
                 .show_ui(ui, |ui| {
-                    for profile in &config.profiles {
+                    for profile in &profiles {
                         if ui
                             .selectable_label(
                                 state.selected_profile.as_ref() == Some(&profile.name),
@@ -256,14 +323,100 @@ pub fn render(
                     if let Some(ref profile) = state.selected_profile {
                         tracing::info!("Deploy to profile: {}", profile);
                         state.loading = true;
+                        state.deploy_logs.remove(profile);
                         let _ = command_tx.send(AsyncCommand::Deploy {
                             profile: profile.clone(),
                         });
                     }
                 }
             });
+
+            ui.add_enabled_ui(!profiles.is_empty(), |ui| {
+                if ui.button(format!("🚀 Deploy all {}", profiles.len())).clicked() {
+                    let names: Vec<String> = profiles.iter().map(|p| p.name.clone()).collect();
+                    tracing::info!("Deploy to profiles: {}", names.join(", "));
+                    state.loading = true;
+                    for name in &names {
+                        state.deploy_logs.remove(name);
+                    }
+                    let _ = command_tx.send(AsyncCommand::DeployMany { profiles: names });
+                }
+            });
+        });
+
+        ui.add_space(4.0);
+
+        // Health checks and commands on the selected hosts
+        ui.horizontal(|ui| {
+            ui.add_enabled_ui(selector.is_ok(), |ui| {
+                if ui.button("🩺 Health check").clicked() {
+                    state.loading = true;
+                    let _ = command_tx.send(AsyncCommand::BatchHealth {
+                        select: state.selector.clone(),
+                    });
+                }
+            });
+            ui.add(egui::TextEdit::singleline(&mut state.batch_command).hint_text("uptime"));
+            // An empty selector would run the command everywhere; ask for `*`
+            let can_run = selector.is_ok() && !state.selector.trim().is_empty() && !state.batch_command.trim().is_empty();
+            ui.add_enabled_ui(can_run, |ui| {
+                if ui.button("▶ Run command").clicked() {
+                    tracing::info!("Run {:?} on {}", state.batch_command, state.selector);
+                    state.loading = true;
+                    let _ = command_tx.send(AsyncCommand::RunCommand {
+                        select: state.selector.clone(),
+                        command: state.batch_command.trim().to_string(),
+                    });
+                }
+            });
         });
 
+        if !state.batch_reports.is_empty() {
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                ui.label("Batch Results:");
+                if ui.small_button("Clear").clicked() {
+                    state.batch_reports.clear();
+                }
+            });
+            egui::ScrollArea::vertical()
+                .id_salt("batch_results")
+                .max_height(250.0)
+                .show(ui, |ui| {
+                    for (index, report) in state.batch_reports.iter().enumerate() {
+                        let color = if report.failed() == 0 {
+                            crate::theme::colors::ONLINE
+                        } else {
+                            crate::theme::colors::WARNING
+                        };
+                        egui::CollapsingHeader::new(egui::RichText::new(format!(
+                            "{} ({} hosts, {} failed)",
+                            report.title,
+                            report.lines.len(),
+                            report.failed()
+                        )).color(color))
+                        .id_salt(("batch_report", state.batch_reports.len() - index))
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            for line in &report.lines {
+                                ui.horizontal(|ui| {
+                                    let color = if line.ok {
+                                        crate::theme::colors::ONLINE
+                                    } else {
+                                        crate::theme::colors::OFFLINE
+                                    };
+                                    ui.colored_label(color, line.status);
+                                    ui.strong(&line.host);
+                                });
+                                if !line.output.trim().is_empty() {
+                                    ui.monospace(line.output.trim_end());
+                                }
+                            }
+                        });
+                    }
+                });
+        }
+
         // Show deployment status
         if !state.deployment_status.is_empty() {
             ui.add_space(8.0);
@@ -284,8 +437,92 @@ pub fn render(
                 });
             }
         }
+
+        // Live output of the selected profile's deploy
+        if let Some(log) = state
+            .selected_profile
+            .as_ref()
+            .and_then(|p| state.deploy_logs.get(p))
+        {
+            ui.add_space(8.0);
+            egui::ScrollArea::vertical()
+                .id_salt("deploy_output")
+                .max_height(250.0)
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    ui.add(
+                        egui::TextEdit::multiline(&mut log.as_str())
+                            .font(egui::TextStyle::Monospace)
+                            .desired_width(f32::INFINITY)
+                            .desired_rows(10),
+                    );
+                });
+        }
     });
 
+    // SSH host keys awaiting a decision
+    if !state.pending_host_keys.is_empty() {
+        ui.add_space(12.0);
+        ui.group(|ui| {
+            ui.heading("🔑 SSH Host Keys");
+            ui.add_space(4.0);
+
+            for key in &state.pending_host_keys {
+                ui.horizontal(|ui| {
+                    let (label, color, action) = match key.reason {
+                        control_panel_core::ssh::RejectReason::Changed => {
+                            ("CHANGED", crate::theme::colors::OFFLINE, "Replace key")
+                        }
+                        control_panel_core::ssh::RejectReason::Unknown => {
+                            ("UNKNOWN", crate::theme::colors::WARNING, "Accept key")
+                        }
+                    };
+                    ui.colored_label(color, label);
+                    ui.strong(format!("{}:{}", key.host, key.port));
+                    ui.monospace(format!("{} {}", key.algorithm, key.fingerprint));
+
+                    if ui.small_button(action).clicked() {
+                        let _ = command_tx.send(AsyncCommand::AcceptHostKey {
+                            host: key.host.clone(),
+                            port: key.port,
+                            fingerprint: key.fingerprint.clone(),
+                        });
+                    }
+                });
+            }
+        });
+    }
+
+    // Inventory import mismatches
+    if let Some(ref report) = config.inventory_report {
+        ui.add_space(12.0);
+        ui.group(|ui| {
+            ui.heading("📋 Inventory");
+            ui.add_space(4.0);
+            ui.label(format!(
+                "{} profiles imported, {} mismatches (config.toml values win)",
+                report.imported.len(),
+                report.mismatches.len()
+            ));
+            for source in &report.sources {
+                if let Some(ref error) = source.error {
+                    ui.colored_label(
+                        crate::theme::colors::WARNING,
+                        format!("{} skipped: {}", source.path.display(), error),
+                    );
+                }
+            }
+            egui::ScrollArea::vertical()
+                .id_salt("inventory_mismatches")
+                .max_height(150.0)
+                .show(ui, |ui| {
+                    for mismatch in &report.mismatches {
+                        ui.colored_label(crate::theme::colors::WARNING, mismatch.to_string());
+                    }
+                });
+        });
+    }
+
     ui.add_space(12.0);
 
     // Profile Overview
@@ -296,7 +533,7 @@ pub fn render(
         egui::ScrollArea::vertical()
             .max_height(200.0)
             .show(ui, |ui| {
-                for profile in &config.profiles {
+                for profile in &profiles {
                     ui.horizontal(|ui| {
                         // Type indicator color
                         let type_color = match profile.profile_type.as_str() {
@@ -305,6 +542,7 @@ pub fn render(
                             "lxc" => crate::theme::colors::LXC,
                             "vm" => crate::theme::colors::VM,
                             "darwin" => crate::theme::colors::DARWIN,
+                            "server" => crate::theme::colors::SERVER,
                             _ => crate::theme::colors::UNKNOWN,
                         };
                         ui.colored_label(type_color, "●");
@@ -319,8 +557,24 @@ pub fn render(
                         if let Some(ctid) = profile.ctid {
                             ui.label(format!("CTID: {}", ctid));
                         }
+
+                        if let Some(ref description) = profile.description {
+                            ui.colored_label(crate::theme::colors::MUTED, description);
+                        }
+
+                        render_labels(ui, &config.profile_labels(profile));
                     });
                 }
             });
     });
 }
+
+/// Group and tag chips of a host
+pub fn render_labels(ui: &mut Ui, labels: &control_panel_core::Labels) {
+    for group in &labels.groups {
+        ui.colored_label(crate::theme::colors::DESKTOP, format!("@{}", group));
+    }
+    for tag in &labels.tags {
+        ui.colored_label(crate::theme::colors::MUTED, tag);
+    }
+}
@@ -0,0 +1,188 @@
+//! Inventory panel - Docker nodes, profiles and Grafana dashboards edited in
+//! config.toml
+
+use crate::app::{AsyncCommand, CommandSender};
+use control_panel_core::config_edit::{self, FieldKind, Section};
+use control_panel_core::Config;
+use egui::{Context, Ui};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// State for the Inventory panel
+#[derive(Default)]
+pub struct InventoryPanelState {
+    /// Section shown
+    pub section: Section,
+    /// The form is open
+    pub editing: bool,
+    /// Entry the form replaces (`None` for a new one)
+    pub original: Option<String>,
+    /// Form values per TOML key
+    pub values: HashMap<String, String>,
+    /// Entry waiting for a second click on delete
+    pub confirm_delete: Option<String>,
+    /// Waiting for a write
+    pub saving: bool,
+    /// Outcome of the last write
+    pub message: Option<String>,
+    /// Last error
+    pub error: Option<String>,
+}
+
+/// Render the Inventory panel
+pub fn render(
+    _ctx: &Context,
+    ui: &mut Ui,
+    state: &mut InventoryPanelState,
+    config: &Arc<Config>,
+    command_tx: &CommandSender,
+) {
+    ui.heading("📝 Inventory");
+    ui.colored_label(
+        crate::theme::colors::MUTED,
+        "Changes are checked, written to config.toml (comments and formatting kept) and applied right away.",
+    );
+    ui.add_space(8.0);
+
+    // Section tabs
+    ui.horizontal(|ui| {
+        for section in Section::all() {
+            if ui
+                .selectable_label(state.section == *section, format!("{}s", section.label()))
+                .clicked()
+                && state.section != *section
+            {
+                state.section = *section;
+                state.editing = false;
+                state.confirm_delete = None;
+            }
+        }
+
+        if state.saving {
+            ui.spinner();
+        }
+    });
+
+    if let Some(ref message) = state.message {
+        ui.colored_label(crate::theme::colors::ONLINE, message);
+    }
+    if let Some(ref error) = state.error {
+        ui.colored_label(crate::theme::colors::OFFLINE, format!("⚠️ {}", error));
+    }
+
+    ui.add_space(8.0);
+
+    let section = state.section;
+    ui.group(|ui| {
+        ui.horizontal(|ui| {
+            ui.heading(section.header());
+            if ui.button("➕ Add").clicked() {
+                state.editing = true;
+                state.original = None;
+                state.values.clear();
+            }
+        });
+        ui.add_space(4.0);
+
+        egui::ScrollArea::vertical()
+            .id_salt("inventory_entries")
+            .max_height(250.0)
+            .show(ui, |ui| {
+                for name in section.names(config) {
+                    ui.horizontal(|ui| {
+                        ui.strong(&name);
+
+                        if ui.small_button("✏ Edit").clicked() {
+                            if let Some(entry) = section.entry(config, &name) {
+                                state.values = config_edit::entry_to_form(section, &entry);
+                                state.original = Some(name.clone());
+                                state.editing = true;
+                            }
+                        }
+
+                        if state.confirm_delete.as_deref() == Some(name.as_str()) {
+                            if ui.small_button("Confirm delete").clicked() {
+                                tracing::info!("Delete {} {}", section.label(), name);
+                                state.saving = true;
+                                state.confirm_delete = None;
+                                let _ = command_tx.send(AsyncCommand::DeleteEntry {
+                                    section,
+                                    name: name.clone(),
+                                });
+                            }
+                            if ui.small_button("Cancel").clicked() {
+                                state.confirm_delete = None;
+                            }
+                        } else if ui.small_button("🗑 Delete").clicked() {
+                            state.confirm_delete = Some(name.clone());
+                        }
+                    });
+                }
+            });
+    });
+
+    if !state.editing {
+        return;
+    }
+
+    ui.add_space(12.0);
+    ui.group(|ui| {
+        ui.heading(match state.original {
+            Some(ref name) => format!("Edit {} {}", section.label(), name),
+            None => format!("New {}", section.label()),
+        });
+        ui.add_space(4.0);
+
+        egui::Grid::new("inventory_form")
+            .num_columns(2)
+            .spacing([12.0, 6.0])
+            .show(ui, |ui| {
+                for field in section.fields() {
+                    ui.label(if field.required {
+                        format!("{} *", field.label)
+                    } else {
+                        field.label.to_string()
+                    });
+                    let value = state.values.entry(field.key.to_string()).or_default();
+                    match field.kind {
+                        FieldKind::Choice(choices) => {
+                            if value.is_empty() {
+                                *value = choices[0].to_string();
+                            }
+                            egui::ComboBox::from_id_salt(field.key)
+                                .selected_text(value.clone())
+                                .show_ui(ui, |ui| {
+                                    for choice in choices {
+                                        ui.selectable_value(value, choice.to_string(), *choice);
+                                    }
+                                });
+                        }
+                        FieldKind::List => {
+                            ui.add(egui::TextEdit::singleline(value).hint_text("comma-separated"));
+                        }
+                        FieldKind::Number | FieldKind::Text => {
+                            ui.text_edit_singleline(value);
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+
+        ui.add_space(8.0);
+        ui.horizontal(|ui| {
+            if ui.button("💾 Save").clicked() {
+                state.saving = true;
+                state.message = None;
+                state.error = None;
+                let _ = command_tx.send(AsyncCommand::SaveEntry {
+                    section,
+                    original: state.original.clone(),
+                    values: state.values.clone(),
+                });
+            }
+            if ui.button("Cancel").clicked() {
+                state.editing = false;
+            }
+        });
+    });
+}
@@ -0,0 +1,253 @@
+//! Live log view - followed container and stack logs in the Docker panel
+
+use crate::app::{AsyncCommand, CommandSender};
+use control_panel_core::{FollowOptions, LogLine, LogStream};
+use egui::Ui;
+use std::collections::VecDeque;
+use tokio::sync::watch;
+
+/// Lines kept in the view; older ones are dropped
+const MAX_LINES: usize = 5000;
+
+/// What is followed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogTarget {
+    Container(String),
+    Stack(String),
+}
+
+impl LogTarget {
+    pub fn name(&self) -> &str {
+        match self {
+            LogTarget::Container(name) | LogTarget::Stack(name) => name,
+        }
+    }
+}
+
+/// A running (or ended) log follow
+pub struct LogFollowView {
+    /// Tells results of this follow from those of an earlier one
+    pub id: u64,
+    pub node: String,
+    pub target: LogTarget,
+    /// `--since` and `--tail` for the next restart
+    pub since: String,
+    pub tail: String,
+    pub lines: VecDeque<LogLine>,
+    /// Lines received while paused
+    pub pending: Vec<LogLine>,
+    pub paused: bool,
+    pub filter: String,
+    pub timestamps: bool,
+    pub show_stdout: bool,
+    pub show_stderr: bool,
+    /// "Following", "Ended (exit 0)", or the error that stopped it
+    pub status: String,
+    /// Outcome of the last download
+    pub message: Option<String>,
+    /// Dropping this stops the follow (and the remote `docker logs -f`)
+    stop: Option<watch::Sender<bool>>,
+}
+
+impl LogFollowView {
+    /// Start following (the options are checked first)
+    pub fn start(
+        id: u64,
+        node: &str,
+        target: LogTarget,
+        since: &str,
+        tail: &str,
+        command_tx: &CommandSender,
+    ) -> Self {
+        let mut view = Self {
+            id,
+            node: node.to_string(),
+            target: target.clone(),
+            since: since.to_string(),
+            tail: tail.to_string(),
+            lines: VecDeque::new(),
+            pending: Vec::new(),
+            paused: false,
+            filter: String::new(),
+            timestamps: true,
+            show_stdout: true,
+            show_stderr: true,
+            status: "Following".to_string(),
+            message: None,
+            stop: None,
+        };
+        match FollowOptions::parse(since, tail) {
+            Ok(options) => {
+                let (stop, stopped) = watch::channel(false);
+                view.stop = Some(stop);
+                let _ = command_tx.send(AsyncCommand::FollowLogs {
+                    id,
+                    node: node.to_string(),
+                    target,
+                    options,
+                    stopped,
+                });
+            }
+            Err(e) => view.status = e.to_string(),
+        }
+        view
+    }
+
+    pub fn push(&mut self, lines: Vec<LogLine>) {
+        if self.paused {
+            self.pending.extend(lines);
+            return;
+        }
+        self.lines.extend(lines);
+        while self.lines.len() > MAX_LINES {
+            self.lines.pop_front();
+        }
+    }
+
+    /// Stop the remote follower; the lines stay
+    pub fn stop(&mut self) {
+        if self.stop.take().is_some() {
+            self.status = "Stopped".to_string();
+        }
+    }
+
+    pub fn ended(&mut self, status: String) {
+        self.stop = None;
+        self.status = status;
+    }
+
+    fn visible(&self, line: &LogLine) -> bool {
+        let stream = match line.stream {
+            LogStream::Stdout => self.show_stdout,
+            LogStream::Stderr => self.show_stderr,
+        };
+        let filter = self.filter.to_lowercase();
+        stream
+            && (filter.is_empty()
+                || line.text.to_lowercase().contains(&filter)
+                || line.source.as_deref().is_some_and(|s| s.to_lowercase().contains(&filter)))
+    }
+
+    /// Write the kept lines to ~/Downloads (or the temp dir)
+    fn download(&self) -> Result<std::path::PathBuf, std::io::Error> {
+        let dir = std::env::var_os("HOME")
+            .map(|home| std::path::PathBuf::from(home).join("Downloads"))
+            .filter(|dir| dir.is_dir())
+            .unwrap_or_else(std::env::temp_dir);
+        let path = dir.join(format!(
+            "{}-{}.log",
+            self.target.name(),
+            chrono::Local::now().format("%Y%m%d-%H%M%S")
+        ));
+        let text: String = self.lines.iter().map(|l| l.to_text() + "\n").collect();
+        std::fs::write(&path, text)?;
+        Ok(path)
+    }
+}
+
+/// Render the log view. Returns false once it is closed.
+pub fn render(ui: &mut Ui, view: &mut LogFollowView, next_id: &mut u64, command_tx: &CommandSender) -> bool {
+    let mut open = true;
+
+    ui.group(|ui| {
+        ui.horizontal(|ui| {
+            let kind = match view.target {
+                LogTarget::Container(_) => "",
+                LogTarget::Stack(_) => "stack ",
+            };
+            ui.heading(format!("Follow: {}{} ({})", kind, view.target.name(), view.node));
+            let color = if view.stop.is_some() {
+                crate::theme::colors::ONLINE
+            } else {
+                crate::theme::colors::MUTED
+            };
+            ui.colored_label(color, &view.status);
+            if ui.button("✕ Close").clicked() {
+                open = false;
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Since:");
+            ui.add(egui::TextEdit::singleline(&mut view.since).hint_text("10m, 2h").desired_width(120.0));
+            ui.label("Tail:");
+            ui.add(egui::TextEdit::singleline(&mut view.tail).hint_text("all").desired_width(50.0));
+            if ui.button("🔄 Restart").clicked() {
+                *next_id += 1;
+                let (since, tail) = (view.since.clone(), view.tail.clone());
+                *view = LogFollowView::start(*next_id, &view.node, view.target.clone(), &since, &tail, command_tx);
+            }
+            let pause = if view.paused {
+                format!("▶ Resume ({} new)", view.pending.len())
+            } else {
+                "⏸ Pause".to_string()
+            };
+            if ui.button(pause).clicked() {
+                view.paused = !view.paused;
+                if !view.paused {
+                    let pending = std::mem::take(&mut view.pending);
+                    view.push(pending);
+                }
+            }
+            if ui.add_enabled(view.stop.is_some(), egui::Button::new("⏹ Stop")).clicked() {
+                view.stop();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Filter:");
+            ui.text_edit_singleline(&mut view.filter);
+            ui.checkbox(&mut view.timestamps, "Timestamps");
+            ui.checkbox(&mut view.show_stdout, "stdout");
+            ui.checkbox(&mut view.show_stderr, "stderr");
+            if ui.button("🗑 Clear").clicked() {
+                view.lines.clear();
+            }
+            if ui.button("📋 Copy").clicked() {
+                let text: String = view.lines.iter().filter(|l| view.visible(l)).map(|l| l.to_text() + "\n").collect();
+                ui.ctx().copy_text(text);
+            }
+            if ui.button("💾 Download").clicked() {
+                view.message = Some(match view.download() {
+                    Ok(path) => format!("Saved to {}", path.display()),
+                    Err(e) => format!("Download failed: {}", e),
+                });
+            }
+        });
+        if let Some(ref message) = view.message {
+            ui.colored_label(crate::theme::colors::MUTED, message);
+        }
+
+        ui.add_space(4.0);
+
+        let visible: Vec<&LogLine> = view.lines.iter().filter(|l| view.visible(l)).collect();
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        egui::ScrollArea::vertical()
+            .id_salt("docker_follow")
+            .max_height(300.0)
+            .auto_shrink([false, true])
+            .stick_to_bottom(!view.paused)
+            .show_rows(ui, row_height, visible.len(), |ui, rows| {
+                for line in &visible[rows] {
+                    let mut text = String::new();
+                    if view.timestamps {
+                        if let Some(ts) = line.timestamp {
+                            text.push_str(&ts.with_timezone(&chrono::Local).format("%H:%M:%S%.3f ").to_string());
+                        }
+                    }
+                    if let Some(ref source) = line.source {
+                        text.push_str(source);
+                        text.push_str(" | ");
+                    }
+                    text.push_str(&line.text);
+                    let color = match line.stream {
+                        LogStream::Stdout => ui.visuals().text_color(),
+                        LogStream::Stderr => crate::theme::colors::OFFLINE,
+                    };
+                    ui.label(egui::RichText::new(text).monospace().color(color));
+                }
+            });
+    });
+
+    open
+}
@@ -2,7 +2,10 @@
 
 pub mod docker;
 pub mod editor;
+pub mod files;
 pub mod infra;
+pub mod inventory;
+pub mod logs;
 pub mod monitoring;
 pub mod proxmox;
 pub mod sway;
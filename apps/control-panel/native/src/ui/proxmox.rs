@@ -33,9 +33,11 @@ pub fn render(
     ui.add_space(8.0);
 
     // Connection info
-    ui.horizontal(|ui| {
-        ui.label("Proxmox Host:");
-        ui.strong(&config.proxmox.host);
+    ui.horizontal_wrapped(|ui| {
+        ui.label("Proxmox endpoints:");
+        for endpoint in &config.proxmox {
+            ui.strong(&endpoint.name).on_hover_text(format!("{} (cluster {})", endpoint.host, endpoint.cluster_name()));
+        }
 
         ui.separator();
 
@@ -83,11 +85,36 @@ pub fn render(
         } else {
             // Clone to avoid borrow issues
             let containers = state.containers.clone();
+            let shared = shared_ctids(&containers);
+            if !shared.is_empty() {
+                ui.colored_label(
+                    crate::theme::colors::WARNING,
+                    format!(
+                        "⚠ CTIDs used on more than one endpoint: {}",
+                        shared.iter().map(u32::to_string).collect::<Vec<_>>().join(", ")
+                    ),
+                );
+            }
             egui::ScrollArea::vertical()
-                .max_height(300.0)
+                .max_height(400.0)
                 .show(ui, |ui| {
-                    for container in &containers {
-                        render_container_row(ui, state, container, command_tx);
+                    let mut clusters: Vec<&str> = Vec::new();
+                    for endpoint in &config.proxmox {
+                        if !clusters.contains(&endpoint.cluster_name()) {
+                            clusters.push(endpoint.cluster_name());
+                        }
+                    }
+                    for cluster in clusters {
+                        ui.label(egui::RichText::new(format!("Cluster {}", cluster)).strong());
+                        for endpoint in config.proxmox.iter().filter(|e| e.cluster_name() == cluster) {
+                            ui.indent(&endpoint.name, |ui| {
+                                ui.label(format!("{} ({})", endpoint.name, endpoint.host));
+                                for container in containers.iter().filter(|c| c.endpoint == endpoint.name) {
+                                    render_container_row(ui, state, container, shared.contains(&container.ctid), command_tx);
+                                }
+                            });
+                        }
+                        ui.add_space(6.0);
                     }
                 });
         }
@@ -113,6 +140,7 @@ pub fn render(
                     ui.colored_label(status_color, if job.enabled { "●" } else { "○" });
 
                     ui.strong(&job.id);
+                    ui.label(format!("on {}", job.endpoint));
                     ui.label(format!("Schedule: {}", job.schedule));
                     ui.label(format!("Storage: {}", job.storage));
                     ui.label(format!("VMs: {}", job.vmids));
@@ -120,6 +148,7 @@ pub fn render(
                     if ui.small_button("▶ Run Now").clicked() {
                         tracing::info!("Run backup job: {}", job.id);
                         let _ = command_tx.send(AsyncCommand::RunBackupJob {
+                            endpoint: job.endpoint.clone(),
                             job_id: job.id.clone(),
                         });
                     }
@@ -153,6 +182,7 @@ fn render_container_row(
     ui: &mut Ui,
     _state: &mut ProxmoxPanelState,
     container: &control_panel_core::ProxmoxContainer,
+    shared_ctid: bool,
     command_tx: &CommandSender,
 ) {
     ui.horizontal(|ui| {
@@ -168,6 +198,10 @@ fn render_container_row(
         ui.strong(format!("CTID {}", container.ctid));
         ui.label(&container.name);
         ui.label(format!("({})", container.status));
+        if shared_ctid {
+            ui.colored_label(crate::theme::colors::WARNING, "⚠ shared CTID")
+                .on_hover_text("This CTID is also used on another endpoint");
+        }
 
         // Action buttons
         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
@@ -176,12 +210,14 @@ fn render_container_row(
                     if ui.small_button("⏹ Stop").clicked() {
                         tracing::info!("Stop container CTID {}", container.ctid);
                         let _ = command_tx.send(AsyncCommand::ProxmoxStop {
+                            endpoint: container.endpoint.clone(),
                             ctid: container.ctid,
                         });
                     }
                     if ui.small_button("🔄 Restart").clicked() {
                         tracing::info!("Restart container CTID {}", container.ctid);
                         let _ = command_tx.send(AsyncCommand::ProxmoxRestart {
+                            endpoint: container.endpoint.clone(),
                             ctid: container.ctid,
                         });
                     }
@@ -190,6 +226,7 @@ fn render_container_row(
                     if ui.small_button("▶ Start").clicked() {
                         tracing::info!("Start container CTID {}", container.ctid);
                         let _ = command_tx.send(AsyncCommand::ProxmoxStart {
+                            endpoint: container.endpoint.clone(),
                             ctid: container.ctid,
                         });
                     }
@@ -199,3 +236,15 @@ fn render_container_row(
         });
     });
 }
+
+/// CTIDs listed on more than one endpoint
+fn shared_ctids(containers: &[control_panel_core::ProxmoxContainer]) -> Vec<u32> {
+    let mut shared: Vec<u32> = containers
+        .iter()
+        .filter(|c| containers.iter().any(|other| other.ctid == c.ctid && other.endpoint != c.endpoint))
+        .map(|c| c.ctid)
+        .collect();
+    shared.sort();
+    shared.dedup();
+    shared
+}
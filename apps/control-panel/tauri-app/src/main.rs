@@ -16,7 +16,6 @@ use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::Arc;
 use tauri::Manager;
-use tokio::sync::RwLock;
 
 /// Default port used by the systemd service
 const DEFAULT_PORT: u16 = 3100;
@@ -82,38 +81,49 @@ fn check_server_running(port: u16) -> bool {
 }
 
 async fn start_web_server(port: u16) -> Result<()> {
-    // Determine config path (check multiple locations)
-    let config_path = std::env::var("CONFIG_PATH")
-        .or_else(|_| std::env::var("CONTROL_PANEL_CONFIG"))
-        .map(PathBuf::from)
-        .unwrap_or_else(|_| {
-            // Check /etc first (NixOS managed), then home
-            let etc_path = PathBuf::from("/etc/control-panel/config.toml");
-            if etc_path.exists() {
-                return etc_path;
-            }
-            let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-            PathBuf::from(format!("{}/.dotfiles/apps/control-panel/config.toml", home))
-        });
+    // System file, user file (CONFIG_PATH or CONTROL_PANEL_CONFIG),
+    // CONTROL_PANEL_* overrides
+    let mut layers = control_panel_core::ConfigLayers::standard();
+    if std::env::var_os("CONFIG_PATH").is_none() {
+        if let Ok(path) = std::env::var("CONTROL_PANEL_CONFIG") {
+            layers.files[1] = PathBuf::from(path);
+        }
+    }
 
     // Load configuration
-    let config: Config = if config_path.exists() {
-        tracing::info!("Loading config from: {:?}", config_path);
-        let content = std::fs::read_to_string(&config_path)?;
-        toml::from_str(&content)?
-    } else {
-        tracing::warn!("Config not found at {:?}, using defaults", config_path);
-        Config::default()
-    };
+    if layers.existing().is_empty() {
+        tracing::warn!("No config file found at {:?}, using defaults", layers.files);
+    }
+    let config: Config = layers.load()?;
+    tracing::info!("Config loaded from: {}", config.sources.join(", "));
 
     // Create SSH pool
+    let config = control_panel_core::SharedConfig::new(config);
     let ssh_pool = control_panel_core::SshPool::new(&config)?;
 
+    // Apply edits to config.toml without a restart
+    let _watcher = control_panel_core::reload::watch(layers.clone(), config.clone(), ssh_pool.clone())
+        .map_err(|e| tracing::warn!("Config hot reload disabled: {}", e))
+        .ok();
+
+    // Sample container resource usage in the background
+    let stats = control_panel_core::StatsStore::new();
+    tokio::spawn(control_panel_core::docker::stats::run_collector(
+        stats.clone(),
+        ssh_pool.clone(),
+        config.clone(),
+    ));
+
+    // Follow docker events for the container lists
+    let inventory = control_panel_core::ContainerInventory::new();
+    tokio::spawn(control_panel_core::docker::events::run_watcher(
+        inventory.clone(),
+        ssh_pool.clone(),
+        config.clone(),
+    ));
+
     // Create shared state
-    let state = Arc::new(AppState {
-        config,
-        ssh_pool: RwLock::new(ssh_pool),
-    });
+    let state = Arc::new(AppState { config, ssh_pool, layers, stats, inventory, jobs: Default::default() });
 
     // Build router using the shared web library
     let app = build_router(state);
@@ -6,25 +6,39 @@
 pub mod routes;
 
 use axum::{
+    extract::DefaultBodyLimit,
     middleware,
-    routing::{get, post},
+    routing::{get, post, put},
     Router,
 };
-use control_panel_core::Config;
+use control_panel_core::{ConfigLayers, SharedConfig};
 use std::sync::Arc;
-use tokio::sync::RwLock;
 use tower_http::services::ServeDir;
 use tower_http::trace::TraceLayer;
 
 /// Application state shared across handlers
 pub struct AppState {
-    pub config: Config,
-    pub ssh_pool: RwLock<control_panel_core::SshPool>,
+    /// Current configuration, swapped on reload
+    pub config: SharedConfig,
+    pub ssh_pool: control_panel_core::SshPool,
+    /// Files the configuration is read from; inventory edits go to the user file
+    pub layers: ConfigLayers,
+    /// Container resource usage, filled by the stats collector
+    pub stats: control_panel_core::StatsStore,
+    /// Container lists kept current by `docker events`
+    pub inventory: control_panel_core::ContainerInventory,
+    /// Deploys, pulls and rebuilds started by a POST, running detached
+    pub jobs: routes::stream::StreamJobs,
 }
 
 /// Build the full Axum router with all routes registered.
 /// Used by both the standalone web server and the Tauri desktop wrapper.
 pub fn build_router(state: Arc<AppState>) -> Router {
+    // Form encoding can triple the size of an edited file
+    let config = state.config.load();
+    let edit_limit = (config.files.max_edit_bytes as usize).saturating_mul(3) + 4096;
+    let transfer_limit = config.files.max_transfer_bytes as usize;
+
     Router::new()
         // Health check (no auth)
         .route("/health", get(|| async { "ok" }))
@@ -33,11 +47,16 @@ pub fn build_router(state: Arc<AppState>) -> Router {
         // Docker routes
         .route("/docker", get(routes::docker::dashboard))
         .route("/docker/summary", get(routes::docker::summary_fragment))
+        .route("/docker/prune", post(routes::docker::prune))
+        .route("/docker/problems", get(routes::docker::problems_page))
+        .route("/docker/problems/list", get(routes::docker::problems_fragment))
         .route("/docker/{node}", get(routes::docker::node_containers))
         .route(
             "/docker/{node}/containers",
             get(routes::docker::containers_fragment),
         )
+        .route("/docker/{node}/usage", get(routes::docker::usage_fragment))
+        .route("/docker/{node}/events", get(routes::docker::inventory_events))
         .route(
             "/docker/{node}/{container}/start",
             post(routes::docker::start_container),
@@ -54,6 +73,26 @@ pub fn build_router(state: Arc<AppState>) -> Router {
             "/docker/{node}/{container}/logs",
             get(routes::docker::container_logs),
         )
+        .route(
+            "/docker/{node}/{container}/logs/follow",
+            get(routes::docker::follow_container_logs_page),
+        )
+        .route(
+            "/docker/{node}/{container}/logs/stream",
+            get(routes::docker::follow_container_logs),
+        )
+        .route(
+            "/docker/{node}/{container}/inspect",
+            get(routes::docker::container_details),
+        )
+        .route(
+            "/docker/{node}/{container}/pull/stream",
+            post(routes::docker::pull_container_stream),
+        )
+        .route(
+            "/docker/{node}/{container}/pull/stream/{id}",
+            get(routes::stream::attach),
+        )
         // Docker Compose stack operations
         .route(
             "/docker/{node}/stack/{project}/up",
@@ -75,6 +114,14 @@ pub fn build_router(state: Arc<AppState>) -> Router {
             "/docker/{node}/stack/{project}/rebuild",
             post(routes::docker::stack_rebuild),
         )
+        .route(
+            "/docker/{node}/stack/{project}/rebuild/stream",
+            post(routes::docker::stack_rebuild_stream),
+        )
+        .route(
+            "/docker/{node}/stack/{project}/rebuild/stream/{id}",
+            get(routes::stream::attach),
+        )
         .route(
             "/docker/{node}/stack/{project}/pull",
             post(routes::docker::stack_pull),
@@ -83,19 +130,34 @@ pub fn build_router(state: Arc<AppState>) -> Router {
             "/docker/{node}/stack/{project}/logs",
             get(routes::docker::stack_logs),
         )
+        .route(
+            "/docker/{node}/stack/{project}/logs/follow",
+            get(routes::docker::follow_stack_logs_page),
+        )
+        .route(
+            "/docker/{node}/stack/{project}/logs/stream",
+            get(routes::docker::follow_stack_logs),
+        )
         // Proxmox routes
         .route("/proxmox", get(routes::proxmox::dashboard))
         .route(
             "/proxmox/containers",
             get(routes::proxmox::containers_fragment),
         )
-        .route("/proxmox/{ctid}/start", post(routes::proxmox::start))
-        .route("/proxmox/{ctid}/stop", post(routes::proxmox::stop))
-        .route("/proxmox/{ctid}/restart", post(routes::proxmox::restart))
-        .route("/proxmox/{ctid}/status", get(routes::proxmox::status))
+        .route("/proxmox/{endpoint}/{ctid}/start", post(routes::proxmox::start))
+        .route("/proxmox/{endpoint}/{ctid}/stop", post(routes::proxmox::stop))
+        .route("/proxmox/{endpoint}/{ctid}/restart", post(routes::proxmox::restart))
+        .route("/proxmox/{endpoint}/{ctid}/status", get(routes::proxmox::status))
         // Infrastructure routes
         .route("/infra", get(routes::infra::dashboard))
         .route("/infra/graph", get(routes::infra::graph_data))
+        .route("/infra/inventory", get(routes::infra::inventory_page))
+        .route("/infra/config", get(routes::infra::config_page))
+        .route("/infra/edit", get(routes::config_edit::list_page))
+        .route("/infra/edit/{section}", post(routes::config_edit::save))
+        .route("/infra/edit/{section}/new", get(routes::config_edit::new_page))
+        .route("/infra/edit/{section}/{name}", get(routes::config_edit::edit_page))
+        .route("/infra/edit/{section}/{name}/delete", post(routes::config_edit::delete))
         .route("/infra/git/status", get(routes::infra::git_status))
         .route(
             "/infra/git/status-fragment",
@@ -106,16 +168,37 @@ pub fn build_router(state: Arc<AppState>) -> Router {
         .route("/infra/git/push", post(routes::infra::git_push))
         .route("/infra/git/commit", post(routes::infra::git_commit))
         .route("/infra/deploy/{profile}", post(routes::infra::deploy))
+        .route(
+            "/infra/deploy/{profile}/stream",
+            post(routes::infra::deploy_stream),
+        )
+        .route(
+            "/infra/deploy/{profile}/stream/{id}",
+            get(routes::stream::attach),
+        )
         .route(
             "/infra/deploy/{profile}/dry-run",
             post(routes::infra::dry_run),
         )
+        // Batch operations on selected hosts
+        .route("/infra/batch", get(routes::batch::page))
+        .route("/infra/batch/hosts", get(routes::batch::hosts_fragment))
+        .route("/infra/batch/health", post(routes::batch::health))
+        .route("/infra/batch/run", post(routes::batch::run))
         // Deploy-LXC routes
         .route("/infra/deploy-lxc", get(routes::infra::deploy_lxc_page))
         .route(
             "/infra/deploy-lxc/{profile}",
             post(routes::infra::deploy_lxc_execute),
         )
+        .route(
+            "/infra/deploy-lxc/{profile}/stream",
+            post(routes::infra::deploy_lxc_stream),
+        )
+        .route(
+            "/infra/deploy-lxc/{profile}/stream/{id}",
+            get(routes::stream::attach),
+        )
         // Editor routes
         .route("/editor", get(routes::editor::list_profiles))
         .route("/editor/{profile}", get(routes::editor::view_profile))
@@ -130,13 +213,129 @@ pub fn build_router(state: Arc<AppState>) -> Router {
         // Monitoring routes
         .route("/monitoring", get(routes::monitoring::dashboard))
         .route("/monitoring/{uid}", get(routes::monitoring::dashboard))
+        // Terminal routes
+        .route("/terminal", get(routes::terminal::index))
+        .route("/terminal/host/{name}", get(routes::terminal::host_page))
+        .route("/terminal/host/{name}/ws", get(routes::terminal::host_ws))
+        .route("/terminal/lxc/{endpoint}/{ctid}", get(routes::terminal::lxc_page))
+        .route("/terminal/lxc/{endpoint}/{ctid}/ws", get(routes::terminal::lxc_ws))
+        .route(
+            "/terminal/docker/{node}/{container}",
+            get(routes::terminal::container_page),
+        )
+        .route(
+            "/terminal/docker/{node}/{container}/ws",
+            get(routes::terminal::container_ws),
+        )
+        // File browser routes
+        .route("/files", get(routes::files::index))
+        .route("/files/{host}", get(routes::files::browse))
+        .route("/files/{host}/view", get(routes::files::view))
+        .route(
+            "/files/{host}/save",
+            post(routes::files::save).layer(DefaultBodyLimit::max(edit_limit)),
+        )
+        .route("/files/{host}/download", get(routes::files::download))
+        .route(
+            "/files/{host}/upload",
+            put(routes::files::upload).layer(DefaultBodyLimit::max(transfer_limit)),
+        )
+        // Jobs started by the streaming routes above
+        .route("/jobs", get(routes::jobs::page))
+        .route("/jobs/list", get(routes::jobs::list))
+        .route("/jobs/{id}/stream", get(routes::stream::attach))
+        .route("/jobs/{id}/cancel", post(routes::jobs::cancel))
+        // Tunnel routes
+        .route("/tunnels", get(routes::tunnels::dashboard).post(routes::tunnels::open))
+        .route("/tunnels/list", get(routes::tunnels::list))
+        .route("/tunnels/{id}/close", post(routes::tunnels::close))
+        // SSH host key routes
+        .route("/ssh/hostkeys", get(routes::ssh::host_keys_page))
+        .route("/ssh/hostkeys/accept", post(routes::ssh::accept_host_key))
         // Static files
         .nest_service("/static", ServeDir::new("static"))
         // Add state and middleware
         .layer(TraceLayer::new_for_http())
+        .layer(middleware::from_fn_with_state(
+            state.config.clone(),
+            routes::origin::same_origin_middleware,
+        ))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             routes::auth::basic_auth_middleware,
         ))
         .with_state(state)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::extract::ConnectInfo;
+    use axum::http::{header, Method, Request, StatusCode};
+    use control_panel_core::{Config, ConfigLayers, ContainerInventory, SshPool, StatsStore};
+    use std::net::SocketAddr;
+    use tower::ServiceExt;
+
+    fn router() -> Router {
+        let config = SharedConfig::new(Config::default());
+        let ssh_pool = SshPool::new(&config).unwrap();
+        build_router(Arc::new(AppState {
+            config,
+            ssh_pool,
+            layers: ConfigLayers { files: Vec::new(), env: false },
+            stats: StatsStore::new(),
+            inventory: ContainerInventory::new(),
+            jobs: Default::default(),
+        }))
+    }
+
+    /// Status of a form request from a local browser showing a page of `origin`
+    async fn request_from(method: Method, path: &str, origin: Option<&str>) -> StatusCode {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(path)
+            .header(header::HOST, "127.0.0.1:3100")
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded");
+        if let Some(origin) = origin {
+            request = request.header(header::ORIGIN, origin);
+        }
+        let mut request = request.body(Body::empty()).unwrap();
+        request.extensions_mut().insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 50000))));
+        router().oneshot(request).await.unwrap().status()
+    }
+
+    async fn post_from(path: &str, origin: Option<&str>) -> StatusCode {
+        request_from(Method::POST, path, origin).await
+    }
+
+    #[tokio::test]
+    async fn test_batch_run_needs_same_origin() {
+        assert_eq!(post_from("/infra/batch/run", Some("https://evil.example")).await, StatusCode::FORBIDDEN);
+        assert_eq!(post_from("/infra/batch/run", None).await, StatusCode::FORBIDDEN);
+        // Reaches the handler, which rejects the empty form
+        assert_ne!(post_from("/infra/batch/run", Some("http://127.0.0.1:3100")).await, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_file_writes_need_same_origin() {
+        assert_eq!(post_from("/files/web/save", Some("https://evil.example")).await, StatusCode::FORBIDDEN);
+        assert_eq!(
+            request_from(Method::PUT, "/files/web/upload?path=/etc/passwd", Some("https://evil.example")).await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(request_from(Method::PUT, "/files/web/upload?path=/etc/passwd", None).await, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_config_edits_need_same_origin() {
+        assert_eq!(post_from("/infra/edit/profiles", Some("https://evil.example")).await, StatusCode::FORBIDDEN);
+        assert_eq!(post_from("/infra/edit/profiles/web/delete", None).await, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_host_key_accept_needs_same_origin() {
+        assert_eq!(post_from("/ssh/hostkeys/accept", Some("https://evil.example")).await, StatusCode::FORBIDDEN);
+        assert_eq!(post_from("/ssh/hostkeys/accept", Some("null")).await, StatusCode::FORBIDDEN);
+    }
+}
@@ -4,12 +4,10 @@
 //! Can be wrapped by Tauri for a standalone desktop experience.
 
 use anyhow::Result;
-use control_panel_core::Config;
+use control_panel_core::{ConfigLayers, SharedConfig};
 use control_panel_web::{build_router, AppState};
 use std::net::SocketAddr;
-use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::RwLock;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[tokio::main]
@@ -23,36 +21,65 @@ async fn main() -> Result<()> {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    tracing::info!("Starting NixOS Control Panel Web Server");
+    let flag = |name: &str| std::env::args().skip(1).any(|arg| arg == name);
+    if flag("--hash-password") {
+        return hash_password();
+    }
+
+    // System file, user file (CONFIG_PATH), CONTROL_PANEL_* overrides
+    let layers = ConfigLayers::standard();
 
-    // Determine config path
-    let config_path = std::env::var("CONFIG_PATH")
-        .map(PathBuf::from)
-        .unwrap_or_else(|_| {
-            let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-            PathBuf::from(format!("{}/.dotfiles/apps/control-panel/config.toml", home))
-        });
+    if flag("--check-config") {
+        return check_config_file(&layers);
+    }
+    if flag("--print-config") {
+        let config = layers.load()?;
+        println!("# Sources: {}", config.sources.join(", "));
+        print!("{}", control_panel_core::layers::masked(&config));
+        return Ok(());
+    }
+
+    tracing::info!("Starting NixOS Control Panel Web Server");
 
     // Load configuration
-    let config: Config = if config_path.exists() {
-        tracing::info!("Loading config from: {:?}", config_path);
-        let content = std::fs::read_to_string(&config_path)?;
-        toml::from_str(&content)?
-    } else {
-        tracing::warn!("Config not found at {:?}, using defaults", config_path);
-        Config::default()
-    };
+    if layers.existing().is_empty() {
+        tracing::warn!("No config file found at {:?}, using defaults", layers.files);
+    }
+    let config = layers.load()?;
+    tracing::info!("Config loaded from: {}", config.sources.join(", "));
+    for finding in config.validate() {
+        if finding.severity != control_panel_core::Severity::Info {
+            tracing::warn!("Config {} {}: {}", finding.severity, finding.subject, finding.message);
+        }
+    }
 
     let bind_addr = format!("{}:{}", config.server.host, config.server.port);
 
     // Create SSH pool
+    let config = SharedConfig::new(config);
     let ssh_pool = control_panel_core::SshPool::new(&config)?;
 
+    // Apply edits to config.toml without a restart
+    let _watcher = control_panel_core::reload::watch(layers.clone(), config.clone(), ssh_pool.clone())
+        .map_err(|e| tracing::warn!("Config hot reload disabled: {}", e))
+        .ok();
+
     // Create shared state
-    let state = Arc::new(AppState {
-        config: config.clone(),
-        ssh_pool: RwLock::new(ssh_pool),
-    });
+    let stats = control_panel_core::StatsStore::new();
+    tokio::spawn(control_panel_core::docker::stats::run_collector(
+        stats.clone(),
+        ssh_pool.clone(),
+        config.clone(),
+    ));
+
+    let inventory = control_panel_core::ContainerInventory::new();
+    tokio::spawn(control_panel_core::docker::events::run_watcher(
+        inventory.clone(),
+        ssh_pool.clone(),
+        config.clone(),
+    ));
+
+    let state = Arc::new(AppState { config, ssh_pool, layers, stats, inventory, jobs: Default::default() });
 
     // Build router using the shared library function
     let app = build_router(state);
@@ -68,3 +95,39 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
+
+/// `--check-config`: print the configuration findings, fail on errors
+fn check_config_file(layers: &ConfigLayers) -> Result<()> {
+    let config = layers.load()?;
+    let findings = config.validate();
+
+    for finding in &findings {
+        println!("{}", finding);
+    }
+    let count = |severity| findings.iter().filter(|f| f.severity == severity).count();
+    println!(
+        "{}: {} errors, {} warnings, {} notes",
+        config.sources.join(", "),
+        count(control_panel_core::Severity::Error),
+        count(control_panel_core::Severity::Warning),
+        count(control_panel_core::Severity::Info)
+    );
+
+    if control_panel_core::validate::has_errors(&findings) {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// `--hash-password`: read a password from stdin, print its argon2 hash
+/// for `auth.password_hash`
+fn hash_password() -> Result<()> {
+    let mut password = String::new();
+    std::io::stdin().read_line(&mut password)?;
+    let password = password.trim_end_matches(['\r', '\n']);
+    if password.is_empty() {
+        anyhow::bail!("No password on stdin");
+    }
+    println!("{}", control_panel_core::credentials::hash_password(password)?);
+    Ok(())
+}
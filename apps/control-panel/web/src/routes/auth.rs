@@ -8,11 +8,43 @@ use axum::{
     response::Response,
 };
 use base64::{engine::general_purpose::STANDARD, Engine};
+use control_panel_core::config::AuthConfig;
+use std::collections::hash_map::RandomState;
+use std::collections::HashSet;
+use std::hash::BuildHasher;
 use std::net::SocketAddr;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, OnceLock};
 
 use crate::AppState;
 
+/// Logins checked before, so argon2/bcrypt don't run on every request.
+/// Keyed by a hash of the credentials and the stored secret, so a changed
+/// password invalidates them.
+static VERIFIED: Mutex<Option<HashSet<u64>>> = Mutex::new(None);
+const MAX_VERIFIED: usize = 64;
+
+fn check_login(auth: &AuthConfig, username: &str, password: &str) -> bool {
+    static HASHER: OnceLock<RandomState> = OnceLock::new();
+    let Ok(secret) = auth.secret() else {
+        return auth.verify(username, password);
+    };
+    let key = HASHER.get_or_init(RandomState::new).hash_one((username, password, &auth.username, &secret));
+
+    if VERIFIED.lock().unwrap().as_ref().is_some_and(|set| set.contains(&key)) {
+        return true;
+    }
+    if !auth.verify(username, password) {
+        return false;
+    }
+    let mut verified = VERIFIED.lock().unwrap();
+    let set = verified.get_or_insert_with(HashSet::new);
+    if set.len() >= MAX_VERIFIED {
+        set.clear();
+    }
+    set.insert(key);
+    true
+}
+
 /// Basic auth middleware.
 /// Localhost connections bypass auth (for Tauri desktop app and local browser access).
 /// Remote connections require HTTP Basic Auth credentials.
@@ -33,20 +65,18 @@ pub async fn basic_auth_middleware(
     }
 
     // Check for Authorization header
+    let config = state.config.load();
     let auth_header = request
         .headers()
         .get("Authorization")
         .and_then(|h| h.to_str().ok());
 
     if let Some(auth_value) = auth_header {
-        if auth_value.starts_with("Basic ") {
-            let encoded = &auth_value[6..];
+        if let Some(encoded) = auth_value.strip_prefix("Basic ") {
             if let Ok(decoded) = STANDARD.decode(encoded) {
                 if let Ok(credentials) = String::from_utf8(decoded) {
                     if let Some((username, password)) = credentials.split_once(':') {
-                        if username == state.config.auth.username
-                            && password == state.config.auth.password
-                        {
+                        if check_login(&config.auth, username, password) {
                             return Ok(next.run(request).await);
                         }
                     }
@@ -0,0 +1,248 @@
+//! Batch routes: health checks and commands on hosts picked with a selector
+//! (`tag:lxc,!critical`, see `control_panel_core::selector`)
+
+use axum::{
+    extract::{Query, State},
+    response::Html,
+    Form,
+};
+use control_panel_core::{HostResult, Labels, Selector};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use super::html_escape;
+use crate::AppState;
+
+/// `?select=` of pages with a host filter
+#[derive(Debug, Default, Deserialize)]
+pub struct SelectQuery {
+    #[serde(default)]
+    pub select: String,
+}
+
+#[derive(Deserialize)]
+pub struct RunForm {
+    select: String,
+    command: String,
+}
+
+/// Parse a selector, or the error as HTML
+pub fn parse_selector(expression: &str) -> Result<Selector, String> {
+    Selector::parse(expression).map_err(|e| format!("<div class='text-red-400'>{}</div>", html_escape(&e.to_string())))
+}
+
+/// A selector for titles, "every host" when it selects every host
+pub fn describe_selector(selector: &Selector) -> String {
+    if selector.is_all() {
+        "every host".to_string()
+    } else {
+        selector.to_string()
+    }
+}
+
+/// Tag and group chips of a host
+pub fn labels_html(labels: &Labels) -> String {
+    let groups = labels
+        .groups
+        .iter()
+        .map(|g| format!(r##"<span class="px-1 rounded bg-indigo-900 text-indigo-200">@{}</span>"##, html_escape(g)));
+    let tags = labels
+        .tags
+        .iter()
+        .map(|t| format!(r##"<span class="px-1 rounded bg-gray-900 text-gray-300">{}</span>"##, html_escape(t)));
+    format!(
+        r##"<span class="flex flex-wrap gap-1 text-xs mt-1">{}</span>"##,
+        groups.chain(tags).collect::<String>()
+    )
+}
+
+/// Selector syntax reminder shown under filter inputs
+pub const SELECTOR_HELP: &str = r##"<p class="text-xs text-gray-500 mt-1">
+    Comma-separated: <code>tag:lxc</code>, <code>@group</code>, <code>name:LXC_*</code>, a name or tag,
+    <code>*</code>; <code>!</code> excludes (<code>tag:lxc,!critical</code>). Empty selects every host.
+</p>"##;
+
+/// Batch operations page
+pub async fn page(Query(query): Query<SelectQuery>) -> Html<String> {
+    Html(format!(
+        r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Batch - Control Panel</title>
+    <script src="https://unpkg.com/htmx.org@1.9.10"></script>
+    <script src="https://cdn.tailwindcss.com"></script>
+    <style>body {{ background-color: #1a1a2e; color: #eee; }}</style>
+</head>
+<body class="min-h-screen">
+    <nav class="bg-gray-800 border-b border-gray-700 px-6 py-4">
+        <div class="flex items-center justify-between">
+            <h1 class="text-2xl font-bold text-blue-400">NixOS Control Panel</h1>
+            <div class="flex gap-4">
+                <a href="/" class="text-gray-400 hover:text-gray-300">Infrastructure</a>
+                <a href="/docker" class="text-gray-400 hover:text-gray-300">Docker</a>
+                <a href="/proxmox" class="text-gray-400 hover:text-gray-300">Proxmox</a>
+                <a href="/monitoring" class="text-gray-400 hover:text-gray-300">Monitoring</a>
+                <a href="/editor" class="text-gray-400 hover:text-gray-300">Editor</a>
+            </div>
+        </div>
+    </nav>
+
+    <main class="container mx-auto px-6 py-8">
+        <div class="flex items-center gap-4 mb-6">
+            <a href="/infra" class="text-gray-400 hover:text-gray-300">&larr; Back to Infra</a>
+            <h2 class="text-xl font-semibold">Batch Operations</h2>
+        </div>
+
+        <form id="batch-form" class="bg-gray-800 p-4 rounded-lg mb-6" onsubmit="return false">
+            <label class="block text-sm text-gray-400 mb-1" for="select">Hosts</label>
+            <input id="select" name="select" value="{select}" placeholder="tag:lxc,!critical"
+                   class="w-full px-3 py-2 bg-gray-900 rounded font-mono"
+                   hx-get="/infra/batch/hosts" hx-trigger="load, keyup changed delay:300ms" hx-target="#batch-hosts">
+            {help}
+            <div id="batch-hosts" class="mt-3"></div>
+
+            <div class="flex flex-wrap gap-2 mt-4">
+                <button hx-post="/infra/batch/health" hx-include="#batch-form" hx-target="#batch-results" hx-swap="afterbegin"
+                        class="px-4 py-2 bg-green-700 hover:bg-green-600 rounded">Health check</button>
+                <input name="command" placeholder="uptime" class="flex-1 px-3 py-2 bg-gray-900 rounded font-mono">
+                <button hx-post="/infra/batch/run" hx-include="#batch-form" hx-target="#batch-results" hx-swap="afterbegin"
+                        hx-confirm="Run this command on every selected host?"
+                        class="px-4 py-2 bg-amber-600 hover:bg-amber-700 rounded">Run command</button>
+            </div>
+        </form>
+
+        <div class="flex items-center justify-between mb-2">
+            <h3 class="text-lg font-semibold">Results</h3>
+            <button onclick="document.getElementById('batch-results').innerHTML=''"
+                    class="px-3 py-1 bg-gray-700 hover:bg-gray-600 rounded text-sm">Clear</button>
+        </div>
+        <div id="batch-results" class="bg-gray-900 rounded-lg p-4 font-mono text-sm overflow-auto max-h-[40rem] min-h-[4rem] border border-gray-700"></div>
+    </main>
+</body>
+</html>"##,
+        select = html_escape(&query.select),
+        help = SELECTOR_HELP,
+    ))
+}
+
+/// Hosts a selector matches (live preview)
+pub async fn hosts_fragment(State(state): State<Arc<AppState>>, Query(query): Query<SelectQuery>) -> Html<String> {
+    let config = state.config.load();
+    let selector = match parse_selector(&query.select) {
+        Ok(selector) => selector,
+        Err(html) => return Html(html),
+    };
+
+    let hosts = config.select_hosts(&selector);
+    let chips = hosts
+        .iter()
+        .map(|host| {
+            let labels = match config.get_profile(host.name()) {
+                Some(profile) => config.profile_labels(profile),
+                None => config.get_docker_node(host.name()).map(|n| config.node_labels(n)).unwrap_or_default(),
+            };
+            format!(
+                r##"<div class="px-3 py-2 bg-gray-700 rounded"><span class="font-semibold">{}</span>{}</div>"##,
+                html_escape(host.name()),
+                labels_html(&labels)
+            )
+        })
+        .collect::<String>();
+
+    Html(format!(
+        r##"<p class="text-sm text-gray-400 mb-2">{} hosts selected</p>
+        <div class="grid grid-cols-2 md:grid-cols-4 gap-2">{}</div>"##,
+        hosts.len(),
+        chips
+    ))
+}
+
+/// NixOS version of every selected host
+pub async fn health(State(state): State<Arc<AppState>>, Form(form): Form<SelectQuery>) -> Html<String> {
+    let config = state.config.load();
+    let selector = match parse_selector(&form.select) {
+        Ok(selector) => selector,
+        Err(html) => return Html(html),
+    };
+
+    let results = control_panel_core::batch::health(&state.ssh_pool, &config.select_hosts(&selector)).await;
+    Html(results_html(&format!("Health check on {}", describe_selector(&selector)), &results, |result| match result {
+        Ok(Some(version)) => ("OK", "text-green-400", format!("NixOS {}", version)),
+        Ok(None) => ("NOT NIXOS", "text-amber-400", String::new()),
+        Err(e) => ("UNREACHABLE", "text-red-400", e.to_string()),
+    }))
+}
+
+/// Run a command on every selected host
+pub async fn run(State(state): State<Arc<AppState>>, Form(form): Form<RunForm>) -> Html<String> {
+    let config = state.config.load();
+    if form.select.trim().is_empty() {
+        return Html("<div class='text-red-400'>Enter a selector first (<code>*</code> for every host)</div>".to_string());
+    }
+    if form.command.trim().is_empty() {
+        return Html("<div class='text-red-400'>Enter a command</div>".to_string());
+    }
+    let selector = match parse_selector(&form.select) {
+        Ok(selector) => selector,
+        Err(html) => return Html(html),
+    };
+
+    let hosts = config.select_hosts(&selector);
+    let results = control_panel_core::batch::run_command(&state.ssh_pool, &hosts, form.command.trim()).await;
+    Html(results_html(
+        &format!("{} on {}", form.command.trim(), describe_selector(&selector)),
+        &results,
+        |result| match result {
+            Ok(output) if output.success() => ("OK", "text-green-400", output.combined()),
+            Ok(output) => ("FAIL", "text-red-400", format!("[exit {}]\n{}", output.exit_code, output.combined())),
+            Err(e) => ("ERROR", "text-red-400", e.to_string()),
+        },
+    ))
+}
+
+/// One console block with a line per host
+pub fn results_html<T>(
+    title: &str,
+    results: &[HostResult<T>],
+    describe: impl Fn(&Result<T, control_panel_core::AppError>) -> (&'static str, &'static str, String),
+) -> String {
+    let failed = results.iter().filter(|r| r.result.is_err()).count();
+    let rows = results
+        .iter()
+        .map(|r| {
+            let (status, color, output) = describe(&r.result);
+            let output = if output.trim().is_empty() {
+                String::new()
+            } else {
+                format!(
+                    "<pre class=\"text-gray-400 ml-4 text-xs whitespace-pre-wrap\">{}</pre>",
+                    html_escape(output.trim_end())
+                )
+            };
+            format!(
+                r##"<div class="ml-4"><span class="{}">[{}]</span> {}{}</div>"##,
+                color,
+                status,
+                html_escape(&r.host),
+                output
+            )
+        })
+        .collect::<String>();
+    let empty = if results.is_empty() { "<div class='ml-4 text-gray-500'>No hosts selected</div>" } else { "" };
+
+    format!(
+        r##"<div class="border-b border-gray-700 py-2">
+            <span class="text-gray-500">[{ts}]</span> {title}
+            <span class="text-gray-500">({count} hosts, {failed} errors)</span>
+            {rows}{empty}
+        </div>"##,
+        ts = chrono::Local::now().format("%H:%M:%S"),
+        title = html_escape(title),
+        count = results.len(),
+        failed = failed,
+        rows = rows,
+        empty = empty,
+    )
+}
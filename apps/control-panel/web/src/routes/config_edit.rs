@@ -0,0 +1,291 @@
+//! Inventory editor: docker nodes, profiles and Grafana dashboards written
+//! back to config.toml (see `control_panel_core::config_edit`)
+
+use axum::{
+    extract::{Path, State},
+    response::Html,
+    Form,
+};
+use control_panel_core::config_edit::{self, FieldKind, Section};
+use control_panel_core::{AppError, Finding, Severity};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::{html_escape, url_encode};
+use crate::AppState;
+
+fn page_html(title: &str, body: &str) -> String {
+    format!(
+        r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>{title} - Control Panel</title>
+    <script src="https://unpkg.com/htmx.org@1.9.10"></script>
+    <script src="https://cdn.tailwindcss.com"></script>
+    <style>body {{ background-color: #1a1a2e; color: #eee; }}</style>
+</head>
+<body class="min-h-screen">
+    <nav class="bg-gray-800 border-b border-gray-700 px-6 py-4">
+        <div class="flex items-center justify-between">
+            <h1 class="text-2xl font-bold text-blue-400">NixOS Control Panel</h1>
+            <div class="flex gap-4">
+                <a href="/" class="text-gray-400 hover:text-gray-300">Infrastructure</a>
+                <a href="/docker" class="text-gray-400 hover:text-gray-300">Docker</a>
+                <a href="/proxmox" class="text-gray-400 hover:text-gray-300">Proxmox</a>
+                <a href="/monitoring" class="text-gray-400 hover:text-gray-300">Monitoring</a>
+                <a href="/editor" class="text-gray-400 hover:text-gray-300">Editor</a>
+            </div>
+        </div>
+    </nav>
+
+    <main class="container mx-auto px-6 py-8">
+        {body}
+    </main>
+</body>
+</html>"##,
+        title = html_escape(title),
+        body = body,
+    )
+}
+
+fn error_html(message: &str) -> Html<String> {
+    Html(format!("<div class='text-red-400'>{}</div>", html_escape(message)))
+}
+
+/// Outcome of a write, with the warnings the configuration now has
+fn saved_html(message: &str, findings: &[Finding]) -> String {
+    let warnings = findings.iter().filter(|f| f.severity != Severity::Info).count();
+    let warnings = if warnings == 0 {
+        String::new()
+    } else {
+        format!(r##" <a href="/infra/config" class="text-amber-400 underline">{} warnings</a>"##, warnings)
+    };
+    format!(r##"<div class="text-green-400">{}{}</div>"##, html_escape(message), warnings)
+}
+
+/// Where edits are written
+fn user_file(state: &AppState) -> String {
+    state
+        .layers
+        .user_file()
+        .map(|p| p.display().to_string())
+        .unwrap_or_else(|| "no config file".to_string())
+}
+
+/// Entries of every section with edit and delete buttons
+pub async fn list_page(State(state): State<Arc<AppState>>) -> Html<String> {
+    let config = state.config.load();
+    let sections = Section::all()
+        .iter()
+        .map(|section| {
+            let rows = section
+                .names(&config)
+                .iter()
+                .map(|name| {
+                    let summary = section
+                        .entry(&config, name)
+                        .map(|entry| {
+                            let form = config_edit::entry_to_form(*section, &entry);
+                            section.fields()[1..]
+                                .iter()
+                                .filter(|f| f.required)
+                                .filter_map(|f| form.get(f.key))
+                                .map(|v| html_escape(v))
+                                .collect::<Vec<_>>()
+                                .join(" &middot; ")
+                        })
+                        .unwrap_or_default();
+                    format!(
+                        r##"<tr class="border-t border-gray-700">
+                            <td class="py-2 font-semibold">{name}</td>
+                            <td class="py-2 text-gray-400 text-sm">{summary}</td>
+                            <td class="py-2 text-right whitespace-nowrap">
+                                <a href="/infra/edit/{slug}/{name_url}" class="px-3 py-1 bg-gray-700 hover:bg-gray-600 rounded text-sm">Edit</a>
+                                <button hx-post="/infra/edit/{slug}/{name_url}/delete" hx-target="closest tr" hx-swap="outerHTML"
+                                        hx-confirm="Remove {name} from config.toml?"
+                                        class="px-3 py-1 bg-red-700 hover:bg-red-600 rounded text-sm">Delete</button>
+                            </td>
+                        </tr>"##,
+                        name = html_escape(name),
+                        name_url = url_encode(name),
+                        slug = section.slug(),
+                        summary = summary,
+                    )
+                })
+                .collect::<String>();
+            format!(
+                r##"<div class="bg-gray-800 p-4 rounded-lg mb-6">
+                    <div class="flex items-center justify-between mb-2">
+                        <h3 class="text-lg font-semibold">{label}s <span class="text-gray-500 font-mono text-sm">{header}</span></h3>
+                        <a href="/infra/edit/{slug}/new" class="px-3 py-1 bg-blue-600 hover:bg-blue-700 rounded text-sm">Add</a>
+                    </div>
+                    <table class="w-full">{rows}</table>
+                </div>"##,
+                label = section.label(),
+                header = section.header(),
+                slug = section.slug(),
+                rows = rows,
+            )
+        })
+        .collect::<String>();
+
+    Html(page_html(
+        "Edit Inventory",
+        &format!(
+            r##"<div class="flex items-center gap-4 mb-2">
+            <a href="/infra" class="text-gray-400 hover:text-gray-300">&larr; Back to Infra</a>
+            <h2 class="text-xl font-semibold">Edit Inventory</h2>
+        </div>
+        <p class="text-gray-400 text-sm mb-6">Changes are checked, written to <code>{file}</code> (comments and
+            formatting kept) and applied right away. Editing an imported profile adds an override.</p>
+        {sections}"##,
+            file = html_escape(&user_file(&state)),
+            sections = sections,
+        ),
+    ))
+}
+
+/// Form for a new entry
+pub async fn new_page(State(state): State<Arc<AppState>>, Path(slug): Path<String>) -> Html<String> {
+    let Some(section) = Section::from_slug(&slug) else {
+        return error_html(&format!("Unknown section: {}", slug));
+    };
+    Html(form_page(&state, section, None, &HashMap::new()))
+}
+
+/// Form for an existing entry
+pub async fn edit_page(State(state): State<Arc<AppState>>, Path((slug, name)): Path<(String, String)>) -> Html<String> {
+    let Some(section) = Section::from_slug(&slug) else {
+        return error_html(&format!("Unknown section: {}", slug));
+    };
+    let config = state.config.load();
+    let Some(entry) = section.entry(&config, &name) else {
+        return error_html(&format!("{} not found: {}", section.label(), name));
+    };
+    Html(form_page(&state, section, Some(&name), &config_edit::entry_to_form(section, &entry)))
+}
+
+fn form_page(state: &AppState, section: Section, original: Option<&str>, values: &HashMap<String, String>) -> String {
+    let inputs = section
+        .fields()
+        .iter()
+        .map(|field| {
+            let value = values.get(field.key).map(String::as_str).unwrap_or("");
+            let input = match field.kind {
+                FieldKind::Choice(choices) => {
+                    let options = choices
+                        .iter()
+                        .map(|c| {
+                            let selected = if *c == value { " selected" } else { "" };
+                            format!(r##"<option value="{c}"{selected}>{c}</option>"##, c = c, selected = selected)
+                        })
+                        .collect::<String>();
+                    format!(
+                        r##"<select name="{key}" class="w-full px-3 py-2 bg-gray-900 rounded">{options}</select>"##,
+                        key = field.key,
+                        options = options
+                    )
+                }
+                kind => format!(
+                    r##"<input name="{key}" value="{value}" {required} placeholder="{placeholder}"
+                           class="w-full px-3 py-2 bg-gray-900 rounded font-mono">"##,
+                    key = field.key,
+                    value = html_escape(value),
+                    required = if field.required { "required" } else { "" },
+                    placeholder = match kind {
+                        FieldKind::List => "comma-separated",
+                        FieldKind::Number => "number",
+                        _ => "",
+                    },
+                ),
+            };
+            format!(
+                r##"<label class="block">
+                    <span class="text-sm text-gray-400">{label}{star} <code class="text-gray-500">{key}</code></span>
+                    {input}
+                </label>"##,
+                label = field.label,
+                star = if field.required { " *" } else { "" },
+                key = field.key,
+                input = input,
+            )
+        })
+        .collect::<String>();
+
+    let title = match original {
+        Some(name) => format!("Edit {} {}", section.label(), name),
+        None => format!("New {}", section.label()),
+    };
+    page_html(
+        &title,
+        &format!(
+            r##"<div class="flex items-center gap-4 mb-6">
+            <a href="/infra/edit" class="text-gray-400 hover:text-gray-300">&larr; Back to Inventory</a>
+            <h2 class="text-xl font-semibold">{title}</h2>
+        </div>
+        <form hx-post="/infra/edit/{slug}" hx-target="#edit-result" class="bg-gray-800 p-4 rounded-lg">
+            <input type="hidden" name="original" value="{original}">
+            <div class="grid grid-cols-1 md:grid-cols-2 gap-4">{inputs}</div>
+            <div class="flex items-center gap-4 mt-6">
+                <button type="submit" class="px-4 py-2 bg-green-700 hover:bg-green-600 rounded">Save to {file}</button>
+                <div id="edit-result"></div>
+            </div>
+        </form>"##,
+            title = html_escape(&title),
+            slug = section.slug(),
+            original = html_escape(original.unwrap_or("")),
+            inputs = inputs,
+            file = html_escape(&user_file(state)),
+        ),
+    )
+}
+
+/// Add or change an entry (`original` is empty for a new one)
+pub async fn save(
+    State(state): State<Arc<AppState>>,
+    Path(slug): Path<String>,
+    Form(form): Form<HashMap<String, String>>,
+) -> Html<String> {
+    let Some(section) = Section::from_slug(&slug) else {
+        return error_html(&format!("Unknown section: {}", slug));
+    };
+    let original = form.get("original").map(|o| o.trim()).filter(|o| !o.is_empty());
+
+    let result = config_edit::form_to_entry(section, &form).and_then(|entry| {
+        config_edit::save_entry(&state.layers, &state.config, &state.ssh_pool, section, original, &entry)
+            .map(|findings| (entry, findings))
+    });
+    match result {
+        Ok((entry, findings)) => {
+            let name = entry.get("name").and_then(|n| n.as_str()).unwrap_or_default();
+            tracing::info!("Saved {} {} to config.toml", section.label(), name);
+            Html(saved_html(&format!("Saved {} and applied it", name), &findings))
+        }
+        Err(e) => error_html(&describe(&e)),
+    }
+}
+
+/// Remove an entry; answers with the replacement table row
+pub async fn delete(State(state): State<Arc<AppState>>, Path((slug, name)): Path<(String, String)>) -> Html<String> {
+    let Some(section) = Section::from_slug(&slug) else {
+        return error_html(&format!("Unknown section: {}", slug));
+    };
+    let row = |html: String| Html(format!(r##"<tr class="border-t border-gray-700"><td colspan="3" class="py-2">{}</td></tr>"##, html));
+    match config_edit::delete_entry(&state.layers, &state.config, &state.ssh_pool, section, &name) {
+        Ok(findings) => {
+            tracing::info!("Removed {} {} from config.toml", section.label(), name);
+            row(saved_html(&format!("Removed {}", name), &findings))
+        }
+        Err(e) => row(error_html(&format!("{}: {}", name, describe(&e))).0),
+    }
+}
+
+/// Validation messages without the error type prefix
+fn describe(error: &AppError) -> String {
+    match error {
+        AppError::Validation(message) | AppError::Config(message) => message.clone(),
+        other => other.to_string(),
+    }
+}
@@ -1,13 +1,42 @@
 //! Docker routes
 
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     response::Html,
+    Form,
 };
+use control_panel_core::docker::logs;
+use control_panel_core::{Container, FollowOptions, HealthStatus, NodeUsage, ProblemKind, PruneKind, Usage};
+use serde::Deserialize;
 use std::sync::Arc;
 
+use super::{html_escape, url_encode};
+use super::batch::{describe_selector, labels_html, parse_selector, results_html, SelectQuery, SELECTOR_HELP};
+use super::stream::{inventory_sse, log_sse, CommandSse, LOG_VIEWER_JS, STREAM_JS};
 use crate::AppState;
 
+#[derive(Deserialize)]
+pub struct FollowQuery {
+    #[serde(default)]
+    since: String,
+    #[serde(default)]
+    tail: String,
+}
+
+#[derive(Deserialize)]
+pub struct UsageQuery {
+    /// Comma-separated container names of a stack
+    #[serde(default)]
+    containers: String,
+}
+
+#[derive(Deserialize)]
+pub struct PruneForm {
+    #[serde(default)]
+    select: String,
+    kind: PruneKind,
+}
+
 /// Timestamp for console output
 fn timestamp() -> String {
     chrono::Local::now().format("%H:%M:%S").to_string()
@@ -34,16 +63,8 @@ fn console_line(status: &str, color: &str, message: &str, output: &str) -> Strin
     )
 }
 
-/// Simple HTML escaping
-fn html_escape(s: &str) -> String {
-    s.replace('&', "&amp;")
-        .replace('<', "&lt;")
-        .replace('>', "&gt;")
-        .replace('"', "&quot;")
-}
-
 /// Docker dashboard
-pub async fn dashboard(State(_state): State<Arc<AppState>>) -> Html<String> {
+pub async fn dashboard(Query(query): Query<SelectQuery>) -> Html<String> {
     Html(format!(
         r##"<!DOCTYPE html>
 <html lang="en">
@@ -70,30 +91,105 @@ pub async fn dashboard(State(_state): State<Arc<AppState>>) -> Html<String> {
     </nav>
 
     <main class="container mx-auto px-6 py-8">
-        <h2 class="text-xl font-semibold mb-6">Docker Container Management</h2>
+        <div class="flex items-center justify-between mb-6">
+            <h2 class="text-xl font-semibold">Docker Container Management</h2>
+            <a href="/docker/problems?select={select_query}" class="px-3 py-1 bg-amber-700 hover:bg-amber-600 rounded text-sm">Problems</a>
+        </div>
+
+        <form id="node-filter" class="bg-gray-800 p-4 rounded-lg mb-6" method="get" action="/docker">
+            <div class="flex flex-wrap gap-2">
+                <input name="select" value="{select}" placeholder="Filter nodes: tag:lxc,!critical"
+                       class="flex-1 px-3 py-2 bg-gray-900 rounded font-mono">
+                <button class="px-4 py-2 bg-blue-600 hover:bg-blue-700 rounded">Filter</button>
+                <select name="kind" class="px-3 py-2 bg-gray-900 rounded">{kinds}</select>
+                <button hx-post="/docker/prune" hx-include="#node-filter" hx-target="#console-output" hx-swap="beforeend"
+                        hx-confirm="Prune on every node the filter selects?"
+                        class="px-4 py-2 bg-red-700 hover:bg-red-600 rounded">Prune</button>
+            </div>
+            {help}
+        </form>
 
-        <div id="docker-summary" hx-get="/docker/summary" hx-trigger="load, every 60s" hx-swap="innerHTML">
+        <div id="docker-summary" hx-get="/docker/summary?select={select_query}" hx-trigger="load, every 60s" hx-swap="innerHTML">
             <div class="text-gray-500">Loading nodes...</div>
         </div>
+
+        <div class="mt-6">
+            <div class="flex items-center justify-between mb-2">
+                <h3 class="text-lg font-semibold">Console Output</h3>
+                <button onclick="document.getElementById('console-output').innerHTML=''"
+                        class="px-3 py-1 bg-gray-700 hover:bg-gray-600 rounded text-sm">Clear</button>
+            </div>
+            <div id="console-output"
+                 class="bg-gray-900 rounded-lg p-4 font-mono text-sm overflow-auto max-h-96 min-h-[4rem] border border-gray-700">
+                <div class="text-gray-600">Console ready. Operations will appear here.</div>
+            </div>
+        </div>
     </main>
 </body>
-</html>"##
+</html>"##,
+        select = html_escape(&query.select),
+        select_query = url_encode(&query.select),
+        kinds = PruneKind::all()
+            .iter()
+            .map(|k| format!(r##"<option value="{k}">{k}</option>"##, k = k))
+            .collect::<String>(),
+        help = SELECTOR_HELP,
     ))
 }
 
 /// Docker summary fragment (auto-refreshed)
-pub async fn summary_fragment(State(state): State<Arc<AppState>>) -> Html<String> {
-    let nodes_html = state
-        .config
-        .docker_nodes
+///
+/// All nodes are queried at the same time, so one slow or offline node
+/// doesn't hold up the rest of the page.
+pub async fn summary_fragment(State(state): State<Arc<AppState>>, Query(query): Query<SelectQuery>) -> Html<String> {
+    let config = state.config.load();
+    let selector = match parse_selector(&query.select) {
+        Ok(selector) => selector,
+        Err(html) => return Html(html),
+    };
+    let nodes: Vec<_> = config.select_docker_nodes(&selector).into_iter().cloned().collect();
+    if nodes.is_empty() {
+        return Html("<div class='text-gray-500'>No docker node matches the filter</div>".to_string());
+    }
+    let summaries = control_panel_core::docker::commands::get_all_node_summaries(&state.ssh_pool, &nodes).await;
+
+    let nodes_html = summaries
         .iter()
-        .map(|node| {
+        .map(|summary| {
+            let status_html = if summary.online {
+                let problems = match summary.problems {
+                    0 => String::new(),
+                    n => format!(r##" <span class="text-amber-400 ml-2">{} with problems</span>"##, n),
+                };
+                format!(
+                    r##"<span class="text-green-500">{} running</span>
+                    <span class="text-gray-500">/ {} total</span>{}"##,
+                    summary.running, summary.total, problems
+                )
+            } else {
+                r##"<span class="text-red-500">Offline</span>"##.to_string()
+            };
+            // Show which address answered when the node has several
+            let host = match summary.address {
+                Some(ref address) if *address != summary.host => {
+                    format!("{} <span class=\"text-xs text-blue-400\">via {}</span>", summary.host, html_escape(address))
+                }
+                _ => summary.host.clone(),
+            };
             format!(
-                r##"<a href="/docker/{}" class="bg-gray-800 p-4 rounded-lg hover:bg-gray-700 transition">
-                    <h3 class="text-lg font-semibold">{}</h3>
-                    <p class="text-gray-400">{}</p>
+                r##"<a href="/docker/{name}" class="bg-gray-800 p-4 rounded-lg hover:bg-gray-700 transition">
+                    <h3 class="text-lg font-semibold">{name}</h3>
+                    <p class="text-gray-400">{host}</p>
+                    <p class="text-sm mt-2">{status}</p>
+                    {labels}
                 </a>"##,
-                node.name, node.name, node.host
+                name = summary.name,
+                host = host,
+                status = status_html,
+                labels = config
+                    .get_docker_node(&summary.name)
+                    .map(|n| labels_html(&config.node_labels(n)))
+                    .unwrap_or_default(),
             )
         })
         .collect::<Vec<_>>()
@@ -105,6 +201,131 @@ pub async fn summary_fragment(State(state): State<Arc<AppState>>) -> Html<String
     ))
 }
 
+/// Problems view: unhealthy, restart-looping and OOM-killed containers on
+/// every node (or those a selector matches)
+pub async fn problems_page(Query(query): Query<SelectQuery>) -> Html<String> {
+    Html(format!(
+        r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Problems - Docker</title>
+    <script src="https://unpkg.com/htmx.org@1.9.10"></script>
+    <script src="https://cdn.tailwindcss.com"></script>
+    <style>body {{ background-color: #1a1a2e; color: #eee; }}</style>
+</head>
+<body class="min-h-screen">
+    <nav class="bg-gray-800 border-b border-gray-700 px-6 py-4">
+        <div class="flex items-center justify-between">
+            <h1 class="text-2xl font-bold text-blue-400">NixOS Control Panel</h1>
+            <div class="flex gap-4">
+                <a href="/" class="text-gray-400 hover:text-gray-300">Infrastructure</a>
+                <a href="/docker" class="text-blue-400">Docker</a>
+                <a href="/proxmox" class="text-gray-400 hover:text-gray-300">Proxmox</a>
+                <a href="/monitoring" class="text-gray-400 hover:text-gray-300">Monitoring</a>
+                <a href="/editor" class="text-gray-400 hover:text-gray-300">Editor</a>
+            </div>
+        </div>
+    </nav>
+
+    <main class="container mx-auto px-6 py-8">
+        <div class="flex items-center gap-4 mb-2">
+            <a href="/docker?select={select_query}" class="text-gray-400 hover:text-gray-300">&larr; Back</a>
+            <h2 class="text-xl font-semibold">Container Problems</h2>
+        </div>
+        <p class="text-gray-400 text-sm mb-6">Failing healthchecks, containers in a restart loop and out-of-memory kills of the last day.</p>
+
+        <div hx-get="/docker/problems/list?select={select_query}" hx-trigger="load, every 60s" hx-swap="innerHTML">
+            <div class="text-gray-500">Checking nodes...</div>
+        </div>
+    </main>
+</body>
+</html>"##,
+        select_query = url_encode(&query.select),
+    ))
+}
+
+/// Problems of the selected nodes (auto-refreshed)
+pub async fn problems_fragment(State(state): State<Arc<AppState>>, Query(query): Query<SelectQuery>) -> Html<String> {
+    let config = state.config.load();
+    let selector = match parse_selector(&query.select) {
+        Ok(selector) => selector,
+        Err(html) => return Html(html),
+    };
+    let nodes: Vec<_> = config.select_docker_nodes(&selector).into_iter().cloned().collect();
+    let results = control_panel_core::docker::problems::find_problems(&state.ssh_pool, &nodes).await;
+
+    let rows = results
+        .iter()
+        .flat_map(|node| {
+            let error = node.error.as_ref().map(|e| {
+                format!(
+                    r##"<tr class="border-t border-gray-700">
+                        <td class="py-2 font-semibold">{node}</td>
+                        <td class="py-2" colspan="3"><span class="text-red-400">Unreachable:</span> <span class="text-gray-400 text-sm">{error}</span></td>
+                    </tr>"##,
+                    node = html_escape(&node.node),
+                    error = html_escape(e),
+                )
+            });
+            let problems = node.problems.iter().map(|p| {
+                let color = match p.kind {
+                    ProblemKind::Unhealthy => "text-amber-400",
+                    ProblemKind::RestartLoop | ProblemKind::OomKilled => "text-red-400",
+                };
+                format!(
+                    r##"<tr class="border-t border-gray-700">
+                        <td class="py-2"><a href="/docker/{node}" class="font-semibold hover:underline">{node}</a></td>
+                        <td class="py-2">{container} <span class="text-gray-500 text-sm">{project}</span></td>
+                        <td class="py-2 {color}">{kind}</td>
+                        <td class="py-2 text-gray-400 text-sm">{detail}</td>
+                    </tr>"##,
+                    node = html_escape(&node.node),
+                    container = html_escape(&p.container),
+                    project = html_escape(p.project.as_deref().unwrap_or("")),
+                    color = color,
+                    kind = p.kind.display(),
+                    detail = html_escape(&p.detail),
+                )
+            });
+            error.into_iter().chain(problems)
+        })
+        .collect::<String>();
+
+    if rows.is_empty() {
+        return Html(format!(
+            "<div class='text-green-400'>No problems on {} nodes</div>",
+            results.len()
+        ));
+    }
+    Html(format!(
+        r##"<div class="bg-gray-800 p-4 rounded-lg">
+            <table class="w-full">
+                <tr class="text-left text-gray-400 text-sm"><th>Node</th><th>Container</th><th>Problem</th><th>Detail</th></tr>
+                {rows}
+            </table>
+        </div>"##,
+        rows = rows,
+    ))
+}
+
+/// Prune on every node the filter selects
+pub async fn prune(State(state): State<Arc<AppState>>, Form(form): Form<PruneForm>) -> Html<String> {
+    let config = state.config.load();
+    let selector = match parse_selector(&form.select) {
+        Ok(selector) => selector,
+        Err(html) => return Html(html),
+    };
+    let nodes: Vec<&str> = config.select_docker_nodes(&selector).iter().map(|n| n.name.as_str()).collect();
+
+    let results = control_panel_core::batch::prune(&state.ssh_pool, &nodes, form.kind).await;
+    Html(results_html(&format!("Prune {} on {}", form.kind, describe_selector(&selector)), &results, |result| match result {
+        Ok(output) => ("OK", "text-green-400", output.clone()),
+        Err(e) => ("FAIL", "text-red-400", e.to_string()),
+    }))
+}
+
 /// Node containers list
 pub async fn node_containers(
     State(_state): State<Arc<AppState>>,
@@ -120,6 +341,7 @@ pub async fn node_containers(
     <script src="https://unpkg.com/htmx.org@1.9.10"></script>
     <script src="https://cdn.tailwindcss.com"></script>
     <style>body {{ background-color: #1a1a2e; color: #eee; }}</style>
+    {stream_js}
 </head>
 <body class="min-h-screen">
     <nav class="bg-gray-800 border-b border-gray-700 px-6 py-4">
@@ -139,11 +361,40 @@ pub async fn node_containers(
         <div class="flex items-center gap-4 mb-6">
             <a href="/docker" class="text-gray-400 hover:text-gray-300">&larr; Back</a>
             <h2 class="text-xl font-semibold">{node}</h2>
+            <span id="inventory-status" class="text-sm text-gray-500"></span>
         </div>
 
-        <div id="container-list" hx-get="/docker/{node}/containers" hx-trigger="load, every 60s" hx-swap="innerHTML">
+        <div id="container-details"></div>
+
+        <div id="container-list" hx-get="/docker/{node}/containers" hx-trigger="load, inventory" hx-swap="innerHTML">
             <div class="text-gray-500">Loading containers...</div>
         </div>
+        <script>
+        // Reload the list when docker events change it; poll as before while
+        // the node's event stream (or this page's connection) is down
+        (() => {{
+            const list = document.getElementById('container-list');
+            const status = document.getElementById('inventory-status');
+            const refresh = () => htmx.trigger(list, 'inventory');
+            let poll = null, connected = false;
+            const polling = on => {{
+                if (on && !poll) poll = setInterval(refresh, 60000);
+                if (!on && poll) {{ clearInterval(poll); poll = null; }}
+                status.textContent = on ? 'polling every 60s' : '● live';
+                status.className = on ? 'text-sm text-gray-500' : 'text-sm text-green-500';
+            }};
+            const events = new EventSource('/docker/{node_url}/events');
+            events.addEventListener('changed', e => {{
+                const change = JSON.parse(e.data);
+                polling(!change.live);
+                // The first event is the current state, which the list
+                // already shows unless the connection dropped before
+                if (connected) refresh();
+                connected = true;
+            }});
+            events.onerror = () => polling(true);
+        }})();
+        </script>
 
         <!-- Console Output Panel -->
         <div class="mt-6">
@@ -160,29 +411,34 @@ pub async fn node_containers(
     </main>
 </body>
 </html>"##,
-        node = node
+        node = node,
+        node_url = url_encode(&node),
+        stream_js = STREAM_JS,
     ))
 }
 
-/// Containers list fragment (auto-refreshed)
+/// Containers list fragment (reloaded on docker events, else polled)
 pub async fn containers_fragment(
     State(state): State<Arc<AppState>>,
     Path(node): Path<String>,
 ) -> Html<String> {
-    let mut ssh_pool = state.ssh_pool.write().await;
-
-    let containers = match control_panel_core::docker::commands::list_containers(&mut ssh_pool, &node).await {
-        Ok(c) => c,
-        Err(e) => {
-            return Html(format!(
-                "<div class='text-red-500'>Error loading containers: {}</div>",
-                html_escape(&e.to_string())
-            ))
-        }
+
+    // From the event-fed cache while the node's stream is up
+    let containers = match state.inventory.containers(&node) {
+        Some(containers) => containers,
+        None => match control_panel_core::docker::commands::list_containers(&state.ssh_pool, &node).await {
+            Ok(c) => c,
+            Err(e) => return Html(super::ssh::error_html(&e)),
+        },
     };
 
     let stacks = control_panel_core::docker::commands::group_by_stack(containers);
 
+    // Usage comes from the collector's samples; the stack headers poll for
+    // new ones without listing the containers again
+    let config = state.config.load();
+    let usage = config.stats.enabled.then(|| state.stats.node(&node).unwrap_or_default());
+
     let html = stacks
         .iter()
         .map(|stack| {
@@ -199,18 +455,33 @@ pub async fn containers_fragment(
                         r##"<div class="flex items-center justify-between p-2 bg-gray-700 rounded">
                             <div>
                                 <span class="{status_color}">{name}</span>
+                                {health}
                                 <span class="text-gray-400 ml-2 text-sm">{image}</span>
+                                <div class="text-gray-500 text-xs mt-1">{meta}</div>
+                                <div id="{usage_id}" class="text-xs mt-1">{usage}</div>
                             </div>
                             <div class="flex gap-1">
                                 <button hx-post="/docker/{node}/{name}/start" hx-target="#console-output" hx-swap="beforeend" class="px-2 py-1 bg-green-700 hover:bg-green-600 rounded text-xs">Start</button>
                                 <button hx-post="/docker/{node}/{name}/stop" hx-target="#console-output" hx-swap="beforeend" class="px-2 py-1 bg-red-700 hover:bg-red-600 rounded text-xs">Stop</button>
                                 <button hx-post="/docker/{node}/{name}/restart" hx-target="#console-output" hx-swap="beforeend" class="px-2 py-1 bg-blue-700 hover:bg-blue-600 rounded text-xs">Restart</button>
+                                <button onclick="streamTo('/docker/{node}/{name}/pull/stream', 'console-output', 'Pull {name} on {node}')" class="px-2 py-1 bg-purple-700 hover:bg-purple-600 rounded text-xs">Pull</button>
                                 <button hx-get="/docker/{node}/{name}/logs" hx-target="#console-output" hx-swap="beforeend" class="px-2 py-1 bg-gray-600 hover:bg-gray-500 rounded text-xs">Logs</button>
+                                <a href="/docker/{node}/{name}/logs/follow" target="_blank" class="px-2 py-1 bg-gray-600 hover:bg-gray-500 rounded text-xs">Follow</a>
+                                <button hx-get="/docker/{node}/{name}/inspect" hx-target="#container-details" class="px-2 py-1 bg-gray-600 hover:bg-gray-500 rounded text-xs">Details</button>
+                                <a href="/terminal/docker/{node}/{name}" target="_blank" class="px-2 py-1 bg-gray-900 hover:bg-gray-800 rounded text-xs">Shell</a>
                             </div>
                         </div>"##,
                         status_color = status_color,
                         name = c.name,
+                        health = c.health.map(health_badge).unwrap_or_default(),
                         image = html_escape(&c.image),
+                        meta = html_escape(&container_meta(c)),
+                        usage_id = usage_id("container", &c.name),
+                        usage = usage
+                            .as_ref()
+                            .and_then(|u| u.container(&c.name))
+                            .map(|u| usage_html(u, false))
+                            .unwrap_or_default(),
                         node = node,
                     )
                 })
@@ -225,9 +496,10 @@ pub async fn containers_fragment(
                         <button hx-post="/docker/{node}/stack/{project}/stop" hx-target="#console-output" hx-swap="beforeend" class="px-2 py-1 bg-yellow-700 hover:bg-yellow-600 rounded text-xs">Stop</button>
                         <button hx-post="/docker/{node}/stack/{project}/restart" hx-target="#console-output" hx-swap="beforeend" class="px-2 py-1 bg-blue-700 hover:bg-blue-600 rounded text-xs">Restart</button>
                         <button hx-post="/docker/{node}/stack/{project}/pull" hx-target="#console-output" hx-swap="beforeend" class="px-2 py-1 bg-purple-700 hover:bg-purple-600 rounded text-xs">Pull</button>
-                        <button hx-post="/docker/{node}/stack/{project}/rebuild" hx-target="#console-output" hx-swap="beforeend" class="px-2 py-1 bg-orange-700 hover:bg-orange-600 rounded text-xs">Rebuild</button>
+                        <button onclick="streamTo('/docker/{node}/stack/{project}/rebuild/stream', 'console-output', 'Stack rebuild \'{project}\' on {node}')" class="px-2 py-1 bg-orange-700 hover:bg-orange-600 rounded text-xs">Rebuild</button>
                         <button hx-post="/docker/{node}/stack/{project}/down" hx-target="#console-output" hx-swap="beforeend" class="px-2 py-1 bg-red-800 hover:bg-red-700 rounded text-xs">Down</button>
                         <button hx-get="/docker/{node}/stack/{project}/logs" hx-target="#console-output" hx-swap="beforeend" class="px-2 py-1 bg-gray-600 hover:bg-gray-500 rounded text-xs">Logs</button>
+                        <a href="/docker/{node}/stack/{project}/logs/follow" target="_blank" class="px-2 py-1 bg-gray-600 hover:bg-gray-500 rounded text-xs">Follow</a>
                     </div>"##,
                     node = node,
                     project = stack.name,
@@ -236,10 +508,25 @@ pub async fn containers_fragment(
                 String::new()
             };
 
+            let stack_usage = match usage {
+                Some(ref usage) => {
+                    let names: Vec<&str> = stack.containers.iter().map(|c| c.name.as_str()).collect();
+                    format!(
+                        r##"<span class="text-xs" hx-get="/docker/{node}/usage?containers={names}" hx-trigger="every {interval}s" hx-swap="innerHTML">{total}</span>"##,
+                        node = node,
+                        names = url_encode(&names.join(",")),
+                        interval = config.stats.interval_secs.max(5),
+                        total = usage.total(names.iter().copied()).map(|u| usage_html(&u, true)).unwrap_or_default(),
+                    )
+                }
+                None => String::new(),
+            };
+
             format!(
                 r##"<div class="bg-gray-800 p-4 rounded-lg mb-4">
                     <div class="flex items-center gap-4 mb-2">
                         <h3 class="text-lg font-semibold">{name} ({running}/{total})</h3>
+                        {stack_usage}
                         {stack_actions}
                     </div>
                     <div class="space-y-2">{containers_html}</div>
@@ -247,6 +534,7 @@ pub async fn containers_fragment(
                 name = stack.name,
                 running = stack.running_count,
                 total = stack.total_count,
+                stack_usage = stack_usage,
                 stack_actions = stack_actions,
                 containers_html = containers_html,
             )
@@ -254,17 +542,304 @@ pub async fn containers_fragment(
         .collect::<Vec<_>>()
         .join("\n");
 
+    Html(format!("{}{}", usage.as_ref().map(node_usage_html).unwrap_or_default(), html))
+}
+
+/// Changes of a node's containers (SSE); the node page reloads its list on
+/// each
+pub async fn inventory_events(State(state): State<Arc<AppState>>, Path(node): Path<String>) -> CommandSse {
+    inventory_sse(state.inventory.changes(Some(&node)))
+}
+
+/// A stack's total usage, plus its containers' rows swapped out of band
+/// (polled from the stack header)
+pub async fn usage_fragment(
+    State(state): State<Arc<AppState>>,
+    Path(node): Path<String>,
+    Query(query): Query<UsageQuery>,
+) -> Html<String> {
+    let usage = state.stats.node(&node).unwrap_or_default();
+    let names: Vec<&str> = query.containers.split(',').filter(|n| !n.is_empty()).collect();
+
+    let mut html = usage.total(names.iter().copied()).map(|u| usage_html(&u, true)).unwrap_or_default();
+    for name in names {
+        html.push_str(&format!(
+            r##"<div id="{}" hx-swap-oob="innerHTML">{}</div>"##,
+            usage_id("container", name),
+            usage.container(name).map(|u| usage_html(u, false)).unwrap_or_default(),
+        ));
+    }
     Html(html)
 }
 
+/// Element id of a usage line (ids must also work as CSS selectors)
+fn usage_id(kind: &str, name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    format!("usage-{}-{}", kind, name)
+}
+
+/// When the node was last sampled, and which container uses the most memory
+fn node_usage_html(usage: &NodeUsage) -> String {
+    let mut parts = Vec::new();
+    if let Some(updated) = usage.updated {
+        parts.push(format!("Usage sampled {}", updated.with_timezone(&chrono::Local).format("%H:%M:%S")));
+    }
+    if let Some((name, top)) = usage.top_memory() {
+        parts.push(format!("most memory: {} ({})", html_escape(name), format_size(top.memory_bytes)));
+    }
+    let error = usage
+        .error
+        .as_ref()
+        .map(|e| format!(r##" <span class="text-yellow-500">Last sample failed: {}</span>"##, html_escape(e)))
+        .unwrap_or_default();
+    if parts.is_empty() && error.is_empty() {
+        return String::new();
+    }
+    format!(r##"<div class="text-gray-400 text-sm mb-4">{}{}</div>"##, parts.join(" · "), error)
+}
+
+/// CPU, memory, network and block IO of a container, or a stack's total
+fn usage_html(u: &Usage, stack: bool) -> String {
+    let label = |text: &str| format!(r##"<span class="text-gray-500">{}</span>"##, text);
+    let cpu = format!("{} {:.1}%{}", label("CPU"), u.cpu_percent, sparkline(&u.cpu_history, "#60a5fa"));
+    let memory = format!(
+        "{} {} ({:.1}%){}",
+        label("Mem"),
+        format_size(u.memory_bytes),
+        u.memory_percent(),
+        sparkline(&u.memory_history, "#a78bfa")
+    );
+    let (net, block) = if stack {
+        (
+            format!("{} {}/s{}", label("Net"), format_size(u.net_rate as u64), sparkline(&u.net_history, "#34d399")),
+            format!("{} {}/s{}", label("Block"), format_size(u.block_rate as u64), sparkline(&u.block_history, "#fbbf24")),
+        )
+    } else {
+        (
+            format!(
+                "{} ↓{} ↑{} ({}/s){}",
+                label("Net"),
+                format_size(u.net_rx),
+                format_size(u.net_tx),
+                format_size(u.net_rate as u64),
+                sparkline(&u.net_history, "#34d399")
+            ),
+            format!(
+                "{} r {} w {} ({}/s){}",
+                label("Block"),
+                format_size(u.block_read),
+                format_size(u.block_write),
+                format_size(u.block_rate as u64),
+                sparkline(&u.block_history, "#fbbf24")
+            ),
+        )
+    };
+    format!(
+        r##"<span class="inline-flex flex-wrap items-center gap-x-3 text-gray-300">{}<span>{}</span><span>{}</span><span>{}</span><span>{}</span></span>"##,
+        if stack { label(&format!("{} running:", u.containers)) } else { String::new() },
+        cpu,
+        memory,
+        net,
+        block
+    )
+}
+
+/// Inline SVG line of the history, scaled to its largest value
+fn sparkline(values: &[f64], color: &str) -> String {
+    const WIDTH: f64 = 60.0;
+    const HEIGHT: f64 = 14.0;
+    if values.len() < 2 {
+        return String::new();
+    }
+    let max = values.iter().copied().fold(0.0, f64::max);
+    let step = WIDTH / (values.len() - 1) as f64;
+    let points: Vec<String> = values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| {
+            let y = if max > 0.0 { HEIGHT - v / max * (HEIGHT - 1.0) } else { HEIGHT };
+            format!("{:.1},{:.1}", i as f64 * step, y)
+        })
+        .collect();
+    format!(
+        r##"<svg class="inline-block align-middle ml-1" width="60" height="14" viewBox="0 0 60 14"><polyline fill="none" stroke="{}" stroke-width="1.5" points="{}"/></svg>"##,
+        color,
+        points.join(" ")
+    )
+}
+
+/// Human readable size
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Health as a small colored badge
+fn health_badge(health: HealthStatus) -> String {
+    let color = match health {
+        HealthStatus::Healthy => "bg-green-800",
+        HealthStatus::Starting => "bg-yellow-700",
+        HealthStatus::Unhealthy => "bg-red-700",
+    };
+    format!(r##"<span class="px-1 ml-1 rounded text-xs {}">{}</span>"##, color, health.display())
+}
+
+/// Status, published ports, creation time and restart policy of a row
+fn container_meta(c: &Container) -> String {
+    let mut meta = vec![c.status_text.clone()];
+    let ports = c.published_ports();
+    if !ports.is_empty() {
+        meta.push(ports.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", "));
+    }
+    if let Some(created) = c.created {
+        meta.push(format!("created {}", created.format("%Y-%m-%d %H:%M")));
+    }
+    if let Some(ref policy) = c.restart_policy {
+        meta.push(format!("restart {}", policy));
+    }
+    if c.restart_count > 0 {
+        let last_exit = c.exit_code.map(|code| format!(", last exit code {}", code)).unwrap_or_default();
+        meta.push(format!("{} restarts{}", c.restart_count, last_exit));
+    }
+    meta.join(" · ")
+}
+
+/// Container detail panel from `docker inspect`
+pub async fn container_details(
+    State(state): State<Arc<AppState>>,
+    Path((node, container)): Path<(String, String)>,
+) -> Html<String> {
+    let details = match control_panel_core::docker::commands::inspect_container(&state.ssh_pool, &node, &container).await {
+        Ok(details) => details,
+        Err(e) => return Html(super::ssh::error_html(&e)),
+    };
+
+    let time = |t: Option<chrono::DateTime<chrono::Utc>>| {
+        t.map(|t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string()).unwrap_or_else(|| "-".to_string())
+    };
+    let row = |label: &str, value: String| {
+        format!(
+            r##"<tr><td class="pr-4 py-1 text-gray-400 align-top whitespace-nowrap">{}</td><td class="py-1 font-mono text-sm break-all">{}</td></tr>"##,
+            label, value
+        )
+    };
+    let list = |items: Vec<String>| {
+        if items.is_empty() {
+            "-".to_string()
+        } else {
+            items.iter().map(|i| html_escape(i)).collect::<Vec<_>>().join("<br>")
+        }
+    };
+
+    let mut state_text = details.status.display().to_string();
+    if !details.status.is_running() {
+        state_text.push_str(&format!(" (exit code {})", details.exit_code));
+    }
+    if details.oom_killed {
+        state_text.push_str(", killed: out of memory");
+    }
+
+    let health = match details.health {
+        Some(ref health) => {
+            let probes = health
+                .log
+                .iter()
+                .rev()
+                .map(|p| format!("{} exit {}: {}", time(p.start), p.exit_code, p.output))
+                .collect();
+            format!(
+                "{} (failing streak {})<br>{}",
+                health_badge(health.status),
+                health.failing_streak,
+                list(probes)
+            )
+        }
+        None => "no healthcheck".to_string(),
+    };
+
+    let rows = [
+        row("State", html_escape(&state_text)),
+        row("Error", html_escape(details.error.as_deref().unwrap_or("-"))),
+        row("Health", health),
+        row("Image", html_escape(&details.image)),
+        row("Image ID", html_escape(&details.image_id)),
+        row("Digests", list(details.repo_digests.clone())),
+        row("Created", time(details.created)),
+        row("Started", time(details.started_at)),
+        row("Finished", time(details.finished_at)),
+        row("Restarts", format!("{} (policy {})", details.restart_count, html_escape(&details.restart_policy.to_string()))),
+        row("Command", html_escape(&details.command.join(" "))),
+        row("Working dir", html_escape(details.working_dir.as_deref().unwrap_or("-"))),
+        row("Ports", list(details.ports.iter().map(|p| p.to_string()).collect())),
+        row(
+            "Mounts",
+            list(
+                details
+                    .mounts
+                    .iter()
+                    .map(|m| {
+                        format!(
+                            "{} {} -> {}{}",
+                            m.kind,
+                            m.name.as_deref().unwrap_or(&m.source),
+                            m.destination,
+                            if m.read_only { " (ro)" } else { "" }
+                        )
+                    })
+                    .collect(),
+            ),
+        ),
+        row(
+            "Networks",
+            list(
+                details
+                    .networks
+                    .iter()
+                    .map(|n| format!("{} {} ({})", n.name, n.ip_address.as_deref().unwrap_or("-"), n.aliases.join(", ")))
+                    .collect(),
+            ),
+        ),
+        row("Environment", list(details.env.clone())),
+        row("Labels", list(details.labels.iter().map(|(k, v)| format!("{}={}", k, v)).collect())),
+    ]
+    .concat();
+
+    Html(format!(
+        r##"<div class="bg-gray-800 p-4 rounded-lg mb-6 border border-gray-600">
+            <div class="flex items-center justify-between mb-2">
+                <h3 class="text-lg font-semibold">{name} <span class="text-gray-500 text-sm font-mono">{id}</span></h3>
+                <button onclick="document.getElementById('container-details').innerHTML=''"
+                        class="px-3 py-1 bg-gray-700 hover:bg-gray-600 rounded text-sm">Close</button>
+            </div>
+            <table>{rows}</table>
+            <p class="text-gray-500 text-xs mt-2">Environment values are not shown.</p>
+        </div>"##,
+        name = html_escape(&details.name),
+        id = html_escape(&details.id[..details.id.len().min(12)]),
+        rows = rows,
+    ))
+}
+
 /// Start a container
 pub async fn start_container(
     State(state): State<Arc<AppState>>,
     Path((node, container)): Path<(String, String)>,
 ) -> Html<String> {
-    let mut ssh_pool = state.ssh_pool.write().await;
 
-    match control_panel_core::docker::commands::start_container(&mut ssh_pool, &node, &container).await {
+    match control_panel_core::docker::commands::start_container(&state.ssh_pool, &node, &container).await {
         Ok(_) => Html(console_line("OK", "text-green-400", &format!("Start {} on {}", container, node), "")),
         Err(e) => Html(console_line("FAIL", "text-red-400", &format!("Start {} on {}", container, node), &e.to_string())),
     }
@@ -275,9 +850,8 @@ pub async fn stop_container(
     State(state): State<Arc<AppState>>,
     Path((node, container)): Path<(String, String)>,
 ) -> Html<String> {
-    let mut ssh_pool = state.ssh_pool.write().await;
 
-    match control_panel_core::docker::commands::stop_container(&mut ssh_pool, &node, &container).await {
+    match control_panel_core::docker::commands::stop_container(&state.ssh_pool, &node, &container).await {
         Ok(_) => Html(console_line("OK", "text-green-400", &format!("Stop {} on {}", container, node), "")),
         Err(e) => Html(console_line("FAIL", "text-red-400", &format!("Stop {} on {}", container, node), &e.to_string())),
     }
@@ -288,9 +862,8 @@ pub async fn restart_container(
     State(state): State<Arc<AppState>>,
     Path((node, container)): Path<(String, String)>,
 ) -> Html<String> {
-    let mut ssh_pool = state.ssh_pool.write().await;
 
-    match control_panel_core::docker::commands::restart_container(&mut ssh_pool, &node, &container).await {
+    match control_panel_core::docker::commands::restart_container(&state.ssh_pool, &node, &container).await {
         Ok(_) => Html(console_line("OK", "text-green-400", &format!("Restart {} on {}", container, node), "")),
         Err(e) => Html(console_line("FAIL", "text-red-400", &format!("Restart {} on {}", container, node), &e.to_string())),
     }
@@ -301,14 +874,22 @@ pub async fn container_logs(
     State(state): State<Arc<AppState>>,
     Path((node, container)): Path<(String, String)>,
 ) -> Html<String> {
-    let mut ssh_pool = state.ssh_pool.write().await;
 
-    match control_panel_core::docker::commands::get_container_logs(&mut ssh_pool, &node, &container, 100).await {
+    match control_panel_core::docker::commands::get_container_logs(&state.ssh_pool, &node, &container, 100).await {
         Ok(logs) => Html(console_line("LOGS", "text-cyan-400", &format!("{} on {}", container, node), &logs)),
         Err(e) => Html(console_line("FAIL", "text-red-400", &format!("Logs {} on {}", container, node), &e.to_string())),
     }
 }
 
+/// Start pulling a container's image; returns the job id to stream from
+pub async fn pull_container_stream(
+    State(state): State<Arc<AppState>>,
+    Path((node, container)): Path<(String, String)>,
+) -> String {
+    let output = control_panel_core::docker::commands::pull_container_stream(&state.ssh_pool, &node, &container).await;
+    state.jobs.start(format!("Pull {} on {}", container, node), output)
+}
+
 // ============================================================================
 // Docker Compose Stack Operations
 // ============================================================================
@@ -318,9 +899,8 @@ pub async fn stack_up(
     State(state): State<Arc<AppState>>,
     Path((node, project)): Path<(String, String)>,
 ) -> Html<String> {
-    let mut ssh_pool = state.ssh_pool.write().await;
 
-    match control_panel_core::docker::commands::stack_up(&mut ssh_pool, &node, &project).await {
+    match control_panel_core::docker::commands::stack_up(&state.ssh_pool, &node, &project).await {
         Ok(output) => Html(console_line("OK", "text-green-400", &format!("Stack up '{}' on {}", project, node), &output)),
         Err(e) => Html(console_line("FAIL", "text-red-400", &format!("Stack up '{}' on {}", project, node), &e.to_string())),
     }
@@ -331,9 +911,8 @@ pub async fn stack_stop(
     State(state): State<Arc<AppState>>,
     Path((node, project)): Path<(String, String)>,
 ) -> Html<String> {
-    let mut ssh_pool = state.ssh_pool.write().await;
 
-    match control_panel_core::docker::commands::stack_stop(&mut ssh_pool, &node, &project).await {
+    match control_panel_core::docker::commands::stack_stop(&state.ssh_pool, &node, &project).await {
         Ok(output) => Html(console_line("OK", "text-green-400", &format!("Stack stop '{}' on {}", project, node), &output)),
         Err(e) => Html(console_line("FAIL", "text-red-400", &format!("Stack stop '{}' on {}", project, node), &e.to_string())),
     }
@@ -344,9 +923,8 @@ pub async fn stack_down(
     State(state): State<Arc<AppState>>,
     Path((node, project)): Path<(String, String)>,
 ) -> Html<String> {
-    let mut ssh_pool = state.ssh_pool.write().await;
 
-    match control_panel_core::docker::commands::stack_down(&mut ssh_pool, &node, &project).await {
+    match control_panel_core::docker::commands::stack_down(&state.ssh_pool, &node, &project).await {
         Ok(output) => Html(console_line("OK", "text-green-400", &format!("Stack down '{}' on {}", project, node), &output)),
         Err(e) => Html(console_line("FAIL", "text-red-400", &format!("Stack down '{}' on {}", project, node), &e.to_string())),
     }
@@ -357,9 +935,8 @@ pub async fn stack_restart(
     State(state): State<Arc<AppState>>,
     Path((node, project)): Path<(String, String)>,
 ) -> Html<String> {
-    let mut ssh_pool = state.ssh_pool.write().await;
 
-    match control_panel_core::docker::commands::stack_restart(&mut ssh_pool, &node, &project).await {
+    match control_panel_core::docker::commands::stack_restart(&state.ssh_pool, &node, &project).await {
         Ok(output) => Html(console_line("OK", "text-green-400", &format!("Stack restart '{}' on {}", project, node), &output)),
         Err(e) => Html(console_line("FAIL", "text-red-400", &format!("Stack restart '{}' on {}", project, node), &e.to_string())),
     }
@@ -370,22 +947,29 @@ pub async fn stack_rebuild(
     State(state): State<Arc<AppState>>,
     Path((node, project)): Path<(String, String)>,
 ) -> Html<String> {
-    let mut ssh_pool = state.ssh_pool.write().await;
 
-    match control_panel_core::docker::commands::stack_rebuild(&mut ssh_pool, &node, &project).await {
+    match control_panel_core::docker::commands::stack_rebuild(&state.ssh_pool, &node, &project).await {
         Ok(output) => Html(console_line("OK", "text-green-400", &format!("Stack rebuild '{}' on {}", project, node), &output)),
         Err(e) => Html(console_line("FAIL", "text-red-400", &format!("Stack rebuild '{}' on {}", project, node), &e.to_string())),
     }
 }
 
+/// Start a stack rebuild; returns the job id to stream from
+pub async fn stack_rebuild_stream(
+    State(state): State<Arc<AppState>>,
+    Path((node, project)): Path<(String, String)>,
+) -> String {
+    let output = control_panel_core::docker::commands::stack_rebuild_stream(&state.ssh_pool, &node, &project).await;
+    state.jobs.start(format!("Stack rebuild '{}' on {}", project, node), output)
+}
+
 /// Stack pull
 pub async fn stack_pull(
     State(state): State<Arc<AppState>>,
     Path((node, project)): Path<(String, String)>,
 ) -> Html<String> {
-    let mut ssh_pool = state.ssh_pool.write().await;
 
-    match control_panel_core::docker::commands::stack_pull(&mut ssh_pool, &node, &project).await {
+    match control_panel_core::docker::commands::stack_pull(&state.ssh_pool, &node, &project).await {
         Ok(output) => Html(console_line("OK", "text-green-400", &format!("Stack pull '{}' on {}", project, node), &output)),
         Err(e) => Html(console_line("FAIL", "text-red-400", &format!("Stack pull '{}' on {}", project, node), &e.to_string())),
     }
@@ -396,10 +980,103 @@ pub async fn stack_logs(
     State(state): State<Arc<AppState>>,
     Path((node, project)): Path<(String, String)>,
 ) -> Html<String> {
-    let mut ssh_pool = state.ssh_pool.write().await;
 
-    match control_panel_core::docker::commands::stack_logs(&mut ssh_pool, &node, &project, 100).await {
+    match control_panel_core::docker::commands::stack_logs(&state.ssh_pool, &node, &project, 100).await {
         Ok(logs) => Html(console_line("LOGS", "text-cyan-400", &format!("Stack '{}' on {}", project, node), &logs)),
         Err(e) => Html(console_line("FAIL", "text-red-400", &format!("Stack logs '{}' on {}", project, node), &e.to_string())),
     }
 }
+
+/// Live log viewer of a container
+pub async fn follow_container_logs_page(Path((node, container)): Path<(String, String)>) -> Html<String> {
+    Html(log_viewer_page(
+        &format!("{} on {}", container, node),
+        &format!("/docker/{}/{}/logs/stream", url_encode(&node), url_encode(&container)),
+        &container,
+        &node,
+    ))
+}
+
+/// Live log viewer of a compose stack
+pub async fn follow_stack_logs_page(Path((node, project)): Path<(String, String)>) -> Html<String> {
+    Html(log_viewer_page(
+        &format!("Stack {} on {}", project, node),
+        &format!("/docker/{}/stack/{}/logs/stream", url_encode(&node), url_encode(&project)),
+        &project,
+        &node,
+    ))
+}
+
+fn log_viewer_page(title: &str, stream_url: &str, file: &str, node: &str) -> String {
+    format!(
+        r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Logs: {title}</title>
+    <script src="https://cdn.tailwindcss.com"></script>
+    <style>body {{ background-color: #1a1a2e; color: #eee; }}</style>
+    {viewer_js}
+</head>
+<body class="min-h-screen">
+    <main class="px-6 py-4" id="log-viewer" data-stream="{stream_url}" data-file="{file}">
+        <div class="flex flex-wrap items-center gap-3 mb-3">
+            <a href="/docker/{node_url}" class="text-gray-400 hover:text-gray-300">&larr; {node}</a>
+            <h2 class="text-lg font-semibold">Logs: {title}</h2>
+            <span id="log-status" class="text-gray-400">CONNECTING</span>
+        </div>
+        <div class="flex flex-wrap items-center gap-2 mb-3 text-sm">
+            <input id="log-since" placeholder="since: 10m, 2h, 2025-01-13T10:00:00Z" class="px-2 py-1 bg-gray-900 rounded font-mono w-64">
+            <input id="log-tail" value="200" title="Lines of history (empty for all)" class="px-2 py-1 bg-gray-900 rounded font-mono w-20">
+            <button id="log-start" class="px-3 py-1 bg-green-700 hover:bg-green-600 rounded">Restart</button>
+            <button id="log-pause" class="px-3 py-1 bg-yellow-700 hover:bg-yellow-600 rounded">Pause</button>
+            <button id="log-stop" class="px-3 py-1 bg-red-700 hover:bg-red-600 rounded">Stop</button>
+            <input id="log-filter" placeholder="Filter" class="px-2 py-1 bg-gray-900 rounded w-48">
+            <label><input id="log-ts" type="checkbox" checked> Timestamps</label>
+            <label><input id="log-stdout" type="checkbox" checked> stdout</label>
+            <label><input id="log-stderr" type="checkbox" checked> stderr</label>
+            <label><input id="log-scroll" type="checkbox" checked> Follow</label>
+            <button id="log-clear" class="px-3 py-1 bg-gray-700 hover:bg-gray-600 rounded">Clear</button>
+            <button id="log-download" class="px-3 py-1 bg-blue-600 hover:bg-blue-700 rounded">Download</button>
+        </div>
+        <div id="log-output"
+             class="bg-gray-900 rounded-lg p-3 font-mono text-xs whitespace-pre-wrap overflow-auto border border-gray-700"
+             style="height: calc(100vh - 9rem)"></div>
+    </main>
+</body>
+</html>"##,
+        title = html_escape(title),
+        viewer_js = LOG_VIEWER_JS,
+        stream_url = html_escape(stream_url),
+        file = html_escape(file),
+        node = html_escape(node),
+        node_url = url_encode(node),
+    )
+}
+
+/// Follow a container's logs (SSE)
+pub async fn follow_container_logs(
+    State(state): State<Arc<AppState>>,
+    Path((node, container)): Path<(String, String)>,
+    Query(query): Query<FollowQuery>,
+) -> CommandSse {
+    let stream = match FollowOptions::parse(&query.since, &query.tail) {
+        Ok(options) => logs::follow_container_logs(&state.ssh_pool, &node, &container, &options).await,
+        Err(e) => Err(e),
+    };
+    log_sse(stream, false)
+}
+
+/// Follow a compose stack's logs (SSE)
+pub async fn follow_stack_logs(
+    State(state): State<Arc<AppState>>,
+    Path((node, project)): Path<(String, String)>,
+    Query(query): Query<FollowQuery>,
+) -> CommandSse {
+    let stream = match FollowOptions::parse(&query.since, &query.tail) {
+        Ok(options) => logs::follow_stack_logs(&state.ssh_pool, &node, &project, &options).await,
+        Err(e) => Err(e),
+    };
+    log_sse(stream, true)
+}
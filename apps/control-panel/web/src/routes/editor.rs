@@ -12,7 +12,8 @@ use crate::AppState;
 
 /// List all profiles
 pub async fn list_profiles(State(state): State<Arc<AppState>>) -> Html<String> {
-    let profiles = control_panel_core::editor::list_profiles(&state.config.dotfiles.path)
+    let config = state.config.load();
+    let profiles = control_panel_core::editor::list_profiles(&config.dotfiles.path)
         .unwrap_or_default();
 
     let profiles_html = profiles
@@ -71,7 +72,8 @@ pub async fn view_profile(
     State(state): State<Arc<AppState>>,
     Path(profile): Path<String>,
 ) -> Html<String> {
-    match control_panel_core::editor::parse_profile(&profile, &state.config.dotfiles.path) {
+    let config = state.config.load();
+    match control_panel_core::editor::parse_profile(&profile, &config.dotfiles.path) {
         Ok(config) => {
             let system_settings_html = config
                 .system_settings
@@ -168,7 +170,8 @@ pub async fn toggle_flag(
     State(state): State<Arc<AppState>>,
     Path((profile, flag)): Path<(String, String)>,
 ) -> Html<String> {
-    match control_panel_core::editor::toggle_flag(&profile, &state.config.dotfiles.path, &flag) {
+    let config = state.config.load();
+    match control_panel_core::editor::toggle_flag(&profile, &config.dotfiles.path, &flag) {
         Ok(new_val) => Html(format!(
             "<div class='text-green-500'>{} = {}</div>",
             flag, new_val
@@ -189,11 +192,12 @@ pub async fn duplicate_profile(
     Path(profile): Path<String>,
     Form(form): Form<DuplicateForm>,
 ) -> Html<String> {
+    let config = state.config.load();
     match control_panel_core::editor::duplicate_profile(
         &profile,
         &form.new_name,
         &form.new_hostname,
-        &state.config.dotfiles.path,
+        &config.dotfiles.path,
     ) {
         Ok(result) => Html(format!(
             "<div class='text-green-500'>Created profile: {}</div>",
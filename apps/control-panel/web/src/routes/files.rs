@@ -0,0 +1,474 @@
+//! Remote file browser and editor (SFTP)
+//!
+//! Files are opened in the editor up to `files.max_edit_bytes`; uploads and
+//! downloads are limited to `files.max_transfer_bytes`. Saving over a file
+//! keeps a `.bak-<timestamp>` copy with the same permissions next to it
+//! unless `files.backup` is off. Downloads are streamed, not buffered.
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Response},
+    Form,
+};
+use control_panel_core::ssh::sftp::{self, FileEntry, FileKind};
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio_util::io::ReaderStream;
+
+use super::{html_escape, url_encode};
+use crate::AppState;
+
+/// Human readable file size
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PathQuery {
+    #[serde(default)]
+    path: String,
+}
+
+#[derive(Deserialize)]
+pub struct UploadQuery {
+    path: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+pub struct SaveForm {
+    path: String,
+    content: String,
+    /// Line endings of the file as loaded (browsers submit CRLF)
+    #[serde(default)]
+    crlf: bool,
+}
+
+fn page(title: &str, body: &str) -> Html<String> {
+    Html(format!(
+        r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>{title} - Files</title>
+    <script src="https://unpkg.com/htmx.org@1.9.10"></script>
+    <script src="https://cdn.tailwindcss.com"></script>
+    <link rel="stylesheet" href="https://cdnjs.cloudflare.com/ajax/libs/highlight.js/11.9.0/styles/github-dark.min.css">
+    <script src="https://cdnjs.cloudflare.com/ajax/libs/highlight.js/11.9.0/highlight.min.js"></script>
+    <script src="https://cdnjs.cloudflare.com/ajax/libs/highlight.js/11.9.0/languages/nix.min.js"></script>
+    <script src="https://cdnjs.cloudflare.com/ajax/libs/highlight.js/11.9.0/languages/dockerfile.min.js"></script>
+    <script src="https://cdnjs.cloudflare.com/ajax/libs/highlight.js/11.9.0/languages/nginx.min.js"></script>
+    <style>body {{ background-color: #1a1a2e; color: #eee; }}</style>
+</head>
+<body class="min-h-screen">
+    <nav class="bg-gray-800 border-b border-gray-700 px-6 py-4">
+        <div class="flex items-center justify-between">
+            <h1 class="text-2xl font-bold text-blue-400">NixOS Control Panel</h1>
+            <div class="flex gap-4">
+                <a href="/" class="text-gray-400 hover:text-gray-300">Infrastructure</a>
+                <a href="/docker" class="text-gray-400 hover:text-gray-300">Docker</a>
+                <a href="/proxmox" class="text-gray-400 hover:text-gray-300">Proxmox</a>
+                <a href="/monitoring" class="text-gray-400 hover:text-gray-300">Monitoring</a>
+                <a href="/editor" class="text-gray-400 hover:text-gray-300">Editor</a>
+                <a href="/files" class="text-cyan-400">Files</a>
+            </div>
+        </div>
+    </nav>
+
+    <main class="container mx-auto px-6 py-8">
+        {body}
+    </main>
+</body>
+</html>"##,
+        title = html_escape(title),
+        body = body,
+    ))
+}
+
+fn error_box(message: impl std::fmt::Display) -> String {
+    format!(
+        "<div class='bg-red-900/50 text-red-300 p-4 rounded'>{}</div>",
+        html_escape(&message.to_string())
+    )
+}
+
+/// Breadcrumb links for each directory of a path
+fn breadcrumbs(host: &str, path: &str) -> String {
+    let mut html = format!(
+        r##"<a href="/files/{host}?path=/" class="text-blue-400 hover:underline">{host_label}:/</a>"##,
+        host = url_encode(host),
+        host_label = html_escape(host),
+    );
+    let mut current = String::new();
+    for part in path.split('/').filter(|p| !p.is_empty()) {
+        current.push('/');
+        current.push_str(part);
+        html.push_str(&format!(
+            r##"<a href="/files/{}?path={}" class="text-blue-400 hover:underline">{}</a>/"##,
+            url_encode(host),
+            url_encode(&current),
+            html_escape(part)
+        ));
+    }
+    html
+}
+
+/// Host picker: docker nodes and profiles with an address
+pub async fn index(State(state): State<Arc<AppState>>) -> Html<String> {
+    let config = state.config.load();
+    let mut hosts: Vec<&str> = config.docker_nodes.iter().map(|n| n.name.as_str()).collect();
+    for profile in &config.profiles {
+        if !hosts.contains(&profile.name.as_str()) && !profile.all_addresses().is_empty() {
+            hosts.push(&profile.name);
+        }
+    }
+
+    let hosts_html = hosts
+        .iter()
+        .map(|name| {
+            format!(
+                r##"<a href="/files/{}" class="block p-3 bg-gray-800 hover:bg-gray-700 rounded">{}</a>"##,
+                url_encode(name),
+                html_escape(name)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    page(
+        "Hosts",
+        &format!(
+            r##"<h2 class="text-xl font-semibold mb-2">📁 Files</h2>
+        <p class="text-gray-400 mb-6">
+            Browse, edit, upload and download files over SFTP. Editing is limited
+            to {edit}, transfers to {transfer}.
+        </p>
+        <div class="grid grid-cols-2 md:grid-cols-4 gap-3">
+            {hosts_html}
+        </div>"##,
+            edit = format_size(config.files.max_edit_bytes),
+            transfer = format_size(config.files.max_transfer_bytes),
+            hosts_html = hosts_html,
+        ),
+    )
+}
+
+fn entry_row(host: &str, entry: &FileEntry) -> String {
+    let host_enc = url_encode(host);
+    let path_enc = url_encode(&entry.path);
+    let (icon, link) = match entry.kind {
+        FileKind::Dir => ("📁", format!("/files/{}?path={}", host_enc, path_enc)),
+        FileKind::Symlink => ("🔗", format!("/files/{}/view?path={}", host_enc, path_enc)),
+        _ => ("📄", format!("/files/{}/view?path={}", host_enc, path_enc)),
+    };
+    let download = if entry.kind == FileKind::Dir {
+        String::new()
+    } else {
+        format!(
+            r##"<a href="/files/{}/download?path={}" class="px-2 py-1 bg-gray-900 hover:bg-gray-800 rounded text-xs">Download</a>"##,
+            host_enc, path_enc
+        )
+    };
+
+    format!(
+        r##"<tr class="border-b border-gray-700 hover:bg-gray-700/50">
+            <td class="py-2 px-3"><a href="{link}" class="hover:text-blue-400">{icon} {name}</a></td>
+            <td class="py-2 px-3 text-right text-gray-400">{size}</td>
+            <td class="py-2 px-3 font-mono text-gray-400">{mode}</td>
+            <td class="py-2 px-3 text-gray-400">{modified}</td>
+            <td class="py-2 px-3 text-right">{download}</td>
+        </tr>"##,
+        link = link,
+        icon = icon,
+        name = html_escape(&entry.name),
+        size = if entry.kind == FileKind::Dir { String::new() } else { format_size(entry.size) },
+        mode = entry.mode_string(),
+        modified = entry
+            .modified
+            .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default(),
+        download = download,
+    )
+}
+
+/// Directory listing, starting at the login directory
+pub async fn browse(
+    State(state): State<Arc<AppState>>,
+    Path(host): Path<String>,
+    Query(query): Query<PathQuery>,
+) -> Html<String> {
+    let config = state.config.load();
+    let listing = async {
+        let sftp = state.ssh_pool.sftp(&host).await?;
+        let path = if query.path.is_empty() { sftp.home().await? } else { query.path.clone() };
+        let entries = sftp.list(&path).await?;
+        Ok::<_, control_panel_core::AppError>((path, entries))
+    }
+    .await;
+
+    let (path, entries) = match listing {
+        Ok(listing) => listing,
+        Err(e) => return page(&host, &error_box(e)),
+    };
+
+    let parent_row = if path != "/" {
+        format!(
+            r##"<tr class="border-b border-gray-700"><td class="py-2 px-3" colspan="5"><a href="/files/{}?path={}" class="hover:text-blue-400">⬆ ..</a></td></tr>"##,
+            url_encode(&host),
+            url_encode(&sftp::parent(&path))
+        )
+    } else {
+        String::new()
+    };
+    let rows = entries
+        .iter()
+        .filter(|e| e.name != "." && e.name != "..")
+        .map(|e| entry_row(&host, e))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    page(
+        &host,
+        &format!(
+            r##"<div class="flex items-center justify-between mb-6">
+            <h2 class="text-xl font-semibold font-mono">{crumbs}</h2>
+            <form id="upload-form" class="flex items-center gap-2">
+                <input type="file" id="upload-file" class="text-sm text-gray-400">
+                <button type="submit" class="px-3 py-1 bg-blue-600 hover:bg-blue-700 rounded text-sm">Upload here</button>
+            </form>
+        </div>
+        <div id="upload-result" class="mb-4"></div>
+        <div class="bg-gray-800 rounded-lg overflow-hidden">
+            <table class="w-full text-sm">
+                <thead class="bg-gray-900 text-gray-400">
+                    <tr>
+                        <th class="py-2 px-3 text-left">Name</th>
+                        <th class="py-2 px-3 text-right">Size</th>
+                        <th class="py-2 px-3 text-left">Mode</th>
+                        <th class="py-2 px-3 text-left">Modified</th>
+                        <th class="py-2 px-3"></th>
+                    </tr>
+                </thead>
+                <tbody>
+                    {parent_row}
+                    {rows}
+                </tbody>
+            </table>
+        </div>
+        <script>
+            document.getElementById("upload-form").addEventListener("submit", async (event) => {{
+                event.preventDefault();
+                const file = document.getElementById("upload-file").files[0];
+                const result = document.getElementById("upload-result");
+                if (!file) return;
+                if (file.size > {max_transfer}) {{
+                    result.innerHTML = "<div class='text-red-400'>File is larger than the upload limit</div>";
+                    return;
+                }}
+                result.innerHTML = "<div class='text-gray-400'>Uploading " + file.name.replace(/</g, "&lt;") + "...</div>";
+                const url = "/files/{host_enc}/upload?path=" + encodeURIComponent({path_json}) + "&name=" + encodeURIComponent(file.name);
+                const response = await fetch(url, {{ method: "PUT", body: file }});
+                if (response.ok) {{
+                    location.reload();
+                }} else {{
+                    result.innerHTML = await response.text();
+                }}
+            }});
+        </script>"##,
+            crumbs = breadcrumbs(&host, &path),
+            parent_row = parent_row,
+            rows = rows,
+            max_transfer = config.files.max_transfer_bytes,
+            host_enc = url_encode(&host),
+            path_json = serde_json::to_string(&path).unwrap_or_default().replace('<', "\\u003c"),
+        ),
+    )
+}
+
+/// File view with syntax highlighting, and an editor for text files
+pub async fn view(
+    State(state): State<Arc<AppState>>,
+    Path(host): Path<String>,
+    Query(query): Query<PathQuery>,
+) -> Html<String> {
+    let config = state.config.load();
+    let path = query.path;
+    let max = config.files.max_edit_bytes;
+    let data = async {
+        let sftp = state.ssh_pool.sftp(&host).await?;
+        sftp.read(&path, max).await
+    }
+    .await;
+
+    let header = format!(
+        r##"<div class="flex items-center justify-between mb-4">
+            <h2 class="text-xl font-semibold font-mono">{crumbs}</h2>
+            <a href="/files/{host}/download?path={path}" class="px-3 py-1 bg-gray-700 hover:bg-gray-600 rounded text-sm">Download</a>
+        </div>"##,
+        crumbs = breadcrumbs(&host, &path),
+        host = url_encode(&host),
+        path = url_encode(&path),
+    );
+
+    let data = match data {
+        Ok(data) => data,
+        Err(e) => return page(&path, &format!("{}{}", header, error_box(e))),
+    };
+    if !sftp::is_text(&data) {
+        return page(
+            &path,
+            &format!("{}{}", header, error_box("Binary file; download it instead")),
+        );
+    }
+
+    let text = String::from_utf8_lossy(&data);
+    let language = sftp::syntax_for_path(&path);
+    page(
+        &path,
+        &format!(
+            r##"{header}
+        <div class="mb-3 flex items-center gap-3">
+            <button id="edit-toggle" class="px-3 py-1 bg-blue-600 hover:bg-blue-700 rounded text-sm">Edit</button>
+            <span class="text-gray-400 text-sm">{language} · {size}</span>
+        </div>
+        <pre id="viewer" class="rounded-lg overflow-auto text-sm" style="max-height: 75vh"><code class="language-{language}">{escaped}</code></pre>
+        <form id="editor" class="hidden" hx-post="/files/{host_enc}/save" hx-target="#save-result">
+            <input type="hidden" name="path" value="{path_attr}">
+            <input type="hidden" name="crlf" value="{crlf}">
+            <textarea name="content" spellcheck="false" class="w-full bg-gray-900 text-gray-100 font-mono text-sm p-3 rounded-lg" style="height: 70vh">{escaped}</textarea>
+            <div class="flex items-center gap-3 mt-3">
+                <button type="submit" class="px-3 py-1 bg-green-600 hover:bg-green-700 rounded text-sm">Save</button>
+                <span class="text-gray-400 text-sm">{backup_note}</span>
+            </div>
+        </form>
+        <div id="save-result" class="mt-3"></div>
+        <script>
+            hljs.highlightAll();
+            document.getElementById("edit-toggle").addEventListener("click", () => {{
+                document.getElementById("viewer").classList.toggle("hidden");
+                document.getElementById("editor").classList.toggle("hidden");
+            }});
+        </script>"##,
+            header = header,
+            language = language,
+            size = format_size(data.len() as u64),
+            escaped = html_escape(&text),
+            host_enc = url_encode(&host),
+            path_attr = html_escape(&path),
+            crlf = text.contains("\r\n"),
+            backup_note = if config.files.backup {
+                "The current file is kept as a .bak copy"
+            } else {
+                "No backup is kept (files.backup = false)"
+            },
+        ),
+    )
+}
+
+/// Save an edited file
+pub async fn save(
+    State(state): State<Arc<AppState>>,
+    Path(host): Path<String>,
+    Form(form): Form<SaveForm>,
+) -> Html<String> {
+    let config = state.config.load();
+    let content = if form.crlf { form.content } else { form.content.replace("\r\n", "\n") };
+    if content.len() as u64 > config.files.max_edit_bytes {
+        return Html(error_box(format!(
+            "Content is over the {} edit limit",
+            format_size(config.files.max_edit_bytes)
+        )));
+    }
+
+    let result = async {
+        let sftp = state.ssh_pool.sftp(&host).await?;
+        sftp.write(&form.path, content.as_bytes(), config.files.backup, config.files.max_edit_bytes).await
+    }
+    .await;
+
+    match result {
+        Ok(Some(backup)) => Html(format!(
+            "<div class='text-green-400'>Saved {} (previous version in {})</div>",
+            html_escape(&form.path),
+            html_escape(&backup)
+        )),
+        Ok(None) => Html(format!("<div class='text-green-400'>Saved {}</div>", html_escape(&form.path))),
+        Err(e) => Html(error_box(e)),
+    }
+}
+
+/// Download a file, streamed from the host as it is read
+pub async fn download(
+    State(state): State<Arc<AppState>>,
+    Path(host): Path<String>,
+    Query(query): Query<PathQuery>,
+) -> Response {
+    let config = state.config.load();
+    let result = async {
+        let sftp = state.ssh_pool.sftp(&host).await?;
+        sftp.into_reader(&query.path, config.files.max_transfer_bytes).await
+    }
+    .await;
+
+    match result {
+        Ok(file) => {
+            let name: String = query
+                .path
+                .rsplit('/')
+                .next()
+                .unwrap_or("download")
+                .chars()
+                .map(|c| if c == '"' || c.is_control() { '_' } else { c })
+                .collect();
+            (
+                [
+                    (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+                    (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", name)),
+                    (header::CONTENT_LENGTH, file.size.to_string()),
+                ],
+                Body::from_stream(ReaderStream::new(file)),
+            )
+                .into_response()
+        }
+        Err(e) => (StatusCode::BAD_GATEWAY, e.to_string()).into_response(),
+    }
+}
+
+/// Upload a file (raw request body) into a directory
+pub async fn upload(
+    State(state): State<Arc<AppState>>,
+    Path(host): Path<String>,
+    Query(query): Query<UploadQuery>,
+    body: Bytes,
+) -> Response {
+    let config = state.config.load();
+    if query.name.is_empty() || query.name.contains('/') || query.name == "." || query.name == ".." {
+        return (StatusCode::BAD_REQUEST, error_box("Invalid file name")).into_response();
+    }
+
+    let target = sftp::join(&query.path, &query.name);
+    let result = async {
+        let sftp = state.ssh_pool.sftp(&host).await?;
+        sftp.write(&target, &body, config.files.backup, config.files.max_transfer_bytes).await
+    }
+    .await;
+
+    match result {
+        Ok(_) => Html(format!("<div class='text-green-400'>Uploaded {}</div>", html_escape(&target))).into_response(),
+        Err(e) => (StatusCode::BAD_GATEWAY, error_box(e)).into_response(),
+    }
+}
@@ -1,25 +1,37 @@
 //! Infrastructure routes (git, deploy, graph)
 
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     response::{Html, Json},
     Form,
 };
+use control_panel_core::Severity;
 use serde::Deserialize;
 use std::sync::Arc;
 
+use super::{html_escape, url_encode};
+use super::batch::{labels_html, parse_selector, SelectQuery, SELECTOR_HELP};
+use super::stream::STREAM_JS;
 use crate::AppState;
 
-/// Simple HTML escaping
-fn html_escape(s: &str) -> String {
-    s.replace('&', "&amp;")
-        .replace('<', "&lt;")
-        .replace('>', "&gt;")
-        .replace('"', "&quot;")
+/// Client-side helper: deploy profiles one after the other, stopping at the
+/// first failure
+const DEPLOY_ALL_JS: &str = r##"<script>
+async function deployAll(urlFor, profiles, targetId) {
+    for (const profile of profiles) {
+        if (!await streamTo(urlFor(profile), targetId, 'Deploy ' + profile)) break;
+    }
 }
+</script>"##;
 
 /// Infrastructure dashboard
-pub async fn dashboard(State(state): State<Arc<AppState>>) -> Html<String> {
+pub async fn dashboard(State(state): State<Arc<AppState>>, Query(query): Query<SelectQuery>) -> Html<String> {
+    let config = state.config.load();
+    let (selected, filter_error) = match parse_selector(&query.select) {
+        Ok(selector) => (config.select_profiles(&selector), String::new()),
+        Err(html) => (Vec::new(), html),
+    };
+    let names: Vec<&str> = selected.iter().map(|p| p.name.as_str()).collect();
     Html(format!(
         r##"<!DOCTYPE html>
 <html lang="en">
@@ -30,6 +42,8 @@ pub async fn dashboard(State(state): State<Arc<AppState>>) -> Html<String> {
     <script src="https://unpkg.com/htmx.org@1.9.10"></script>
     <script src="https://cdn.tailwindcss.com"></script>
     <style>body {{ background-color: #1a1a2e; color: #eee; }}</style>
+    {stream_js}
+    {deploy_all_js}
 </head>
 <body class="min-h-screen">
     <nav class="bg-gray-800 border-b border-gray-700 px-6 py-4">
@@ -62,38 +76,331 @@ pub async fn dashboard(State(state): State<Arc<AppState>>) -> Html<String> {
                 <div id="git-result" class="mt-4"></div>
             </div>
 
+            {inventory}
+            {config}
+
             <div class="bg-gray-800 p-4 rounded-lg col-span-full">
                 <div class="flex items-center justify-between mb-4">
                     <h3 class="text-lg font-semibold">Deploy to Profile</h3>
-                    <a href="/infra/deploy-lxc" class="px-4 py-2 bg-amber-600 hover:bg-amber-700 rounded text-sm">
-                        LXC Batch Deploy
-                    </a>
+                    <div class="flex gap-2">
+                        <a href="/infra/edit" class="px-4 py-2 bg-gray-700 hover:bg-gray-600 rounded text-sm">
+                            Edit Inventory
+                        </a>
+                        <a href="/infra/batch?select={select_query}" class="px-4 py-2 bg-gray-700 hover:bg-gray-600 rounded text-sm">
+                            Health &amp; Commands
+                        </a>
+                        <a href="/infra/deploy-lxc" class="px-4 py-2 bg-amber-600 hover:bg-amber-700 rounded text-sm">
+                            LXC Batch Deploy
+                        </a>
+                    </div>
                 </div>
+                <form method="get" action="/infra" class="mb-4">
+                    <div class="flex gap-2">
+                        <input name="select" value="{select}" placeholder="Filter profiles: tag:lxc,!critical"
+                               class="flex-1 px-3 py-2 bg-gray-900 rounded font-mono">
+                        <button class="px-4 py-2 bg-blue-600 hover:bg-blue-700 rounded">Filter</button>
+                        <button type="button" data-profiles="{names_json}"
+                                onclick="deployAll(p => '/infra/deploy/' + p + '/stream', JSON.parse(this.dataset.profiles), 'deploy-result')"
+                                class="px-4 py-2 bg-green-700 hover:bg-green-600 rounded" {deploy_all_disabled}>Deploy all {count}</button>
+                    </div>
+                    {help}
+                    {filter_error}
+                </form>
                 <div class="grid grid-cols-2 md:grid-cols-4 gap-2">
                     {profiles}
                 </div>
-                <div id="deploy-result" class="mt-4"></div>
+                <div id="deploy-result" class="mt-4 bg-gray-900 rounded-lg p-4 font-mono text-sm overflow-auto max-h-[32rem]"></div>
             </div>
         </div>
     </main>
 </body>
 </html>"##,
-        profiles = state
-            .config
-            .profiles
+        profiles = selected
             .iter()
             .map(|p| format!(
-                r##"<button hx-post="/infra/deploy/{}" hx-target="#deploy-result" class="px-4 py-2 bg-gray-700 hover:bg-gray-600 rounded">{}</button>"##,
-                p.name, p.name
+                r##"<button onclick="streamTo('/infra/deploy/{name}/stream', 'deploy-result', 'Deploy {name}')" class="px-4 py-2 bg-gray-700 hover:bg-gray-600 rounded text-left">{name}{address}{labels}</button>"##,
+                name = p.name,
+                address = state
+                    .ssh_pool
+                    .profile_address(&p.name)
+                    .map(|a| format!(r##"<span class="block text-xs text-gray-400">{}</span>"##, html_escape(&a)))
+                    .unwrap_or_default(),
+                labels = labels_html(&config.profile_labels(p)),
             ))
             .collect::<Vec<_>>()
-            .join("\n")
+            .join("\n"),
+        select = html_escape(&query.select),
+        select_query = url_encode(&query.select),
+        names_json = html_escape(&serde_json::to_string(&names).unwrap_or_default()),
+        count = names.len(),
+        deploy_all_disabled = if names.is_empty() { "disabled" } else { "" },
+        help = SELECTOR_HELP,
+        filter_error = filter_error,
+        deploy_all_js = DEPLOY_ALL_JS,
+        inventory = inventory_summary(&state),
+        config = config_summary(&state),
+        stream_js = STREAM_JS,
+    ))
+}
+
+/// Inventory import summary for the dashboard
+fn inventory_summary(state: &AppState) -> String {
+    let config = state.config.load();
+    let Some(ref report) = config.inventory_report else {
+        return String::new();
+    };
+    let skipped = report.sources.iter().filter(|s| s.error.is_some()).count();
+    let color = if report.mismatches.is_empty() && skipped == 0 { "text-green-400" } else { "text-amber-400" };
+
+    format!(
+        r##"<a href="/infra/inventory" class="bg-gray-800 p-4 rounded-lg hover:bg-gray-700 col-span-full block">
+                <h3 class="text-lg font-semibold mb-1">Inventory</h3>
+                <span class="{color}">{imported} profiles imported, {mismatches} mismatches, {skipped} sources skipped</span>
+            </a>"##,
+        color = color,
+        imported = report.imported.len(),
+        mismatches = report.mismatches.len(),
+        skipped = skipped,
+    )
+}
+
+/// Configuration sources and findings for the dashboard
+fn config_summary(state: &AppState) -> String {
+    let config = state.config.load();
+    let findings = config.validate();
+    let count = |severity| findings.iter().filter(|f| f.severity == severity).count();
+    let (errors, warnings) = (count(Severity::Error), count(Severity::Warning));
+    let color = if errors > 0 { "text-red-400" } else if warnings > 0 { "text-amber-400" } else { "text-green-400" };
+
+    format!(
+        r##"<a href="/infra/config" class="bg-gray-800 p-4 rounded-lg hover:bg-gray-700 col-span-full block">
+                <h3 class="text-lg font-semibold mb-1">Configuration</h3>
+                <span class="{color}">{errors} errors, {warnings} warnings</span>
+                <span class="block text-xs text-gray-400">{sources}</span>
+            </a>"##,
+        color = color,
+        errors = errors,
+        warnings = warnings,
+        sources = html_escape(&config.sources.join(", ")),
+    )
+}
+
+/// Loaded configuration with secrets masked, its sources and findings
+pub async fn config_page(State(state): State<Arc<AppState>>) -> Html<String> {
+    let config = state.config.load();
+    let sources = config
+        .sources
+        .iter()
+        .map(|s| format!(r##"<li class="font-mono">{}</li>"##, html_escape(s)))
+        .collect::<String>();
+    let findings = config.validate();
+    let findings = if findings.is_empty() {
+        r##"<p class="text-green-400">No problems found</p>"##.to_string()
+    } else {
+        findings
+            .iter()
+            .map(|f| {
+                let color = match f.severity {
+                    Severity::Error => "text-red-400",
+                    Severity::Warning => "text-amber-400",
+                    Severity::Info => "text-gray-400",
+                };
+                format!(
+                    r##"<li><span class="{}">{}</span> <span class="font-mono">{}</span>: {}{}</li>"##,
+                    color,
+                    f.severity,
+                    html_escape(&f.subject),
+                    html_escape(&f.message),
+                    f.hint
+                        .as_deref()
+                        .map(|h| format!(r##"<span class="block text-xs text-gray-400 ml-4">{}</span>"##, html_escape(h)))
+                        .unwrap_or_default()
+                )
+            })
+            .collect::<String>()
+    };
+
+    Html(format!(
+        r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Configuration - Control Panel</title>
+    <script src="https://cdn.tailwindcss.com"></script>
+    <style>body {{ background-color: #1a1a2e; color: #eee; }}</style>
+</head>
+<body class="min-h-screen">
+    <nav class="bg-gray-800 border-b border-gray-700 px-6 py-4">
+        <div class="flex items-center justify-between">
+            <h1 class="text-2xl font-bold text-blue-400">NixOS Control Panel</h1>
+            <div class="flex gap-4">
+                <a href="/" class="text-gray-400 hover:text-gray-300">Infrastructure</a>
+                <a href="/docker" class="text-gray-400 hover:text-gray-300">Docker</a>
+                <a href="/proxmox" class="text-gray-400 hover:text-gray-300">Proxmox</a>
+                <a href="/monitoring" class="text-gray-400 hover:text-gray-300">Monitoring</a>
+                <a href="/editor" class="text-gray-400 hover:text-gray-300">Editor</a>
+            </div>
+        </div>
+    </nav>
+
+    <main class="container mx-auto px-6 py-8">
+        <div class="flex items-center gap-4 mb-6">
+            <a href="/infra" class="text-gray-400 hover:text-gray-300">&larr; Back</a>
+            <h2 class="text-xl font-semibold">Configuration</h2>
+        </div>
+        <div class="bg-gray-800 p-4 rounded-lg mb-6">
+            <h3 class="text-lg font-semibold mb-2">Sources</h3>
+            <p class="text-gray-400 text-sm mb-2">Later sources override earlier ones.</p>
+            <ol class="list-decimal ml-6 text-sm">{sources}</ol>
+        </div>
+        <div class="bg-gray-800 p-4 rounded-lg mb-6">
+            <h3 class="text-lg font-semibold mb-2">Findings</h3>
+            <ul class="text-sm space-y-1">{findings}</ul>
+        </div>
+        <div class="bg-gray-800 p-4 rounded-lg">
+            <h3 class="text-lg font-semibold mb-2">Loaded values</h3>
+            <p class="text-gray-400 text-sm mb-2">Passwords and hashes are masked.</p>
+            <pre class="text-sm font-mono overflow-x-auto">{toml}</pre>
+        </div>
+    </main>
+</body>
+</html>"##,
+        sources = sources,
+        findings = findings,
+        toml = html_escape(&control_panel_core::layers::masked(&config)),
+    ))
+}
+
+/// Inventory import report: sources, imported profiles and mismatches
+pub async fn inventory_page(State(state): State<Arc<AppState>>) -> Html<String> {
+    let config = state.config.load();
+    let body = match config.inventory_report {
+        None => r##"<p class="text-gray-400">Inventory import is off. Set <code>[inventory] enabled = true</code>
+            in config.toml to source profiles from deploy-servers.conf and profiles/registry.toml.</p>"##
+            .to_string(),
+        Some(ref report) => {
+            let sources = report
+                .sources
+                .iter()
+                .map(|s| {
+                    let status = match s.error {
+                        Some(ref e) => format!("<span class='text-amber-400'>skipped: {}</span>", html_escape(e)),
+                        None => format!("<span class='text-green-400'>{} profiles</span>", s.entries),
+                    };
+                    format!(
+                        r##"<tr class="border-b border-gray-700"><td class="py-2 px-3">{}</td><td class="py-2 px-3 font-mono">{}</td><td class="py-2 px-3">{}</td></tr>"##,
+                        s.source,
+                        html_escape(&s.path.display().to_string()),
+                        status
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            let mismatches = if report.mismatches.is_empty() {
+                r##"<tr><td colspan="3" class="py-2 px-3 text-green-400">Sources agree</td></tr>"##.to_string()
+            } else {
+                report
+                    .mismatches
+                    .iter()
+                    .map(|m| {
+                        let values = m
+                            .values
+                            .iter()
+                            .map(|(source, value)| {
+                                format!(
+                                    "<div><span class='text-gray-400'>{}:</span> {}</div>",
+                                    source,
+                                    value
+                                        .as_deref()
+                                        .map(html_escape)
+                                        .unwrap_or_else(|| "<span class='text-red-400'>missing</span>".to_string())
+                                )
+                            })
+                            .collect::<String>();
+                        format!(
+                            r##"<tr class="border-b border-gray-700"><td class="py-2 px-3 font-semibold">{}</td><td class="py-2 px-3">{}</td><td class="py-2 px-3 font-mono">{}</td></tr>"##,
+                            html_escape(&m.profile),
+                            html_escape(&m.field),
+                            values
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            };
+
+            let imported = if report.imported.is_empty() {
+                "none".to_string()
+            } else {
+                report.imported.iter().map(|n| html_escape(n)).collect::<Vec<_>>().join(", ")
+            };
+
+            format!(
+                r##"<div class="bg-gray-800 p-4 rounded-lg mb-6">
+            <h3 class="text-lg font-semibold mb-2">Sources</h3>
+            <table class="w-full text-sm">{sources}</table>
+        </div>
+        <div class="bg-gray-800 p-4 rounded-lg mb-6">
+            <h3 class="text-lg font-semibold mb-2">Imported (not in config.toml)</h3>
+            <p class="text-gray-300">{imported}</p>
+        </div>
+        <div class="bg-gray-800 p-4 rounded-lg">
+            <h3 class="text-lg font-semibold mb-2">Mismatches</h3>
+            <p class="text-gray-400 text-sm mb-2">config.toml values win over the imported ones.</p>
+            <table class="w-full text-sm">
+                <thead class="text-gray-400"><tr><th class="py-2 px-3 text-left">Profile</th><th class="py-2 px-3 text-left">Field</th><th class="py-2 px-3 text-left">Values</th></tr></thead>
+                <tbody>{mismatches}</tbody>
+            </table>
+        </div>"##,
+                sources = sources,
+                imported = imported,
+                mismatches = mismatches,
+            )
+        }
+    };
+
+    Html(format!(
+        r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Inventory - Control Panel</title>
+    <script src="https://cdn.tailwindcss.com"></script>
+    <style>body {{ background-color: #1a1a2e; color: #eee; }}</style>
+</head>
+<body class="min-h-screen">
+    <nav class="bg-gray-800 border-b border-gray-700 px-6 py-4">
+        <div class="flex items-center justify-between">
+            <h1 class="text-2xl font-bold text-blue-400">NixOS Control Panel</h1>
+            <div class="flex gap-4">
+                <a href="/" class="text-gray-400 hover:text-gray-300">Infrastructure</a>
+                <a href="/docker" class="text-gray-400 hover:text-gray-300">Docker</a>
+                <a href="/proxmox" class="text-gray-400 hover:text-gray-300">Proxmox</a>
+                <a href="/monitoring" class="text-gray-400 hover:text-gray-300">Monitoring</a>
+                <a href="/editor" class="text-gray-400 hover:text-gray-300">Editor</a>
+            </div>
+        </div>
+    </nav>
+
+    <main class="container mx-auto px-6 py-8">
+        <div class="flex items-center gap-4 mb-6">
+            <a href="/infra" class="text-gray-400 hover:text-gray-300">&larr; Back</a>
+            <h2 class="text-xl font-semibold">Fleet Inventory</h2>
+        </div>
+        {body}
+    </main>
+</body>
+</html>"##,
+        body = body,
     ))
 }
 
 /// Git status fragment (auto-refreshed)
 pub async fn git_status_fragment(State(state): State<Arc<AppState>>) -> Html<String> {
-    let git_status = control_panel_core::infra::git::get_status(&state.config.dotfiles.path).ok();
+    let config = state.config.load();
+    let git_status = control_panel_core::infra::git::get_status(&config.dotfiles.path).ok();
 
     if let Some(status) = git_status {
         Html(format!(
@@ -117,19 +424,22 @@ pub async fn git_status_fragment(State(state): State<Arc<AppState>>) -> Html<Str
 
 /// Get graph data as JSON
 pub async fn graph_data(State(state): State<Arc<AppState>>) -> Json<control_panel_core::GraphData> {
-    let data = control_panel_core::infra::graph::generate_graph_data(&state.config);
+    let config = state.config.load();
+    let data = control_panel_core::infra::graph::generate_graph_data(&config);
     Json(data)
 }
 
 /// Get git status
 pub async fn git_status(State(state): State<Arc<AppState>>) -> Json<Option<control_panel_core::GitStatus>> {
-    let status = control_panel_core::infra::git::get_status(&state.config.dotfiles.path).ok();
+    let config = state.config.load();
+    let status = control_panel_core::infra::git::get_status(&config.dotfiles.path).ok();
     Json(status)
 }
 
 /// Get git diff
 pub async fn git_diff(State(state): State<Arc<AppState>>) -> Html<String> {
-    match control_panel_core::infra::git::get_diff(&state.config.dotfiles.path) {
+    let config = state.config.load();
+    match control_panel_core::infra::git::get_diff(&config.dotfiles.path) {
         Ok(diff) => Html(format!(
             "<pre class='bg-gray-900 p-4 rounded overflow-auto text-sm'>{}</pre>",
             diff
@@ -140,7 +450,8 @@ pub async fn git_diff(State(state): State<Arc<AppState>>) -> Html<String> {
 
 /// Pull from remote
 pub async fn git_pull(State(state): State<Arc<AppState>>) -> Html<String> {
-    match control_panel_core::infra::git::pull(&state.config.dotfiles.path) {
+    let config = state.config.load();
+    match control_panel_core::infra::git::pull(&config.dotfiles.path) {
         Ok(_) => Html("<div class='text-green-500'>Pull successful</div>".to_string()),
         Err(e) => Html(format!("<div class='text-red-500'>Error: {}</div>", e)),
     }
@@ -148,7 +459,8 @@ pub async fn git_pull(State(state): State<Arc<AppState>>) -> Html<String> {
 
 /// Push to remote
 pub async fn git_push(State(state): State<Arc<AppState>>) -> Html<String> {
-    match control_panel_core::infra::git::push(&state.config.dotfiles.path) {
+    let config = state.config.load();
+    match control_panel_core::infra::git::push(&config.dotfiles.path) {
         Ok(_) => Html("<div class='text-green-500'>Push successful</div>".to_string()),
         Err(e) => Html(format!("<div class='text-red-500'>Error: {}</div>", e)),
     }
@@ -164,7 +476,8 @@ pub async fn git_commit(
     State(state): State<Arc<AppState>>,
     Form(form): Form<CommitForm>,
 ) -> Html<String> {
-    match control_panel_core::infra::git::commit(&state.config.dotfiles.path, &form.message) {
+    let config = state.config.load();
+    match control_panel_core::infra::git::commit(&config.dotfiles.path, &form.message) {
         Ok(_) => Html("<div class='text-green-500'>Commit successful</div>".to_string()),
         Err(e) => Html(format!("<div class='text-red-500'>Error: {}</div>", e)),
     }
@@ -175,13 +488,13 @@ pub async fn deploy(
     State(state): State<Arc<AppState>>,
     Path(profile): Path<String>,
 ) -> Html<String> {
-    let mut ssh_pool = state.ssh_pool.write().await;
+    let config = state.config.load();
 
     match control_panel_core::infra::deploy::deploy(
-        &mut ssh_pool,
+        &state.ssh_pool,
         &profile,
         &profile,
-        &state.config.dotfiles.path,
+        &config.dotfiles.path,
     )
     .await
     {
@@ -198,22 +511,34 @@ pub async fn deploy(
                 ))
             }
         }
-        Err(e) => Html(format!("<div class='text-red-500'>Error: {}</div>", html_escape(&e.to_string()))),
+        Err(e) => Html(super::ssh::error_html(&e)),
     }
 }
 
+/// Start deploying to a profile; returns the job id to stream from
+pub async fn deploy_stream(
+    State(state): State<Arc<AppState>>,
+    Path(profile): Path<String>,
+) -> String {
+    let config = state.config.load();
+
+    let output =
+        control_panel_core::infra::deploy::deploy_stream(&state.ssh_pool, &profile, &config.dotfiles.path).await;
+    state.jobs.start(format!("Deploy {}", profile), output)
+}
+
 /// Dry run deployment
 pub async fn dry_run(
     State(state): State<Arc<AppState>>,
     Path(profile): Path<String>,
 ) -> Html<String> {
-    let mut ssh_pool = state.ssh_pool.write().await;
+    let config = state.config.load();
 
     match control_panel_core::infra::deploy::dry_run(
-        &mut ssh_pool,
+        &state.ssh_pool,
         &profile,
         &profile,
-        &state.config.dotfiles.path,
+        &config.dotfiles.path,
     )
     .await
     {
@@ -230,7 +555,7 @@ pub async fn dry_run(
                 ))
             }
         }
-        Err(e) => Html(format!("<div class='text-red-500'>Error: {}</div>", html_escape(&e.to_string()))),
+        Err(e) => Html(super::ssh::error_html(&e)),
     }
 }
 
@@ -238,29 +563,21 @@ pub async fn dry_run(
 // Deploy-LXC Workflow
 // ============================================================================
 
-/// LXC server definition for the deploy page
-struct LxcServer {
-    profile: &'static str,
-    ip: &'static str,
-    description: &'static str,
-}
-
-/// Static LXC server list matching deploy-lxc.sh SERVERS
-const LXC_SERVERS: &[LxcServer] = &[
-    LxcServer { profile: "LXC_HOME", ip: "192.168.8.80", description: "Homelab services" },
-    LxcServer { profile: "LXC_proxy", ip: "192.168.8.102", description: "Cloudflare tunnel & NPM" },
-    LxcServer { profile: "LXC_plane", ip: "192.168.8.86", description: "Production container" },
-    // LXC_liftcraftTEST and LXC_portfolioprod migrated to VPS (100.64.0.6)
-    LxcServer { profile: "LXC_mailer", ip: "192.168.8.89", description: "Mail & monitoring" },
-    LxcServer { profile: "LXC_monitoring", ip: "192.168.8.85", description: "Prometheus & Grafana monitoring" },
-    LxcServer { profile: "LXC_database", ip: "192.168.8.103", description: "Centralized PostgreSQL, MariaDB & Redis" },
-];
+/// Hosts the deploy-lxc page lists unless `?select=` says otherwise
+const DEFAULT_LXC_SELECTOR: &str = "tag:lxc";
 
 /// Deploy-LXC page
-pub async fn deploy_lxc_page(State(_state): State<Arc<AppState>>) -> Html<String> {
-    let servers_html = LXC_SERVERS
+pub async fn deploy_lxc_page(State(state): State<Arc<AppState>>, Query(query): Query<SelectQuery>) -> Html<String> {
+    let config = state.config.load();
+    let select = if query.select.trim().is_empty() { DEFAULT_LXC_SELECTOR } else { query.select.as_str() };
+    let (profiles, filter_error) = match parse_selector(select) {
+        Ok(selector) => (config.select_profiles(&selector), String::new()),
+        Err(html) => (Vec::new(), html),
+    };
+
+    let servers_html = profiles
         .iter()
-        .map(|s| {
+        .map(|p| {
             format!(
                 r##"<div class="flex items-center justify-between p-4 bg-gray-800 rounded-lg">
                     <div class="flex items-center gap-4">
@@ -269,18 +586,18 @@ pub async fn deploy_lxc_page(State(_state): State<Arc<AppState>>) -> Html<String
                             <span class="font-semibold">{profile}</span>
                             <span class="text-gray-500 ml-2">{ip}</span>
                             <p class="text-gray-400 text-sm">{desc}</p>
+                            {labels}
                         </div>
                     </div>
-                    <button hx-post="/infra/deploy-lxc/{profile}"
-                            hx-target="#deploy-console"
-                            hx-swap="beforeend"
+                    <button onclick="streamTo('/infra/deploy-lxc/{profile}/stream', 'deploy-console', 'Deploy {profile}')"
                             class="px-4 py-2 bg-blue-600 hover:bg-blue-700 rounded text-sm">
                         Deploy
                     </button>
                 </div>"##,
-                profile = s.profile,
-                ip = s.ip,
-                desc = s.description,
+                profile = html_escape(&p.name),
+                ip = html_escape(p.ip.as_deref().unwrap_or_default()),
+                desc = html_escape(p.description.as_deref().unwrap_or_default()),
+                labels = labels_html(&config.profile_labels(p)),
             )
         })
         .collect::<Vec<_>>()
@@ -296,6 +613,8 @@ pub async fn deploy_lxc_page(State(_state): State<Arc<AppState>>) -> Html<String
     <script src="https://unpkg.com/htmx.org@1.9.10"></script>
     <script src="https://cdn.tailwindcss.com"></script>
     <style>body {{ background-color: #1a1a2e; color: #eee; }}</style>
+    {stream_js}
+    {deploy_all_js}
 </head>
 <body class="min-h-screen">
     <nav class="bg-gray-800 border-b border-gray-700 px-6 py-4">
@@ -322,6 +641,17 @@ pub async fn deploy_lxc_page(State(_state): State<Arc<AppState>>) -> Html<String
             git fetch &rarr; git reset --hard origin/main &rarr; install.sh
         </p>
 
+        <form method="get" action="/infra/deploy-lxc" class="bg-gray-800 p-4 rounded-lg mb-4">
+            <div class="flex gap-2">
+                <input name="select" value="{select}" class="flex-1 px-3 py-2 bg-gray-900 rounded font-mono">
+                <button class="px-4 py-2 bg-blue-600 hover:bg-blue-700 rounded">Filter</button>
+                <button type="button" onclick="deploySelected()"
+                        class="px-4 py-2 bg-amber-600 hover:bg-amber-700 rounded">Deploy selected</button>
+            </div>
+            {help}
+            {filter_error}
+        </form>
+
         <div class="space-y-3 mb-6">
             {servers_html}
         </div>
@@ -339,24 +669,47 @@ pub async fn deploy_lxc_page(State(_state): State<Arc<AppState>>) -> Html<String
             </div>
         </div>
     </main>
+    <script>
+    function deploySelected() {{
+        const profiles = [...document.querySelectorAll('.lxc-checkbox:checked')].map(c => c.dataset.profile);
+        deployAll(p => '/infra/deploy-lxc/' + p + '/stream', profiles, 'deploy-console');
+    }}
+    </script>
 </body>
 </html>"##,
-        servers_html = servers_html
+        servers_html = servers_html,
+        select = html_escape(select),
+        help = SELECTOR_HELP,
+        filter_error = filter_error,
+        stream_js = STREAM_JS,
+        deploy_all_js = DEPLOY_ALL_JS,
     ))
 }
 
+/// Start the deploy-lxc workflow for a single profile; returns the job id to stream from
+pub async fn deploy_lxc_stream(
+    State(state): State<Arc<AppState>>,
+    Path(profile): Path<String>,
+) -> String {
+    let config = state.config.load();
+
+    let output =
+        control_panel_core::infra::deploy::deploy_lxc_stream(&state.ssh_pool, &profile, &config.dotfiles.path).await;
+    state.jobs.start(format!("Deploy LXC {}", profile), output)
+}
+
 /// Execute deploy-lxc workflow for a single profile
 pub async fn deploy_lxc_execute(
     State(state): State<Arc<AppState>>,
     Path(profile): Path<String>,
 ) -> Html<String> {
-    let mut ssh_pool = state.ssh_pool.write().await;
+    let config = state.config.load();
     let ts = chrono::Local::now().format("%H:%M:%S").to_string();
 
     let results = match control_panel_core::infra::deploy::deploy_lxc_node(
-        &mut ssh_pool,
+        &state.ssh_pool,
         &profile,
-        &state.config.dotfiles.path,
+        &config.dotfiles.path,
     )
     .await
     {
@@ -0,0 +1,133 @@
+//! Running and recent jobs (deploys, pulls, rebuilds) to re-attach to or cancel
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Html,
+};
+use std::sync::Arc;
+
+use super::html_escape;
+use super::stream::{JobInfo, JobStatus, STREAM_JS};
+use crate::AppState;
+
+fn job_row(job: &JobInfo) -> String {
+    let (status, running) = match job.status {
+        JobStatus::Running => ("<span class='text-blue-400'>RUNNING</span>".to_string(), true),
+        JobStatus::Exited(0) => ("<span class='text-green-400'>OK</span>".to_string(), false),
+        JobStatus::Exited(code) => (format!("<span class='text-red-400'>FAIL (exit {})</span>", code), false),
+        JobStatus::Failed => ("<span class='text-red-400'>FAIL</span>".to_string(), false),
+        JobStatus::Cancelled => ("<span class='text-yellow-400'>CANCELLED</span>".to_string(), false),
+    };
+    let cancel = if running {
+        format!(
+            r##"<button hx-post="/jobs/{id}/cancel" hx-target="#job-list" hx-confirm="Cancel this job? The remote command is stopped wherever it is." class="px-2 py-1 bg-red-600 hover:bg-red-700 rounded text-xs">Cancel</button>"##,
+            id = job.id,
+        )
+    } else {
+        String::new()
+    };
+
+    format!(
+        r##"<tr class="border-b border-gray-700">
+            <td class="py-2 px-3 text-gray-400">{started}</td>
+            <td class="py-2 px-3">{label}</td>
+            <td class="py-2 px-3">{status}</td>
+            <td class="py-2 px-3 text-right">
+                <button data-label="{label}" onclick="attachTo('/jobs/{id}/stream', '{id}', 'job-output', this.dataset.label)" class="px-2 py-1 bg-blue-600 hover:bg-blue-700 rounded text-xs">Output</button>
+                {cancel}
+            </td>
+        </tr>"##,
+        started = job.started.format("%Y-%m-%d %H:%M:%S"),
+        label = html_escape(&job.label),
+        status = status,
+        id = job.id,
+        cancel = cancel,
+    )
+}
+
+fn job_table(state: &AppState) -> Html<String> {
+    let jobs = state.jobs.list();
+    let rows = if jobs.is_empty() {
+        r##"<tr><td colspan="4" class="py-4 px-3 text-gray-400">No jobs in the last hour</td></tr>"##.to_string()
+    } else {
+        jobs.iter().map(job_row).collect::<Vec<_>>().join("\n")
+    };
+
+    Html(format!(
+        r##"<table class="w-full text-sm">
+            <thead class="bg-gray-900 text-gray-400">
+                <tr>
+                    <th class="py-2 px-3 text-left">Started</th>
+                    <th class="py-2 px-3 text-left">Job</th>
+                    <th class="py-2 px-3 text-left">Status</th>
+                    <th class="py-2 px-3"></th>
+                </tr>
+            </thead>
+            <tbody>{rows}</tbody>
+        </table>"##,
+        rows = rows,
+    ))
+}
+
+/// Jobs page
+pub async fn page(State(_state): State<Arc<AppState>>) -> Html<String> {
+    Html(format!(
+        r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Jobs - Control Panel</title>
+    <script src="https://unpkg.com/htmx.org@1.9.10"></script>
+    <script src="https://cdn.tailwindcss.com"></script>
+    <style>body {{ background-color: #1a1a2e; color: #eee; }}</style>
+    {stream_js}
+</head>
+<body class="min-h-screen">
+    <nav class="bg-gray-800 border-b border-gray-700 px-6 py-4">
+        <div class="flex items-center justify-between">
+            <h1 class="text-2xl font-bold text-blue-400">NixOS Control Panel</h1>
+            <div class="flex gap-4">
+                <a href="/" class="text-gray-400 hover:text-gray-300">Infrastructure</a>
+                <a href="/docker" class="text-gray-400 hover:text-gray-300">Docker</a>
+                <a href="/proxmox" class="text-gray-400 hover:text-gray-300">Proxmox</a>
+                <a href="/monitoring" class="text-gray-400 hover:text-gray-300">Monitoring</a>
+                <a href="/editor" class="text-gray-400 hover:text-gray-300">Editor</a>
+            </div>
+        </div>
+    </nav>
+
+    <main class="container mx-auto px-6 py-8">
+        <h2 class="text-xl font-semibold mb-2">⏱ Jobs</h2>
+        <p class="text-gray-400 mb-6">
+            Deploys, pulls and rebuilds keep running when their page is closed.
+            Finished jobs are kept for an hour.
+        </p>
+
+        <div id="job-list" class="bg-gray-800 rounded-lg overflow-hidden mb-6" hx-get="/jobs/list" hx-trigger="load, every 5s">
+        </div>
+
+        <div id="job-output" class="bg-gray-900 rounded-lg p-4 font-mono text-sm max-h-[32rem] overflow-y-auto"></div>
+    </main>
+</body>
+</html>"##,
+        stream_js = STREAM_JS,
+    ))
+}
+
+/// Job list fragment
+pub async fn list(State(state): State<Arc<AppState>>) -> Html<String> {
+    job_table(&state)
+}
+
+/// Cancel a running job. Returns the job list for the jobs page; other
+/// pages only look at the status.
+pub async fn cancel(State(state): State<Arc<AppState>>, Path(id): Path<String>) -> (StatusCode, Html<String>) {
+    let status = if state.jobs.cancel(&id) {
+        StatusCode::OK
+    } else {
+        StatusCode::NOT_FOUND
+    };
+    (status, job_table(&state))
+}
@@ -1,22 +1,50 @@
 //! Web routes for the control panel
 
 pub mod auth;
+pub mod batch;
+pub mod config_edit;
 pub mod docker;
 pub mod editor;
+pub mod files;
 pub mod infra;
+pub mod jobs;
 pub mod monitoring;
+pub mod origin;
 pub mod proxmox;
+pub mod ssh;
+pub mod stream;
+pub mod terminal;
+pub mod tunnels;
 
 use axum::response::Html;
 use std::sync::Arc;
 
 use crate::AppState;
 
+/// Simple HTML escaping
+pub fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Percent-encode a path segment or query parameter value
+pub fn url_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
 /// Index page handler
 pub async fn index(
     axum::extract::State(state): axum::extract::State<Arc<AppState>>,
 ) -> Html<String> {
-    let graph_data = control_panel_core::infra::graph::generate_graph_data(&state.config);
+    let config = state.config.load();
+    let graph_data = control_panel_core::infra::graph::generate_graph_data(&config);
     let graph_json = serde_json::to_string(&graph_data).unwrap_or_default();
 
     Html(format!(
@@ -56,7 +84,7 @@ pub async fn index(
             <svg id="graph" width="100%" height="100%"></svg>
         </div>
 
-        <div class="grid grid-cols-1 md:grid-cols-3 gap-4">
+        <div class="grid grid-cols-1 md:grid-cols-4 gap-4">
             <a href="/docker" class="bg-gray-800 p-4 rounded-lg hover:bg-gray-700 transition">
                 <h3 class="text-lg font-semibold text-blue-400">🐳 Docker</h3>
                 <p class="text-gray-400">Manage containers across LXC nodes</p>
@@ -69,6 +97,26 @@ pub async fn index(
                 <h3 class="text-lg font-semibold text-amber-400">🏗️ Infrastructure</h3>
                 <p class="text-gray-400">Git operations and deployments</p>
             </a>
+            <a href="/terminal" class="bg-gray-800 p-4 rounded-lg hover:bg-gray-700 transition">
+                <h3 class="text-lg font-semibold text-gray-200">&gt;_ Terminal</h3>
+                <p class="text-gray-400">Shell on hosts, LXC consoles and containers</p>
+            </a>
+            <a href="/files" class="bg-gray-800 p-4 rounded-lg hover:bg-gray-700 transition">
+                <h3 class="text-lg font-semibold text-cyan-400">📁 Files</h3>
+                <p class="text-gray-400">Browse and edit files on hosts over SFTP</p>
+            </a>
+            <a href="/tunnels" class="bg-gray-800 p-4 rounded-lg hover:bg-gray-700 transition">
+                <h3 class="text-lg font-semibold text-indigo-400">🔀 Tunnels</h3>
+                <p class="text-gray-400">Reach internal service UIs through SSH</p>
+            </a>
+            <a href="/jobs" class="bg-gray-800 p-4 rounded-lg hover:bg-gray-700 transition">
+                <h3 class="text-lg font-semibold text-purple-400">⏱ Jobs</h3>
+                <p class="text-gray-400">Follow or cancel running deploys, pulls and rebuilds</p>
+            </a>
+            <a href="/ssh/hostkeys" class="bg-gray-800 p-4 rounded-lg hover:bg-gray-700 transition">
+                <h3 class="text-lg font-semibold text-red-400">🔑 Host Keys</h3>
+                <p class="text-gray-400">Review changed or unknown SSH host keys</p>
+            </a>
         </div>
     </main>
 
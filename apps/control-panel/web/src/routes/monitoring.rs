@@ -13,8 +13,9 @@ pub async fn dashboard(
     State(state): State<Arc<AppState>>,
     path: Option<Path<String>>,
 ) -> Html<String> {
+    let config = state.config.load();
     // Get grafana config with fallback to empty
-    let grafana = state.config.grafana.as_ref();
+    let grafana = config.grafana.as_ref();
     let empty_dashboards = Vec::new();
     let dashboards = grafana.map(|g| &g.dashboards).unwrap_or(&empty_dashboards);
     let base_url = grafana.map(|g| g.base_url.as_str()).unwrap_or("");
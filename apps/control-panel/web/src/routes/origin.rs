@@ -0,0 +1,108 @@
+//! Same-origin check for requests that change something
+//!
+//! Local connections skip auth and browsers resend cached Basic
+//! credentials, so without this any page the operator visits could post
+//! forms here (run batch commands, save files, accept host keys).
+
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header, HeaderMap, Method, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use control_panel_core::SharedConfig;
+
+/// Refuse requests other than GET and HEAD whose `Origin` (or, without
+/// one, `Referer`) isn't this panel or one of `server.allowed_origins`.
+/// Requests naming neither are refused too.
+pub async fn same_origin_middleware(
+    State(config): State<SharedConfig>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    if matches!(*request.method(), Method::GET | Method::HEAD) {
+        return next.run(request).await;
+    }
+
+    let config = config.load();
+    let headers = request.headers();
+    let source = header_value(headers, header::ORIGIN).or_else(|| header_value(headers, header::REFERER).map(referer_origin));
+    if source.is_some_and(|origin| same_origin(headers, origin, &config.server.allowed_origins)) {
+        return next.run(request).await;
+    }
+
+    tracing::warn!(
+        "Refused {} {} from origin {:?}",
+        request.method(),
+        request.uri().path(),
+        source.unwrap_or("(none)")
+    );
+    (StatusCode::FORBIDDEN, "Only this panel's own pages may make changes").into_response()
+}
+
+/// `Origin` is present and names the requested host, or an allowed origin
+pub fn origin_allowed(headers: &HeaderMap, allowed: &[String]) -> bool {
+    header_value(headers, header::ORIGIN).is_some_and(|origin| same_origin(headers, origin, allowed))
+}
+
+fn header_value(headers: &HeaderMap, name: header::HeaderName) -> Option<&str> {
+    headers.get(name).and_then(|v| v.to_str().ok()).map(str::trim)
+}
+
+/// `scheme://host[:port]` of a URL
+fn referer_origin(referer: &str) -> &str {
+    let Some(start) = referer.find("://").map(|i| i + 3) else {
+        return referer;
+    };
+    let end = referer[start..].find(['/', '?', '#']).map_or(referer.len(), |i| start + i);
+    &referer[..end]
+}
+
+fn same_origin(headers: &HeaderMap, origin: &str, allowed: &[String]) -> bool {
+    let origin = origin.trim_end_matches('/');
+    if allowed.iter().any(|a| a.trim_end_matches('/').eq_ignore_ascii_case(origin)) {
+        return true;
+    }
+    let Some(origin_host) = origin.strip_prefix("http://").or_else(|| origin.strip_prefix("https://")) else {
+        return false;
+    };
+    header_value(headers, header::HOST).is_some_and(|host| host.eq_ignore_ascii_case(origin_host))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(origin: Option<&str>, host: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::HOST, host.parse().unwrap());
+        if let Some(origin) = origin {
+            headers.insert(header::ORIGIN, origin.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_foreign_origin_is_refused() {
+        assert!(origin_allowed(&headers(Some("http://127.0.0.1:3000"), "127.0.0.1:3000"), &[]));
+        assert!(origin_allowed(&headers(Some("https://Panel.home"), "panel.home"), &[]));
+
+        assert!(!origin_allowed(&headers(Some("https://evil.example"), "127.0.0.1:3000"), &[]));
+        // Same host, other port: another local service's page
+        assert!(!origin_allowed(&headers(Some("http://127.0.0.1:8080"), "127.0.0.1:3000"), &[]));
+        assert!(!origin_allowed(&headers(Some("null"), "127.0.0.1:3000"), &[]));
+        assert!(!origin_allowed(&headers(None, "127.0.0.1:3000"), &[]));
+
+        let allowed = ["https://panel.example.com/".to_string()];
+        assert!(origin_allowed(&headers(Some("https://panel.example.com"), "10.0.0.5:3000"), &allowed));
+        assert!(!origin_allowed(&headers(Some("https://evil.example"), "10.0.0.5:3000"), &allowed));
+    }
+
+    #[test]
+    fn test_referer_origin() {
+        assert_eq!(referer_origin("http://127.0.0.1:3000/infra/batch?select=*"), "http://127.0.0.1:3000");
+        assert_eq!(referer_origin("https://panel.home"), "https://panel.home");
+        assert_eq!(referer_origin("https://evil.example#x"), "https://evil.example");
+    }
+}
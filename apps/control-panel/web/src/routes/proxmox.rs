@@ -4,12 +4,21 @@ use axum::{
     extract::{Path, State},
     response::Html,
 };
+use control_panel_core::infra::proxmox::{ctid_collisions, list_all_containers};
 use std::sync::Arc;
 
+use super::html_escape;
 use crate::AppState;
 
 /// Proxmox dashboard
 pub async fn dashboard(State(state): State<Arc<AppState>>) -> Html<String> {
+    let config = state.config.load();
+    let endpoints = config
+        .proxmox
+        .iter()
+        .map(|p| format!("{} ({})", html_escape(&p.name), html_escape(&p.host)))
+        .collect::<Vec<_>>()
+        .join(", ");
     Html(format!(
         r##"<!DOCTYPE html>
 <html lang="en">
@@ -39,7 +48,7 @@ pub async fn dashboard(State(state): State<Arc<AppState>>) -> Html<String> {
         <h2 class="text-xl font-semibold mb-6">Proxmox Container Management</h2>
 
         <div class="mb-4 text-gray-400">
-            Host: {host}
+            Endpoints: {endpoints}
         </div>
 
         <div id="proxmox-containers" hx-get="/proxmox/containers" hx-trigger="load, every 60s" hx-swap="innerHTML">
@@ -48,99 +57,200 @@ pub async fn dashboard(State(state): State<Arc<AppState>>) -> Html<String> {
     </main>
 </body>
 </html>"##,
-        host = state.config.proxmox.host
+        endpoints = endpoints
     ))
 }
 
-/// Proxmox containers list fragment (auto-refreshed)
+/// Proxmox containers fragment, grouped by cluster and endpoint
+/// (auto-refreshed)
 pub async fn containers_fragment(State(state): State<Arc<AppState>>) -> Html<String> {
-    let mut ssh_pool = state.ssh_pool.write().await;
+    let config = state.config.load();
+    let lists = list_all_containers(&state.ssh_pool, &config.proxmox).await;
+    let collisions = ctid_collisions(&lists);
 
-    let containers = control_panel_core::infra::proxmox::list_containers(&mut ssh_pool)
-        .await
-        .unwrap_or_default();
+    // Configured host of each container, to tell colliding CTIDs apart
+    let host_of = |endpoint: &str, ctid: u32| {
+        config
+            .docker_nodes
+            .iter()
+            .filter(|n| n.ctid == ctid && config.get_node_proxmox(n).is_some_and(|e| e.name == endpoint))
+            .map(|n| n.name.clone())
+            .chain(
+                config
+                    .profiles
+                    .iter()
+                    .filter(|p| p.ctid == Some(ctid) && config.get_profile_proxmox(p).is_some_and(|e| e.name == endpoint))
+                    .map(|p| p.name.clone()),
+            )
+            .next()
+    };
 
-    let containers_html = containers
+    let mut clusters: Vec<&str> = Vec::new();
+    for list in &lists {
+        if !clusters.contains(&list.cluster.as_str()) {
+            clusters.push(&list.cluster);
+        }
+    }
+
+    let clusters_html = clusters
         .iter()
-        .map(|c| {
-            let status_color = match c.status.as_str() {
-                "running" => "text-green-500",
-                "stopped" => "text-red-500",
-                _ => "text-gray-500",
-            };
-            format!(
-                r##"<div class="flex items-center justify-between p-4 bg-gray-800 rounded-lg">
+        .map(|cluster| {
+            let endpoints_html = lists
+                .iter()
+                .filter(|list| list.cluster == *cluster)
+                .map(|list| {
+                    let body = match list.containers {
+                        Err(ref e) => super::ssh::error_html(e),
+                        Ok(ref containers) if containers.is_empty() => {
+                            r##"<div class="text-gray-500">No containers</div>"##.to_string()
+                        }
+                        Ok(ref containers) => containers
+                            .iter()
+                            .map(|c| {
+                                let status_color = match c.status.as_str() {
+                                    "running" => "text-green-500",
+                                    "stopped" => "text-red-500",
+                                    _ => "text-gray-500",
+                                };
+                                let collides = if collisions.iter().any(|collision| collision.ctid == c.ctid) {
+                                    r##"<span class="text-amber-400 ml-2" title="CTID also used on another endpoint">⚠ shared CTID</span>"##
+                                } else {
+                                    ""
+                                };
+                                let host = host_of(&list.endpoint, c.ctid)
+                                    .map(|h| format!(r##"<span class="text-blue-300 ml-2">{}</span>"##, html_escape(&h)))
+                                    .unwrap_or_default();
+                                let base = format!("/proxmox/{}/{}", html_escape(&list.endpoint), c.ctid);
+                                format!(
+                                    r##"<div class="flex items-center justify-between p-4 bg-gray-800 rounded-lg">
                     <div>
-                        <span class="font-semibold">CTID {}</span>
-                        <span class="text-gray-400 ml-2">{}</span>
-                        <span class="{} ml-2">{}</span>
+                        <span class="font-semibold">CTID {ctid}</span>
+                        <span class="text-gray-400 ml-2">{name}</span>
+                        <span class="{status_color} ml-2">{status}</span>{host}{collides}
                     </div>
                     <div class="flex gap-2">
-                        <button hx-post="/proxmox/{}/start" hx-swap="none" class="px-3 py-1 bg-green-600 rounded">Start</button>
-                        <button hx-post="/proxmox/{}/stop" hx-swap="none" class="px-3 py-1 bg-red-600 rounded">Stop</button>
-                        <button hx-post="/proxmox/{}/restart" hx-swap="none" class="px-3 py-1 bg-blue-600 rounded">Restart</button>
+                        <button hx-post="{base}/start" hx-swap="none" class="px-3 py-1 bg-green-600 rounded">Start</button>
+                        <button hx-post="{base}/stop" hx-swap="none" class="px-3 py-1 bg-red-600 rounded">Stop</button>
+                        <button hx-post="{base}/restart" hx-swap="none" class="px-3 py-1 bg-blue-600 rounded">Restart</button>
+                        <a href="/terminal/lxc/{endpoint}/{ctid}" target="_blank" class="px-3 py-1 bg-gray-700 rounded">Console</a>
                     </div>
                 </div>"##,
-                c.ctid, c.name, status_color, c.status, c.ctid, c.ctid, c.ctid
+                                    ctid = c.ctid,
+                                    name = html_escape(&c.name),
+                                    status_color = status_color,
+                                    status = html_escape(&c.status),
+                                    host = host,
+                                    collides = collides,
+                                    base = base,
+                                    endpoint = html_escape(&list.endpoint),
+                                )
+                            })
+                            .collect::<Vec<_>>()
+                            .join("\n"),
+                    };
+                    format!(
+                        r##"<div class="space-y-2">
+                <h4 class="text-gray-300">{} <span class="text-gray-500 text-sm">{}</span></h4>
+                {}
+            </div>"##,
+                        html_escape(&list.endpoint),
+                        html_escape(&list.host),
+                        body
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            format!(
+                r##"<section class="space-y-4">
+            <h3 class="text-lg font-semibold text-blue-300">Cluster {}</h3>
+            {}
+        </section>"##,
+                html_escape(cluster),
+                endpoints_html
             )
         })
         .collect::<Vec<_>>()
         .join("\n");
 
     Html(format!(
-        r##"<div class="space-y-4">{}</div>"##,
-        containers_html
+        r##"{}<div class="space-y-8">{}</div>"##,
+        collisions_html(&collisions),
+        clusters_html
     ))
 }
 
+/// Summary of CTIDs in use on more than one endpoint
+fn collisions_html(collisions: &[control_panel_core::CtidCollision]) -> String {
+    if collisions.is_empty() {
+        return String::new();
+    }
+    let rows = collisions
+        .iter()
+        .map(|c| {
+            let uses = c
+                .containers
+                .iter()
+                .map(|(endpoint, name)| format!("{} on {}", html_escape(name), html_escape(endpoint)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let scope = if c.across_clusters { "across clusters" } else { "within a cluster" };
+            format!(r##"<li><span class="font-semibold">CTID {}</span> {}: {}</li>"##, c.ctid, scope, uses)
+        })
+        .collect::<String>();
+    format!(
+        r##"<div class="bg-amber-900/30 border border-amber-700 rounded-lg p-4 mb-6">
+            <h3 class="font-semibold text-amber-400 mb-2">CTIDs used on more than one endpoint</h3>
+            <p class="text-sm text-gray-400 mb-2">Bind profiles and docker nodes to their endpoint with <code>proxmox = "..."</code> so commands reach the right container.</p>
+            <ul class="text-sm space-y-1">{}</ul>
+        </div>"##,
+        rows
+    )
+}
+
 /// Start container
 pub async fn start(
     State(state): State<Arc<AppState>>,
-    Path(ctid): Path<u32>,
+    Path((endpoint, ctid)): Path<(String, u32)>,
 ) -> Html<String> {
-    let mut ssh_pool = state.ssh_pool.write().await;
 
-    match control_panel_core::infra::proxmox::start_container(&mut ssh_pool, ctid).await {
+    match control_panel_core::infra::proxmox::start_container(&state.ssh_pool, &endpoint, ctid).await {
         Ok(_) => Html(format!("<div class='text-green-500'>Started CTID {}</div>", ctid)),
-        Err(e) => Html(format!("<div class='text-red-500'>Error: {}</div>", e)),
+        Err(e) => Html(format!("<div class='text-red-500'>Error: {}</div>", html_escape(&e.to_string()))),
     }
 }
 
 /// Stop container
 pub async fn stop(
     State(state): State<Arc<AppState>>,
-    Path(ctid): Path<u32>,
+    Path((endpoint, ctid)): Path<(String, u32)>,
 ) -> Html<String> {
-    let mut ssh_pool = state.ssh_pool.write().await;
 
-    match control_panel_core::infra::proxmox::stop_container(&mut ssh_pool, ctid).await {
+    match control_panel_core::infra::proxmox::stop_container(&state.ssh_pool, &endpoint, ctid).await {
         Ok(_) => Html(format!("<div class='text-green-500'>Stopped CTID {}</div>", ctid)),
-        Err(e) => Html(format!("<div class='text-red-500'>Error: {}</div>", e)),
+        Err(e) => Html(format!("<div class='text-red-500'>Error: {}</div>", html_escape(&e.to_string()))),
     }
 }
 
 /// Restart container
 pub async fn restart(
     State(state): State<Arc<AppState>>,
-    Path(ctid): Path<u32>,
+    Path((endpoint, ctid)): Path<(String, u32)>,
 ) -> Html<String> {
-    let mut ssh_pool = state.ssh_pool.write().await;
 
-    match control_panel_core::infra::proxmox::restart_container(&mut ssh_pool, ctid).await {
+    match control_panel_core::infra::proxmox::restart_container(&state.ssh_pool, &endpoint, ctid).await {
         Ok(_) => Html(format!("<div class='text-green-500'>Restarted CTID {}</div>", ctid)),
-        Err(e) => Html(format!("<div class='text-red-500'>Error: {}</div>", e)),
+        Err(e) => Html(format!("<div class='text-red-500'>Error: {}</div>", html_escape(&e.to_string()))),
     }
 }
 
 /// Get container status
 pub async fn status(
     State(state): State<Arc<AppState>>,
-    Path(ctid): Path<u32>,
+    Path((endpoint, ctid)): Path<(String, u32)>,
 ) -> Html<String> {
-    let mut ssh_pool = state.ssh_pool.write().await;
 
-    match control_panel_core::infra::proxmox::get_container_status(&mut ssh_pool, ctid).await {
-        Ok(status) => Html(format!("<div>CTID {}: {}</div>", ctid, status)),
-        Err(e) => Html(format!("<div class='text-red-500'>Error: {}</div>", e)),
+    match control_panel_core::infra::proxmox::get_container_status(&state.ssh_pool, &endpoint, ctid).await {
+        Ok(status) => Html(format!("<div>CTID {}: {}</div>", ctid, html_escape(&status))),
+        Err(e) => Html(format!("<div class='text-red-500'>Error: {}</div>", html_escape(&e.to_string()))),
     }
 }
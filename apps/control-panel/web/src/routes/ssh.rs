@@ -0,0 +1,145 @@
+//! SSH host key routes (review and accept rejected keys)
+
+use axum::{extract::State, response::Html, Form};
+use control_panel_core::AppError;
+use serde::Deserialize;
+use std::sync::Arc;
+
+use super::html_escape;
+use crate::AppState;
+
+/// Render an error, with a link to the host key review page for host key failures
+pub fn error_html(e: &AppError) -> String {
+    match e {
+        AppError::HostKeyMismatch { host, fingerprint } | AppError::HostKeyUnknown { host, fingerprint } => {
+            let title = if matches!(e, AppError::HostKeyMismatch { .. }) {
+                "Host key changed"
+            } else {
+                "Unknown host key"
+            };
+            format!(
+                r##"<div class="bg-red-900/40 border border-red-700 rounded p-3 text-sm">
+                    <span class="text-red-400 font-semibold">{title}</span> for <span class="font-mono">{host}</span>
+                    <p class="font-mono text-xs text-gray-300 mt-1">{fingerprint}</p>
+                    <a href="/ssh/hostkeys" class="text-blue-400 hover:text-blue-300">Review host keys &rarr;</a>
+                </div>"##,
+                title = title,
+                host = html_escape(host),
+                fingerprint = html_escape(fingerprint),
+            )
+        }
+        _ => format!("<div class='text-red-500'>Error: {}</div>", html_escape(&e.to_string())),
+    }
+}
+
+/// Host key review page
+pub async fn host_keys_page(State(state): State<Arc<AppState>>) -> Html<String> {
+    let pending = state.ssh_pool.host_keys().pending();
+
+    let rows_html = if pending.is_empty() {
+        r##"<div class="text-gray-500">No rejected host keys.</div>"##.to_string()
+    } else {
+        pending
+            .iter()
+            .map(|k| {
+                let (label, color, action) = match k.reason {
+                    control_panel_core::ssh::RejectReason::Changed => ("CHANGED", "text-red-400", "Replace key"),
+                    control_panel_core::ssh::RejectReason::Unknown => ("UNKNOWN", "text-yellow-400", "Accept key"),
+                };
+                format!(
+                    r##"<div class="flex items-center justify-between p-4 bg-gray-800 rounded-lg">
+                        <div>
+                            <span class="{color} font-semibold">{label}</span>
+                            <span class="font-semibold ml-2">{host}:{port}</span>
+                            <p class="font-mono text-sm text-gray-400">{algorithm} {fingerprint}</p>
+                        </div>
+                        <form hx-post="/ssh/hostkeys/accept" hx-target="#hostkey-result" hx-swap="beforeend">
+                            <input type="hidden" name="host" value="{host}">
+                            <input type="hidden" name="port" value="{port}">
+                            <input type="hidden" name="fingerprint" value="{fingerprint}">
+                            <button class="px-3 py-1 bg-amber-600 hover:bg-amber-700 rounded text-sm">{action}</button>
+                        </form>
+                    </div>"##,
+                    color = color,
+                    label = label,
+                    host = html_escape(&k.host),
+                    port = k.port,
+                    algorithm = html_escape(&k.algorithm),
+                    fingerprint = html_escape(&k.fingerprint),
+                    action = action,
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    Html(format!(
+        r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Host Keys - Control Panel</title>
+    <script src="https://unpkg.com/htmx.org@1.9.10"></script>
+    <script src="https://cdn.tailwindcss.com"></script>
+    <style>body {{ background-color: #1a1a2e; color: #eee; }}</style>
+</head>
+<body class="min-h-screen">
+    <nav class="bg-gray-800 border-b border-gray-700 px-6 py-4">
+        <div class="flex items-center justify-between">
+            <h1 class="text-2xl font-bold text-blue-400">NixOS Control Panel</h1>
+            <div class="flex gap-4">
+                <a href="/" class="text-gray-400 hover:text-gray-300">Infrastructure</a>
+                <a href="/docker" class="text-gray-400 hover:text-gray-300">Docker</a>
+                <a href="/proxmox" class="text-gray-400 hover:text-gray-300">Proxmox</a>
+                <a href="/monitoring" class="text-gray-400 hover:text-gray-300">Monitoring</a>
+                <a href="/editor" class="text-gray-400 hover:text-gray-300">Editor</a>
+            </div>
+        </div>
+    </nav>
+
+    <main class="container mx-auto px-6 py-8">
+        <h2 class="text-xl font-semibold mb-2">🔑 SSH Host Keys</h2>
+        <p class="text-gray-400 mb-6">
+            Keys rejected during a connection. Compare the fingerprint with
+            <code class="bg-gray-800 px-2 py-1 rounded">ssh-keygen -lf /etc/ssh/ssh_host_ed25519_key.pub</code>
+            on the host before accepting.
+        </p>
+
+        <div class="space-y-3 mb-6">
+            {rows_html}
+        </div>
+
+        <div id="hostkey-result" class="space-y-1"></div>
+    </main>
+</body>
+</html>"##,
+        rows_html = rows_html
+    ))
+}
+
+#[derive(Deserialize)]
+pub struct AcceptForm {
+    host: String,
+    port: u16,
+    /// The fingerprint shown on the page, so a key swapped in since can't be accepted
+    fingerprint: String,
+}
+
+/// Accept (or replace) the pending key for a host
+pub async fn accept_host_key(
+    State(state): State<Arc<AppState>>,
+    Form(form): Form<AcceptForm>,
+) -> Html<String> {
+    let host_keys = state.ssh_pool.host_keys().clone();
+
+    match host_keys.accept(&form.host, form.port, &form.fingerprint) {
+        Ok(key) => Html(format!(
+            "<div class='text-green-500'>Trusted {}:{} ({})</div>",
+            html_escape(&key.host),
+            key.port,
+            html_escape(&key.fingerprint)
+        )),
+        Err(e) => Html(format!("<div class='text-red-500'>Error: {}</div>", html_escape(&e.to_string()))),
+    }
+}
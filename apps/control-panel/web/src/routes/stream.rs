@@ -0,0 +1,549 @@
+//! Server-Sent Events for live command output
+//!
+//! Long-running operations (deploys, image pulls, stack rebuilds) are started
+//! with a POST, which returns a job id. The job runs on its own, whether or
+//! not anyone watches: closing the page or a dropped connection leaves the
+//! remote command running, and only an explicit cancel stops it. Its output
+//! is kept (up to `MAX_JOB_OUTPUT`) and sent to every EventSource that
+//! attaches to `GET …/stream/{id}` as numbered `stdout` / `stderr` events,
+//! followed by a single `exit` event with the exit code. Failures before the
+//! command starts are sent as a `failed` event, a cancel as `cancelled`.
+//! Browsers reconnect with `Last-Event-ID` and get only what they missed.
+//!
+//! Followed logs are sent as parsed `line` events instead, and container
+//! inventory changes as `changed` events; those stop when their EventSource
+//! closes.
+
+use axum::extract::{Path, State};
+use axum::http::HeaderMap;
+use axum::response::sse::{Event, KeepAlive, KeepAliveStream, Sse};
+use chrono::{DateTime, Local};
+use control_panel_core::docker::logs::chunk_lines;
+use control_panel_core::{AppError, CommandStream, InventoryChange, OutputChunk};
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::hash::BuildHasher;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, watch};
+use tokio::task::AbortHandle;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::{Stream, StreamExt};
+
+use crate::AppState;
+
+/// Boxed SSE event stream
+type EventStream = Pin<Box<dyn Stream<Item = Result<Event, Infallible>> + Send>>;
+
+/// SSE response type shared by all streaming routes
+pub type CommandSse = Sse<KeepAliveStream<EventStream>>;
+
+/// Output kept per job; the oldest events are dropped beyond this
+const MAX_JOB_OUTPUT: usize = 4 * 1024 * 1024;
+
+/// Finished jobs stay available for re-attaching this long
+const FINISHED_JOB_TTL: Duration = Duration::from_secs(3600);
+
+/// Where a job stands
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JobStatus {
+    Running,
+    Exited(u32),
+    /// Failed to start, or ended without an exit status
+    Failed,
+    Cancelled,
+}
+
+/// Events of a job so far, numbered from 0 (the SSE event id)
+#[derive(Default)]
+struct JobLog {
+    /// Number of the first event still held
+    first: usize,
+    events: VecDeque<(&'static str, String)>,
+    bytes: usize,
+    finished: Option<Instant>,
+}
+
+/// A command running independently of any viewer
+struct Job {
+    label: String,
+    started: DateTime<Local>,
+    status: Mutex<JobStatus>,
+    log: Mutex<JobLog>,
+    /// Bumped on every new event
+    changed: watch::Sender<()>,
+    task: Mutex<Option<AbortHandle>>,
+}
+
+impl Job {
+    fn status(&self) -> JobStatus {
+        *self.status.lock().unwrap()
+    }
+
+    /// Record an event; `end` finishes the job unless it already finished
+    fn push(&self, kind: &'static str, data: String, end: Option<JobStatus>) {
+        let mut status = self.status.lock().unwrap();
+        if *status != JobStatus::Running {
+            return;
+        }
+        {
+            let mut log = self.log.lock().unwrap();
+            log.bytes += data.len();
+            log.events.push_back((kind, data));
+            while log.bytes > MAX_JOB_OUTPUT && log.events.len() > 1 {
+                if let Some((_, dropped)) = log.events.pop_front() {
+                    log.bytes -= dropped.len();
+                    log.first += 1;
+                }
+            }
+            if let Some(end) = end {
+                *status = end;
+                log.finished = Some(Instant::now());
+            }
+        }
+        self.changed.send_replace(());
+    }
+
+    /// Events from number `from` on, and whether the job has finished
+    fn events_from(&self, from: usize) -> (usize, Vec<(&'static str, String)>, bool) {
+        let log = self.log.lock().unwrap();
+        let from = from.max(log.first);
+        let events = log.events.iter().skip(from - log.first).cloned().collect();
+        (from, events, log.finished.is_some())
+    }
+}
+
+/// Relay a command's output into its job until it ends
+async fn run_job(job: Arc<Job>, mut stream: CommandStream) {
+    while let Some(chunk) = stream.next().await {
+        match chunk {
+            OutputChunk::Stdout(text) => job.push("stdout", text.trim_end_matches('\n').to_string(), None),
+            OutputChunk::Stderr(text) => job.push("stderr", text.trim_end_matches('\n').to_string(), None),
+            OutputChunk::Exit(code) => job.push("exit", code.to_string(), Some(JobStatus::Exited(code))),
+        }
+    }
+    job.push("failed", "Command ended without an exit status".to_string(), Some(JobStatus::Failed));
+}
+
+/// Summary of a job for the jobs page
+pub struct JobInfo {
+    pub id: String,
+    pub label: String,
+    pub started: DateTime<Local>,
+    pub status: JobStatus,
+}
+
+/// Long-running commands (deploys, pulls, rebuilds) started by a POST. They
+/// run on their own task, independent of any EventSource: viewers attach by
+/// id with `GET …/stream/{id}` (resuming after `Last-Event-ID` on reconnect)
+/// and only `cancel` stops them.
+#[derive(Clone, Default)]
+pub struct StreamJobs {
+    jobs: Arc<Mutex<HashMap<String, Arc<Job>>>>,
+    next: Arc<AtomicU64>,
+    hasher: Arc<RandomState>,
+}
+
+impl StreamJobs {
+    /// Run a started command (or record why it couldn't start) and return
+    /// the id to attach with
+    pub fn start(&self, label: impl Into<String>, output: Result<CommandStream, AppError>) -> String {
+        let id = format!("{:016x}", self.hasher.hash_one(self.next.fetch_add(1, Ordering::Relaxed)));
+        let job = Arc::new(Job {
+            label: label.into(),
+            started: Local::now(),
+            status: Mutex::new(JobStatus::Running),
+            log: Mutex::default(),
+            changed: watch::Sender::new(()),
+            task: Mutex::default(),
+        });
+
+        match output {
+            Ok(stream) => {
+                let task = tokio::spawn(run_job(job.clone(), stream));
+                *job.task.lock().unwrap() = Some(task.abort_handle());
+            }
+            Err(e) => job.push("failed", e.to_string(), Some(JobStatus::Failed)),
+        }
+
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.retain(|_, job| job.log.lock().unwrap().finished.is_none_or(|at| at.elapsed() < FINISHED_JOB_TTL));
+        jobs.insert(id.clone(), job);
+        id
+    }
+
+    /// Stop a running job, which closes its SSH channel. `false` if the job
+    /// is unknown or already finished.
+    pub fn cancel(&self, id: &str) -> bool {
+        let Some(job) = self.jobs.lock().unwrap().get(id).cloned() else {
+            return false;
+        };
+        if job.status() != JobStatus::Running {
+            return false;
+        }
+        if let Some(task) = job.task.lock().unwrap().take() {
+            task.abort();
+        }
+        job.push("cancelled", "Cancelled".to_string(), Some(JobStatus::Cancelled));
+        tracing::info!("Cancelled job {} ({})", id, job.label);
+        true
+    }
+
+    /// Jobs still held, newest first
+    pub fn list(&self) -> Vec<JobInfo> {
+        let mut jobs: Vec<JobInfo> = self
+            .jobs
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, job)| JobInfo {
+                id: id.clone(),
+                label: job.label.clone(),
+                started: job.started,
+                status: job.status(),
+            })
+            .collect();
+        jobs.sort_by_key(|job| std::cmp::Reverse(job.started));
+        jobs
+    }
+
+    /// A job's events from number `from` on, as SSE events, following new
+    /// ones until the job ends. `None` if the job is unknown.
+    fn follow(&self, id: &str, mut from: usize) -> Option<ReceiverStream<Result<Event, Infallible>>> {
+        let job = self.jobs.lock().unwrap().get(id).cloned()?;
+        let (tx, rx) = mpsc::channel(64);
+        tokio::spawn(async move {
+            let mut changed = job.changed.subscribe();
+            loop {
+                let (first, events, finished) = job.events_from(from);
+                for (number, (kind, data)) in (first..).zip(events) {
+                    let event = Event::default().id(number.to_string()).event(kind).data(data);
+                    if tx.send(Ok(event)).await.is_err() {
+                        return;
+                    }
+                    from = number + 1;
+                }
+                if finished || changed.changed().await.is_err() {
+                    return;
+                }
+            }
+        });
+        Some(ReceiverStream::new(rx))
+    }
+}
+
+/// Attach to a job started by a POST (SSE stream), resuming after
+/// `Last-Event-ID` when the browser reconnects. The other path parameters
+/// only mirror the POST route; the job id alone selects the job.
+pub async fn attach(
+    State(state): State<Arc<AppState>>,
+    Path(params): Path<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> CommandSse {
+    let id = params.get("id").map(String::as_str).unwrap_or_default();
+    let from = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok())
+        .map_or(0, |last| last + 1);
+
+    let events: EventStream = match state.jobs.follow(id, from) {
+        Some(events) => Box::pin(events),
+        None => Box::pin(tokio_stream::once(Ok(Event::default()
+            .event("failed")
+            .data(format!("Unknown job '{}' (finished over an hour ago?)", id))))),
+    };
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+/// Turn a log follow stream into an SSE response: a `lines` event per output
+/// chunk (JSON array of `{stream, ts, source, text}`), then `exit` when the
+/// follower ends. The remote follower is stopped when the EventSource closes.
+pub fn log_sse(result: Result<CommandStream, AppError>, stack: bool) -> CommandSse {
+    let events: EventStream = match result {
+        Ok(stream) => Box::pin(stream.map(move |chunk| {
+            let event = match chunk {
+                OutputChunk::Exit(code) => Event::default().event("exit").data(code.to_string()),
+                ref output => {
+                    let lines: Vec<_> = chunk_lines(output, stack)
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|line| {
+                            serde_json::json!({
+                                "stream": line.stream,
+                                "ts": line.timestamp_text(),
+                                "source": line.source,
+                                "text": line.text,
+                            })
+                        })
+                        .collect();
+                    Event::default().event("lines").data(serde_json::Value::from(lines).to_string())
+                }
+            };
+            Ok(event)
+        })),
+        Err(e) => Box::pin(tokio_stream::once(Ok(Event::default()
+            .event("failed")
+            .data(e.to_string())))),
+    };
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+/// Container inventory changes as `changed` events (an `InventoryChange` as
+/// JSON), the current state first
+pub fn inventory_sse(changes: ReceiverStream<InventoryChange>) -> CommandSse {
+    let events: EventStream = Box::pin(changes.map(|change| {
+        Ok(Event::default()
+            .event("changed")
+            .data(serde_json::to_string(&change).unwrap_or_default()))
+    }));
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+/// Client side of the log viewer page. Reads its settings from
+/// `#log-viewer` (`data-stream`: SSE URL, `data-file`: download name) and
+/// drives the controls inside it. Pausing keeps the follower running and
+/// buffers new lines; the filter and stream toggles only hide lines.
+pub const LOG_VIEWER_JS: &str = r##"<style>
+.hide-ts .log-ts { display: none; }
+</style>
+<script>
+document.addEventListener('DOMContentLoaded', () => {
+    const viewer = document.getElementById('log-viewer');
+    const out = document.getElementById('log-output');
+    const $ = id => document.getElementById(id);
+    const MAX_LINES = 5000;
+    let es = null, paused = false, pending = [], lines = [];
+
+    const setStatus = (text, cls) => { $('log-status').textContent = text; $('log-status').className = cls; };
+    const matches = l => {
+        if (!$('log-stdout').checked && l.stream === 'stdout') return false;
+        if (!$('log-stderr').checked && l.stream === 'stderr') return false;
+        const filter = $('log-filter').value.toLowerCase();
+        return !filter || (l.source || '').toLowerCase().includes(filter) || l.text.toLowerCase().includes(filter);
+    };
+    const show = l => {
+        const row = document.createElement('div');
+        row.className = l.stream === 'stderr' ? 'text-red-300' : '';
+        const ts = document.createElement('span');
+        ts.className = 'log-ts text-gray-500';
+        ts.textContent = l.ts ? l.ts.replace('T', ' ').slice(0, 23) + ' ' : '';
+        const source = document.createElement('span');
+        source.className = 'text-blue-300';
+        source.textContent = l.source ? l.source + ' | ' : '';
+        row.append(ts, source, document.createTextNode(l.text));
+        row.log = l;
+        row.style.display = matches(l) ? '' : 'none';
+        out.appendChild(row);
+        lines.push(l);
+        if (lines.length > MAX_LINES) { lines.shift(); out.firstChild.remove(); }
+        if ($('log-scroll').checked) out.scrollTop = out.scrollHeight;
+    };
+    const stop = () => {
+        if (es) { es.close(); es = null; setStatus('STOPPED', 'text-gray-400'); }
+    };
+    const connect = () => {
+        stop();
+        out.innerHTML = '';
+        lines = []; pending = [];
+        const params = new URLSearchParams({ since: $('log-since').value, tail: $('log-tail').value });
+        es = new EventSource(viewer.dataset.stream + '?' + params);
+        setStatus('FOLLOWING', 'text-green-400');
+        es.addEventListener('lines', e => {
+            for (const l of JSON.parse(e.data)) {
+                if (paused) pending.push(l);
+                else show(l);
+            }
+            if (paused) $('log-pause').textContent = 'Resume (' + pending.length + ' new)';
+        });
+        es.addEventListener('exit', e => { es.close(); es = null; setStatus('ENDED (exit ' + e.data + ')', 'text-yellow-400'); });
+        es.addEventListener('failed', e => {
+            show({ stream: 'stderr', text: e.data });
+            es.close(); es = null; setStatus('FAIL', 'text-red-400');
+        });
+        es.onerror = () => { if (es) { es.close(); es = null; setStatus('DISCONNECTED', 'text-yellow-400'); } };
+    };
+    const refilter = () => { for (const row of out.children) row.style.display = matches(row.log) ? '' : 'none'; };
+
+    $('log-start').onclick = connect;
+    $('log-stop').onclick = stop;
+    $('log-pause').onclick = () => {
+        paused = !paused;
+        if (!paused) { pending.forEach(show); pending = []; }
+        $('log-pause').textContent = paused ? 'Resume' : 'Pause';
+    };
+    $('log-clear').onclick = () => { out.innerHTML = ''; lines = []; };
+    $('log-filter').oninput = refilter;
+    $('log-stdout').onchange = refilter;
+    $('log-stderr').onchange = refilter;
+    $('log-ts').onchange = () => out.classList.toggle('hide-ts', !$('log-ts').checked);
+    $('log-download').onclick = () => {
+        const text = lines.map(l => [l.ts, l.source && l.source + ' |', l.stream === 'stderr' && '[stderr]', l.text]
+            .filter(Boolean).join(' ')).join('\n') + '\n';
+        const a = document.createElement('a');
+        a.href = URL.createObjectURL(new Blob([text], { type: 'text/plain' }));
+        a.download = viewer.dataset.file + '-' + new Date().toISOString().replace(/[:.]/g, '-') + '.log';
+        a.click();
+        URL.revokeObjectURL(a.href);
+    };
+    window.addEventListener('pagehide', stop);
+    connect();
+});
+</script>"##;
+
+/// Client-side helpers. `streamTo(url, targetId, label)` starts a job with a
+/// POST to `url` and shows its live output from `url/{id}` in a console block
+/// appended to `targetId`; `attachTo(streamUrl, jobId, targetId, label)` shows
+/// a job that is already running. Both return a promise that resolves to
+/// true once the command exits with 0 (false otherwise), so batches can run
+/// one host after the other. A dropped connection is left to the browser's
+/// reconnect, which resumes the output; the block's Cancel button is the
+/// only thing that stops the job.
+pub const STREAM_JS: &str = r##"<script>
+function consoleBlock(targetId, label) {
+    const target = document.getElementById(targetId);
+    const block = document.createElement('div');
+    block.className = 'border-b border-gray-700 py-2';
+    const ts = document.createElement('span');
+    ts.className = 'text-gray-500';
+    ts.textContent = '[' + new Date().toLocaleTimeString('en-GB') + '] ';
+    const status = document.createElement('span');
+    const title = document.createElement('span');
+    title.textContent = ' ' + label;
+    const cancel = document.createElement('button');
+    cancel.className = 'ml-2 px-2 py-0.5 bg-red-700 hover:bg-red-600 rounded text-xs hidden';
+    cancel.textContent = 'Cancel';
+    const pre = document.createElement('pre');
+    pre.className = 'text-gray-400 ml-4 text-xs whitespace-pre-wrap';
+    block.append(ts, status, title, cancel, pre);
+    target.appendChild(block);
+
+    let resolve;
+    const view = {
+        done: new Promise(r => resolve = r),
+        finished: false,
+        cancel,
+        status: (text, cls) => { status.textContent = text; status.className = cls; },
+        append: (text, cls) => {
+            const line = document.createElement('span');
+            if (cls) line.className = cls;
+            line.textContent = text + '\n';
+            pre.appendChild(line);
+            target.scrollTop = target.scrollHeight;
+        },
+        finish: (text, cls, ok) => {
+            view.finished = true;
+            view.status(text, cls);
+            cancel.remove();
+            resolve(!!ok);
+        },
+    };
+    view.status('STARTING', 'text-blue-400');
+    return view;
+}
+
+function attachTo(streamUrl, jobId, targetId, label, view) {
+    view = view || consoleBlock(targetId, label);
+    view.cancel.classList.remove('hidden');
+    view.cancel.onclick = () => {
+        if (confirm('Cancel ' + label + '? The remote command is stopped wherever it is.')) {
+            fetch('/jobs/' + encodeURIComponent(jobId) + '/cancel', { method: 'POST' });
+        }
+    };
+
+    const es = new EventSource(streamUrl);
+    const end = (text, cls, ok) => { es.close(); view.finish(text, cls, ok); };
+    es.onopen = () => view.status('RUNNING', 'text-blue-400');
+    es.addEventListener('stdout', e => view.append(e.data));
+    es.addEventListener('stderr', e => view.append(e.data, 'text-red-300'));
+    es.addEventListener('exit', e => {
+        if (e.data === '0') end('OK', 'text-green-400', true);
+        else end('FAIL (exit ' + e.data + ')', 'text-red-400');
+    });
+    es.addEventListener('failed', e => {
+        view.append(e.data, 'text-red-400');
+        end('FAIL', 'text-red-400');
+    });
+    es.addEventListener('cancelled', () => end('CANCELLED', 'text-yellow-400'));
+    // The browser reconnects by itself (sending Last-Event-ID) unless the
+    // server refused the stream outright
+    es.onerror = () => {
+        if (view.finished) return;
+        if (es.readyState === EventSource.CLOSED) end('DISCONNECTED', 'text-yellow-400');
+        else view.status('RECONNECTING', 'text-yellow-400');
+    };
+    return view.done;
+}
+
+function streamTo(url, targetId, label) {
+    const view = consoleBlock(targetId, label);
+    return fetch(url, { method: 'POST' })
+        .then(r => r.ok ? r.text() : Promise.reject(new Error('HTTP ' + r.status)))
+        .then(id => attachTo(url + '/' + encodeURIComponent(id), id, targetId, label, view))
+        .catch(e => {
+            view.append('Could not start: ' + e.message, 'text-red-400');
+            view.finish('FAIL', 'text-red-400');
+            return false;
+        });
+}
+</script>"##;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Number of events an attaching viewer gets, up to the end of the job
+    async fn follow_count(jobs: &StreamJobs, id: &str, from: usize) -> usize {
+        let mut events = jobs.follow(id, from).unwrap();
+        let mut count = 0;
+        while events.next().await.is_some() {
+            count += 1;
+        }
+        count
+    }
+
+    #[tokio::test]
+    async fn test_job_runs_without_viewer_and_replays() {
+        let jobs = StreamJobs::default();
+        let (tx, rx) = mpsc::channel(8);
+        let id = jobs.start("Deploy web", Ok(ReceiverStream::new(rx)));
+        tx.send(OutputChunk::Stdout("building\n".to_string())).await.unwrap();
+        tx.send(OutputChunk::Stdout("switching\n".to_string())).await.unwrap();
+        tx.send(OutputChunk::Exit(0)).await.unwrap();
+        drop(tx);
+
+        // Nobody watched; the output is still there, and resumes by number
+        while jobs.list()[0].status == JobStatus::Running {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(jobs.list()[0].status, JobStatus::Exited(0));
+        let job = jobs.jobs.lock().unwrap()[&id].clone();
+        let (_, events, finished) = job.events_from(1);
+        assert!(finished);
+        assert_eq!(events, vec![("stdout", "switching".to_string()), ("exit", "0".to_string())]);
+        assert_eq!(follow_count(&jobs, &id, 0).await, 3);
+        assert_eq!(follow_count(&jobs, &id, 2).await, 1);
+        assert!(jobs.follow("unknown", 0).is_none());
+        assert!(!jobs.cancel(&id));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_stops_job() {
+        let jobs = StreamJobs::default();
+        let (tx, rx) = mpsc::channel(8);
+        let id = jobs.start("Pull nginx", Ok(ReceiverStream::new(rx)));
+        assert!(jobs.cancel(&id));
+        assert_eq!(jobs.list()[0].status, JobStatus::Cancelled);
+        // The command's stream was dropped with the task
+        tx.closed().await;
+        assert!(!jobs.cancel(&id));
+
+        let failed = jobs.start("Deploy db", Err(AppError::Validation("no".to_string())));
+        assert_eq!(jobs.list().iter().find(|j| j.id == failed).unwrap().status, JobStatus::Failed);
+    }
+}
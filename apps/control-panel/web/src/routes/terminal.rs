@@ -0,0 +1,385 @@
+//! Browser terminal (xterm.js over a websocket)
+//!
+//! Protocol: the browser sends JSON text frames,
+//! `{"type":"input","data":"..."}` for keystrokes and
+//! `{"type":"resize","cols":120,"rows":40}` when the terminal is resized.
+//! The server sends raw terminal output as binary frames, and JSON text
+//! frames for `{"type":"exit","code":0}`, `{"type":"idle"}` and
+//! `{"type":"error","message":"..."}`.
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, Query, State,
+    },
+    http::{header, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use control_panel_core::{TerminalInput, TerminalOutput, TerminalTarget};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use super::html_escape;
+use super::origin::origin_allowed;
+use crate::AppState;
+
+/// Initial terminal size, sent by the page when it opens the socket
+#[derive(Deserialize)]
+pub struct TerminalSize {
+    #[serde(default = "default_cols")]
+    cols: u32,
+    #[serde(default = "default_rows")]
+    rows: u32,
+}
+
+fn default_cols() -> u32 {
+    80
+}
+
+fn default_rows() -> u32 {
+    24
+}
+
+/// Messages from the browser
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum ClientMessage {
+    Input { data: String },
+    Resize { cols: u32, rows: u32 },
+}
+
+/// Terminal picker: hosts and LXC consoles
+pub async fn index(State(state): State<Arc<AppState>>) -> Html<String> {
+    let config = state.config.load();
+    let mut hosts: Vec<&str> = config.docker_nodes.iter().map(|n| n.name.as_str()).collect();
+    for profile in &config.profiles {
+        if !hosts.contains(&profile.name.as_str()) && !profile.all_addresses().is_empty() {
+            hosts.push(&profile.name);
+        }
+    }
+
+    // (endpoint, CTID, name); the same CTID can exist on two endpoints
+    let mut ctids: Vec<(&str, u32, &str)> = config
+        .profiles
+        .iter()
+        .filter_map(|p| Some((config.get_profile_proxmox(p)?.name.as_str(), p.ctid?, p.name.as_str())))
+        .collect();
+    for node in &config.docker_nodes {
+        let Some(endpoint) = config.get_node_proxmox(node) else {
+            continue;
+        };
+        if !ctids.iter().any(|(e, ctid, _)| *e == endpoint.name && *ctid == node.ctid) {
+            ctids.push((&endpoint.name, node.ctid, &node.name));
+        }
+    }
+    ctids.sort();
+    let multiple_endpoints = config.proxmox.len() > 1;
+
+    let hosts_html = hosts
+        .iter()
+        .map(|name| {
+            format!(
+                r##"<a href="/terminal/host/{name}" target="_blank" class="block p-3 bg-gray-800 hover:bg-gray-700 rounded">{label}</a>"##,
+                name = html_escape(name),
+                label = html_escape(name),
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let lxc_html = ctids
+        .iter()
+        .map(|(endpoint, ctid, name)| {
+            format!(
+                r##"<a href="/terminal/lxc/{endpoint}/{ctid}" target="_blank" class="block p-3 bg-gray-800 hover:bg-gray-700 rounded">
+                    <span class="font-semibold">CTID {ctid}</span> <span class="text-gray-400">{name}</span>{on}
+                </a>"##,
+                endpoint = html_escape(endpoint),
+                ctid = ctid,
+                name = html_escape(name),
+                on = if multiple_endpoints {
+                    format!(r##" <span class="text-gray-500 text-sm">on {}</span>"##, html_escape(endpoint))
+                } else {
+                    String::new()
+                },
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Html(format!(
+        r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Terminal - Control Panel</title>
+    <script src="https://cdn.tailwindcss.com"></script>
+    <style>body {{ background-color: #1a1a2e; color: #eee; }}</style>
+</head>
+<body class="min-h-screen">
+    <nav class="bg-gray-800 border-b border-gray-700 px-6 py-4">
+        <div class="flex items-center justify-between">
+            <h1 class="text-2xl font-bold text-blue-400">NixOS Control Panel</h1>
+            <div class="flex gap-4">
+                <a href="/" class="text-gray-400 hover:text-gray-300">Infrastructure</a>
+                <a href="/docker" class="text-gray-400 hover:text-gray-300">Docker</a>
+                <a href="/proxmox" class="text-gray-400 hover:text-gray-300">Proxmox</a>
+                <a href="/monitoring" class="text-gray-400 hover:text-gray-300">Monitoring</a>
+                <a href="/editor" class="text-gray-400 hover:text-gray-300">Editor</a>
+            </div>
+        </div>
+    </nav>
+
+    <main class="container mx-auto px-6 py-8">
+        <h2 class="text-xl font-semibold mb-2">&gt;_ Terminal</h2>
+        <p class="text-gray-400 mb-6">
+            {idle}. Container shells
+            are opened from the <a href="/docker" class="text-blue-400">Docker</a> page.
+        </p>
+
+        <h3 class="text-lg font-semibold mb-3">Hosts</h3>
+        <div class="grid grid-cols-2 md:grid-cols-4 gap-3 mb-8">
+            {hosts_html}
+        </div>
+
+        <h3 class="text-lg font-semibold mb-3">LXC consoles (pct enter)</h3>
+        <div class="grid grid-cols-2 md:grid-cols-4 gap-3">
+            {lxc_html}
+        </div>
+    </main>
+</body>
+</html>"##,
+        idle = match config.terminal.idle_timeout_secs {
+            0 => "Sessions stay open without input".to_string(),
+            secs => format!("Sessions close after {} minutes without input", secs / 60),
+        },
+        hosts_html = hosts_html,
+        lxc_html = lxc_html,
+    ))
+}
+
+/// Terminal page for a host
+pub async fn host_page(Path(name): Path<String>) -> Html<String> {
+    terminal_page(&name, &format!("/terminal/host/{}/ws", name))
+}
+
+/// Terminal page for an LXC console
+pub async fn lxc_page(Path((endpoint, ctid)): Path<(String, u32)>) -> Html<String> {
+    terminal_page(
+        &format!("LXC {} on {}", ctid, endpoint),
+        &format!("/terminal/lxc/{}/{}/ws", endpoint, ctid),
+    )
+}
+
+/// Terminal page for a docker container
+pub async fn container_page(Path((node, container)): Path<(String, String)>) -> Html<String> {
+    terminal_page(
+        &format!("{} on {}", container, node),
+        &format!("/terminal/docker/{}/{}/ws", node, container),
+    )
+}
+
+/// Websocket for a host terminal
+pub async fn host_ws(
+    ws: WebSocketUpgrade,
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    Query(size): Query<TerminalSize>,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(refused) = refuse_foreign_origin(&state, &headers) {
+        return refused;
+    }
+    let user = session_user(&headers);
+    ws.on_upgrade(move |socket| run_terminal(socket, state, TerminalTarget::Host(name), size, user))
+}
+
+/// Websocket for an LXC console
+pub async fn lxc_ws(
+    ws: WebSocketUpgrade,
+    State(state): State<Arc<AppState>>,
+    Path((endpoint, ctid)): Path<(String, u32)>,
+    Query(size): Query<TerminalSize>,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(refused) = refuse_foreign_origin(&state, &headers) {
+        return refused;
+    }
+    let user = session_user(&headers);
+    let target = TerminalTarget::Lxc { proxmox: endpoint, ctid };
+    ws.on_upgrade(move |socket| run_terminal(socket, state, target, size, user))
+}
+
+/// Websocket for a docker container shell
+pub async fn container_ws(
+    ws: WebSocketUpgrade,
+    State(state): State<Arc<AppState>>,
+    Path((node, container)): Path<(String, String)>,
+    Query(size): Query<TerminalSize>,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(refused) = refuse_foreign_origin(&state, &headers) {
+        return refused;
+    }
+    let user = session_user(&headers);
+    let target = TerminalTarget::Container { node, container };
+    ws.on_upgrade(move |socket| run_terminal(socket, state, target, size, user))
+}
+
+/// Refuse websockets opened by other sites' pages. Local connections skip
+/// auth and browsers resend cached credentials on the handshake, so any
+/// page could otherwise open a shell.
+fn refuse_foreign_origin(state: &AppState, headers: &HeaderMap) -> Option<Response> {
+    let config = state.config.load();
+    if origin_allowed(headers, &config.server.allowed_origins) {
+        return None;
+    }
+    tracing::warn!(
+        "Refused terminal websocket from origin {:?}",
+        headers.get(header::ORIGIN).and_then(|o| o.to_str().ok()).unwrap_or("(none)")
+    );
+    Some((StatusCode::FORBIDDEN, "Terminal websockets only open from this panel's pages").into_response())
+}
+
+/// Who opened a session, for the transcript: the basic auth user, or
+/// `localhost` (the auth middleware lets local connections through)
+fn session_user(headers: &HeaderMap) -> String {
+    headers
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|v| v.strip_prefix("Basic "))
+        .and_then(|encoded| STANDARD.decode(encoded).ok())
+        .and_then(|decoded| String::from_utf8(decoded).ok())
+        .and_then(|credentials| credentials.split_once(':').map(|(user, _)| user.to_string()))
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+fn control_message(value: serde_json::Value) -> Message {
+    Message::Text(value.to_string().into())
+}
+
+/// Bridge a websocket and a terminal session until either side closes
+async fn run_terminal(
+    mut socket: WebSocket,
+    state: Arc<AppState>,
+    target: TerminalTarget,
+    size: TerminalSize,
+    user: String,
+) {
+    let mut session = match state
+        .ssh_pool
+        .open_terminal(&target, size.cols, size.rows, &user)
+        .await
+    {
+        Ok(session) => session,
+        Err(e) => {
+            tracing::warn!("Terminal to {} failed: {}", target, e);
+            let message = serde_json::json!({"type": "error", "message": e.to_string()});
+            let _ = socket.send(control_message(message)).await;
+            return;
+        }
+    };
+
+    loop {
+        tokio::select! {
+            msg = socket.recv() => {
+                let input = match msg {
+                    Some(Ok(Message::Text(text))) => match serde_json::from_str::<ClientMessage>(&text) {
+                        Ok(ClientMessage::Input { data }) => TerminalInput::Data(data.into_bytes()),
+                        Ok(ClientMessage::Resize { cols, rows }) => TerminalInput::Resize { cols, rows },
+                        Err(_) => continue,
+                    },
+                    Some(Ok(Message::Binary(data))) => TerminalInput::Data(data.to_vec()),
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                };
+                if session.input.send(input).await.is_err() {
+                    break;
+                }
+            }
+            output = session.output.recv() => {
+                let message = match output {
+                    Some(TerminalOutput::Data(data)) => Message::Binary(data.into()),
+                    Some(TerminalOutput::Exit(code)) => control_message(serde_json::json!({"type": "exit", "code": code})),
+                    Some(TerminalOutput::IdleTimeout) => control_message(serde_json::json!({"type": "idle"})),
+                    None => break,
+                };
+                if socket.send(message).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    // Dropping the session closes the remote channel
+    let _ = socket.send(Message::Close(None)).await;
+}
+
+/// Full-window xterm.js page connected to `ws_path`
+fn terminal_page(title: &str, ws_path: &str) -> Html<String> {
+    Html(format!(
+        r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>{title} - Terminal</title>
+    <link rel="stylesheet" href="https://cdn.jsdelivr.net/npm/@xterm/xterm@5.5.0/css/xterm.min.css">
+    <script src="https://cdn.jsdelivr.net/npm/@xterm/xterm@5.5.0/lib/xterm.min.js"></script>
+    <script src="https://cdn.jsdelivr.net/npm/@xterm/addon-fit@0.10.0/lib/addon-fit.min.js"></script>
+    <style>
+        html, body {{ margin: 0; height: 100%; background-color: #1a1a2e; color: #eee; font-family: sans-serif; }}
+        #bar {{ padding: 6px 12px; background: #1f2937; font-size: 14px; display: flex; justify-content: space-between; }}
+        #status {{ color: #9ca3af; }}
+        #terminal {{ position: absolute; top: 32px; bottom: 0; left: 0; right: 0; padding: 4px; }}
+    </style>
+</head>
+<body>
+    <div id="bar"><span>&gt;_ {title}</span><span id="status">connecting...</span></div>
+    <div id="terminal"></div>
+    <script>
+        const status = document.getElementById('status');
+        const term = new Terminal({{ cursorBlink: true, fontSize: 14, theme: {{ background: '#111827' }} }});
+        const fit = new FitAddon.FitAddon();
+        term.loadAddon(fit);
+        term.open(document.getElementById('terminal'));
+        fit.fit();
+
+        const proto = location.protocol === 'https:' ? 'wss:' : 'ws:';
+        const ws = new WebSocket(proto + '//' + location.host + {ws_path} + '?cols=' + term.cols + '&rows=' + term.rows);
+        ws.binaryType = 'arraybuffer';
+
+        const setStatus = (text, color) => {{ status.textContent = text; status.style.color = color; }};
+        ws.onopen = () => {{ setStatus('connected', '#4ade80'); term.focus(); }};
+        ws.onmessage = e => {{
+            if (typeof e.data !== 'string') {{
+                term.write(new Uint8Array(e.data));
+                return;
+            }}
+            const msg = JSON.parse(e.data);
+            if (msg.type === 'exit') setStatus('exited (' + msg.code + ')', msg.code === 0 ? '#9ca3af' : '#f87171');
+            else if (msg.type === 'idle') setStatus('closed (idle)', '#facc15');
+            else if (msg.type === 'error') {{
+                term.write('\r\n\x1b[31m' + msg.message + '\x1b[0m\r\n');
+                setStatus('failed', '#f87171');
+            }}
+        }};
+        ws.onclose = () => {{ if (status.textContent === 'connected') setStatus('disconnected', '#facc15'); }};
+
+        term.onData(data => {{
+            if (ws.readyState === WebSocket.OPEN) ws.send(JSON.stringify({{ type: 'input', data }}));
+        }});
+        term.onResize(({{ cols, rows }}) => {{
+            if (ws.readyState === WebSocket.OPEN) ws.send(JSON.stringify({{ type: 'resize', cols, rows }}));
+        }});
+        window.addEventListener('resize', () => fit.fit());
+    </script>
+</body>
+</html>"##,
+        title = html_escape(title),
+        // JS string literal; `<` escaped so a name can't close the script tag
+        ws_path = serde_json::to_string(ws_path).unwrap_or_default().replace('<', "\\u003c"),
+    ))
+}
@@ -0,0 +1,249 @@
+//! SSH tunnel manager (local port forwards to services on hosts)
+
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderMap},
+    response::Html,
+    Form,
+};
+use control_panel_core::TunnelInfo;
+use serde::Deserialize;
+use std::sync::Arc;
+
+use super::html_escape;
+use crate::AppState;
+
+/// Services commonly forwarded, offered as presets
+const PRESETS: &[(u16, &str)] = &[
+    (3000, "App / Grafana"),
+    (3001, "App"),
+    (9092, "cAdvisor"),
+    (9100, "Node Exporter"),
+    (9090, "Prometheus"),
+];
+
+#[derive(Deserialize)]
+pub struct OpenForm {
+    host: String,
+    #[serde(default = "default_remote_host")]
+    remote_host: String,
+    remote_port: u16,
+}
+
+fn default_remote_host() -> String {
+    "localhost".to_string()
+}
+
+/// Host name the browser should use for a tunnel: the bind address, or the
+/// name the browser used for the panel when tunnels listen on all interfaces
+fn browser_host(state: &AppState, headers: &HeaderMap) -> String {
+    let config = state.config.load();
+    let bind = config.tunnels.bind_address.as_str();
+    if bind != "0.0.0.0" && bind != "::" {
+        return bind.to_string();
+    }
+    headers
+        .get(header::HOST)
+        .and_then(|h| h.to_str().ok())
+        .map(|host| match host.rsplit_once(':') {
+            Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name.to_string(),
+            _ => host.to_string(),
+        })
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+fn format_bytes(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+    } else if bytes >= 1024 {
+        format!("{:.1} KiB", bytes as f64 / 1024.0)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+fn tunnel_row(tunnel: &TunnelInfo, host: &str) -> String {
+    let url = format!("http://{}:{}/", host, tunnel.local_address.port());
+    let status = if tunnel.connections > 0 {
+        format!("<span class='text-green-400'>{} open</span>", tunnel.connections)
+    } else {
+        format!("<span class='text-gray-400'>idle {}m</span>", tunnel.idle_secs / 60)
+    };
+
+    format!(
+        r##"<tr class="border-b border-gray-700">
+            <td class="py-2 px-3">{host_name}</td>
+            <td class="py-2 px-3 font-mono">{remote_host}:{remote_port}</td>
+            <td class="py-2 px-3 font-mono">{local}</td>
+            <td class="py-2 px-3">{status}</td>
+            <td class="py-2 px-3 text-gray-400">{total} conn · {sent} / {received}</td>
+            <td class="py-2 px-3 text-right">
+                <a href="{url}" target="_blank" class="px-2 py-1 bg-blue-600 hover:bg-blue-700 rounded text-xs">Open</a>
+                <button hx-post="/tunnels/{id}/close" hx-target="#tunnel-list" class="px-2 py-1 bg-red-600 hover:bg-red-700 rounded text-xs">Close</button>
+            </td>
+        </tr>"##,
+        host_name = html_escape(&tunnel.host),
+        remote_host = html_escape(&tunnel.remote_host),
+        remote_port = tunnel.remote_port,
+        local = tunnel.local_address,
+        status = status,
+        total = tunnel.total_connections,
+        sent = format_bytes(tunnel.bytes_sent),
+        received = format_bytes(tunnel.bytes_received),
+        url = html_escape(&url),
+        id = tunnel.id,
+    )
+}
+
+fn tunnel_table(state: &AppState, headers: &HeaderMap, message: &str) -> Html<String> {
+    let tunnels = state.ssh_pool.tunnels();
+    let host = browser_host(state, headers);
+
+    let rows = if tunnels.is_empty() {
+        r##"<tr><td colspan="6" class="py-4 px-3 text-gray-400">No open tunnels</td></tr>"##.to_string()
+    } else {
+        tunnels
+            .iter()
+            .map(|t| tunnel_row(t, &host))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    Html(format!(
+        r##"{message}
+        <table class="w-full text-sm">
+            <thead class="bg-gray-900 text-gray-400">
+                <tr>
+                    <th class="py-2 px-3 text-left">Through</th>
+                    <th class="py-2 px-3 text-left">Destination</th>
+                    <th class="py-2 px-3 text-left">Local</th>
+                    <th class="py-2 px-3 text-left">Status</th>
+                    <th class="py-2 px-3 text-left">Traffic</th>
+                    <th class="py-2 px-3"></th>
+                </tr>
+            </thead>
+            <tbody>{rows}</tbody>
+        </table>"##,
+        message = message,
+        rows = rows,
+    ))
+}
+
+/// Tunnel manager page
+pub async fn dashboard(State(state): State<Arc<AppState>>) -> Html<String> {
+    let config = state.config.load();
+    let mut hosts: Vec<&str> = config.docker_nodes.iter().map(|n| n.name.as_str()).collect();
+    for profile in &config.profiles {
+        if !hosts.contains(&profile.name.as_str()) && !profile.all_addresses().is_empty() {
+            hosts.push(&profile.name);
+        }
+    }
+
+    let host_options = hosts
+        .iter()
+        .map(|h| format!(r##"<option value="{0}">{0}</option>"##, html_escape(h)))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let presets = PRESETS
+        .iter()
+        .map(|(port, label)| format!(r##"<option value="{}">{}</option>"##, port, label))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Html(format!(
+        r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Tunnels - Control Panel</title>
+    <script src="https://unpkg.com/htmx.org@1.9.10"></script>
+    <script src="https://cdn.tailwindcss.com"></script>
+    <style>body {{ background-color: #1a1a2e; color: #eee; }}</style>
+</head>
+<body class="min-h-screen">
+    <nav class="bg-gray-800 border-b border-gray-700 px-6 py-4">
+        <div class="flex items-center justify-between">
+            <h1 class="text-2xl font-bold text-blue-400">NixOS Control Panel</h1>
+            <div class="flex gap-4">
+                <a href="/" class="text-gray-400 hover:text-gray-300">Infrastructure</a>
+                <a href="/docker" class="text-gray-400 hover:text-gray-300">Docker</a>
+                <a href="/proxmox" class="text-gray-400 hover:text-gray-300">Proxmox</a>
+                <a href="/monitoring" class="text-gray-400 hover:text-gray-300">Monitoring</a>
+                <a href="/editor" class="text-gray-400 hover:text-gray-300">Editor</a>
+            </div>
+        </div>
+    </nav>
+
+    <main class="container mx-auto px-6 py-8">
+        <h2 class="text-xl font-semibold mb-2">🔀 Tunnels</h2>
+        <p class="text-gray-400 mb-6">
+            Forward a local port to a service that is only reachable from a host
+            (like <code>ssh -L</code>). Tunnels listen on {bind} and close after
+            {idle} minutes without connections.
+        </p>
+
+        <form hx-post="/tunnels" hx-target="#tunnel-list" class="bg-gray-800 p-4 rounded-lg mb-6 flex flex-wrap items-end gap-4">
+            <label class="flex flex-col text-sm">Through host
+                <select name="host" class="bg-gray-900 rounded px-2 py-1 mt-1">{host_options}</select>
+            </label>
+            <label class="flex flex-col text-sm">Destination (from the host)
+                <input name="remote_host" value="localhost" class="bg-gray-900 rounded px-2 py-1 mt-1">
+            </label>
+            <label class="flex flex-col text-sm">Port
+                <input name="remote_port" type="number" min="1" max="65535" list="port-presets" required class="bg-gray-900 rounded px-2 py-1 mt-1 w-28">
+                <datalist id="port-presets">{presets}</datalist>
+            </label>
+            <button type="submit" class="px-3 py-1 bg-blue-600 hover:bg-blue-700 rounded">Open tunnel</button>
+        </form>
+
+        <div id="tunnel-list" class="bg-gray-800 rounded-lg overflow-hidden" hx-get="/tunnels/list" hx-trigger="load, every 5s">
+        </div>
+    </main>
+</body>
+</html>"##,
+        bind = html_escape(&config.tunnels.bind_address),
+        idle = config.tunnels.idle_timeout_secs / 60,
+        host_options = host_options,
+        presets = presets,
+    ))
+}
+
+/// Tunnel list fragment
+pub async fn list(State(state): State<Arc<AppState>>, headers: HeaderMap) -> Html<String> {
+    tunnel_table(&state, &headers, "")
+}
+
+/// Open a tunnel (or return the running one for the same destination)
+pub async fn open(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Form(form): Form<OpenForm>,
+) -> Html<String> {
+    let remote_host = form.remote_host.trim();
+    let message = match state.ssh_pool.open_tunnel(&form.host, remote_host, form.remote_port).await {
+        Ok(tunnel) => format!(
+            "<div class='p-3 text-green-400'>Forwarding {} to {}:{} via {}</div>",
+            tunnel.local_address,
+            html_escape(&tunnel.remote_host),
+            tunnel.remote_port,
+            html_escape(&tunnel.host)
+        ),
+        Err(e) => format!("<div class='p-3 text-red-400'>{}</div>", html_escape(&e.to_string())),
+    };
+    tunnel_table(&state, &headers, &message)
+}
+
+/// Close a tunnel
+pub async fn close(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<u64>,
+) -> Html<String> {
+    let message = if state.ssh_pool.close_tunnel(id) {
+        String::new()
+    } else {
+        "<div class='p-3 text-gray-400'>Tunnel was already closed</div>".to_string()
+    };
+    tunnel_table(&state, &headers, &message)
+}
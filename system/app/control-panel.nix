@@ -56,7 +56,9 @@ let
 
     [auth]
     username = "${secrets.httpAuthUser}"
-    password = "${secrets.httpAuthPassword}"
+    ${if secrets ? httpAuthPasswordHash
+      then ''password_hash = "${secrets.httpAuthPasswordHash}"''
+      else ''password = "${secrets.httpAuthPassword}"''}
 
     [ssh]
     private_key_path = "${secrets.sshPrivateKeyPath}"